@@ -18,6 +18,7 @@ use crate::prelude::*;
 pub mod channel;
 pub mod sample;
 pub mod signal;
+pub mod waveform;
 
 mod _core {
     pub use aoide_core::audio::*;
@@ -66,6 +67,39 @@ impl From<DurationMs> for _core::DurationMs {
     }
 }
 
+///////////////////////////////////////////////////////////////////////
+// AudioRange
+///////////////////////////////////////////////////////////////////////
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct AudioRange {
+    start_ms: PositionMs,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_ms: Option<PositionMs>,
+}
+
+impl From<AudioRange> for _core::AudioRange {
+    fn from(from: AudioRange) -> Self {
+        let AudioRange { start_ms, end_ms } = from;
+        Self {
+            start: start_ms.into(),
+            end: end_ms.map(Into::into),
+        }
+    }
+}
+
+impl From<_core::AudioRange> for AudioRange {
+    fn from(from: _core::AudioRange) -> Self {
+        let _core::AudioRange { start, end } = from;
+        Self {
+            start_ms: start.into(),
+            end_ms: end.map(Into::into),
+        }
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////
 // AudioContent
 ///////////////////////////////////////////////////////////////////////
@@ -85,9 +119,21 @@ pub struct AudioContent {
     #[serde(skip_serializing_if = "Option::is_none")]
     bitrate_bps: Option<BitrateBps>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bitrate_mode: Option<BitrateMode>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     loudness_lufs: Option<LoudnessLufs>,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    loudness_range_lu: Option<LoudnessRangeLu>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    true_peak_dbtp: Option<TruePeakDbtp>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    range: Option<AudioRange>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     encoder: Option<String>,
 }
@@ -99,7 +145,11 @@ impl From<AudioContent> for _core::AudioContent {
             channels,
             sample_rate_hz,
             bitrate_bps,
+            bitrate_mode,
             loudness_lufs,
+            loudness_range_lu,
+            true_peak_dbtp,
+            range,
             encoder,
         } = from;
         Self {
@@ -107,7 +157,14 @@ impl From<AudioContent> for _core::AudioContent {
             channels: channels.map(Into::into),
             sample_rate: sample_rate_hz.map(Into::into),
             bitrate: bitrate_bps.map(Into::into),
+            bitrate_mode: bitrate_mode.map(Into::into),
             loudness: loudness_lufs.map(Into::into),
+            loudness_range: loudness_range_lu.map(Into::into),
+            true_peak: true_peak_dbtp.map(Into::into),
+            // The waveform is not part of the track JSON and
+            // only served separately
+            waveform: None,
+            range: range.map(Into::into),
             encoder: encoder.map(Into::into),
         }
     }
@@ -120,7 +177,12 @@ impl From<_core::AudioContent> for AudioContent {
             channels,
             sample_rate,
             bitrate,
+            bitrate_mode,
             loudness,
+            loudness_range,
+            true_peak,
+            waveform: _,
+            range,
             encoder,
         } = from;
         Self {
@@ -128,7 +190,11 @@ impl From<_core::AudioContent> for AudioContent {
             channels: channels.map(Into::into),
             sample_rate_hz: sample_rate.map(Into::into),
             bitrate_bps: bitrate.map(Into::into),
+            bitrate_mode: bitrate_mode.map(Into::into),
             loudness_lufs: loudness.map(Into::into),
+            loudness_range_lu: loudness_range.map(Into::into),
+            true_peak_dbtp: true_peak.map(Into::into),
+            range: range.map(Into::into),
             encoder: encoder.map(Into::into),
         }
     }
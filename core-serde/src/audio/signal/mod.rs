@@ -38,6 +38,33 @@ impl From<BitrateBps> for _core::BitrateBps {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize_repr, Deserialize_repr, JsonSchema)]
+#[repr(u8)]
+pub enum BitrateMode {
+    Constant = 1,
+    Variable = 2,
+}
+
+impl From<_core::BitrateMode> for BitrateMode {
+    fn from(from: _core::BitrateMode) -> Self {
+        use _core::BitrateMode::*;
+        match from {
+            Constant => Self::Constant,
+            Variable => Self::Variable,
+        }
+    }
+}
+
+impl From<BitrateMode> for _core::BitrateMode {
+    fn from(from: BitrateMode) -> Self {
+        use BitrateMode::*;
+        match from {
+            Constant => Self::Constant,
+            Variable => Self::Variable,
+        }
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////
 // SampleRate
 ///////////////////////////////////////////////////////////////////////
@@ -78,3 +105,41 @@ impl From<LoudnessLufs> for _core::LoudnessLufs {
         Self(lufs)
     }
 }
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LoudnessRangeLu(_core::LuValue);
+
+impl From<_core::LoudnessRangeLu> for LoudnessRangeLu {
+    fn from(from: _core::LoudnessRangeLu) -> Self {
+        let _core::LoudnessRangeLu(lu) = from;
+        Self(lu)
+    }
+}
+
+impl From<LoudnessRangeLu> for _core::LoudnessRangeLu {
+    fn from(from: LoudnessRangeLu) -> Self {
+        let LoudnessRangeLu(lu) = from;
+        Self(lu)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// TruePeak
+///////////////////////////////////////////////////////////////////////
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct TruePeakDbtp(_core::DbtpValue);
+
+impl From<_core::TruePeakDbtp> for TruePeakDbtp {
+    fn from(from: _core::TruePeakDbtp) -> Self {
+        let _core::TruePeakDbtp(dbtp) = from;
+        Self(dbtp)
+    }
+}
+
+impl From<TruePeakDbtp> for _core::TruePeakDbtp {
+    fn from(from: TruePeakDbtp) -> Self {
+        let TruePeakDbtp(dbtp) = from;
+        Self(dbtp)
+    }
+}
@@ -0,0 +1,77 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::prelude::*;
+
+mod _core {
+    pub use aoide_core::audio::waveform::*;
+}
+
+///////////////////////////////////////////////////////////////////////
+// WaveformOverview
+///////////////////////////////////////////////////////////////////////
+
+/// Amplitudes `[peak, rms, low, mid, high]`, each in the range 0..=255
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WaveformBin(u8, u8, u8, u8, u8);
+
+impl From<_core::WaveformBin> for WaveformBin {
+    fn from(from: _core::WaveformBin) -> Self {
+        let _core::WaveformBin {
+            peak,
+            rms,
+            low,
+            mid,
+            high,
+        } = from;
+        Self(peak, rms, low, mid, high)
+    }
+}
+
+impl From<WaveformBin> for _core::WaveformBin {
+    fn from(from: WaveformBin) -> Self {
+        let WaveformBin(peak, rms, low, mid, high) = from;
+        Self {
+            peak,
+            rms,
+            low,
+            mid,
+            high,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WaveformOverview {
+    bins: Vec<WaveformBin>,
+}
+
+impl From<_core::WaveformOverview> for WaveformOverview {
+    fn from(from: _core::WaveformOverview) -> Self {
+        Self {
+            bins: from.bins().iter().copied().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<WaveformOverview> for _core::WaveformOverview {
+    fn from(from: WaveformOverview) -> Self {
+        let WaveformOverview { bins } = from;
+        Self::new(bins.into_iter().map(Into::into).collect())
+    }
+}
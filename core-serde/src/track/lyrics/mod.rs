@@ -0,0 +1,103 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{audio::PositionMs, prelude::*};
+
+mod _core {
+    pub use aoide_core::track::lyrics::{Lyrics, SyncedLine};
+}
+
+use aoide_core::util::IsDefault;
+
+///////////////////////////////////////////////////////////////////////
+// SyncedLine
+///////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SyncedLine {
+    pub position_ms: PositionMs,
+
+    pub text: String,
+}
+
+impl From<_core::SyncedLine> for SyncedLine {
+    fn from(from: _core::SyncedLine) -> Self {
+        let _core::SyncedLine { position, text } = from;
+        Self {
+            position_ms: position.into(),
+            text,
+        }
+    }
+}
+
+impl From<SyncedLine> for _core::SyncedLine {
+    fn from(from: SyncedLine) -> Self {
+        let SyncedLine { position_ms, text } = from;
+        Self {
+            position: position_ms.into(),
+            text,
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Lyrics
+///////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Lyrics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+
+    #[serde(skip_serializing_if = "IsDefault::is_default", default)]
+    pub lines: Vec<SyncedLine>,
+}
+
+impl From<_core::Lyrics> for Lyrics {
+    fn from(from: _core::Lyrics) -> Self {
+        let _core::Lyrics {
+            language,
+            text,
+            lines,
+        } = from;
+        Self {
+            language,
+            text,
+            lines: lines.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<Lyrics> for _core::Lyrics {
+    fn from(from: Lyrics) -> Self {
+        let Lyrics {
+            language,
+            text,
+            lines,
+        } = from;
+        Self {
+            language,
+            text,
+            lines: lines.into_iter().map(Into::into).collect(),
+        }
+    }
+}
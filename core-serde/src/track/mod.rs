@@ -19,11 +19,12 @@ pub mod actor;
 pub mod album;
 pub mod cue;
 pub mod index;
+pub mod lyrics;
 pub mod metric;
 pub mod release;
 pub mod title;
 
-use self::{actor::*, album::*, cue::*, index::*, metric::*, release::*, title::*};
+use self::{actor::*, album::*, cue::*, index::*, lyrics::*, metric::*, release::*, title::*};
 
 use crate::media::Source;
 
@@ -73,6 +74,9 @@ pub struct Track {
     #[serde(skip_serializing_if = "IsDefault::is_default", default)]
     pub cues: Vec<Cue>,
 
+    #[serde(skip_serializing_if = "IsDefault::is_default", default)]
+    pub lyrics: Vec<Lyrics>,
+
     #[serde(skip_serializing_if = "IsDefault::is_default", default)]
     pub play_counter: PlayCounter,
 }
@@ -90,6 +94,7 @@ impl From<_core::Track> for Track {
             color,
             metrics,
             cues,
+            lyrics,
             play_counter,
         } = from;
         Self {
@@ -103,6 +108,7 @@ impl From<_core::Track> for Track {
             color: color.map(Into::into),
             metrics: metrics.into(),
             cues: cues.untie().into_iter().map(Into::into).collect(),
+            lyrics: lyrics.untie().into_iter().map(Into::into).collect(),
             play_counter: play_counter.into(),
         }
     }
@@ -121,6 +127,7 @@ impl From<Track> for _core::Track {
             color,
             metrics,
             cues,
+            lyrics,
             play_counter,
         } = from;
         Self {
@@ -151,6 +158,13 @@ impl From<Track> for _core::Track {
                     .collect::<Vec<_>>()
                     .canonicalize_into(),
             ),
+            lyrics: Canonical::tie(
+                lyrics
+                    .into_iter()
+                    .map(Into::into)
+                    .collect::<Vec<_>>()
+                    .canonicalize_into(),
+            ),
             play_counter: play_counter.into(),
         }
     }
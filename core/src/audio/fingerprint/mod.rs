@@ -0,0 +1,112 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+pub type SubFingerprint = u32;
+
+/// The number of sub-fingerprints per second of audio
+pub const SUB_FINGERPRINTS_PER_SECOND: u32 = 20;
+
+// Fingerprints of different encodings might be slightly shifted,
+// e.g. due to encoder delay or leading silence
+const MAX_ALIGNMENT_OFFSET: usize = 2 * SUB_FINGERPRINTS_PER_SECOND as usize;
+
+// Shorter overlaps are not significant for comparing fingerprints
+const MIN_OVERLAP: usize = 5 * SUB_FINGERPRINTS_PER_SECOND as usize;
+
+/// An acoustic fingerprint of an audio signal
+///
+/// Each sub-fingerprint encodes how the spectral energy of a short
+/// section of the signal changes across frequency bands and over time
+/// in a 32-bit word. Different encodings of the same recording result
+/// in similar fingerprints, independent of their file tags.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fingerprint(Vec<SubFingerprint>);
+
+impl Fingerprint {
+    pub const fn new(sub_fingerprints: Vec<SubFingerprint>) -> Self {
+        Self(sub_fingerprints)
+    }
+
+    pub fn sub_fingerprints(&self) -> &[SubFingerprint] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Encode all sub-fingerprints in little-endian byte order
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0
+            .iter()
+            .flat_map(|sub_fingerprint| sub_fingerprint.to_le_bytes().to_vec())
+            .collect()
+    }
+
+    /// Decode all sub-fingerprints from little-endian byte order
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let chunks = bytes.chunks_exact(4);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+        Some(Self(
+            chunks
+                .map(|chunk| {
+                    SubFingerprint::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])
+                })
+                .collect(),
+        ))
+    }
+
+    /// Compare two fingerprints
+    ///
+    /// Returns the ratio of matching bits for the best alignment of
+    /// both fingerprints, i.e. 1.0 for identical fingerprints and
+    /// about 0.5 for unrelated fingerprints. Returns `None` if the
+    /// fingerprints are too short for a meaningful comparison.
+    pub fn similarity(&self, other: &Self) -> Option<f64> {
+        let lhs = self.sub_fingerprints();
+        let rhs = other.sub_fingerprints();
+        let bit_error_rate = |lhs: &[SubFingerprint], rhs: &[SubFingerprint]| {
+            let overlap = lhs.len().min(rhs.len());
+            if overlap < MIN_OVERLAP {
+                return None;
+            }
+            let bit_errors: u32 = lhs
+                .iter()
+                .zip(rhs)
+                .map(|(lhs, rhs)| (lhs ^ rhs).count_ones())
+                .sum();
+            Some(f64::from(bit_errors) / (overlap * 32) as f64)
+        };
+        (0..=MAX_ALIGNMENT_OFFSET)
+            .flat_map(|offset| {
+                let shifted_lhs = lhs.get(offset..).and_then(|lhs| bit_error_rate(lhs, rhs));
+                let shifted_rhs = rhs.get(offset..).and_then(|rhs| bit_error_rate(lhs, rhs));
+                shifted_lhs.into_iter().chain(shifted_rhs)
+            })
+            .min_by(|lhs, rhs| lhs.partial_cmp(rhs).expect("finite bit error rate"))
+            .map(|bit_error_rate| 1.0 - bit_error_rate)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,67 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+fn pseudo_random_fingerprint(seed: u32, len: usize) -> Fingerprint {
+    let mut state = seed;
+    Fingerprint::new(
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                state
+            })
+            .collect(),
+    )
+}
+
+#[test]
+fn bytes_roundtrip() {
+    let fingerprint = pseudo_random_fingerprint(1, 200);
+    let bytes = fingerprint.to_bytes();
+    assert_eq!(800, bytes.len());
+    assert_eq!(Some(fingerprint), Fingerprint::from_bytes(&bytes));
+    assert_eq!(None, Fingerprint::from_bytes(&bytes[1..]));
+}
+
+#[test]
+fn similarity_of_identical_fingerprints() {
+    let fingerprint = pseudo_random_fingerprint(1, 200);
+    assert_eq!(Some(1.0), fingerprint.similarity(&fingerprint));
+}
+
+#[test]
+fn similarity_of_shifted_fingerprints() {
+    let fingerprint = pseudo_random_fingerprint(1, 200);
+    let shifted = Fingerprint::new(fingerprint.sub_fingerprints()[7..].to_vec());
+    assert_eq!(Some(1.0), fingerprint.similarity(&shifted));
+    assert_eq!(Some(1.0), shifted.similarity(&fingerprint));
+}
+
+#[test]
+fn similarity_of_unrelated_fingerprints() {
+    let lhs = pseudo_random_fingerprint(1, 200);
+    let rhs = pseudo_random_fingerprint(2, 200);
+    let similarity = lhs.similarity(&rhs).unwrap();
+    assert!(similarity < 0.6, "{}", similarity);
+}
+
+#[test]
+fn similarity_of_short_fingerprints() {
+    let fingerprint = pseudo_random_fingerprint(1, MIN_OVERLAP - 1);
+    assert_eq!(None, fingerprint.similarity(&fingerprint));
+}
@@ -16,10 +16,12 @@
 use crate::prelude::*;
 
 pub mod channel;
+pub mod fingerprint;
 pub mod sample;
 pub mod signal;
+pub mod waveform;
 
-use self::{channel::*, sample::*, signal::*};
+use self::{channel::*, sample::*, signal::*, waveform::WaveformOverview};
 
 use std::{fmt, time::Duration};
 
@@ -126,6 +128,53 @@ impl fmt::Display for DurationMs {
     }
 }
 
+///////////////////////////////////////////////////////////////////////
+// AudioRange
+///////////////////////////////////////////////////////////////////////
+
+/// A range within the audio stream of a file
+///
+/// Virtual tracks, e.g. defined by a CUE sheet, share a single file
+/// and only cover a part of its audio stream. An open range extends
+/// until the end of the audio stream.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AudioRange {
+    pub start: PositionMs,
+
+    pub end: Option<PositionMs>,
+}
+
+impl AudioRange {
+    pub fn duration(&self) -> Option<DurationMs> {
+        self.end
+            .map(|end| DurationMs::from_inner(end.0 - self.start.0))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AudioRangeInvalidity {
+    Start(PositionMsInvalidity),
+    End(PositionMsInvalidity),
+    StartNegative,
+    Empty,
+}
+
+impl Validate for AudioRange {
+    type Invalidity = AudioRangeInvalidity;
+
+    fn validate(&self) -> ValidationResult<Self::Invalidity> {
+        ValidationContext::new()
+            .validate_with(&self.start, AudioRangeInvalidity::Start)
+            .validate_with(&self.end, AudioRangeInvalidity::End)
+            .invalidate_if(self.start.0 < 0.0, AudioRangeInvalidity::StartNegative)
+            .invalidate_if(
+                self.end.map(|end| end <= self.start).unwrap_or(false),
+                AudioRangeInvalidity::Empty,
+            )
+            .into()
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////
 // AudioContent
 ///////////////////////////////////////////////////////////////////////
@@ -140,8 +189,19 @@ pub struct AudioContent {
 
     pub bitrate: Option<BitrateBps>,
 
+    pub bitrate_mode: Option<BitrateMode>,
+
     pub loudness: Option<LoudnessLufs>,
 
+    pub loudness_range: Option<LoudnessRangeLu>,
+
+    pub true_peak: Option<TruePeakDbtp>,
+
+    pub waveform: Option<WaveformOverview>,
+
+    /// Only for virtual tracks
+    pub range: Option<AudioRange>,
+
     // Encoder and settings
     pub encoder: Option<String>,
 }
@@ -153,6 +213,9 @@ pub enum AudioContentInvalidity {
     SampleRate(SampleRateHzInvalidity),
     Bitrate(BitrateBpsInvalidity),
     Loudness(LoudnessLufsInvalidity),
+    LoudnessRange(LoudnessRangeLuInvalidity),
+    TruePeak(TruePeakDbtpInvalidity),
+    Range(AudioRangeInvalidity),
     EncoderEmpty,
 }
 
@@ -166,6 +229,9 @@ impl Validate for AudioContent {
             .validate_with(&self.sample_rate, AudioContentInvalidity::SampleRate)
             .validate_with(&self.bitrate, AudioContentInvalidity::Bitrate)
             .validate_with(&self.loudness, AudioContentInvalidity::Loudness)
+            .validate_with(&self.loudness_range, AudioContentInvalidity::LoudnessRange)
+            .validate_with(&self.true_peak, AudioContentInvalidity::TruePeak)
+            .validate_with(&self.range, AudioContentInvalidity::Range)
             .invalidate_if(
                 self.encoder
                     .as_deref()
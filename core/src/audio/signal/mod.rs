@@ -21,6 +21,8 @@ use crate::{
     prelude::*,
 };
 
+use num_derive::{FromPrimitive, ToPrimitive};
+
 use std::{f64, fmt};
 
 ///////////////////////////////////////////////////////////////////////
@@ -78,6 +80,15 @@ impl fmt::Display for BitrateBps {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
+pub enum BitrateMode {
+    /// Constant bitrate (CBR)
+    Constant = 1,
+
+    /// Variable bitrate (VBR), including average bitrate (ABR) encodings
+    Variable = 2,
+}
+
 ///////////////////////////////////////////////////////////////////////
 // SampleRate
 ///////////////////////////////////////////////////////////////////////
@@ -291,6 +302,86 @@ impl fmt::Display for LoudnessLufs {
     }
 }
 
+pub type LuValue = f64;
+
+/// The loudness range (LRA) according to EBU Tech 3342 in
+/// "Loudness Units" (LU), i.e. the variation of loudness
+/// over the course of a track.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct LoudnessRangeLu(pub LuValue);
+
+impl LoudnessRangeLu {
+    pub const fn unit_of_measure() -> &'static str {
+        "LU"
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LoudnessRangeLuInvalidity {
+    OutOfRange,
+}
+
+impl Validate for LoudnessRangeLu {
+    type Invalidity = LoudnessRangeLuInvalidity;
+
+    fn validate(&self) -> ValidationResult<Self::Invalidity> {
+        ValidationContext::new()
+            .invalidate_if(
+                !self.0.is_finite() || self.0 < 0.0,
+                LoudnessRangeLuInvalidity::OutOfRange,
+            )
+            .into()
+    }
+}
+
+impl fmt::Display for LoudnessRangeLu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.0, Self::unit_of_measure())
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// TruePeak
+///////////////////////////////////////////////////////////////////////
+
+pub type DbtpValue = f64;
+
+/// The maximum true peak level according to ITU-R BS.1770 in
+/// "Decibels relative to full scale, true peak" (dBTP).
+///
+/// In contrast to the sample peak level the true peak level
+/// also includes peaks between samples that are estimated
+/// by oversampling. Values above 0 dBTP indicate clipping.
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct TruePeakDbtp(pub DbtpValue);
+
+impl TruePeakDbtp {
+    pub const fn unit_of_measure() -> &'static str {
+        "dBTP"
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TruePeakDbtpInvalidity {
+    OutOfRange,
+}
+
+impl Validate for TruePeakDbtp {
+    type Invalidity = TruePeakDbtpInvalidity;
+
+    fn validate(&self) -> ValidationResult<Self::Invalidity> {
+        ValidationContext::new()
+            .invalidate_if(!self.0.is_finite(), TruePeakDbtpInvalidity::OutOfRange)
+            .into()
+    }
+}
+
+impl fmt::Display for TruePeakDbtp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.0, Self::unit_of_measure())
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////
 // Tests
 ///////////////////////////////////////////////////////////////////////
@@ -67,3 +67,51 @@ fn duration_to_string() {
         .to_string()
         .ends_with(DurationMs::unit_of_measure()));
 }
+
+#[test]
+fn validate_audio_range() {
+    assert!(AudioRange {
+        start: PositionMs(0.0),
+        end: None,
+    }
+    .validate()
+    .is_ok());
+    assert!(AudioRange {
+        start: PositionMs(1000.0),
+        end: Some(PositionMs(2000.0)),
+    }
+    .validate()
+    .is_ok());
+    assert!(AudioRange {
+        start: PositionMs(-1.0),
+        end: None,
+    }
+    .validate()
+    .is_err());
+    assert!(AudioRange {
+        start: PositionMs(1000.0),
+        end: Some(PositionMs(1000.0)),
+    }
+    .validate()
+    .is_err());
+}
+
+#[test]
+fn audio_range_duration() {
+    assert_eq!(
+        None,
+        AudioRange {
+            start: PositionMs(1000.0),
+            end: None,
+        }
+        .duration()
+    );
+    assert_eq!(
+        Some(DurationMs(1500.0)),
+        AudioRange {
+            start: PositionMs(1000.0),
+            end: Some(PositionMs(2500.0)),
+        }
+        .duration()
+    );
+}
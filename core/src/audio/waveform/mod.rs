@@ -0,0 +1,109 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+/// The number of bins of a waveform overview
+pub const WAVEFORM_OVERVIEW_BIN_COUNT: usize = 256;
+
+/// The amplitudes of a section of an audio signal
+///
+/// All amplitudes are linear and scaled from 0.0..=1.0 to 0..=255.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WaveformBin {
+    /// Peak amplitude of all channels
+    pub peak: u8,
+
+    /// RMS amplitude of the whole spectrum
+    pub rms: u8,
+
+    /// RMS amplitude of the low frequencies (bass), i.e. below 250 Hz
+    pub low: u8,
+
+    /// RMS amplitude of the mid frequencies between 250 Hz and 4 kHz
+    pub mid: u8,
+
+    /// RMS amplitude of the high frequencies (treble), i.e. above 4 kHz
+    pub high: u8,
+}
+
+impl WaveformBin {
+    const ENCODED_LEN: usize = 5;
+}
+
+/// A compact summary of the waveform of an audio signal
+///
+/// The signal is divided into bins of equal length for rendering
+/// an overview of the whole track without decoding it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WaveformOverview(Vec<WaveformBin>);
+
+impl WaveformOverview {
+    pub const fn new(bins: Vec<WaveformBin>) -> Self {
+        Self(bins)
+    }
+
+    pub fn bins(&self) -> &[WaveformBin] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Encode all bins as consecutive bytes in the order
+    /// `peak`, `rms`, `low`, `mid`, `high`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0
+            .iter()
+            .flat_map(|bin| {
+                let WaveformBin {
+                    peak,
+                    rms,
+                    low,
+                    mid,
+                    high,
+                } = *bin;
+                vec![peak, rms, low, mid, high]
+            })
+            .collect()
+    }
+
+    /// Decode all bins from consecutive bytes
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let chunks = bytes.chunks_exact(WaveformBin::ENCODED_LEN);
+        if !chunks.remainder().is_empty() {
+            return None;
+        }
+        Some(Self(
+            chunks
+                .map(|chunk| WaveformBin {
+                    peak: chunk[0],
+                    rms: chunk[1],
+                    low: chunk[2],
+                    mid: chunk[3],
+                    high: chunk[4],
+                })
+                .collect(),
+        ))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
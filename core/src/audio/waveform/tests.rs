@@ -0,0 +1,47 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+#[test]
+fn bytes_roundtrip() {
+    let overview = WaveformOverview::new(
+        (0..=255)
+            .map(|i| WaveformBin {
+                peak: i,
+                rms: i / 2,
+                low: i / 3,
+                mid: i / 4,
+                high: 255 - i,
+            })
+            .collect(),
+    );
+    let bytes = overview.to_bytes();
+    assert_eq!(256 * 5, bytes.len());
+    assert_eq!(&[2, 1, 0, 0, 253], &bytes[10..15]);
+    assert_eq!(Some(overview), WaveformOverview::from_bytes(&bytes));
+}
+
+#[test]
+fn decode_incomplete_bytes() {
+    assert_eq!(
+        Some(WaveformOverview::default()),
+        WaveformOverview::from_bytes(&[])
+    );
+    assert_eq!(None, WaveformOverview::from_bytes(&[1, 2, 3, 4]));
+    assert_eq!(None, WaveformOverview::from_bytes(&[1, 2, 3, 4, 5, 6]));
+}
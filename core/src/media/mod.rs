@@ -14,7 +14,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::{
-    audio::{AudioContent, AudioContentInvalidity},
+    audio::{AudioContent, AudioContentInvalidity, AudioRange},
     prelude::*,
 };
 
@@ -80,6 +80,66 @@ impl fmt::Display for SourcePath {
     }
 }
 
+/// The prefix of a temporal media fragment
+///
+/// See also: <https://www.w3.org/TR/media-frags/#naming-time>
+const TEMPORAL_FRAGMENT_PREFIX: &str = "t=";
+
+fn format_fragment_secs(millis: f64) -> String {
+    let secs = format!("{:.3}", millis / 1000.0);
+    secs.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+fn is_temporal_fragment(fragment: &str) -> bool {
+    fragment
+        .strip_prefix(TEMPORAL_FRAGMENT_PREFIX)
+        .map(|range| {
+            !range.is_empty()
+                && range
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+        })
+        .unwrap_or(false)
+}
+
+impl SourcePath {
+    /// The path of a virtual track within a file
+    ///
+    /// Virtual tracks that share a single file are distinguished by
+    /// a temporal media fragment with their range in seconds, e.g.
+    /// `album.flac#t=312.5,640.25`.
+    pub fn virtual_track(file_path: &str, range: &AudioRange) -> Self {
+        let mut path = format!(
+            "{}#{}{}",
+            file_path,
+            TEMPORAL_FRAGMENT_PREFIX,
+            format_fragment_secs(range.start.0)
+        );
+        if let Some(end) = range.end {
+            path.push(',');
+            path.push_str(&format_fragment_secs(end.0));
+        }
+        path.into()
+    }
+
+    /// Split the path of a virtual track into the path of the file
+    /// and the temporal media fragment
+    ///
+    /// Other paths are returned unmodified without a fragment.
+    pub fn split_virtual_track(path: &str) -> (&str, Option<&str>) {
+        if let Some((file_path, fragment)) = path.rsplit_once('#') {
+            if is_temporal_fragment(fragment) {
+                return (file_path, Some(fragment));
+            }
+        }
+        (path, None)
+    }
+
+    pub fn is_virtual_track(path: &str) -> bool {
+        Self::split_virtual_track(path).1.is_some()
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, FromPrimitive, ToPrimitive)]
 pub enum SourcePathKind {
     /// Percent-encoded URI (case-sensitive)
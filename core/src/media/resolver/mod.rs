@@ -129,7 +129,10 @@ impl VirtualFilePathResolver {
         }
     }
 
+    /// Build the path of the file, i.e. without the fragment
+    /// of a virtual track
     pub fn build_file_path(&self, slash_path: &str) -> PathBuf {
+        let (slash_path, _) = SourcePath::split_virtual_track(slash_path);
         if let Some(base_file_path) = &self.base_file_path {
             let mut path_buf =
                 PathBuf::with_capacity(base_file_path.as_os_str().len() + slash_path.len());
@@ -155,29 +158,41 @@ impl SourcePathResolver for VirtualFilePathResolver {
         } else if url.scheme() != FILE_URL_SCHEME {
             return Err(ResolveFromUrlError::InvalidUrl);
         }
-        match url.to_file_path() {
+        let slash_path = match url.to_file_path() {
             Ok(file_path) => {
-                if file_path.is_absolute() {
-                    if let Some(slash_path) = file_path.to_slash() {
-                        if let Some(base_slash_path) = &self.base_slash_path {
-                            let stripped_path = slash_path.strip_prefix(base_slash_path);
-                            if let Some(stripped_path) = stripped_path {
-                                return Ok(stripped_path.to_owned().into());
-                            }
-                        } else {
-                            return Ok(slash_path.into());
-                        }
-                    }
+                if !file_path.is_absolute() {
+                    return Err(ResolveFromUrlError::InvalidUrl);
                 }
-                Err(ResolveFromUrlError::InvalidUrl)
+                let slash_path = file_path
+                    .to_slash()
+                    .ok_or(ResolveFromUrlError::InvalidUrl)?;
+                if let Some(base_slash_path) = &self.base_slash_path {
+                    slash_path
+                        .strip_prefix(base_slash_path)
+                        .ok_or(ResolveFromUrlError::InvalidUrl)?
+                        .to_owned()
+                } else {
+                    slash_path
+                }
+            }
+            Err(()) => return Err(ResolveFromUrlError::InvalidUrl),
+        };
+        // Preserve the temporal fragment of virtual tracks
+        if let Some(fragment) = url.fragment() {
+            let path = format!("{}#{}", slash_path, fragment);
+            if SourcePath::is_virtual_track(&path) {
+                return Ok(path.into());
             }
-            Err(()) => Err(ResolveFromUrlError::InvalidUrl),
         }
+        Ok(slash_path.into())
     }
 
     fn resolve_url_from_path(&self, slash_path: &str) -> Result<Url, ResolveFromPathError> {
-        Url::from_file_path(self.build_file_path(slash_path))
-            .map_err(|()| ResolveFromPathError::InvalidPath)
+        let mut url = Url::from_file_path(self.build_file_path(slash_path))
+            .map_err(|()| ResolveFromPathError::InvalidPath)?;
+        let (_, fragment) = SourcePath::split_virtual_track(slash_path);
+        url.set_fragment(fragment);
+        Ok(url)
     }
 }
 
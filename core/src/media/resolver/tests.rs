@@ -91,3 +91,32 @@ fn invalid_local_file_base_url() -> Result<(), ResolveFromPathError> {
     ));
     Ok(())
 }
+
+#[test]
+fn resolve_url_from_virtual_track_path_roundtrip() -> Result<(), ResolveFromPathError> {
+    let base_url = Url::parse("file:///Test%20path/").unwrap();
+    let resolver = VirtualFilePathResolver::with_base_url(base_url);
+    let slash_path = SourcePath::from("Album/Mix #1.flac#t=312.5,640.25".to_owned());
+    let url = resolver.resolve_url_from_path(&slash_path)?;
+    assert_eq!(
+        Url::parse("file:///Test%20path/Album/Mix%20%231.flac#t=312.5,640.25").unwrap(),
+        url
+    );
+    assert_eq!(
+        PathBuf::from("/Test path/Album/Mix #1.flac"),
+        resolver.build_file_path(&slash_path)
+    );
+    assert_eq!(slash_path, resolver.resolve_path_from_url(&url).unwrap());
+    Ok(())
+}
+
+#[test]
+fn resolve_path_from_url_ignores_other_fragments() {
+    let url = Url::parse("file:///Test%20path/file.mp3#chapter1").unwrap();
+    assert_eq!(
+        SourcePath::from("/Test path/file.mp3".to_owned()),
+        VirtualFilePathResolver::default()
+            .resolve_path_from_url(&url)
+            .unwrap()
+    );
+}
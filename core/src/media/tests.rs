@@ -111,3 +111,42 @@ fn given_locked_update() {
         }
     }
 }
+
+#[test]
+fn virtual_track_path() {
+    use crate::audio::PositionMs;
+
+    let path = SourcePath::virtual_track(
+        "Album/Mix #1.flac",
+        &AudioRange {
+            start: PositionMs(312_500.0),
+            end: Some(PositionMs(640_250.0)),
+        },
+    );
+    assert_eq!("Album/Mix #1.flac#t=312.5,640.25", path.as_str());
+    assert_eq!(
+        ("Album/Mix #1.flac", Some("t=312.5,640.25")),
+        SourcePath::split_virtual_track(&path)
+    );
+    let path = SourcePath::virtual_track(
+        "Album/Mix.flac",
+        &AudioRange {
+            start: PositionMs(0.0),
+            end: None,
+        },
+    );
+    assert_eq!("Album/Mix.flac#t=0", path.as_str());
+}
+
+#[test]
+fn split_virtual_track_without_fragment() {
+    assert_eq!(
+        ("Album/Mix #1.flac", None),
+        SourcePath::split_virtual_track("Album/Mix #1.flac")
+    );
+    assert_eq!(
+        ("Album/Mix#t=1.flac", None),
+        SourcePath::split_virtual_track("Album/Mix#t=1.flac")
+    );
+    assert!(!SourcePath::is_virtual_track("next#*path/file.mp3"));
+}
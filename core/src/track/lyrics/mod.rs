@@ -0,0 +1,134 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use std::cmp::Ordering;
+
+use crate::{
+    audio::{PositionMs, PositionMsInvalidity},
+    prelude::*,
+};
+
+/// A single line of time-synchronized lyrics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncedLine {
+    /// The position when this line starts
+    pub position: PositionMs,
+
+    pub text: String,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SyncedLineInvalidity {
+    Position(PositionMsInvalidity),
+    PositionNegative,
+}
+
+impl Validate for SyncedLine {
+    type Invalidity = SyncedLineInvalidity;
+
+    fn validate(&self) -> ValidationResult<Self::Invalidity> {
+        ValidationContext::new()
+            .validate_with(&self.position, SyncedLineInvalidity::Position)
+            .invalidate_if(
+                self.position.0 < 0.0,
+                SyncedLineInvalidity::PositionNegative,
+            )
+            .into()
+    }
+}
+
+/// The lyrics of a track in a single language.
+///
+/// Lyrics may consist of an unsynchronized plain text and/or
+/// a list of time-synchronized lines, e.g. imported from an
+/// ID3v2 USLT and SYLT frame with the same language.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lyrics {
+    /// An ISO 639-2 language code, e.g. "eng" or "deu"
+    pub language: Option<String>,
+
+    /// The unsynchronized plain text
+    pub text: Option<String>,
+
+    /// Time-synchronized lines, ordered by position
+    pub lines: Vec<SyncedLine>,
+}
+
+impl Lyrics {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none() && self.lines.is_empty()
+    }
+}
+
+impl CanonicalOrd for Lyrics {
+    fn canonical_cmp(&self, other: &Self) -> Ordering {
+        self.language.cmp(&other.language)
+    }
+}
+
+impl IsCanonical for Lyrics {
+    fn is_canonical(&self) -> bool {
+        self.lines
+            .windows(2)
+            .all(|pair| pair[0].position <= pair[1].position)
+    }
+}
+
+impl Canonicalize for Lyrics {
+    fn canonicalize(&mut self) {
+        self.lines.sort_by(|lhs, rhs| {
+            lhs.position
+                .partial_cmp(&rhs.position)
+                .unwrap_or(Ordering::Equal)
+        });
+        debug_assert!(self.is_canonical());
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LyricsInvalidity {
+    LanguageEmpty,
+    TextEmpty,
+    Empty,
+    Line(SyncedLineInvalidity),
+}
+
+impl Validate for Lyrics {
+    type Invalidity = LyricsInvalidity;
+
+    fn validate(&self) -> ValidationResult<Self::Invalidity> {
+        let mut context =
+            ValidationContext::new().invalidate_if(self.is_empty(), LyricsInvalidity::Empty);
+        if let Some(ref language) = self.language {
+            context =
+                context.invalidate_if(language.trim().is_empty(), LyricsInvalidity::LanguageEmpty);
+        }
+        if let Some(ref text) = self.text {
+            context = context.invalidate_if(text.trim().is_empty(), LyricsInvalidity::TextEmpty);
+        }
+        self.lines
+            .iter()
+            .fold(context, |context, line| {
+                context.validate_with(line, LyricsInvalidity::Line)
+            })
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests;
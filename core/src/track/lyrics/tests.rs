@@ -0,0 +1,73 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+#[test]
+fn canonicalize_lines_by_position() {
+    let mut lyrics = Lyrics {
+        language: Some("eng".into()),
+        text: None,
+        lines: vec![
+            SyncedLine {
+                position: PositionMs(2000.0),
+                text: "second".into(),
+            },
+            SyncedLine {
+                position: PositionMs(1000.0),
+                text: "first".into(),
+            },
+        ],
+    };
+    assert!(!lyrics.is_canonical());
+    lyrics.canonicalize();
+    assert!(lyrics.is_canonical());
+    assert_eq!("first", lyrics.lines[0].text);
+    assert_eq!("second", lyrics.lines[1].text);
+}
+
+#[test]
+fn validate() {
+    assert!(Lyrics::default().validate().is_err());
+    assert!(Lyrics {
+        language: Some(" ".into()),
+        text: Some("text".into()),
+        lines: vec![],
+    }
+    .validate()
+    .is_err());
+    assert!(Lyrics {
+        language: None,
+        text: None,
+        lines: vec![SyncedLine {
+            position: PositionMs(-1.0),
+            text: "line".into(),
+        }],
+    }
+    .validate()
+    .is_err());
+    assert!(Lyrics {
+        language: Some("eng".into()),
+        text: Some("text".into()),
+        lines: vec![SyncedLine {
+            position: PositionMs(0.0),
+            text: "line".into(),
+        }],
+    }
+    .validate()
+    .is_ok());
+}
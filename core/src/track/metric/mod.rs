@@ -25,9 +25,15 @@ use bitflags::bitflags;
 
 bitflags! {
     pub struct MetricsFlags: u8 {
-        const TEMPO_BPM_LOCKED      = 0b00000001;
-        const KEY_SIGNATURE_LOCKED  = 0b00000010;
-        const TIME_SIGNATURE_LOCKED = 0b00000100;
+        const TEMPO_BPM_LOCKED       = 0b00000001;
+        const KEY_SIGNATURE_LOCKED   = 0b00000010;
+        const TIME_SIGNATURE_LOCKED  = 0b00000100;
+        // The tempo has been estimated by analyzing the audio stream
+        // instead of being read from a file tag
+        const TEMPO_BPM_ANALYZED     = 0b00001000;
+        // The key signature has been detected by analyzing the audio
+        // stream instead of being read from a file tag
+        const KEY_SIGNATURE_ANALYZED = 0b00010000;
     }
 }
 
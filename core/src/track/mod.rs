@@ -19,12 +19,13 @@ pub mod actor;
 pub mod album;
 pub mod cue;
 pub mod index;
+pub mod lyrics;
 pub mod metric;
 pub mod release;
 pub mod tag;
 pub mod title;
 
-use self::{actor::*, album::*, cue::*, index::*, metric::*, release::*, title::*};
+use self::{actor::*, album::*, cue::*, index::*, lyrics::*, metric::*, release::*, title::*};
 
 use crate::{media::*, prelude::*, tag::*};
 
@@ -50,6 +51,8 @@ pub struct Track {
 
     pub cues: Canonical<Vec<Cue>>,
 
+    pub lyrics: Canonical<Vec<Lyrics>>,
+
     pub play_counter: PlayCounter,
 }
 
@@ -66,6 +69,7 @@ impl Track {
             color: Default::default(),
             metrics: Default::default(),
             cues: Default::default(),
+            lyrics: Default::default(),
             play_counter: Default::default(),
         }
     }
@@ -137,6 +141,7 @@ impl Track {
             color,
             cues,
             indexes,
+            lyrics,
             media_source,
             metrics,
             play_counter,
@@ -150,6 +155,7 @@ impl Track {
             color: newer_color,
             cues: newer_cues,
             indexes: newer_indexes,
+            lyrics: newer_lyrics,
             media_source: mut newer_media_source,
             metrics: newer_metrics,
             play_counter: newer_play_counter,
@@ -161,6 +167,21 @@ impl Track {
         newer_media_source.collected_at = newer_media_source
             .collected_at
             .min(media_source.collected_at);
+        // Preserve analyzed properties that are not provided by the newer source
+        let Content::Audio(audio_content) = &mut media_source.content;
+        let Content::Audio(newer_audio_content) = &mut newer_media_source.content;
+        if newer_audio_content.loudness.is_none() {
+            newer_audio_content.loudness = audio_content.loudness.take();
+        }
+        if newer_audio_content.loudness_range.is_none() {
+            newer_audio_content.loudness_range = audio_content.loudness_range.take();
+        }
+        if newer_audio_content.true_peak.is_none() {
+            newer_audio_content.true_peak = audio_content.true_peak.take();
+        }
+        if newer_audio_content.waveform.is_none() {
+            newer_audio_content.waveform = audio_content.waveform.take();
+        }
         *media_source = newer_media_source;
         // Do not replace existing data with empty data
         if !newer_actors.is_empty() {
@@ -169,7 +190,7 @@ impl Track {
         if !newer_album.is_default() {
             *album = newer_album;
         }
-        if newer_color.is_none() {
+        if newer_color.is_some() {
             *color = newer_color;
         }
         if !newer_cues.is_empty() {
@@ -178,6 +199,9 @@ impl Track {
         if !newer_indexes.is_default() {
             *indexes = newer_indexes;
         }
+        if !newer_lyrics.is_empty() {
+            *lyrics = newer_lyrics;
+        }
         if !newer_play_counter.is_default() {
             *play_counter = newer_play_counter;
         }
@@ -203,23 +227,60 @@ impl Track {
                 time_signature: newer_time_signature,
                 flags: newer_flags,
             } = newer_metrics;
+            let earlier_flags = *flags;
             *flags = newer_flags
                 & !(MetricsFlags::TEMPO_BPM_LOCKED
+                    | MetricsFlags::TEMPO_BPM_ANALYZED
                     | MetricsFlags::KEY_SIGNATURE_LOCKED
+                    | MetricsFlags::KEY_SIGNATURE_ANALYZED
                     | MetricsFlags::TIME_SIGNATURE_LOCKED);
-            if newer_tempo_bpm.is_some() {
+            // A locked tempo is never replaced by an estimated tempo
+            let keep_tempo_bpm = earlier_flags.contains(MetricsFlags::TEMPO_BPM_LOCKED)
+                && newer_flags.contains(MetricsFlags::TEMPO_BPM_ANALYZED);
+            if newer_tempo_bpm.is_some() && !keep_tempo_bpm {
                 *tempo_bpm = newer_tempo_bpm;
                 flags.set(
                     MetricsFlags::TEMPO_BPM_LOCKED,
                     newer_flags.contains(MetricsFlags::TEMPO_BPM_LOCKED),
                 );
+                flags.set(
+                    MetricsFlags::TEMPO_BPM_ANALYZED,
+                    newer_flags.contains(MetricsFlags::TEMPO_BPM_ANALYZED),
+                );
+            } else {
+                // Keep the flags of the preserved tempo
+                flags.set(
+                    MetricsFlags::TEMPO_BPM_LOCKED,
+                    earlier_flags.contains(MetricsFlags::TEMPO_BPM_LOCKED),
+                );
+                flags.set(
+                    MetricsFlags::TEMPO_BPM_ANALYZED,
+                    earlier_flags.contains(MetricsFlags::TEMPO_BPM_ANALYZED),
+                );
             }
-            if !newer_key_signature.is_default() {
+            // A locked key signature is never replaced by a detected key signature
+            let keep_key_signature = earlier_flags.contains(MetricsFlags::KEY_SIGNATURE_LOCKED)
+                && newer_flags.contains(MetricsFlags::KEY_SIGNATURE_ANALYZED);
+            if !newer_key_signature.is_default() && !keep_key_signature {
                 *key_signature = newer_key_signature;
                 flags.set(
                     MetricsFlags::KEY_SIGNATURE_LOCKED,
                     newer_flags.contains(MetricsFlags::KEY_SIGNATURE_LOCKED),
                 );
+                flags.set(
+                    MetricsFlags::KEY_SIGNATURE_ANALYZED,
+                    newer_flags.contains(MetricsFlags::KEY_SIGNATURE_ANALYZED),
+                );
+            } else {
+                // Keep the flags of the preserved key signature
+                flags.set(
+                    MetricsFlags::KEY_SIGNATURE_LOCKED,
+                    earlier_flags.contains(MetricsFlags::KEY_SIGNATURE_LOCKED),
+                );
+                flags.set(
+                    MetricsFlags::KEY_SIGNATURE_ANALYZED,
+                    earlier_flags.contains(MetricsFlags::KEY_SIGNATURE_ANALYZED),
+                );
             }
             if !newer_time_signature.is_default() {
                 *time_signature = newer_time_signature;
@@ -244,6 +305,7 @@ pub enum TrackInvalidity {
     Color(ColorInvalidity),
     Metrics(MetricsInvalidity),
     Cue(CueInvalidity),
+    Lyrics(LyricsInvalidity),
 }
 
 impl Validate for Track {
@@ -274,6 +336,14 @@ impl Validate for Track {
                     })
                     .into(),
             )
+            .merge_result(
+                self.lyrics
+                    .iter()
+                    .fold(ValidationContext::new(), |context, next| {
+                        context.validate_with(next, Self::Invalidity::Lyrics)
+                    })
+                    .into(),
+            )
             .into()
     }
 }
@@ -297,3 +367,10 @@ pub struct PlayCounter {
     pub last_played_at: Option<DateTime>,
     pub times_played: Option<PlayCount>,
 }
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -67,4 +67,8 @@ lazy_static! {
     pub static ref FACET_POPULARITY: Label = Label::new("popularity".into());
     pub static ref FACET_SPEECHINESS: Label = Label::new("speechiness".into());
     pub static ref FACET_VALENCE: Label = Label::new("valence".into()); // a measure for happiness
+
+    // Personal rating, e.g. imported from DJ software. The score
+    // is the number of stars divided by the maximum number of stars.
+    pub static ref LABEL_RATING: Label = Label::new("rating".into());
 }
@@ -0,0 +1,83 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::{
+    audio::{
+        signal::LoudnessLufs,
+        waveform::{WaveformBin, WaveformOverview},
+        AudioContent,
+    },
+    util::clock::DateTime,
+};
+
+fn new_track(audio_content: AudioContent) -> Track {
+    Track::new_from_media_source(Source {
+        collected_at: DateTime::new_timestamp_millis(0),
+        synchronized_at: None,
+        path: SourcePath::new("file:///music/track.mp3".to_owned()),
+        content_type: "audio/mpeg".to_owned(),
+        content_digest: None,
+        content_metadata_flags: Default::default(),
+        content: audio_content.into(),
+        artwork: Default::default(),
+    })
+}
+
+#[test]
+fn merge_newer_from_synchronized_media_source_preserves_analyzed_audio_content() {
+    let waveform = WaveformOverview::new(vec![WaveformBin::default()]);
+    let mut track = new_track(AudioContent {
+        loudness: Some(LoudnessLufs(-8.0)),
+        waveform: Some(waveform.clone()),
+        ..Default::default()
+    });
+    let newer = new_track(AudioContent {
+        encoder: Some("LAME".to_owned()),
+        ..Default::default()
+    });
+    track.merge_newer_from_synchronized_media_source(newer);
+    let Content::Audio(audio_content) = &track.media_source.content;
+    assert_eq!(Some(LoudnessLufs(-8.0)), audio_content.loudness);
+    assert_eq!(Some(waveform), audio_content.waveform);
+    assert_eq!(Some("LAME"), audio_content.encoder.as_deref());
+}
+
+#[test]
+fn merge_newer_from_synchronized_media_source_replaces_provided_loudness() {
+    let mut track = new_track(AudioContent {
+        loudness: Some(LoudnessLufs(-8.0)),
+        ..Default::default()
+    });
+    let newer = new_track(AudioContent {
+        loudness: Some(LoudnessLufs(-10.0)),
+        ..Default::default()
+    });
+    track.merge_newer_from_synchronized_media_source(newer);
+    let Content::Audio(audio_content) = &track.media_source.content;
+    assert_eq!(Some(LoudnessLufs(-10.0)), audio_content.loudness);
+}
+
+#[test]
+fn merge_newer_from_synchronized_media_source_preserves_color() {
+    let mut track = new_track(Default::default());
+    track.color = Some(Color::Index(1));
+    let newer = new_track(Default::default());
+    track.merge_newer_from_synchronized_media_source(newer);
+    assert_eq!(Some(Color::Index(1)), track.color);
+}
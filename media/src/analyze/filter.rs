@@ -0,0 +1,131 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use std::f64::consts::PI;
+
+/// Coefficients of a biquad filter with normalized a0 = 1
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Biquad {
+    pub b: [f64; 3],
+    pub a: [f64; 2],
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BiquadState {
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    /// Low pass filter according to the "Audio EQ Cookbook"
+    pub fn low_pass(sample_rate: u32, cutoff_hz: f64, q: f64) -> Self {
+        let (cos_w0, alpha) = cookbook_params(sample_rate, cutoff_hz, q);
+        Self::normalized(
+            [(1.0 - cos_w0) / 2.0, 1.0 - cos_w0, (1.0 - cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    /// High pass filter according to the "Audio EQ Cookbook"
+    pub fn high_pass(sample_rate: u32, cutoff_hz: f64, q: f64) -> Self {
+        let (cos_w0, alpha) = cookbook_params(sample_rate, cutoff_hz, q);
+        Self::normalized(
+            [(1.0 + cos_w0) / 2.0, -(1.0 + cos_w0), (1.0 + cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    /// Band pass filter with a constant peak gain of 0 dB according
+    /// to the "Audio EQ Cookbook"
+    pub fn band_pass(sample_rate: u32, center_hz: f64, q: f64) -> Self {
+        let (cos_w0, alpha) = cookbook_params(sample_rate, center_hz, q);
+        Self::normalized(
+            [alpha, 0.0, -alpha],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    fn normalized(b: [f64; 3], a: [f64; 3]) -> Self {
+        let a0 = a[0];
+        Self {
+            b: [b[0] / a0, b[1] / a0, b[2] / a0],
+            a: [a[1] / a0, a[2] / a0],
+        }
+    }
+
+    pub fn process(&self, state: &mut BiquadState, x0: f64) -> f64 {
+        let y0 = self.b[0] * x0 + self.b[1] * state.x[0] + self.b[2] * state.x[1]
+            - self.a[0] * state.y[0]
+            - self.a[1] * state.y[1];
+        state.x = [x0, state.x[0]];
+        state.y = [y0, state.y[0]];
+        y0
+    }
+}
+
+fn cookbook_params(sample_rate: u32, frequency_hz: f64, q: f64) -> (f64, f64) {
+    // Keep the frequency below the Nyquist frequency
+    let frequency_hz = frequency_hz.min(f64::from(sample_rate) * 0.45);
+    let w0 = 2.0 * PI * frequency_hz / f64::from(sample_rate);
+    (w0.cos(), w0.sin() / (2.0 * q))
+}
+
+/// Reduces the sample rate of a signal by an integer factor
+///
+/// The signal is low pass filtered before decimation to prevent aliasing.
+#[derive(Debug, Clone, Default)]
+pub struct Decimator {
+    anti_aliasing_filter: Biquad,
+    anti_aliasing_states: [BiquadState; 2],
+    factor: u32,
+    pos: u32,
+    sample_rate: f64,
+}
+
+impl Decimator {
+    /// Decimate to the lowest sample rate that is not lower than
+    /// the given minimum sample rate
+    pub fn new(sample_rate: u32, min_sample_rate: u32, cutoff_hz: f64) -> Self {
+        let factor = (sample_rate / min_sample_rate).max(1);
+        Self {
+            anti_aliasing_filter: Biquad::low_pass(sample_rate, cutoff_hz, 0.707),
+            factor,
+            sample_rate: f64::from(sample_rate) / f64::from(factor),
+            ..Default::default()
+        }
+    }
+
+    /// The sample rate of the decimated signal
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Returns the next sample of the decimated signal (if any)
+    pub fn process(&mut self, x: f64) -> Option<f64> {
+        // Two cascaded filter stages
+        let mut y = x;
+        for state in &mut self.anti_aliasing_states {
+            y = self.anti_aliasing_filter.process(state, y);
+        }
+        self.pos += 1;
+        if self.pos < self.factor {
+            return None;
+        }
+        self.pos = 0;
+        Some(y)
+    }
+}
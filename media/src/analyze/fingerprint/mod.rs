@@ -0,0 +1,203 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{
+    filter::{Biquad, BiquadState, Decimator},
+    AudioSink,
+};
+
+use aoide_core::audio::fingerprint::{Fingerprint, SubFingerprint, SUB_FINGERPRINTS_PER_SECOND};
+
+use std::collections::VecDeque;
+
+// The signal is decimated to a sample rate of at least 5 kHz
+// that covers all frequency bands
+const MIN_DECIMATED_SAMPLE_RATE: u32 = 5_000;
+
+// Cutoff frequency of the anti-aliasing filter before decimation
+const DECIMATION_CUTOFF_HZ: f64 = 2_200.0;
+
+// The energy differences of adjacent bands result in 32 bits
+const BAND_COUNT: usize = 33;
+
+const MIN_BAND_HZ: f64 = 300.0;
+
+const MAX_BAND_HZ: f64 = 2_000.0;
+
+// The energy of each frame is accumulated over multiple overlapping
+// sub-blocks, one sub-block per sub-fingerprint
+const FRAME_SUB_BLOCKS: usize = 6;
+
+// Only the beginning of a track is considered
+const MAX_SECS: u32 = 60;
+
+// Tracks that are shorter don't result in a significant fingerprint
+const MIN_SECS: u32 = 5;
+
+/// Calculates an acoustic fingerprint of a decoded audio signal
+///
+/// The energy of the signal is measured in logarithmically spaced
+/// frequency bands. Each bit of a sub-fingerprint encodes the sign
+/// of the energy difference between adjacent bands compared to the
+/// previous frame, which is robust against changes of the overall
+/// level and lossy encoding.
+#[derive(Debug, Default)]
+pub struct FingerprintAnalyzer {
+    channel_count: usize,
+    decimator: Decimator,
+    band_filters: Vec<(Biquad, BiquadState)>,
+    sub_block_len: usize,
+    sub_block_pos: usize,
+    max_sub_blocks: usize,
+    sub_block_count: usize,
+    // Energy of all bands within the current sub-block
+    sub_block_energies: Vec<f64>,
+    // Energy of all bands within the most recent sub-blocks
+    recent_sub_block_energies: VecDeque<Vec<f64>>,
+    // Differences between the energies of adjacent bands in the previous frame
+    last_band_differences: Option<Vec<f64>>,
+    sub_fingerprints: Vec<SubFingerprint>,
+}
+
+fn band_edge_hz(band_index: usize) -> f64 {
+    MIN_BAND_HZ * (MAX_BAND_HZ / MIN_BAND_HZ).powf(band_index as f64 / BAND_COUNT as f64)
+}
+
+impl FingerprintAnalyzer {
+    fn finish_sub_block(&mut self) {
+        self.sub_block_pos = 0;
+        self.sub_block_count += 1;
+        if self.recent_sub_block_energies.len() == FRAME_SUB_BLOCKS {
+            self.recent_sub_block_energies.pop_front();
+        }
+        self.recent_sub_block_energies.push_back(std::mem::replace(
+            &mut self.sub_block_energies,
+            vec![0.0; BAND_COUNT],
+        ));
+        if self.recent_sub_block_energies.len() < FRAME_SUB_BLOCKS {
+            return;
+        }
+        let frame_energies: Vec<f64> = (0..BAND_COUNT)
+            .map(|band_index| {
+                self.recent_sub_block_energies
+                    .iter()
+                    .map(|energies| energies[band_index])
+                    .sum()
+            })
+            .collect();
+        let band_differences: Vec<f64> = frame_energies
+            .windows(2)
+            .map(|adjacent| adjacent[0] - adjacent[1])
+            .collect();
+        if let Some(last_band_differences) = &self.last_band_differences {
+            let sub_fingerprint = band_differences
+                .iter()
+                .zip(last_band_differences)
+                .enumerate()
+                .fold(
+                    0,
+                    |sub_fingerprint, (bit, (difference, last_difference))| {
+                        if difference - last_difference > 0.0 {
+                            sub_fingerprint | (1 << bit)
+                        } else {
+                            sub_fingerprint
+                        }
+                    },
+                );
+            self.sub_fingerprints.push(sub_fingerprint);
+        }
+        self.last_band_differences = Some(band_differences);
+    }
+
+    /// Finish the calculation after all samples have been received
+    ///
+    /// Returns `None` if the signal is too short.
+    pub fn finish(self) -> Option<Fingerprint> {
+        if self.sub_fingerprints.len() < (MIN_SECS * SUB_FINGERPRINTS_PER_SECOND) as usize {
+            return None;
+        }
+        Some(Fingerprint::new(self.sub_fingerprints))
+    }
+}
+
+impl AudioSink for FingerprintAnalyzer {
+    fn open(&mut self, channel_count: u16, sample_rate: u32) {
+        let decimator =
+            Decimator::new(sample_rate, MIN_DECIMATED_SAMPLE_RATE, DECIMATION_CUTOFF_HZ);
+        let decimated_sample_rate = decimator.sample_rate().round() as u32;
+        let band_filters = (0..BAND_COUNT)
+            .map(|band_index| {
+                let lower_hz = band_edge_hz(band_index);
+                let upper_hz = band_edge_hz(band_index + 1);
+                let center_hz = (lower_hz * upper_hz).sqrt();
+                let filter = Biquad::band_pass(
+                    decimated_sample_rate,
+                    center_hz,
+                    center_hz / (upper_hz - lower_hz),
+                );
+                (filter, BiquadState::default())
+            })
+            .collect();
+        *self = Self {
+            channel_count: usize::from(channel_count),
+            sub_block_len: (decimator.sample_rate() / f64::from(SUB_FINGERPRINTS_PER_SECOND))
+                .round() as usize,
+            max_sub_blocks: (MAX_SECS * SUB_FINGERPRINTS_PER_SECOND) as usize + FRAME_SUB_BLOCKS,
+            decimator,
+            band_filters,
+            sub_block_energies: vec![0.0; BAND_COUNT],
+            ..Default::default()
+        };
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        if self.channel_count == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channel_count) {
+            if self.sub_block_count >= self.max_sub_blocks {
+                return;
+            }
+            // Downmix to mono
+            let sample =
+                frame.iter().copied().map(f64::from).sum::<f64>() / self.channel_count as f64;
+            let sample = match self.decimator.process(sample) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            for ((filter, state), energy) in self
+                .band_filters
+                .iter_mut()
+                .zip(self.sub_block_energies.iter_mut())
+            {
+                let filtered = filter.process(state, sample);
+                *energy += filtered * filtered;
+            }
+            self.sub_block_pos += 1;
+            if self.sub_block_pos == self.sub_block_len {
+                self.finish_sub_block();
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
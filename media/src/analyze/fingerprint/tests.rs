@@ -0,0 +1,111 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use std::f64::consts::PI;
+
+const SAMPLE_RATE: u32 = 44_100;
+
+/// Deterministic pseudo-random numbers from a linear congruential generator
+struct PseudoRandom(u32);
+
+impl PseudoRandom {
+    fn next(&mut self) -> u32 {
+        self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        self.0
+    }
+
+    fn next_unit(&mut self) -> f64 {
+        f64::from(self.next()) / f64::from(u32::MAX) - 0.5
+    }
+}
+
+/// A melody of random notes with a duration of 250 ms each
+fn random_melody(seed: u32, secs: u32) -> Vec<f64> {
+    let mut random = PseudoRandom(seed);
+    let note_len = SAMPLE_RATE / 4;
+    (0..secs * 4)
+        .flat_map(|_| {
+            // MIDI notes 48 - 83
+            let midi_note = 48 + random.next() % 36;
+            let frequency = 440.0 * 2f64.powf((f64::from(midi_note) - 69.0) / 12.0);
+            (0..note_len).map(move |i| {
+                let t = f64::from(i) / f64::from(SAMPLE_RATE);
+                // Decaying amplitude
+                let amplitude = 0.5 * (1.0 - f64::from(i) / f64::from(note_len));
+                amplitude * (2.0 * PI * frequency * t).sin()
+            })
+        })
+        .collect()
+}
+
+fn fingerprint(channel_count: u16, signal: &[f64]) -> Option<Fingerprint> {
+    let samples: Vec<_> = signal
+        .iter()
+        .flat_map(|&sample| vec![sample as f32; channel_count.into()])
+        .collect();
+    let mut analyzer = FingerprintAnalyzer::default();
+    analyzer.open(channel_count, SAMPLE_RATE);
+    // Feed the samples in arbitrary chunks
+    for chunk in samples.chunks(channel_count as usize * 1000) {
+        analyzer.write(chunk);
+    }
+    analyzer.finish()
+}
+
+#[test]
+fn similar_signals() {
+    let signal = random_melody(1, 30);
+    let original = fingerprint(2, &signal).unwrap();
+    assert_eq!(Some(1.0), original.similarity(&original));
+    // Attenuated and distorted by noise with leading silence
+    let mut noise = PseudoRandom(1);
+    let distorted_signal: Vec<_> = vec![0.0; SAMPLE_RATE as usize / 30]
+        .into_iter()
+        .chain(
+            signal
+                .iter()
+                .map(|sample| 0.5 * sample + 0.01 * noise.next_unit()),
+        )
+        .collect();
+    let distorted = fingerprint(1, &distorted_signal).unwrap();
+    let similarity = original.similarity(&distorted).unwrap();
+    assert!(similarity > 0.85, "{}", similarity);
+}
+
+#[test]
+fn different_signals() {
+    let lhs = fingerprint(2, &random_melody(1, 30)).unwrap();
+    let rhs = fingerprint(2, &random_melody(2, 30)).unwrap();
+    let similarity = lhs.similarity(&rhs).unwrap();
+    assert!(similarity < 0.65, "{}", similarity);
+}
+
+#[test]
+fn limited_duration() {
+    let fingerprint = fingerprint(1, &random_melody(1, MAX_SECS + 10)).unwrap();
+    assert_eq!(
+        (MAX_SECS * SUB_FINGERPRINTS_PER_SECOND) as usize,
+        fingerprint.sub_fingerprints().len()
+    );
+}
+
+#[test]
+fn short_signal_has_no_fingerprint() {
+    assert!(fingerprint(1, &random_melody(1, MIN_SECS - 1)).is_none());
+}
@@ -0,0 +1,240 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{filter::Decimator, AudioSink};
+
+use aoide_core::music::key::{KeyCode, KeySignature};
+
+use std::f64::consts::PI;
+
+// The signal is decimated to a sample rate of at least 5 kHz
+// that covers the fundamental frequencies of all analyzed pitches
+const MIN_DECIMATED_SAMPLE_RATE: u32 = 5_000;
+
+// Cutoff frequency of the anti-aliasing filter before decimation
+const DECIMATION_CUTOFF_HZ: f64 = 2_200.0;
+
+// Blocks of 500 ms provide a frequency resolution below one semitone
+// for the lowest analyzed pitch
+const BLOCKS_PER_SECOND: u32 = 2;
+
+// MIDI note numbers of the analyzed pitches: C2 - B6
+const MIN_MIDI_NOTE: u8 = 36;
+
+const MAX_MIDI_NOTE: u8 = 95;
+
+const PITCH_CLASS_COUNT: usize = 12;
+
+// Blocks with a lower RMS level are considered as silence
+const MIN_BLOCK_RMS: f64 = 1e-4;
+
+/// Krumhansl-Kessler profile of major keys, starting with the tonic
+const MAJOR_PROFILE: [f64; PITCH_CLASS_COUNT] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+
+/// Krumhansl-Kessler profile of minor keys, starting with the tonic
+const MINOR_PROFILE: [f64; PITCH_CLASS_COUNT] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// Major keys by the pitch class of their tonic, starting with C
+const MAJOR_KEYS: [KeyCode; PITCH_CLASS_COUNT] = [
+    KeyCode::Cmaj,
+    KeyCode::Dbmaj,
+    KeyCode::Dmaj,
+    KeyCode::Ebmaj,
+    KeyCode::Emaj,
+    KeyCode::Fmaj,
+    KeyCode::Gbmaj,
+    KeyCode::Gmaj,
+    KeyCode::Abmaj,
+    KeyCode::Amaj,
+    KeyCode::Bbmaj,
+    KeyCode::Bmaj,
+];
+
+/// Minor keys by the pitch class of their tonic, starting with C
+const MINOR_KEYS: [KeyCode; PITCH_CLASS_COUNT] = [
+    KeyCode::Cmin,
+    KeyCode::Dbmin,
+    KeyCode::Dmin,
+    KeyCode::Ebmin,
+    KeyCode::Emin,
+    KeyCode::Fmin,
+    KeyCode::Gbmin,
+    KeyCode::Gmin,
+    KeyCode::Abmin,
+    KeyCode::Amin,
+    KeyCode::Bbmin,
+    KeyCode::Bmin,
+];
+
+fn midi_note_frequency(midi_note: u8) -> f64 {
+    // Standard tuning with A4 = 440 Hz
+    440.0 * 2f64.powf((f64::from(midi_note) - 69.0) / 12.0)
+}
+
+/// Power of a single frequency within a block of samples
+fn goertzel_power(samples: &[f64], sample_rate: f64, frequency: f64) -> f64 {
+    let coeff = 2.0 * (2.0 * PI * frequency / sample_rate).cos();
+    let (s1, s2) = samples
+        .iter()
+        .fold((0.0, 0.0), |(s1, s2), x| (x + coeff * s1 - s2, s1));
+    s1 * s1 + s2 * s2 - coeff * s1 * s2
+}
+
+/// Pearson correlation coefficient of the chroma vector and a key profile
+/// that is rotated to the given tonic
+fn correlation(
+    chroma: &[f64; PITCH_CLASS_COUNT],
+    profile: &[f64; PITCH_CLASS_COUNT],
+    tonic: usize,
+) -> f64 {
+    let chroma_mean = chroma.iter().sum::<f64>() / PITCH_CLASS_COUNT as f64;
+    let profile_mean = profile.iter().sum::<f64>() / PITCH_CLASS_COUNT as f64;
+    let (mut covariance, mut chroma_variance, mut profile_variance) = (0.0, 0.0, 0.0);
+    for (pitch_class, chroma_value) in chroma.iter().enumerate() {
+        let x = chroma_value - chroma_mean;
+        let y =
+            profile[(pitch_class + PITCH_CLASS_COUNT - tonic) % PITCH_CLASS_COUNT] - profile_mean;
+        covariance += x * y;
+        chroma_variance += x * x;
+        profile_variance += y * y;
+    }
+    covariance / (chroma_variance * profile_variance).sqrt()
+}
+
+/// Detects the musical key of a decoded audio signal
+///
+/// The spectral energy of all pitches within 5 octaves is accumulated
+/// into a chromagram. The key is determined by correlating the chroma
+/// vector with the major and minor key profiles for all 12 tonics.
+#[derive(Debug, Default)]
+pub struct KeyAnalyzer {
+    channel_count: usize,
+    decimator: Decimator,
+    window: Vec<f64>,
+    block: Vec<f64>,
+    chroma: [f64; PITCH_CLASS_COUNT],
+}
+
+impl KeyAnalyzer {
+    fn finish_block(&mut self) {
+        let mean_square = self.block.iter().map(|x| x * x).sum::<f64>() / self.block.len() as f64;
+        if mean_square.sqrt() >= MIN_BLOCK_RMS {
+            let windowed: Vec<_> = self
+                .block
+                .iter()
+                .zip(&self.window)
+                .map(|(x, w)| x * w)
+                .collect();
+            let mut block_chroma = [0.0; PITCH_CLASS_COUNT];
+            for midi_note in MIN_MIDI_NOTE..=MAX_MIDI_NOTE {
+                let power = goertzel_power(
+                    &windowed,
+                    self.decimator.sample_rate(),
+                    midi_note_frequency(midi_note),
+                );
+                block_chroma[usize::from(midi_note) % PITCH_CLASS_COUNT] += power.sqrt();
+            }
+            // Each block contributes equally, independent of its loudness
+            let block_sum: f64 = block_chroma.iter().sum();
+            if block_sum > 0.0 {
+                for (sum, value) in self.chroma.iter_mut().zip(&block_chroma) {
+                    *sum += value / block_sum;
+                }
+            }
+        }
+        self.block.clear();
+    }
+
+    /// Finish the detection after all samples have been received
+    ///
+    /// Returns `None` if the signal contains no tonal content.
+    pub fn finish(self) -> Option<KeySignature> {
+        let Self { chroma, .. } = self;
+        if chroma.iter().sum::<f64>() <= 0.0 {
+            return None;
+        }
+        let (key_code, score) = (0..PITCH_CLASS_COUNT)
+            .flat_map(|tonic| {
+                vec![
+                    (
+                        MAJOR_KEYS[tonic],
+                        correlation(&chroma, &MAJOR_PROFILE, tonic),
+                    ),
+                    (
+                        MINOR_KEYS[tonic],
+                        correlation(&chroma, &MINOR_PROFILE, tonic),
+                    ),
+                ]
+            })
+            .max_by(|(_, lhs), (_, rhs)| {
+                lhs.partial_cmp(rhs).unwrap_or(std::cmp::Ordering::Equal)
+            })?;
+        if score.is_finite() && score > 0.0 {
+            Some(KeySignature::new(key_code))
+        } else {
+            None
+        }
+    }
+}
+
+impl AudioSink for KeyAnalyzer {
+    fn open(&mut self, channel_count: u16, sample_rate: u32) {
+        let decimator =
+            Decimator::new(sample_rate, MIN_DECIMATED_SAMPLE_RATE, DECIMATION_CUTOFF_HZ);
+        let block_len = (decimator.sample_rate() / f64::from(BLOCKS_PER_SECOND)).round() as usize;
+        *self = Self {
+            channel_count: usize::from(channel_count),
+            decimator,
+            // Hann window
+            window: (0..block_len)
+                .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f64 / block_len as f64).cos())
+                .collect(),
+            block: Vec::with_capacity(block_len),
+            ..Default::default()
+        };
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        if self.channel_count == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channel_count) {
+            // Downmix to mono
+            let sample =
+                frame.iter().copied().map(f64::from).sum::<f64>() / self.channel_count as f64;
+            let sample = match self.decimator.process(sample) {
+                Some(sample) => sample,
+                None => continue,
+            };
+            self.block.push(sample);
+            if self.block.len() == self.window.len() {
+                self.finish_block();
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
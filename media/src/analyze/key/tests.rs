@@ -0,0 +1,101 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+const SAMPLE_RATE: u32 = 44_100;
+
+/// Play a sequence of chords for 2 seconds each
+///
+/// Each chord is given by the MIDI notes of its pitches.
+fn chord_progression(channel_count: u16, chords: &[&[u8]]) -> Vec<f32> {
+    let chord_len = SAMPLE_RATE * 2;
+    chords
+        .iter()
+        .flat_map(|chord| {
+            (0..chord_len).flat_map(move |i| {
+                let t = f64::from(i) / f64::from(SAMPLE_RATE);
+                let sample = chord
+                    .iter()
+                    .map(|&midi_note| 0.1 * (2.0 * PI * midi_note_frequency(midi_note) * t).sin())
+                    .sum::<f64>();
+                vec![sample as f32; channel_count.into()]
+            })
+        })
+        .collect()
+}
+
+fn detect(channel_count: u16, samples: &[f32]) -> Option<KeySignature> {
+    let mut analyzer = KeyAnalyzer::default();
+    analyzer.open(channel_count, SAMPLE_RATE);
+    // Feed the samples in arbitrary chunks
+    for chunk in samples.chunks(channel_count as usize * 1000) {
+        analyzer.write(chunk);
+    }
+    analyzer.finish()
+}
+
+#[test]
+fn c_major_cadence() {
+    // I - IV - V - I
+    let samples = chord_progression(
+        2,
+        &[
+            &[48, 60, 64, 67],
+            &[41, 60, 65, 69],
+            &[43, 59, 62, 67],
+            &[48, 60, 64, 67],
+        ],
+    );
+    assert_eq!(Some(KeySignature::new(KeyCode::Cmaj)), detect(2, &samples));
+}
+
+#[test]
+fn a_minor_cadence() {
+    // i - iv - V - i
+    let samples = chord_progression(
+        1,
+        &[
+            &[45, 57, 60, 64],
+            &[50, 57, 62, 65],
+            &[52, 56, 59, 64],
+            &[45, 57, 60, 64],
+        ],
+    );
+    assert_eq!(Some(KeySignature::new(KeyCode::Amin)), detect(1, &samples));
+}
+
+#[test]
+fn e_flat_major_cadence() {
+    // I - IV - V - I
+    let samples = chord_progression(
+        2,
+        &[
+            &[51, 63, 67, 70],
+            &[44, 63, 68, 72],
+            &[46, 62, 65, 70],
+            &[51, 63, 67, 70],
+        ],
+    );
+    assert_eq!(Some(KeySignature::new(KeyCode::Ebmaj)), detect(2, &samples));
+}
+
+#[test]
+fn silence_has_no_key() {
+    let samples = vec![0.0; 2 * SAMPLE_RATE as usize * 10];
+    assert_eq!(None, detect(2, &samples));
+}
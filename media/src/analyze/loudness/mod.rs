@@ -0,0 +1,373 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{
+    filter::{Biquad, BiquadState},
+    AudioSink,
+};
+
+use aoide_core::audio::signal::{LoudnessLufs, LoudnessRangeLu, TruePeakDbtp};
+
+use semval::IsValid as _;
+
+use std::{collections::VecDeque, f64::consts::PI};
+
+// Gating blocks are composed of sub-blocks with a duration of 100 ms
+const SUB_BLOCKS_PER_SECOND: u32 = 10;
+
+// Momentary loudness: 400 ms
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+
+// Short-term loudness: 3 s
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+
+const RANGE_RELATIVE_GATE_LU: f64 = -20.0;
+
+const RANGE_LOWER_PERCENTILE: f64 = 0.10;
+
+const RANGE_UPPER_PERCENTILE: f64 = 0.95;
+
+/// The two stages of the K-weighting filter for the given sample rate
+///
+/// The coefficients of ITU-R BS.1770 are only specified for 48 kHz
+/// and are derived from the analog prototype filters for other
+/// sample rates.
+fn k_weighting_filters(sample_rate: u32) -> [Biquad; 2] {
+    let sample_rate = f64::from(sample_rate);
+    // Stage 1: High shelf filter that models the acoustic effects of the head
+    let shelf = {
+        let f0 = 1_681.974_450_955_533;
+        let gain_db = 3.999_843_853_973_347;
+        let q = 0.707_175_236_955_419_6;
+        let k = (PI * f0 / sample_rate).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.499_666_774_154_541_6);
+        let a0 = 1.0 + k / q + k * k;
+        Biquad {
+            b: [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        }
+    };
+    // Stage 2: High pass filter (RLB weighting)
+    let high_pass = {
+        let f0 = 38.135_470_876_024_44;
+        let q = 0.500_327_037_323_877_3;
+        let k = (PI * f0 / sample_rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        Biquad {
+            b: [1.0, -2.0, 1.0],
+            a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        }
+    };
+    [shelf, high_pass]
+}
+
+/// Weighting of channels in the standard order of WAV files,
+/// i.e. L, R, C, LFE, Ls, Rs
+fn channel_weight(channel_index: usize, channel_count: usize) -> f64 {
+    match (channel_count, channel_index) {
+        // Ls and Rs of 5.0 without LFE
+        (5, 3) | (5, 4) => 1.41,
+        // LFE is ignored
+        (6..=usize::MAX, 3) => 0.0,
+        (6..=usize::MAX, 4) | (6..=usize::MAX, 5) => 1.41,
+        (_, _) => 1.0,
+    }
+}
+
+// Number of taps of each phase of the interpolation filter
+const TRUE_PEAK_FILTER_TAPS: usize = 12;
+
+/// Polyphase FIR interpolation filter for 4x oversampling
+///
+/// ITU-R BS.1770-4, Annex 2
+const TRUE_PEAK_FILTER_PHASES: [[f64; TRUE_PEAK_FILTER_TAPS]; 4] = [
+    [
+        0.001_708_984_375_0,
+        0.010_986_328_125_0,
+        -0.019_653_320_312_5,
+        0.033_203_125_000_0,
+        -0.059_448_242_187_5,
+        0.137_329_101_562_5,
+        0.972_167_968_750_0,
+        -0.102_294_921_875_0,
+        0.047_607_421_875_0,
+        -0.026_611_328_125_0,
+        0.014_892_578_125_0,
+        -0.008_300_781_250_0,
+    ],
+    [
+        -0.029_174_804_687_5,
+        0.029_296_875_000_0,
+        -0.051_757_812_500_0,
+        0.089_111_328_125_0,
+        -0.166_503_906_250_0,
+        0.465_087_890_625_0,
+        0.779_785_156_250_0,
+        -0.200_317_382_812_5,
+        0.101_562_500_000_0,
+        -0.058_227_539_062_5,
+        0.033_081_054_687_5,
+        -0.018_920_898_437_5,
+    ],
+    [
+        -0.018_920_898_437_5,
+        0.033_081_054_687_5,
+        -0.058_227_539_062_5,
+        0.101_562_500_000_0,
+        -0.200_317_382_812_5,
+        0.779_785_156_250_0,
+        0.465_087_890_625_0,
+        -0.166_503_906_250_0,
+        0.089_111_328_125_0,
+        -0.051_757_812_500_0,
+        0.029_296_875_000_0,
+        -0.029_174_804_687_5,
+    ],
+    [
+        -0.008_300_781_250_0,
+        0.014_892_578_125_0,
+        -0.026_611_328_125_0,
+        0.047_607_421_875_0,
+        -0.102_294_921_875_0,
+        0.972_167_968_750_0,
+        0.137_329_101_562_5,
+        -0.059_448_242_187_5,
+        0.033_203_125_000_0,
+        -0.019_653_320_312_5,
+        0.010_986_328_125_0,
+        0.001_708_984_375_0,
+    ],
+];
+
+#[derive(Debug, Clone, Default)]
+struct ChannelState {
+    weight: f64,
+    filter_states: [BiquadState; 2],
+    // The most recent input samples for true peak detection, newest first
+    history: [f64; TRUE_PEAK_FILTER_TAPS],
+}
+
+/// Results of a loudness measurement
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LoudnessMeasurement {
+    /// Integrated loudness according to ITU-R BS.1770 / EBU R128
+    pub integrated: Option<LoudnessLufs>,
+
+    /// Loudness range according to EBU Tech 3342
+    pub range: Option<LoudnessRangeLu>,
+
+    /// Maximum true peak level of all channels
+    pub true_peak: Option<TruePeakDbtp>,
+}
+
+/// Measures the loudness of a decoded audio signal
+/// according to ITU-R BS.1770 and EBU R128
+#[derive(Debug, Default)]
+pub struct LoudnessAnalyzer {
+    filters: [Biquad; 2],
+    channels: Vec<ChannelState>,
+    sub_block_len: usize,
+    sub_block_pos: usize,
+    // Sum of the weighted squares of all channels within the current sub-block
+    sub_block_sum: f64,
+    // Weighted mean squares of the most recent sub-blocks
+    recent_sub_blocks: VecDeque<f64>,
+    // Weighted mean squares of overlapping blocks for the integrated loudness
+    momentary_blocks: Vec<f64>,
+    // Weighted mean squares of overlapping blocks for the loudness range
+    short_term_blocks: Vec<f64>,
+    true_peak: f64,
+}
+
+fn mean(values: impl IntoIterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values
+        .into_iter()
+        .fold((0.0, 0usize), |(sum, count), value| {
+            (sum + value, count + 1)
+        });
+    if count > 0 {
+        Some(sum / count as f64)
+    } else {
+        None
+    }
+}
+
+fn loudness_from_mean_square(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+/// Apply the absolute gate and a relative gate to the given blocks
+fn gated_blocks(blocks: &[f64], relative_gate_lu: f64) -> Vec<f64> {
+    let absolute_gated = blocks
+        .iter()
+        .copied()
+        .filter(|&block| loudness_from_mean_square(block) > ABSOLUTE_GATE_LUFS);
+    let relative_gate = match mean(absolute_gated.clone()) {
+        Some(mean_square) => loudness_from_mean_square(mean_square) + relative_gate_lu,
+        None => return Vec::new(),
+    };
+    absolute_gated
+        .filter(|&block| loudness_from_mean_square(block) > relative_gate)
+        .collect()
+}
+
+impl LoudnessAnalyzer {
+    fn finish_sub_block(&mut self) {
+        let mean_square = self.sub_block_sum / self.sub_block_len as f64;
+        self.sub_block_sum = 0.0;
+        self.sub_block_pos = 0;
+        if self.recent_sub_blocks.len() == SHORT_TERM_SUB_BLOCKS {
+            self.recent_sub_blocks.pop_front();
+        }
+        self.recent_sub_blocks.push_back(mean_square);
+        let recent_count = self.recent_sub_blocks.len();
+        if recent_count >= MOMENTARY_SUB_BLOCKS {
+            let momentary = mean(
+                self.recent_sub_blocks
+                    .iter()
+                    .skip(recent_count - MOMENTARY_SUB_BLOCKS)
+                    .copied(),
+            );
+            self.momentary_blocks.extend(momentary);
+        }
+        if recent_count >= SHORT_TERM_SUB_BLOCKS {
+            let short_term = mean(self.recent_sub_blocks.iter().copied());
+            self.short_term_blocks.extend(short_term);
+        }
+    }
+
+    fn integrated_loudness(&self) -> Option<LoudnessLufs> {
+        let loudness = mean(gated_blocks(
+            &self.momentary_blocks,
+            INTEGRATED_RELATIVE_GATE_LU,
+        ))
+        .map(loudness_from_mean_square)
+        .map(LoudnessLufs)?;
+        if loudness.is_valid() {
+            Some(loudness)
+        } else {
+            None
+        }
+    }
+
+    fn loudness_range(&self) -> Option<LoudnessRangeLu> {
+        let mut loudness: Vec<_> = gated_blocks(&self.short_term_blocks, RANGE_RELATIVE_GATE_LU)
+            .into_iter()
+            .map(loudness_from_mean_square)
+            .collect();
+        if loudness.is_empty() {
+            return None;
+        }
+        loudness.sort_by(|lhs, rhs| lhs.partial_cmp(rhs).expect("finite loudness"));
+        let percentile = |p: f64| loudness[((loudness.len() - 1) as f64 * p).round() as usize];
+        let range = LoudnessRangeLu(
+            percentile(RANGE_UPPER_PERCENTILE) - percentile(RANGE_LOWER_PERCENTILE),
+        );
+        if range.is_valid() {
+            Some(range)
+        } else {
+            None
+        }
+    }
+
+    fn true_peak(&self) -> Option<TruePeakDbtp> {
+        let true_peak = TruePeakDbtp(20.0 * self.true_peak.log10());
+        if true_peak.is_valid() {
+            Some(true_peak)
+        } else {
+            // Silence
+            None
+        }
+    }
+
+    /// Finish the measurement after all samples have been received
+    pub fn finish(self) -> LoudnessMeasurement {
+        LoudnessMeasurement {
+            integrated: self.integrated_loudness(),
+            range: self.loudness_range(),
+            true_peak: self.true_peak(),
+        }
+    }
+}
+
+impl AudioSink for LoudnessAnalyzer {
+    fn open(&mut self, channel_count: u16, sample_rate: u32) {
+        let channel_count = usize::from(channel_count);
+        *self = Self {
+            filters: k_weighting_filters(sample_rate),
+            channels: (0..channel_count)
+                .map(|channel_index| ChannelState {
+                    weight: channel_weight(channel_index, channel_count),
+                    ..Default::default()
+                })
+                .collect(),
+            sub_block_len: ((sample_rate + SUB_BLOCKS_PER_SECOND / 2) / SUB_BLOCKS_PER_SECOND)
+                as usize,
+            ..Default::default()
+        };
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        let channel_count = self.channels.len();
+        if channel_count == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(channel_count) {
+            for (channel, &sample) in self.channels.iter_mut().zip(frame) {
+                let sample = f64::from(sample);
+                // K-weighted power
+                let filtered = self
+                    .filters
+                    .iter()
+                    .zip(channel.filter_states.iter_mut())
+                    .fold(sample, |x, (filter, state)| filter.process(state, x));
+                self.sub_block_sum += channel.weight * filtered * filtered;
+                // True peak
+                channel.history.rotate_right(1);
+                channel.history[0] = sample;
+                for phase in &TRUE_PEAK_FILTER_PHASES {
+                    let interpolated: f64 = phase
+                        .iter()
+                        .zip(channel.history.iter())
+                        .map(|(coeff, x)| coeff * x)
+                        .sum();
+                    self.true_peak = self.true_peak.max(interpolated.abs());
+                }
+            }
+            self.sub_block_pos += 1;
+            if self.sub_block_pos == self.sub_block_len {
+                self.finish_sub_block();
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
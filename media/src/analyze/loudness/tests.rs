@@ -0,0 +1,104 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+fn sine_wave(
+    channel_count: u16,
+    sample_rate: u32,
+    frequency: f64,
+    amplitude: f64,
+    secs: u32,
+) -> Vec<f32> {
+    let frame_count = sample_rate * secs;
+    (0..frame_count)
+        .flat_map(|i| {
+            let sample =
+                amplitude * (2.0 * PI * frequency * f64::from(i) / f64::from(sample_rate)).sin();
+            vec![sample as f32; channel_count.into()]
+        })
+        .collect()
+}
+
+fn measure(channel_count: u16, sample_rate: u32, samples: &[f32]) -> LoudnessMeasurement {
+    let mut analyzer = LoudnessAnalyzer::default();
+    analyzer.open(channel_count, sample_rate);
+    // Feed the samples in arbitrary chunks
+    for chunk in samples.chunks(channel_count as usize * 1000) {
+        analyzer.write(chunk);
+    }
+    analyzer.finish()
+}
+
+#[test]
+fn stereo_sine_1khz() {
+    // A stereo sine wave with 1 kHz at -20 dBFS measures -20 LUFS
+    for &sample_rate in &[44_100, 48_000, 96_000] {
+        let samples = sine_wave(2, sample_rate, 1000.0, 0.1, 10);
+        let measurement = measure(2, sample_rate, &samples);
+        let integrated = measurement.integrated.unwrap().0;
+        assert!((integrated + 20.0).abs() < 0.1, "{}", integrated);
+        let range = measurement.range.unwrap().0;
+        assert!(range < 0.1, "{}", range);
+        let true_peak = measurement.true_peak.unwrap().0;
+        assert!((true_peak + 20.0).abs() < 0.1, "{}", true_peak);
+    }
+}
+
+#[test]
+fn mono_sine_1khz() {
+    // A single channel is 3 dB less loud than two channels
+    let samples = sine_wave(1, 48_000, 1000.0, 0.1, 10);
+    let measurement = measure(1, 48_000, &samples);
+    let integrated = measurement.integrated.unwrap().0;
+    assert!((integrated + 23.01).abs() < 0.1, "{}", integrated);
+}
+
+#[test]
+fn loudness_range_of_alternating_levels() {
+    // 20 s at -20 dBFS followed by 20 s at -30 dBFS
+    let mut samples = sine_wave(2, 48_000, 1000.0, 0.1, 20);
+    samples.extend(sine_wave(2, 48_000, 1000.0, 0.031_622_776_6, 20));
+    let measurement = measure(2, 48_000, &samples);
+    let range = measurement.range.unwrap().0;
+    assert!((range - 10.0).abs() < 0.2, "{}", range);
+    // Both parts pass the relative gate of -10 LU
+    let integrated = measurement.integrated.unwrap().0;
+    assert!((integrated + 22.6).abs() < 0.2, "{}", integrated);
+}
+
+#[test]
+fn silence() {
+    let samples = vec![0.0; 2 * 48_000 * 5];
+    assert_eq!(LoudnessMeasurement::default(), measure(2, 48_000, &samples));
+}
+
+#[test]
+fn true_peak_of_intersample_peak() {
+    // A sine wave at a quarter of the sample rate with a phase
+    // offset of 45 degrees never hits its peak on a sample
+    let sample_rate = 48_000;
+    let samples: Vec<f32> = (0..sample_rate)
+        .map(|i| (0.5 * (PI / 2.0 * f64::from(i) + PI / 4.0).sin()) as f32)
+        .collect();
+    let sample_peak = samples.iter().fold(0f32, |peak, x| peak.max(x.abs()));
+    assert!(sample_peak < 0.36);
+    let measurement = measure(1, sample_rate, &samples);
+    let true_peak = measurement.true_peak.unwrap().0;
+    // 20 * log10(0.5) = -6.02 dBTP
+    assert!((true_peak + 6.02).abs() < 0.5, "{}", true_peak);
+}
@@ -0,0 +1,289 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{io::import::ImportTrackFlags, Result};
+
+use aoide_core::{
+    audio::fingerprint::Fingerprint,
+    media::Content,
+    track::{metric::MetricsFlags, Track},
+};
+
+use std::{ffi::OsStr, fs::File, io::ErrorKind, path::Path};
+
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error as DecoderError,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+mod filter;
+
+pub mod fingerprint;
+pub mod key;
+pub mod loudness;
+pub mod tempo;
+pub mod waveform;
+
+use self::{
+    fingerprint::FingerprintAnalyzer, key::KeyAnalyzer, loudness::LoudnessAnalyzer,
+    tempo::TempoAnalyzer, waveform::WaveformAnalyzer,
+};
+
+/// Receives the decoded samples of an audio stream
+pub trait AudioSink {
+    /// Prepare for receiving samples with the given signal properties
+    fn open(&mut self, channel_count: u16, sample_rate: u32);
+
+    /// Receive interleaved samples of all channels
+    fn write(&mut self, samples: &[f32]);
+}
+
+/// Decode the first audio track of a file into the given sinks
+///
+/// The file is only decoded once, regardless of the number of sinks.
+pub fn decode_audio_file(path: &Path, sinks: &mut [&mut dyn AudioSink]) -> Result<()> {
+    let file = File::open(path)?;
+    let media_source_stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(OsStr::to_str) {
+        hint.with_extension(extension);
+    }
+    let mut format_reader = symphonia::default::get_probe()
+        .format(
+            &hint,
+            media_source_stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(anyhow::Error::from)?
+        .format;
+    let track = format_reader
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| anyhow::anyhow!("No audio track found"))?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(anyhow::Error::from)?;
+    let mut sample_buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format_reader.next_packet() {
+            Ok(packet) => packet,
+            Err(DecoderError::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                // End of stream
+                break;
+            }
+            Err(err) => return Err(anyhow::Error::from(err).into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(DecoderError::DecodeError(err)) => {
+                // Skip corrupt packets
+                log::debug!("Failed to decode audio packet: {}", err);
+                continue;
+            }
+            Err(err) => return Err(anyhow::Error::from(err).into()),
+        };
+        let sample_buffer = match &mut sample_buffer {
+            Some(sample_buffer) => sample_buffer,
+            None => {
+                let signal_spec = *decoded.spec();
+                let channel_count = signal_spec.channels.count() as u16;
+                for sink in sinks.iter_mut() {
+                    sink.open(channel_count, signal_spec.rate);
+                }
+                sample_buffer
+                    .get_or_insert(SampleBuffer::new(decoded.capacity() as u64, signal_spec))
+            }
+        };
+        sample_buffer.copy_interleaved_ref(decoded);
+        for sink in sinks.iter_mut() {
+            sink.write(sample_buffer.samples());
+        }
+    }
+    if sample_buffer.is_none() {
+        return Err(anyhow::anyhow!("No audio samples decoded").into());
+    }
+    Ok(())
+}
+
+/// Decode and analyze the audio stream of a file
+///
+/// The flags control which analyses are performed. The loudness is
+/// stored in the audio content of the track's media source unless
+/// the content metadata is locked.
+///
+/// The tempo and the key signature are only estimated if they are
+/// neither locked nor have been read from a file tag. Estimated values
+/// are marked as analyzed.
+///
+/// The waveform overview is stored in the audio content of the
+/// track's media source.
+///
+/// The acoustic fingerprint is not part of the track and returned
+/// separately if requested.
+pub fn analyze_audio_file(
+    path: &Path,
+    flags: ImportTrackFlags,
+    track: &mut Track,
+) -> Result<Option<Fingerprint>> {
+    let mut loudness_analyzer = if flags.contains(ImportTrackFlags::ANALYZE_LOUDNESS) {
+        if track.media_source.content_metadata_flags.is_locked() {
+            log::debug!(
+                "Skipping loudness analysis of {} with locked content metadata",
+                track.media_source.path
+            );
+            None
+        } else {
+            Some(LoudnessAnalyzer::default())
+        }
+    } else {
+        None
+    };
+    let mut tempo_analyzer = if flags.contains(ImportTrackFlags::ANALYZE_TEMPO) {
+        let metrics_flags = track.metrics.flags;
+        if metrics_flags.contains(MetricsFlags::TEMPO_BPM_LOCKED) {
+            log::debug!(
+                "Skipping tempo analysis of {} with locked tempo",
+                track.media_source.path
+            );
+            None
+        } else if track.metrics.tempo_bpm.is_some()
+            && !metrics_flags.contains(MetricsFlags::TEMPO_BPM_ANALYZED)
+        {
+            log::debug!(
+                "Skipping tempo analysis of {} with tagged tempo",
+                track.media_source.path
+            );
+            None
+        } else {
+            Some(TempoAnalyzer::default())
+        }
+    } else {
+        None
+    };
+    let mut key_analyzer = if flags.contains(ImportTrackFlags::ANALYZE_KEY_SIGNATURE) {
+        let metrics_flags = track.metrics.flags;
+        if metrics_flags.contains(MetricsFlags::KEY_SIGNATURE_LOCKED) {
+            log::debug!(
+                "Skipping key analysis of {} with locked key signature",
+                track.media_source.path
+            );
+            None
+        } else if !track.metrics.key_signature.is_unknown()
+            && !metrics_flags.contains(MetricsFlags::KEY_SIGNATURE_ANALYZED)
+        {
+            log::debug!(
+                "Skipping key analysis of {} with tagged key signature",
+                track.media_source.path
+            );
+            None
+        } else {
+            Some(KeyAnalyzer::default())
+        }
+    } else {
+        None
+    };
+    let mut fingerprint_analyzer = if flags.contains(ImportTrackFlags::ANALYZE_FINGERPRINT) {
+        Some(FingerprintAnalyzer::default())
+    } else {
+        None
+    };
+    let mut waveform_analyzer = if flags.contains(ImportTrackFlags::ANALYZE_WAVEFORM) {
+        Some(WaveformAnalyzer::default())
+    } else {
+        None
+    };
+    let mut sinks: Vec<&mut dyn AudioSink> = Vec::new();
+    if let Some(loudness_analyzer) = &mut loudness_analyzer {
+        sinks.push(loudness_analyzer);
+    }
+    if let Some(tempo_analyzer) = &mut tempo_analyzer {
+        sinks.push(tempo_analyzer);
+    }
+    if let Some(key_analyzer) = &mut key_analyzer {
+        sinks.push(key_analyzer);
+    }
+    if let Some(fingerprint_analyzer) = &mut fingerprint_analyzer {
+        sinks.push(fingerprint_analyzer);
+    }
+    if let Some(waveform_analyzer) = &mut waveform_analyzer {
+        sinks.push(waveform_analyzer);
+    }
+    if sinks.is_empty() {
+        return Ok(None);
+    }
+    decode_audio_file(path, &mut sinks)?;
+    let Content::Audio(audio_content) = &mut track.media_source.content;
+    if let Some(loudness_analyzer) = loudness_analyzer {
+        let measurement = loudness_analyzer.finish();
+        log::debug!(
+            "Measured loudness of {}: {:?}",
+            track.media_source.path,
+            measurement
+        );
+        // Keep the previous values if the measurement failed
+        if let Some(loudness) = measurement.integrated {
+            audio_content.loudness = Some(loudness);
+        }
+        if let Some(loudness_range) = measurement.range {
+            audio_content.loudness_range = Some(loudness_range);
+        }
+        if let Some(true_peak) = measurement.true_peak {
+            audio_content.true_peak = Some(true_peak);
+        }
+    }
+    if let Some(tempo_analyzer) = tempo_analyzer {
+        let tempo_bpm = tempo_analyzer.finish();
+        log::debug!(
+            "Estimated tempo of {}: {:?}",
+            track.media_source.path,
+            tempo_bpm
+        );
+        // Keep the previous value if the estimation failed
+        if let Some(tempo_bpm) = tempo_bpm {
+            track.metrics.tempo_bpm = Some(tempo_bpm);
+            track.metrics.flags |= MetricsFlags::TEMPO_BPM_ANALYZED;
+        }
+    }
+    if let Some(key_analyzer) = key_analyzer {
+        let key_signature = key_analyzer.finish();
+        log::debug!(
+            "Detected key of {}: {:?}",
+            track.media_source.path,
+            key_signature
+        );
+        // Keep the previous value if the detection failed
+        if let Some(key_signature) = key_signature {
+            track.metrics.key_signature = key_signature;
+            track.metrics.flags |= MetricsFlags::KEY_SIGNATURE_ANALYZED;
+        }
+    }
+    if let Some(waveform_analyzer) = waveform_analyzer {
+        audio_content.waveform = waveform_analyzer.finish();
+    }
+    Ok(fingerprint_analyzer.and_then(FingerprintAnalyzer::finish))
+}
@@ -0,0 +1,259 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{
+    filter::{Biquad, BiquadState},
+    AudioSink,
+};
+
+use aoide_core::music::time::TempoBpm;
+
+use semval::IsValid as _;
+
+// Sampling rate of the onset strength envelope, i.e. a resolution of 5 ms
+const ENVELOPE_FRAMES_PER_SECOND: u32 = 200;
+
+// Tracks that are shorter don't provide enough periods for
+// estimating the tempo reliably
+const MIN_ENVELOPE_SECS: u32 = 5;
+
+const MIN_TEMPO_BPM: f64 = 60.0;
+
+const MAX_TEMPO_BPM: f64 = 200.0;
+
+// The tempo estimates are weighted by a log-normal distribution that
+// resolves octave ambiguities in favor of the most common tempos
+const PRIOR_TEMPO_BPM: f64 = 120.0;
+
+const PRIOR_DEVIATION_OCTAVES: f64 = 1.0;
+
+// Weight of the autocorrelation at the doubled period
+const HARMONIC_WEIGHT: f64 = 0.5;
+
+// Triangular kernel for smoothing the onset strength envelope,
+// which compensates for onsets that don't coincide with frames
+const SMOOTHING_KERNEL: [f64; 5] = [1.0 / 9.0, 2.0 / 9.0, 3.0 / 9.0, 2.0 / 9.0, 1.0 / 9.0];
+
+// Compression of the band energies before detecting onsets
+const LOG_COMPRESSION: f64 = 1_000.0;
+
+// Minimum normalized autocorrelation of the envelope for
+// accepting the estimated tempo
+const MIN_PERIODICITY: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Band {
+    filter: Biquad,
+    filter_state: BiquadState,
+    // Sum of squares within the current envelope frame
+    sum: f64,
+    // Compressed energy of the previous envelope frame
+    last_energy: f64,
+}
+
+impl Band {
+    fn new(filter: Biquad) -> Self {
+        Self {
+            filter,
+            ..Default::default()
+        }
+    }
+}
+
+/// Estimates the tempo of a decoded audio signal
+///
+/// The onsets are detected in separate frequency bands for bass,
+/// mid-range and treble. The periodicity of the resulting onset
+/// strength envelope is then determined by autocorrelation.
+#[derive(Debug, Default)]
+pub struct TempoAnalyzer {
+    channel_count: usize,
+    frame_len: usize,
+    frame_pos: usize,
+    frames_per_second: f64,
+    bands: Vec<Band>,
+    envelope: Vec<f64>,
+}
+
+fn tempo_bpm_from_lag(frames_per_second: f64, lag: f64) -> f64 {
+    60.0 * frames_per_second / lag
+}
+
+fn prior_weight(tempo_bpm: f64) -> f64 {
+    let octaves = (tempo_bpm / PRIOR_TEMPO_BPM).log2() / PRIOR_DEVIATION_OCTAVES;
+    (-0.5 * octaves * octaves).exp()
+}
+
+fn smoothed(signal: &[f64]) -> Vec<f64> {
+    let radius = SMOOTHING_KERNEL.len() / 2;
+    (0..signal.len())
+        .map(|i| {
+            SMOOTHING_KERNEL
+                .iter()
+                .enumerate()
+                .filter_map(|(k, weight)| {
+                    (i + k)
+                        .checked_sub(radius)
+                        .and_then(|j| signal.get(j))
+                        .map(|x| weight * x)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Normalized autocorrelation for all lags up to the given maximum
+///
+/// The mean of the signal is removed and the sum of each lag is
+/// normalized by the number of summands.
+fn autocorrelation(signal: &[f64], max_lag: usize) -> Vec<f64> {
+    let mean = signal.iter().sum::<f64>() / signal.len() as f64;
+    let signal: Vec<_> = signal.iter().map(|x| x - mean).collect();
+    (0..=max_lag.min(signal.len() - 1))
+        .map(|lag| {
+            let sum: f64 = signal
+                .iter()
+                .zip(&signal[lag..])
+                .map(|(lhs, rhs)| lhs * rhs)
+                .sum();
+            sum / (signal.len() - lag) as f64
+        })
+        .collect()
+}
+
+impl TempoAnalyzer {
+    fn finish_frame(&mut self) {
+        let mut onset_strength = 0.0;
+        for band in &mut self.bands {
+            let mean_square = band.sum / self.frame_len as f64;
+            let energy = (1.0 + LOG_COMPRESSION * mean_square).ln();
+            // Only rising energy indicates an onset
+            onset_strength += (energy - band.last_energy).max(0.0);
+            band.last_energy = energy;
+            band.sum = 0.0;
+        }
+        self.envelope.push(onset_strength);
+        self.frame_pos = 0;
+    }
+
+    /// Finish the estimation after all samples have been received
+    ///
+    /// Returns `None` if the signal is too short or doesn't
+    /// reveal a periodic pattern.
+    pub fn finish(self) -> Option<TempoBpm> {
+        let Self {
+            frames_per_second,
+            envelope,
+            ..
+        } = self;
+        if envelope.len() < (MIN_ENVELOPE_SECS * ENVELOPE_FRAMES_PER_SECOND) as usize {
+            return None;
+        }
+        let min_lag = (60.0 * frames_per_second / MAX_TEMPO_BPM).floor() as usize;
+        let max_lag = (60.0 * frames_per_second / MIN_TEMPO_BPM).ceil() as usize;
+        let acf = autocorrelation(&smoothed(&envelope), 2 * max_lag + 1);
+        if !acf[0].is_finite() || acf[0] <= f64::EPSILON {
+            // Silence, constant or invalid signal
+            return None;
+        }
+        let harmonic_acf = |lag: usize| acf.get(2 * lag).copied().unwrap_or_default();
+        let scores: Vec<_> = (min_lag.max(1)..=max_lag + 1)
+            .map(|lag| {
+                let score = acf[lag] + HARMONIC_WEIGHT * harmonic_acf(lag);
+                (
+                    lag,
+                    score * prior_weight(tempo_bpm_from_lag(frames_per_second, lag as f64)),
+                )
+            })
+            .collect();
+        // The first and last score are only needed for the interpolation
+        let (best_index, &(best_lag, best_score)) = scores
+            .iter()
+            .enumerate()
+            .skip(1)
+            .take(scores.len() - 2)
+            .max_by(|(_, (_, lhs)), (_, (_, rhs))| {
+                lhs.partial_cmp(rhs).unwrap_or(std::cmp::Ordering::Equal)
+            })?;
+        if !best_score.is_finite() || best_score <= 0.0 || acf[best_lag] / acf[0] < MIN_PERIODICITY
+        {
+            return None;
+        }
+        // Refine the lag by parabolic interpolation of the neighboring scores
+        let prev_score = scores[best_index - 1].1;
+        let next_score = scores[best_index + 1].1;
+        let curvature = prev_score - 2.0 * best_score + next_score;
+        let lag_offset = if curvature < 0.0 {
+            0.5 * (prev_score - next_score) / curvature
+        } else {
+            0.0
+        };
+        let tempo_bpm = TempoBpm(tempo_bpm_from_lag(
+            frames_per_second,
+            best_lag as f64 + lag_offset,
+        ));
+        if tempo_bpm.is_valid() {
+            Some(tempo_bpm)
+        } else {
+            None
+        }
+    }
+}
+
+impl AudioSink for TempoAnalyzer {
+    fn open(&mut self, channel_count: u16, sample_rate: u32) {
+        let frame_len =
+            ((sample_rate + ENVELOPE_FRAMES_PER_SECOND / 2) / ENVELOPE_FRAMES_PER_SECOND).max(1);
+        *self = Self {
+            channel_count: usize::from(channel_count),
+            frame_len: frame_len as usize,
+            frames_per_second: f64::from(sample_rate) / f64::from(frame_len),
+            bands: vec![
+                Band::new(Biquad::low_pass(sample_rate, 150.0, 0.707)),
+                Band::new(Biquad::band_pass(sample_rate, 800.0, 0.707)),
+                Band::new(Biquad::high_pass(sample_rate, 4_000.0, 0.707)),
+            ],
+            ..Default::default()
+        };
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        if self.channel_count == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channel_count) {
+            // Downmix to mono
+            let sample =
+                frame.iter().copied().map(f64::from).sum::<f64>() / self.channel_count as f64;
+            for band in &mut self.bands {
+                let filtered = band.filter.process(&mut band.filter_state, sample);
+                band.sum += filtered * filtered;
+            }
+            self.frame_pos += 1;
+            if self.frame_pos == self.frame_len {
+                self.finish_frame();
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
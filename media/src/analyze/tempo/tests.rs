@@ -0,0 +1,100 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use std::f64::consts::PI;
+
+/// A metronome with short and decaying clicks at the given tempo
+fn click_track(channel_count: u16, sample_rate: u32, tempo_bpm: f64, secs: u32) -> Vec<f32> {
+    let frame_count = sample_rate * secs;
+    let beat_len = f64::from(sample_rate) * 60.0 / tempo_bpm;
+    let click_len = f64::from(sample_rate) * 0.02;
+    (0..frame_count)
+        .flat_map(|i| {
+            let beat_pos = f64::from(i) % beat_len;
+            let sample = if beat_pos < click_len {
+                let t = beat_pos / f64::from(sample_rate);
+                0.5 * (1.0 - beat_pos / click_len) * (2.0 * PI * 1_000.0 * t).sin()
+            } else {
+                0.0
+            };
+            vec![sample as f32; channel_count.into()]
+        })
+        .collect()
+}
+
+fn estimate(channel_count: u16, sample_rate: u32, samples: &[f32]) -> Option<TempoBpm> {
+    let mut analyzer = TempoAnalyzer::default();
+    analyzer.open(channel_count, sample_rate);
+    // Feed the samples in arbitrary chunks
+    for chunk in samples.chunks(channel_count as usize * 1000) {
+        analyzer.write(chunk);
+    }
+    analyzer.finish()
+}
+
+#[test]
+fn click_track_tempo() {
+    for &(channel_count, sample_rate, tempo_bpm) in &[
+        (2, 44_100, 120.0),
+        (1, 48_000, 95.0),
+        (2, 44_100, 128.0),
+        (2, 96_000, 140.0),
+    ] {
+        let samples = click_track(channel_count, sample_rate, tempo_bpm, 30);
+        let estimated = estimate(channel_count, sample_rate, &samples).unwrap().0;
+        assert!(
+            (estimated - tempo_bpm).abs() < 0.5,
+            "expected {} bpm, estimated {} bpm",
+            tempo_bpm,
+            estimated
+        );
+    }
+}
+
+#[test]
+fn silence_has_no_tempo() {
+    let samples = vec![0.0; 2 * 44_100 * 30];
+    assert!(estimate(2, 44_100, &samples).is_none());
+}
+
+#[test]
+fn short_signal_has_no_tempo() {
+    let samples = click_track(2, 44_100, 120.0, MIN_ENVELOPE_SECS - 1);
+    assert!(estimate(2, 44_100, &samples).is_none());
+}
+
+#[test]
+fn white_noise_has_no_tempo() {
+    // Deterministic pseudo-random numbers from a linear congruential generator
+    let mut state = 1u32;
+    let samples: Vec<_> = (0..44_100 * 30)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (f64::from(state) / f64::from(u32::MAX) - 0.5) as f32
+        })
+        .collect();
+    assert!(estimate(1, 44_100, &samples).is_none());
+}
+
+#[test]
+fn non_finite_signal_has_no_tempo() {
+    let mut samples = click_track(2, 44_100, 120.0, 30);
+    samples[44_100] = f32::INFINITY;
+    assert!(estimate(2, 44_100, &samples).is_none());
+}
@@ -0,0 +1,174 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{
+    filter::{Biquad, BiquadState},
+    AudioSink,
+};
+
+use aoide_core::audio::waveform::{WaveformBin, WaveformOverview, WAVEFORM_OVERVIEW_BIN_COUNT};
+
+// The samples are accumulated in blocks of 50 ms that are
+// finally merged into the bins of the overview
+const BLOCKS_PER_SECOND: u32 = 20;
+
+const LOW_MID_CROSSOVER_HZ: f64 = 250.0;
+
+const MID_HIGH_CROSSOVER_HZ: f64 = 4_000.0;
+
+// Low, mid and high frequencies
+const BAND_COUNT: usize = 3;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Block {
+    peak: f32,
+    // Sum of squares of the whole spectrum
+    sum: f64,
+    band_sums: [f64; BAND_COUNT],
+    len: usize,
+}
+
+/// Generates a waveform overview of a decoded audio signal
+///
+/// The peak amplitude is determined from all channels. The RMS
+/// amplitudes are calculated from the mono downmix of the signal,
+/// both for the whole spectrum and for separate frequency bands.
+#[derive(Debug, Default)]
+pub struct WaveformAnalyzer {
+    channel_count: usize,
+    block_len: usize,
+    // Cascaded filters of each band
+    band_filters: Vec<Vec<(Biquad, BiquadState)>>,
+    block: Block,
+    blocks: Vec<Block>,
+}
+
+fn quantize(amplitude: f64) -> u8 {
+    (amplitude.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn merge_blocks(blocks: &[Block]) -> WaveformBin {
+    let peak = blocks.iter().map(|block| block.peak).fold(0.0, f32::max);
+    let len = blocks.iter().map(|block| block.len).sum::<usize>() as f64;
+    let rms = |sum: f64| (sum / len).sqrt();
+    let band_rms =
+        |band_index: usize| rms(blocks.iter().map(|block| block.band_sums[band_index]).sum());
+    WaveformBin {
+        peak: quantize(f64::from(peak)),
+        rms: quantize(rms(blocks.iter().map(|block| block.sum).sum())),
+        low: quantize(band_rms(0)),
+        mid: quantize(band_rms(1)),
+        high: quantize(band_rms(2)),
+    }
+}
+
+impl WaveformAnalyzer {
+    fn finish_block(&mut self) {
+        self.blocks.push(std::mem::take(&mut self.block));
+    }
+
+    /// Finish the generation after all samples have been received
+    ///
+    /// The overview always consists of the same number of bins,
+    /// independent of the duration of the signal. Returns `None`
+    /// if no samples have been received.
+    pub fn finish(mut self) -> Option<WaveformOverview> {
+        if self.block.len > 0 {
+            self.finish_block();
+        }
+        let blocks = self.blocks;
+        if blocks.is_empty() {
+            return None;
+        }
+        let bins = (0..WAVEFORM_OVERVIEW_BIN_COUNT)
+            .map(|bin_index| {
+                let start = bin_index * blocks.len() / WAVEFORM_OVERVIEW_BIN_COUNT;
+                // Blocks are repeated if the signal is too short
+                let end =
+                    ((bin_index + 1) * blocks.len() / WAVEFORM_OVERVIEW_BIN_COUNT).max(start + 1);
+                merge_blocks(&blocks[start..end])
+            })
+            .collect();
+        Some(WaveformOverview::new(bins))
+    }
+}
+
+impl AudioSink for WaveformAnalyzer {
+    fn open(&mut self, channel_count: u16, sample_rate: u32) {
+        let low_pass = |cutoff_hz| Biquad::low_pass(sample_rate, cutoff_hz, 0.707);
+        let high_pass = |cutoff_hz| Biquad::high_pass(sample_rate, cutoff_hz, 0.707);
+        let band_filters = vec![
+            vec![low_pass(LOW_MID_CROSSOVER_HZ)],
+            vec![
+                high_pass(LOW_MID_CROSSOVER_HZ),
+                low_pass(MID_HIGH_CROSSOVER_HZ),
+            ],
+            vec![high_pass(MID_HIGH_CROSSOVER_HZ)],
+        ]
+        .into_iter()
+        .map(|filters| {
+            filters
+                .into_iter()
+                .map(|filter| (filter, BiquadState::default()))
+                .collect()
+        })
+        .collect();
+        *self = Self {
+            channel_count: usize::from(channel_count),
+            block_len: ((sample_rate / BLOCKS_PER_SECOND) as usize).max(1),
+            band_filters,
+            ..Default::default()
+        };
+    }
+
+    fn write(&mut self, samples: &[f32]) {
+        if self.channel_count == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact(self.channel_count) {
+            let peak = frame
+                .iter()
+                .copied()
+                .map(f32::abs)
+                .fold(self.block.peak, f32::max);
+            // Downmix to mono
+            let sample =
+                frame.iter().copied().map(f64::from).sum::<f64>() / self.channel_count as f64;
+            let block = &mut self.block;
+            block.peak = peak;
+            block.sum += sample * sample;
+            for (filters, band_sum) in self.band_filters.iter_mut().zip(block.band_sums.iter_mut())
+            {
+                let filtered = filters
+                    .iter_mut()
+                    .fold(sample, |x, (filter, state)| filter.process(state, x));
+                *band_sum += filtered * filtered;
+            }
+            block.len += 1;
+            if block.len == self.block_len {
+                self.finish_block();
+            }
+        }
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
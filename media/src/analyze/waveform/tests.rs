@@ -0,0 +1,111 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use std::f64::consts::PI;
+
+const SAMPLE_RATE: u32 = 44_100;
+
+fn sine_wave(frequency: f64, amplitude: f64, secs: f64) -> Vec<f64> {
+    let len = (secs * f64::from(SAMPLE_RATE)) as usize;
+    (0..len)
+        .map(|i| {
+            let t = i as f64 / f64::from(SAMPLE_RATE);
+            amplitude * (2.0 * PI * frequency * t).sin()
+        })
+        .collect()
+}
+
+fn waveform_overview(channel_count: u16, signal: &[f64]) -> Option<WaveformOverview> {
+    let samples: Vec<_> = signal
+        .iter()
+        .flat_map(|&sample| vec![sample as f32; channel_count.into()])
+        .collect();
+    let mut analyzer = WaveformAnalyzer::default();
+    analyzer.open(channel_count, SAMPLE_RATE);
+    // Feed the samples in arbitrary chunks
+    for chunk in samples.chunks(channel_count as usize * 1000) {
+        analyzer.write(chunk);
+    }
+    analyzer.finish()
+}
+
+#[test]
+fn peak_and_rms_of_sine_wave() {
+    let overview = waveform_overview(2, &sine_wave(1_000.0, 0.5, 60.0)).unwrap();
+    assert_eq!(WAVEFORM_OVERVIEW_BIN_COUNT, overview.bins().len());
+    for bin in overview.bins() {
+        // 0.5 * 255
+        assert!((127..=128).contains(&bin.peak), "{:?}", bin);
+        // 0.5 / sqrt(2) * 255
+        assert!((89..=91).contains(&bin.rms), "{:?}", bin);
+        assert!(bin.mid > bin.low, "{:?}", bin);
+        assert!(bin.mid > bin.high, "{:?}", bin);
+    }
+}
+
+#[test]
+fn frequency_bands() {
+    let low = waveform_overview(1, &sine_wave(60.0, 0.5, 10.0)).unwrap();
+    for bin in low.bins() {
+        assert!(bin.low > 4 * bin.mid.max(bin.high), "{:?}", bin);
+    }
+    let high = waveform_overview(1, &sine_wave(10_000.0, 0.5, 10.0)).unwrap();
+    for bin in high.bins() {
+        assert!(bin.high > 4 * bin.low.max(bin.mid), "{:?}", bin);
+    }
+}
+
+#[test]
+fn increasing_amplitude() {
+    let secs = 30;
+    let len = secs * SAMPLE_RATE as usize;
+    let signal: Vec<_> = sine_wave(440.0, 1.0, secs as f64)
+        .into_iter()
+        .enumerate()
+        .map(|(i, sample)| sample * i as f64 / len as f64)
+        .collect();
+    let overview = waveform_overview(2, &signal).unwrap();
+    let bins = overview.bins();
+    assert!(bins.first().unwrap().peak < 2);
+    assert!(bins.last().unwrap().peak > 250);
+    for adjacent in bins.windows(2) {
+        assert!(adjacent[0].peak <= adjacent[1].peak);
+    }
+}
+
+#[test]
+fn short_signal() {
+    // Less blocks than bins
+    let overview = waveform_overview(2, &sine_wave(1_000.0, 0.5, 1.0)).unwrap();
+    assert_eq!(WAVEFORM_OVERVIEW_BIN_COUNT, overview.bins().len());
+}
+
+#[test]
+fn silence() {
+    let overview = waveform_overview(2, &vec![0.0; 10 * SAMPLE_RATE as usize]).unwrap();
+    assert!(overview
+        .bins()
+        .iter()
+        .all(|bin| *bin == WaveformBin::default()));
+}
+
+#[test]
+fn empty_signal() {
+    assert_eq!(None, waveform_overview(2, &[]));
+}
@@ -0,0 +1,336 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{Error, Result};
+
+use aoide_core::{
+    audio::{AudioRange, PositionMs},
+    media::{Content, SourcePath},
+    track::{self, index::Index, metric::MetricsFlags},
+};
+
+use anyhow::anyhow;
+
+///////////////////////////////////////////////////////////////////////
+
+/// The number of frames per second in INDEX positions (mm:ss:ff)
+const FRAMES_PER_SECOND: u32 = 75;
+
+/// A track of a CUE sheet, i.e. a range within a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Track {
+    pub number: u16,
+
+    pub title: Option<String>,
+
+    pub performer: Option<String>,
+
+    pub songwriter: Option<String>,
+
+    /// The position of `INDEX 01`
+    pub start: PositionMs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    /// The path of the file, relative to the CUE sheet
+    pub path: String,
+
+    pub tracks: Vec<Track>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSheet {
+    pub title: Option<String>,
+
+    pub performer: Option<String>,
+
+    pub songwriter: Option<String>,
+
+    pub files: Vec<File>,
+}
+
+/// Split a line into its arguments, considering quoted strings
+fn split_args(line: &str) -> Vec<&str> {
+    let mut args = Vec::new();
+    let mut rest = line.trim();
+    while !rest.is_empty() {
+        let (arg, tail) = if let Some(quoted) = rest.strip_prefix('"') {
+            quoted.split_once('"').unwrap_or((quoted, ""))
+        } else {
+            rest.split_once(char::is_whitespace).unwrap_or((rest, ""))
+        };
+        args.push(arg);
+        rest = tail.trim_start();
+    }
+    args
+}
+
+/// Parse a position `mm:ss:ff` with 75 frames per second
+fn parse_index_position(input: &str) -> Option<PositionMs> {
+    let mut parts = input.splitn(3, ':').map(|part| part.parse::<u32>().ok());
+    let minutes = parts.next()??;
+    let seconds = parts.next()??;
+    let frames = parts.next()??;
+    if seconds >= 60 || frames >= FRAMES_PER_SECOND {
+        return None;
+    }
+    let millis = (f64::from(minutes) * 60.0 + f64::from(seconds)) * 1000.0
+        + f64::from(frames) * 1000.0 / f64::from(FRAMES_PER_SECOND);
+    Some(PositionMs(millis))
+}
+
+fn non_empty(arg: Option<&&str>) -> Option<String> {
+    arg.map(|arg| arg.trim())
+        .filter(|arg| !arg.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+#[derive(Debug, Default)]
+struct PendingTrack {
+    number: u16,
+    title: Option<String>,
+    performer: Option<String>,
+    songwriter: Option<String>,
+    start: Option<PositionMs>,
+}
+
+fn finish_track(files: &mut Vec<File>, pending_track: Option<PendingTrack>) {
+    let PendingTrack {
+        number,
+        title,
+        performer,
+        songwriter,
+        start,
+    } = if let Some(pending_track) = pending_track {
+        pending_track
+    } else {
+        return;
+    };
+    let start = if let Some(start) = start {
+        start
+    } else {
+        log::warn!("Skipping track {} without INDEX 01", number);
+        return;
+    };
+    if let Some(file) = files.last_mut() {
+        file.tracks.push(Track {
+            number,
+            title,
+            performer,
+            songwriter,
+            start,
+        });
+    }
+}
+
+/// Parse a CUE sheet
+///
+/// Only audio tracks with an `INDEX 01` are considered. Pregaps
+/// (`INDEX 00`) are ignored, i.e. each track ends where the next
+/// track in the same file starts. Unsupported commands are ignored.
+pub fn parse_cue_sheet(input: &str) -> Result<CueSheet> {
+    let mut cue_sheet = CueSheet::default();
+    let mut pending_track: Option<PendingTrack> = None;
+    for (line_index, line) in input.trim_start_matches('\u{feff}').lines().enumerate() {
+        let args = split_args(line);
+        let (command, args) = if let Some((command, args)) = args.split_first() {
+            (command.to_ascii_uppercase(), args)
+        } else {
+            continue;
+        };
+        match command.as_str() {
+            "FILE" => {
+                finish_track(&mut cue_sheet.files, pending_track.take());
+                let path = non_empty(args.first()).ok_or_else(|| {
+                    Error::Other(anyhow!("Missing file path in line {}", line_index + 1))
+                })?;
+                cue_sheet.files.push(File {
+                    path,
+                    tracks: Vec::new(),
+                });
+            }
+            "TRACK" => {
+                finish_track(&mut cue_sheet.files, pending_track.take());
+                if cue_sheet.files.is_empty() {
+                    return Err(Error::Other(anyhow!(
+                        "TRACK without FILE in line {}",
+                        line_index + 1
+                    )));
+                }
+                let is_audio = args
+                    .get(1)
+                    .map_or(true, |data_type| data_type.eq_ignore_ascii_case("AUDIO"));
+                if !is_audio {
+                    continue;
+                }
+                let number = args
+                    .first()
+                    .and_then(|number| number.parse().ok())
+                    .ok_or_else(|| {
+                        Error::Other(anyhow!("Invalid track number in line {}", line_index + 1))
+                    })?;
+                pending_track = Some(PendingTrack {
+                    number,
+                    ..Default::default()
+                });
+            }
+            "INDEX" => {
+                if let Some(pending_track) = &mut pending_track {
+                    if args.first().and_then(|number| number.parse::<u8>().ok()) == Some(1) {
+                        let start = args
+                            .get(1)
+                            .and_then(|position| parse_index_position(position))
+                            .ok_or_else(|| {
+                                Error::Other(anyhow!("Invalid INDEX in line {}", line_index + 1))
+                            })?;
+                        pending_track.start = Some(start);
+                    }
+                }
+            }
+            "TITLE" => {
+                let title = non_empty(args.first());
+                if let Some(pending_track) = &mut pending_track {
+                    pending_track.title = title;
+                } else {
+                    cue_sheet.title = title;
+                }
+            }
+            "PERFORMER" => {
+                let performer = non_empty(args.first());
+                if let Some(pending_track) = &mut pending_track {
+                    pending_track.performer = performer;
+                } else {
+                    cue_sheet.performer = performer;
+                }
+            }
+            "SONGWRITER" => {
+                let songwriter = non_empty(args.first());
+                if let Some(pending_track) = &mut pending_track {
+                    pending_track.songwriter = songwriter;
+                } else {
+                    cue_sheet.songwriter = songwriter;
+                }
+            }
+            _ => {
+                log::trace!("Ignoring CUE sheet command {}", command);
+            }
+        }
+    }
+    finish_track(&mut cue_sheet.files, pending_track);
+    Ok(cue_sheet)
+}
+
+/// Create virtual tracks from all tracks of a file
+///
+/// The track that has been imported from the whole file provides
+/// all properties that are shared by the virtual tracks, e.g. the
+/// content type and audio properties. Each virtual track covers
+/// the range from its `INDEX 01` until the `INDEX 01` of the next
+/// track or the end of the file.
+pub fn import_virtual_tracks(
+    cue_sheet: &CueSheet,
+    file: &File,
+    file_track: &track::Track,
+) -> Vec<track::Track> {
+    let file_duration = match &file_track.media_source.content {
+        Content::Audio(audio_content) => audio_content.duration,
+    };
+    let file_end = file_duration.map(|duration| PositionMs(duration.to_inner()));
+    let track_total = file.tracks.len() as u16;
+    let mut virtual_tracks = Vec::with_capacity(file.tracks.len());
+    for (index, cue_track) in file.tracks.iter().enumerate() {
+        let Track {
+            number,
+            title,
+            performer,
+            songwriter,
+            start,
+        } = cue_track;
+        let end = file
+            .tracks
+            .get(index + 1)
+            .map(|next_track| next_track.start)
+            .or(file_end);
+        let range = AudioRange { start: *start, end };
+        if range.end.map_or(false, |end| end <= range.start) {
+            log::warn!(
+                "Skipping track {} with an empty range in {}",
+                number,
+                file_track.media_source.path
+            );
+            continue;
+        }
+        let mut virtual_track = file_track.clone();
+        virtual_track.media_source.path =
+            SourcePath::virtual_track(&file_track.media_source.path, &range);
+        // The digest of the whole file doesn't identify the range
+        virtual_track.media_source.content_digest = None;
+        let Content::Audio(audio_content) = &mut virtual_track.media_source.content;
+        audio_content.duration = range.duration();
+        audio_content.range = Some(range);
+        // Only loudness and waveform of the whole file are available
+        audio_content.loudness = None;
+        audio_content.loudness_range = None;
+        audio_content.true_peak = None;
+        audio_content.waveform = None;
+        // Titles, actors, cues, and lyrics of the whole file don't apply
+        virtual_track.titles = Default::default();
+        virtual_track.actors = Default::default();
+        virtual_track.cues = Default::default();
+        virtual_track.lyrics = Default::default();
+        // Tempo and key of the whole file, either read from file tags
+        // or detected by analyzing the audio stream, don't apply
+        virtual_track.metrics.tempo_bpm = None;
+        virtual_track.metrics.key_signature = Default::default();
+        virtual_track
+            .metrics
+            .flags
+            .remove(MetricsFlags::TEMPO_BPM_ANALYZED | MetricsFlags::KEY_SIGNATURE_ANALYZED);
+        if let Some(title) = title {
+            virtual_track.set_track_title(title.as_str());
+        }
+        if let Some(performer) = performer.as_ref().or_else(|| cue_sheet.performer.as_ref()) {
+            virtual_track.set_track_artist(performer.as_str());
+        }
+        if let Some(songwriter) = songwriter
+            .as_ref()
+            .or_else(|| cue_sheet.songwriter.as_ref())
+        {
+            virtual_track.set_track_composer(songwriter.as_str());
+        }
+        if let Some(album_title) = &cue_sheet.title {
+            virtual_track.set_album_title(album_title.as_str());
+        }
+        if let Some(album_artist) = &cue_sheet.performer {
+            virtual_track.set_album_artist(album_artist.as_str());
+        }
+        virtual_track.indexes.track = Index {
+            number: Some(*number),
+            total: Some(track_total),
+        };
+        virtual_tracks.push(virtual_track);
+    }
+    virtual_tracks
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,181 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::{
+    audio::{AudioContent, DurationMs},
+    media::{ContentMetadataFlags, Source},
+    music::{
+        key::{KeyCode, KeySignature},
+        time::TempoBpm,
+    },
+    track::lyrics::Lyrics,
+    util::{clock::DateTime, Canonical},
+};
+
+const CUE_SHEET: &str = r#"REM GENRE Electronic
+REM DATE 2020
+PERFORMER "Various Artists"
+TITLE "Live @ Club"
+FILE "Live @ Club.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Intro"
+    PERFORMER "DJ One"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Second Track"
+    PERFORMER "Artist Two"
+    SONGWRITER "Writer"
+    INDEX 00 05:10:00
+    INDEX 01 05:12:37
+  TRACK 03 AUDIO
+    TITLE "No Index"
+  TRACK 04 AUDIO
+    TITLE "Outro"
+    INDEX 01 10:00:00
+"#;
+
+#[test]
+fn parse_index_positions() {
+    assert_eq!(Some(PositionMs(0.0)), parse_index_position("00:00:00"));
+    assert_eq!(
+        Some(PositionMs(312_000.0)),
+        parse_index_position("05:12:00")
+    );
+    assert_eq!(Some(PositionMs(61_000.0)), parse_index_position("01:01:00"));
+    assert_eq!(None, parse_index_position("00:60:00"));
+    assert_eq!(None, parse_index_position("00:00:75"));
+    assert_eq!(None, parse_index_position("00:00"));
+    assert_eq!(
+        Some(PositionMs(5_999_999_940_000.0)),
+        parse_index_position("99999999:00:00")
+    );
+}
+
+#[test]
+fn split_quoted_args() {
+    assert_eq!(
+        vec!["FILE", "Live @ Club.flac", "WAVE"],
+        split_args(r#"  FILE "Live @ Club.flac" WAVE"#)
+    );
+    assert_eq!(
+        vec!["INDEX", "01", "00:00:00"],
+        split_args("INDEX 01 00:00:00")
+    );
+    assert!(split_args("   ").is_empty());
+}
+
+#[test]
+fn parse_cue_sheet_with_single_file() {
+    let cue_sheet = parse_cue_sheet(CUE_SHEET).unwrap();
+    assert_eq!(Some("Live @ Club"), cue_sheet.title.as_deref());
+    assert_eq!(Some("Various Artists"), cue_sheet.performer.as_deref());
+    assert_eq!(1, cue_sheet.files.len());
+    let file = &cue_sheet.files[0];
+    assert_eq!("Live @ Club.flac", file.path);
+    // The track without INDEX 01 is skipped
+    assert_eq!(
+        vec![1, 2, 4],
+        file.tracks
+            .iter()
+            .map(|track| track.number)
+            .collect::<Vec<_>>()
+    );
+    let second_track = &file.tracks[1];
+    assert_eq!(Some("Second Track"), second_track.title.as_deref());
+    assert_eq!(Some("Artist Two"), second_track.performer.as_deref());
+    assert_eq!(Some("Writer"), second_track.songwriter.as_deref());
+    assert_eq!(
+        PositionMs(312_000.0 + 37.0 * 1000.0 / 75.0),
+        second_track.start
+    );
+}
+
+#[test]
+fn parse_cue_sheet_track_without_file() {
+    assert!(parse_cue_sheet("TRACK 01 AUDIO\n  INDEX 01 00:00:00\n").is_err());
+}
+
+#[test]
+fn import_virtual_tracks_from_file() {
+    let cue_sheet = parse_cue_sheet(CUE_SHEET).unwrap();
+    let file = &cue_sheet.files[0];
+    let media_source = Source {
+        collected_at: DateTime::now_utc(),
+        synchronized_at: None,
+        path: "Live/Live @ Club.flac".to_owned().into(),
+        content_type: "audio/flac".to_owned(),
+        content_digest: Some(vec![1, 2, 3]),
+        content_metadata_flags: ContentMetadataFlags::RELIABLE,
+        content: Content::Audio(AudioContent {
+            duration: Some(DurationMs::from_inner(900_000.0)),
+            ..Default::default()
+        }),
+        artwork: Default::default(),
+    };
+    let mut file_track = track::Track::new_from_media_source(media_source);
+    file_track.set_track_title("Live @ Club");
+    file_track.metrics.tempo_bpm = Some(TempoBpm(124.0));
+    file_track.metrics.key_signature = KeySignature::new(KeyCode::Amin);
+    file_track.metrics.flags =
+        MetricsFlags::TEMPO_BPM_ANALYZED | MetricsFlags::KEY_SIGNATURE_ANALYZED;
+    file_track.lyrics = Canonical::tie(vec![Lyrics {
+        language: None,
+        text: Some("Lyrics of the whole file".to_owned()),
+        lines: vec![],
+    }]);
+    let virtual_tracks = import_virtual_tracks(&cue_sheet, file, &file_track);
+    assert_eq!(3, virtual_tracks.len());
+
+    let first_track = &virtual_tracks[0];
+    assert_eq!(
+        "Live/Live @ Club.flac#t=0,312.493",
+        first_track.media_source.path.as_str()
+    );
+    assert_eq!(None, first_track.media_source.content_digest);
+    assert_eq!(Some("Intro"), first_track.track_title());
+    assert_eq!(Some("DJ One"), first_track.track_artist());
+    assert_eq!(Some("Live @ Club"), first_track.album_title());
+    assert_eq!(Some("Various Artists"), first_track.album_artist());
+    assert_eq!(Some(1), first_track.indexes.track.number);
+    assert_eq!(Some(3), first_track.indexes.track.total);
+    assert_eq!(None, first_track.metrics.tempo_bpm);
+    assert!(first_track.metrics.key_signature.is_unknown());
+    assert_eq!(MetricsFlags::empty(), first_track.metrics.flags);
+    assert!(first_track.lyrics.is_empty());
+
+    let last_track = &virtual_tracks[2];
+    assert_eq!(
+        "Live/Live @ Club.flac#t=600,900",
+        last_track.media_source.path.as_str()
+    );
+    let Content::Audio(audio_content) = &last_track.media_source.content;
+    assert_eq!(
+        Some(DurationMs::from_inner(300_000.0)),
+        audio_content.duration
+    );
+    assert_eq!(
+        Some(AudioRange {
+            start: PositionMs(600_000.0),
+            end: Some(PositionMs(900_000.0)),
+        }),
+        audio_content.range
+    );
+    // Falls back to the performer of the CUE sheet
+    assert_eq!(Some("Various Artists"), last_track.track_artist());
+}
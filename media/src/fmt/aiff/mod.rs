@@ -0,0 +1,296 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    io::import::{self, *},
+    Result,
+};
+
+use aoide_core::{
+    audio::{
+        channel::ChannelCount,
+        signal::{BitrateBps, SampleRateHz},
+        AudioContent,
+    },
+    media::{Content, ContentMetadataFlags},
+    track::Track,
+};
+
+use semval::IsValid as _;
+use std::{borrow::Cow, time::Duration};
+
+use super::{
+    id3v2,
+    iff::{
+        self, decode_text, read_chunk_data, read_chunk_header, read_id3_chunk, skip_chunk_data,
+        ByteOrder, ChunkId, TextMetadata,
+    },
+};
+
+const FORM_CHUNK_ID: ChunkId = *b"FORM";
+const AIFF_FORM_TYPE: ChunkId = *b"AIFF";
+const AIFC_FORM_TYPE: ChunkId = *b"AIFC";
+const COMM_CHUNK_ID: ChunkId = *b"COMM";
+const NAME_CHUNK_ID: ChunkId = *b"NAME";
+const AUTH_CHUNK_ID: ChunkId = *b"AUTH";
+const COPYRIGHT_CHUNK_ID: ChunkId = *b"(c) ";
+const ANNO_CHUNK_ID: ChunkId = *b"ANNO";
+
+// Both lower and upper case variants are used in the wild
+const ID3_CHUNK_IDS: [ChunkId; 2] = [*b"ID3 ", *b"id3 "];
+
+// Uncompressed sample data in AIFF-C files
+const AIFC_UNCOMPRESSED_TYPES: [ChunkId; 6] =
+    [*b"NONE", *b"sowt", *b"twos", *b"raw ", *b"in24", *b"in32"];
+
+/// Decode an IEEE 754 80-bit extended precision number
+fn parse_extended_float(bytes: [u8; 10]) -> f64 {
+    let sign_exponent = u16::from_be_bytes([bytes[0], bytes[1]]);
+    let exponent = i32::from(sign_exponent & 0x7FFF);
+    let mantissa = u64::from_be_bytes([
+        bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7], bytes[8], bytes[9],
+    ]);
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+    let value = mantissa as f64 * 2f64.powi(exponent - 16383 - 63);
+    if sign_exponent & 0x8000 == 0 {
+        value
+    } else {
+        -value
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CommonChunk {
+    channel_count: u16,
+    sample_frame_count: u32,
+    sample_size: u16,
+    sample_rate: f64,
+    compressed: bool,
+}
+
+/// Calculate the duration from the number of sample frames
+///
+/// Returns `None` if the result is not representable, e.g. for
+/// tiny sample rates.
+fn duration_of_sample_frames(
+    sample_frame_count: u32,
+    sample_rate: SampleRateHz,
+) -> Option<Duration> {
+    let secs = f64::from(sample_frame_count) / sample_rate.to_inner();
+    // Duration::from_secs_f64() would panic otherwise
+    (secs.is_finite() && secs >= 0.0 && secs < u64::MAX as f64)
+        .then(|| Duration::from_secs_f64(secs))
+}
+
+fn parse_common_chunk(data: &[u8], form_type: ChunkId) -> Option<CommonChunk> {
+    if data.len() < 18 {
+        return None;
+    }
+    let channel_count = ByteOrder::BigEndian.read_u16([data[0], data[1]]);
+    let sample_frame_count = ByteOrder::BigEndian.read_u32([data[2], data[3], data[4], data[5]]);
+    let sample_size = ByteOrder::BigEndian.read_u16([data[6], data[7]]);
+    let sample_rate = parse_extended_float([
+        data[8], data[9], data[10], data[11], data[12], data[13], data[14], data[15], data[16],
+        data[17],
+    ]);
+    let compressed = if form_type == AIFC_FORM_TYPE {
+        if data.len() < 22 {
+            return None;
+        }
+        let compression_type = [data[18], data[19], data[20], data[21]];
+        !AIFC_UNCOMPRESSED_TYPES.contains(&compression_type)
+    } else {
+        false
+    };
+    Some(CommonChunk {
+        channel_count,
+        sample_frame_count,
+        sample_size,
+        sample_rate,
+        compressed,
+    })
+}
+
+/// Read and verify the file header
+///
+/// Returns the form type that distinguishes AIFF from AIFF-C files.
+fn read_file_header(reader: &mut Box<dyn Reader>) -> Result<ChunkId> {
+    let mut file_header = [0u8; 12];
+    reader.read_exact(&mut file_header)?;
+    let form_type = [
+        file_header[8],
+        file_header[9],
+        file_header[10],
+        file_header[11],
+    ];
+    if file_header[0..4] != FORM_CHUNK_ID
+        || (form_type != AIFF_FORM_TYPE && form_type != AIFC_FORM_TYPE)
+    {
+        return Err(anyhow::anyhow!("Not an AIFF/AIFF-C file").into());
+    }
+    Ok(form_type)
+}
+
+#[derive(Debug)]
+pub struct ImportTrack;
+
+impl import::ImportTrack for ImportTrack {
+    fn import_track(
+        &self,
+        config: &ImportTrackConfig,
+        flags: ImportTrackFlags,
+        mut track: Track,
+        reader: &mut Box<dyn Reader>,
+    ) -> Result<Track> {
+        let form_type = read_file_header(reader)?;
+
+        let mut common_chunk = None;
+        let mut id3_tag = None;
+        let mut text_metadata = TextMetadata::default();
+        while let Some(chunk_header) = read_chunk_header(reader, ByteOrder::BigEndian)? {
+            match chunk_header.id {
+                COMM_CHUNK_ID => {
+                    common_chunk =
+                        parse_common_chunk(&read_chunk_data(reader, chunk_header)?, form_type);
+                }
+                NAME_CHUNK_ID => {
+                    text_metadata.title = decode_text(&read_chunk_data(reader, chunk_header)?);
+                }
+                AUTH_CHUNK_ID => {
+                    text_metadata.artist = decode_text(&read_chunk_data(reader, chunk_header)?);
+                }
+                COPYRIGHT_CHUNK_ID => {
+                    text_metadata.copyright = decode_text(&read_chunk_data(reader, chunk_header)?);
+                }
+                ANNO_CHUNK_ID => {
+                    // Multiple annotation chunks are allowed, only the first one is used
+                    let annotation = decode_text(&read_chunk_data(reader, chunk_header)?);
+                    if text_metadata.comment.is_none() {
+                        text_metadata.comment = annotation;
+                    }
+                }
+                id if ID3_CHUNK_IDS.contains(&id) => {
+                    if let Some(tag) = read_id3_chunk(reader, chunk_header)? {
+                        id3_tag = Some(tag);
+                    }
+                }
+                _ => {
+                    // Includes the sound data chunk (SSND)
+                    skip_chunk_data(reader, chunk_header)?;
+                }
+            }
+        }
+
+        let common_chunk = common_chunk.ok_or_else(|| anyhow::anyhow!("Missing common chunk"))?;
+
+        if track
+            .media_source
+            .content_metadata_flags
+            .update(ContentMetadataFlags::RELIABLE)
+        {
+            let channel_count = ChannelCount(common_chunk.channel_count);
+            let channels = if channel_count.is_valid() {
+                Some(channel_count.into())
+            } else {
+                log::warn!("Invalid channel count: {}", channel_count.0);
+                None
+            };
+            let sample_rate = SampleRateHz::from_inner(common_chunk.sample_rate);
+            let sample_rate = if sample_rate.is_valid() {
+                Some(sample_rate)
+            } else {
+                log::warn!("Invalid sample rate: {}", sample_rate);
+                None
+            };
+            let duration = sample_rate
+                .and_then(|sample_rate| {
+                    let duration =
+                        duration_of_sample_frames(common_chunk.sample_frame_count, sample_rate);
+                    if duration.is_none() {
+                        log::warn!(
+                            "Invalid duration: {} sample frames at {}",
+                            common_chunk.sample_frame_count,
+                            sample_rate
+                        );
+                    }
+                    duration
+                })
+                .map(Into::into);
+            // The bitrate of compressed data is unknown
+            let bitrate = if common_chunk.compressed {
+                None
+            } else {
+                let bitrate = BitrateBps::from_inner(
+                    common_chunk.sample_rate
+                        * f64::from(common_chunk.channel_count)
+                        * f64::from(common_chunk.sample_size),
+                );
+                if bitrate.is_valid() {
+                    Some(bitrate)
+                } else {
+                    log::warn!("Invalid bitrate: {}", bitrate);
+                    None
+                }
+            };
+            let loudness = id3_tag.as_ref().and_then(id3v2::import_loudness);
+            let encoder = id3_tag
+                .as_ref()
+                .and_then(id3v2::import_encoder)
+                .map(Cow::into_owned);
+            let audio_content = AudioContent {
+                duration,
+                channels,
+                sample_rate,
+                bitrate,
+                bitrate_mode: None,
+                loudness,
+                loudness_range: None,
+                true_peak: None,
+                waveform: None,
+                range: None,
+                encoder,
+            };
+            track.media_source.content = Content::Audio(audio_content);
+        }
+
+        // Prefer the more expressive ID3v2 tag over the text chunks
+        if let Some(id3_tag) = id3_tag {
+            id3v2::import_metadata_into_track(&id3_tag, config, flags, track)
+        } else {
+            Ok(iff::import_text_metadata_into_track(
+                config,
+                text_metadata,
+                track,
+            ))
+        }
+    }
+
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        read_file_header(reader)?;
+        let id3_tag = iff::find_id3_chunk(reader, ByteOrder::BigEndian, &ID3_CHUNK_IDS)?;
+        Ok(id3_tag.as_ref().and_then(id3v2::load_artwork_image))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
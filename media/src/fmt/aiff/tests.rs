@@ -0,0 +1,74 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+#[test]
+fn parse_extended_float_sample_rates() {
+    assert_eq!(
+        44100.0,
+        parse_extended_float([0x40, 0x0E, 0xAC, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])
+    );
+    assert_eq!(
+        48000.0,
+        parse_extended_float([0x40, 0x0E, 0xBB, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00])
+    );
+    assert_eq!(0.0, parse_extended_float([0x00; 10]));
+}
+
+#[test]
+fn parse_common_chunk_aifc_compression() {
+    let mut data = vec![
+        0x00, 0x02, // 2 channels
+        0x00, 0x00, 0xAC, 0x44, // 44100 sample frames
+        0x00, 0x10, // 16 bits/sample
+        0x40, 0x0E, 0xAC, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 44100 Hz
+    ];
+    let common_chunk = parse_common_chunk(&data, AIFF_FORM_TYPE).unwrap();
+    assert_eq!(2, common_chunk.channel_count);
+    assert_eq!(44100, common_chunk.sample_frame_count);
+    assert_eq!(16, common_chunk.sample_size);
+    assert_eq!(44100.0, common_chunk.sample_rate);
+    assert!(!common_chunk.compressed);
+    // The compression type is mandatory for AIFF-C
+    assert!(parse_common_chunk(&data, AIFC_FORM_TYPE).is_none());
+    data.extend_from_slice(b"sowt");
+    assert!(
+        !parse_common_chunk(&data, AIFC_FORM_TYPE)
+            .unwrap()
+            .compressed
+    );
+    data.truncate(18);
+    data.extend_from_slice(b"ima4");
+    assert!(
+        parse_common_chunk(&data, AIFC_FORM_TYPE)
+            .unwrap()
+            .compressed
+    );
+}
+
+#[test]
+fn duration_of_sample_frames_with_tiny_sample_rate() {
+    assert_eq!(
+        Some(Duration::from_secs(2)),
+        duration_of_sample_frames(88200, SampleRateHz::from_inner(44100.0))
+    );
+    assert_eq!(
+        None,
+        duration_of_sample_frames(u32::MAX, SampleRateHz::min())
+    );
+}
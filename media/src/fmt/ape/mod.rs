@@ -0,0 +1,148 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    io::import::{self, *},
+    Result,
+};
+
+use aoide_core::track::Track;
+
+use std::io::SeekFrom;
+
+use super::apev2::{self, StreamProperties};
+
+const MAGIC: &[u8] = b"MAC ";
+
+// The layout of the headers has been changed with version 3.98
+const VERSION_WITH_DESCRIPTOR: u16 = 3980;
+
+const DESCRIPTOR_SIZE: usize = 52;
+const HEADER_SIZE: usize = 24;
+const OLD_HEADER_SIZE: usize = 32;
+
+const COMPRESSION_LEVEL_EXTRA_HIGH: u16 = 4000;
+
+fn le_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn le_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn sample_count(total_frames: u32, blocks_per_frame: u32, final_frame_blocks: u32) -> Option<u64> {
+    if total_frames == 0 {
+        return None;
+    }
+    Some(u64::from(total_frames - 1) * u64::from(blocks_per_frame) + u64::from(final_frame_blocks))
+}
+
+/// Parse the header of files created with version 3.98 or newer
+fn parse_header(header: &[u8]) -> Option<StreamProperties> {
+    if header.len() < HEADER_SIZE {
+        return None;
+    }
+    let blocks_per_frame = le_u32(header, 4);
+    let final_frame_blocks = le_u32(header, 8);
+    let total_frames = le_u32(header, 12);
+    let channel_count = le_u16(header, 18);
+    let sample_rate = le_u32(header, 20);
+    Some(StreamProperties {
+        channel_count,
+        sample_rate,
+        sample_count: sample_count(total_frames, blocks_per_frame, final_frame_blocks),
+    })
+}
+
+/// Parse the header of files created with versions prior to 3.98
+///
+/// The header immediately follows the magic bytes and the version.
+fn parse_old_header(version: u16, header: &[u8]) -> Option<StreamProperties> {
+    if header.len() < OLD_HEADER_SIZE {
+        return None;
+    }
+    let compression_level = le_u16(header, 6);
+    let channel_count = le_u16(header, 10);
+    let sample_rate = le_u32(header, 12);
+    let total_frames = le_u32(header, 24);
+    let final_frame_blocks = le_u32(header, 28);
+    let blocks_per_frame = if version >= 3950 {
+        73_728 * 4
+    } else if version >= 3900
+        || (version >= 3800 && compression_level == COMPRESSION_LEVEL_EXTRA_HIGH)
+    {
+        73_728
+    } else {
+        9_216
+    };
+    Some(StreamProperties {
+        channel_count,
+        sample_rate,
+        sample_count: sample_count(total_frames, blocks_per_frame, final_frame_blocks),
+    })
+}
+
+#[derive(Debug)]
+pub struct ImportTrack;
+
+impl import::ImportTrack for ImportTrack {
+    fn import_track(
+        &self,
+        config: &ImportTrackConfig,
+        flags: ImportTrackFlags,
+        track: Track,
+        reader: &mut Box<dyn Reader>,
+    ) -> Result<Track> {
+        apev2::skip_id3v2_tag(reader)?;
+        let mut descriptor = [0u8; DESCRIPTOR_SIZE];
+        reader.read_exact(&mut descriptor)?;
+        if !descriptor.starts_with(MAGIC) {
+            return Err(anyhow::anyhow!("Not a Monkey's Audio file").into());
+        }
+        let version = le_u16(&descriptor, 4);
+        let stream_props = if version >= VERSION_WITH_DESCRIPTOR {
+            // The header follows the descriptor
+            let descriptor_size = le_u32(&descriptor, 8) as usize;
+            if descriptor_size < DESCRIPTOR_SIZE {
+                return Err(anyhow::anyhow!("Invalid descriptor size: {}", descriptor_size).into());
+            }
+            reader.seek(SeekFrom::Current(
+                (descriptor_size - DESCRIPTOR_SIZE) as i64,
+            ))?;
+            let mut header = [0u8; HEADER_SIZE];
+            reader.read_exact(&mut header)?;
+            parse_header(&header)
+        } else {
+            parse_old_header(version, &descriptor)
+        }
+        .ok_or_else(|| anyhow::anyhow!("Invalid Monkey's Audio header"))?;
+        apev2::import_track(reader, stream_props, config, flags, track)
+    }
+
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        apev2::load_artwork_image(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests;
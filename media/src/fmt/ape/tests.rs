@@ -0,0 +1,70 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+#[test]
+fn sample_count_without_frames() {
+    assert_eq!(None, sample_count(0, 73_728, 0));
+    assert_eq!(Some(73_728 + 1_000), sample_count(2, 73_728, 1_000));
+}
+
+#[test]
+fn parse_header_stereo() {
+    let header = [
+        0x00, 0x00, 0x00, 0x00, // compression level and format flags
+        0x00, 0x20, 0x01, 0x00, // 73_728 blocks per frame
+        0xE8, 0x03, 0x00, 0x00, // 1_000 final frame blocks
+        0x03, 0x00, 0x00, 0x00, // 3 total frames
+        0x10, 0x00, // 16 bits per sample
+        0x02, 0x00, // 2 channels
+        0x44, 0xAC, 0x00, 0x00, // 44100 Hz
+    ];
+    assert_eq!(
+        Some(StreamProperties {
+            channel_count: 2,
+            sample_rate: 44_100,
+            sample_count: Some(2 * 73_728 + 1_000),
+        }),
+        parse_header(&header)
+    );
+    assert_eq!(None, parse_header(&header[..HEADER_SIZE - 1]));
+}
+
+#[test]
+fn parse_old_header_blocks_per_frame_by_version() {
+    let mut header = [0u8; OLD_HEADER_SIZE];
+    header[..4].copy_from_slice(MAGIC);
+    header[10] = 0x01; // 1 channel
+    header[12..16].copy_from_slice(&48_000u32.to_le_bytes());
+    header[24..28].copy_from_slice(&2u32.to_le_bytes()); // 2 total frames
+    header[28..32].copy_from_slice(&100u32.to_le_bytes()); // final frame blocks
+    let expected = |blocks_per_frame| {
+        Some(StreamProperties {
+            channel_count: 1,
+            sample_rate: 48_000,
+            sample_count: Some(blocks_per_frame + 100),
+        })
+    };
+    assert_eq!(expected(73_728 * 4), parse_old_header(3950, &header));
+    assert_eq!(expected(73_728), parse_old_header(3900, &header));
+    assert_eq!(expected(9_216), parse_old_header(3800, &header));
+    // Extra high compression
+    header[6..8].copy_from_slice(&COMPRESSION_LEVEL_EXTRA_HIGH.to_le_bytes());
+    assert_eq!(expected(73_728), parse_old_header(3800, &header));
+    assert_eq!(None, parse_old_header(3800, &header[..OLD_HEADER_SIZE - 1]));
+}
@@ -0,0 +1,344 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    io::import::{ArtworkImage, ImportTrackConfig, ImportTrackFlags, Reader},
+    util::{
+        digest::MediaDigest, parse_artwork_from_embedded_image,
+        select_artwork_image_from_embedded_images,
+    },
+    Result,
+};
+
+use aoide_core::{
+    audio::{
+        channel::ChannelCount,
+        signal::{BitrateBps, SampleRateHz},
+        AudioContent,
+    },
+    media::{Content, ContentMetadataFlags},
+    track::Track,
+};
+
+use semval::IsValid as _;
+use std::{io::SeekFrom, time::Duration};
+
+use super::vorbis;
+
+const PREAMBLE: &[u8] = b"APETAGEX";
+
+const FOOTER_SIZE: u32 = 32;
+
+const ID3V1_TAG_SIZE: u64 = 128;
+
+const ID3V2_HEADER_SIZE: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemValue {
+    /// UTF-8 text with multiple values separated by NULL characters
+    Text(String),
+    Binary(Vec<u8>),
+    Locator(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub key: String,
+    pub value: ItemValue,
+}
+
+/// An APEv2 (or APEv1) tag
+///
+/// https://wiki.hydrogenaud.io/index.php?title=APEv2_specification
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tag {
+    pub items: Vec<Item>,
+}
+
+impl Tag {
+    fn binary_item(&self, key: &str) -> Option<&[u8]> {
+        self.items.iter().find_map(|item| match &item.value {
+            ItemValue::Binary(data) if item.key.eq_ignore_ascii_case(key) => Some(data.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Translate all text items into Vorbis comments
+    ///
+    /// Most keys are identical, only some common keys need to be mapped
+    /// to the corresponding Vorbis field names. The mapping conforms to
+    /// TagLib, i.e. Mixxx.
+    pub fn to_vorbis_comments(&self) -> Vec<(String, String)> {
+        let mut vorbis_comments = Vec::with_capacity(self.items.len());
+        for item in &self.items {
+            let text = match &item.value {
+                ItemValue::Text(text) => text,
+                _ => continue,
+            };
+            let key = item.key.to_ascii_uppercase();
+            let key = match key.as_str() {
+                "TRACK" => "TRACKNUMBER".to_owned(),
+                "DISC" => "DISCNUMBER".to_owned(),
+                "YEAR" => "DATE".to_owned(),
+                "ALBUM ARTIST" => "ALBUMARTIST".to_owned(),
+                "MIXARTIST" => "REMIXER".to_owned(),
+                "PUBLISHER" => "LABEL".to_owned(),
+                _ => key,
+            };
+            for value in text.split('\0').filter(|value| !value.is_empty()) {
+                vorbis_comments.push((key.clone(), value.to_owned()));
+            }
+        }
+        vorbis_comments
+    }
+}
+
+fn parse_items(mut data: &[u8], item_count: u32) -> Vec<Item> {
+    let mut items = Vec::with_capacity(item_count.min(256) as usize);
+    for _ in 0..item_count {
+        if data.len() < 8 {
+            log::warn!("Truncated APE tag item");
+            break;
+        }
+        let value_size = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let item_flags = u32::from_le_bytes([data[4], data[5], data[6], data[7]]);
+        data = &data[8..];
+        let key_len = match data.iter().position(|b| *b == 0) {
+            Some(key_len) => key_len,
+            None => {
+                log::warn!("Unterminated APE tag item key");
+                break;
+            }
+        };
+        let key = String::from_utf8_lossy(&data[..key_len]).into_owned();
+        data = &data[key_len + 1..];
+        if data.len() < value_size {
+            log::warn!("Truncated value of APE tag item '{}'", key);
+            break;
+        }
+        let value_data = &data[..value_size];
+        data = &data[value_size..];
+        let value = match (item_flags >> 1) & 0b11 {
+            0 => ItemValue::Text(String::from_utf8_lossy(value_data).into_owned()),
+            1 => ItemValue::Binary(value_data.to_vec()),
+            2 => ItemValue::Locator(String::from_utf8_lossy(value_data).into_owned()),
+            _ => {
+                log::warn!("Unsupported value type of APE tag item '{}'", key);
+                continue;
+            }
+        };
+        items.push(Item { key, value });
+    }
+    items
+}
+
+/// Read an APE tag at the end of the file
+///
+/// The APE tag might be followed by an ID3v1 tag. APE tags at the
+/// start of a file are not supported.
+pub fn read_tag(reader: &mut Box<dyn Reader>) -> Result<Option<Tag>> {
+    let mut end_pos = reader.seek(SeekFrom::End(0))?;
+    if end_pos >= ID3V1_TAG_SIZE {
+        reader.seek(SeekFrom::Start(end_pos - ID3V1_TAG_SIZE))?;
+        let mut id3v1_header = [0u8; 3];
+        reader.read_exact(&mut id3v1_header)?;
+        if &id3v1_header == b"TAG" {
+            end_pos -= ID3V1_TAG_SIZE;
+        }
+    }
+    if end_pos < u64::from(FOOTER_SIZE) {
+        return Ok(None);
+    }
+    reader.seek(SeekFrom::Start(end_pos - u64::from(FOOTER_SIZE)))?;
+    let mut footer = [0u8; FOOTER_SIZE as usize];
+    reader.read_exact(&mut footer)?;
+    if !footer.starts_with(PREAMBLE) {
+        return Ok(None);
+    }
+    // The tag size includes the footer but excludes the optional header
+    let tag_size = u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]);
+    let item_count = u32::from_le_bytes([footer[16], footer[17], footer[18], footer[19]]);
+    if tag_size < FOOTER_SIZE || u64::from(tag_size) > end_pos {
+        log::warn!("Invalid APE tag size: {}", tag_size);
+        return Ok(None);
+    }
+    reader.seek(SeekFrom::Start(end_pos - u64::from(tag_size)))?;
+    let mut data = vec![0u8; (tag_size - FOOTER_SIZE) as usize];
+    reader.read_exact(&mut data)?;
+    let items = parse_items(&data, item_count);
+    Ok(Some(Tag { items }))
+}
+
+/// Skip an optional ID3v2 tag at the start of the file
+///
+/// Returns the position of the first byte after the ID3v2 tag.
+pub fn skip_id3v2_tag(reader: &mut Box<dyn Reader>) -> Result<u64> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut header = [0u8; ID3V2_HEADER_SIZE];
+    reader.read_exact(&mut header)?;
+    if !header.starts_with(b"ID3") {
+        return Ok(reader.seek(SeekFrom::Start(0))?);
+    }
+    // Syncsafe integer with 7 bits per byte
+    let tag_size = header[6..10]
+        .iter()
+        .fold(0u64, |size, b| (size << 7) | u64::from(b & 0x7F));
+    // Optional footer
+    let footer_size = if header[5] & 0x10 != 0 {
+        ID3V2_HEADER_SIZE as u64
+    } else {
+        0
+    };
+    Ok(reader.seek(SeekFrom::Start(
+        ID3V2_HEADER_SIZE as u64 + tag_size + footer_size,
+    ))?)
+}
+
+/// Basic properties of an audio stream, parsed from format specific headers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamProperties {
+    pub channel_count: u16,
+    pub sample_rate: u32,
+    pub sample_count: Option<u64>,
+}
+
+/// The image data of all cover art items in the order of preference
+fn artwork_images(ape_tag: &Tag) -> impl Iterator<Item = &[u8]> {
+    // The binary value starts with a NULL-terminated description,
+    // usually the file name, followed by the image data
+    ape_tag
+        .binary_item("Cover Art (Front)")
+        .into_iter()
+        .chain(ape_tag.binary_item("Cover Art (Media)"))
+        .chain(ape_tag.binary_item("Cover Art (Leaflet)"))
+        .chain(ape_tag.binary_item("Cover Art (Other)"))
+        .filter_map(|data| {
+            data.iter()
+                .position(|b| *b == 0)
+                .map(|pos| &data[pos + 1..])
+        })
+}
+
+/// Load the embedded artwork image from an APE tag
+pub fn load_artwork_image(reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+    Ok(read_tag(reader)?.and_then(|ape_tag| {
+        select_artwork_image_from_embedded_images(
+            artwork_images(&ape_tag).map(|image_data| (image_data, None)),
+        )
+    }))
+}
+
+/// Import a track from a file with audio properties parsed from the
+/// stream headers and metadata read from an APE tag
+pub fn import_track(
+    reader: &mut Box<dyn Reader>,
+    stream_props: StreamProperties,
+    config: &ImportTrackConfig,
+    flags: ImportTrackFlags,
+    mut track: Track,
+) -> Result<Track> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let ape_tag = read_tag(reader)?.unwrap_or_default();
+    let vorbis_comments = ape_tag.to_vorbis_comments();
+
+    if track
+        .media_source
+        .content_metadata_flags
+        .update(ContentMetadataFlags::RELIABLE)
+    {
+        let channel_count = ChannelCount(stream_props.channel_count);
+        let channels = if channel_count.is_valid() {
+            Some(channel_count.into())
+        } else {
+            log::warn!("Invalid channel count: {}", channel_count.0);
+            None
+        };
+        let sample_rate = SampleRateHz::from_inner(stream_props.sample_rate.into());
+        let sample_rate = if sample_rate.is_valid() {
+            Some(sample_rate)
+        } else {
+            log::warn!("Invalid sample rate: {}", sample_rate);
+            None
+        };
+        let mut duration = None;
+        let mut bitrate = None;
+        if let Some(sample_count) = stream_props.sample_count {
+            if stream_props.sample_rate > 0 {
+                let secs = sample_count as f64 / f64::from(stream_props.sample_rate);
+                duration = Some(Duration::from_secs_f64(secs).into());
+                if secs > 0.0 {
+                    // Average bitrate, including the (small) overhead of all headers
+                    let avg_bitrate = BitrateBps::from_inner(file_len as f64 * 8.0 / secs);
+                    if avg_bitrate.is_valid() {
+                        bitrate = Some(avg_bitrate);
+                    } else {
+                        log::warn!("Invalid bitrate: {}", avg_bitrate);
+                    }
+                }
+            }
+        }
+        let loudness = vorbis::import_loudness(&vorbis_comments);
+        let encoder = vorbis::import_encoder(&vorbis_comments).map(Into::into);
+        let audio_content = AudioContent {
+            duration,
+            channels,
+            sample_rate,
+            bitrate,
+            bitrate_mode: None,
+            loudness,
+            loudness_range: None,
+            true_peak: None,
+            waveform: None,
+            range: None,
+            encoder,
+        };
+        track.media_source.content = Content::Audio(audio_content);
+    }
+
+    let mut track = vorbis::import_metadata_into_track(&vorbis_comments, config, flags, track)?;
+
+    if flags.contains(ImportTrackFlags::ARTWORK) {
+        let mut image_digest = if flags.contains(ImportTrackFlags::ARTWORK_DIGEST) {
+            if flags.contains(ImportTrackFlags::ARTWORK_DIGEST_SHA256) {
+                // Compatibility
+                MediaDigest::sha256()
+            } else {
+                // Default
+                MediaDigest::new()
+            }
+        } else {
+            Default::default()
+        };
+        let artwork = artwork_images(&ape_tag)
+            .filter_map(|image_data| {
+                parse_artwork_from_embedded_image(image_data, None, &mut image_digest)
+            })
+            .next();
+        if let Some(artwork) = artwork {
+            track.media_source.artwork = artwork;
+        }
+    }
+
+    Ok(track)
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
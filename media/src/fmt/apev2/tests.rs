@@ -0,0 +1,92 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+fn encode_item(key: &str, item_flags: u32, value: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    data.extend_from_slice(&item_flags.to_le_bytes());
+    data.extend_from_slice(key.as_bytes());
+    data.push(0);
+    data.extend_from_slice(value);
+    data
+}
+
+#[test]
+fn parse_text_and_binary_items() {
+    let mut data = encode_item("Title", 0, b"Title");
+    data.extend(encode_item("Genre", 0, b"Deep House\0Techno"));
+    data.extend(encode_item(
+        "Cover Art (Front)",
+        0b10,
+        b"cover.jpg\0\xFF\xD8",
+    ));
+    let items = parse_items(&data, 3);
+    assert_eq!(
+        vec![
+            Item {
+                key: "Title".to_owned(),
+                value: ItemValue::Text("Title".to_owned()),
+            },
+            Item {
+                key: "Genre".to_owned(),
+                value: ItemValue::Text("Deep House\0Techno".to_owned()),
+            },
+            Item {
+                key: "Cover Art (Front)".to_owned(),
+                value: ItemValue::Binary(b"cover.jpg\0\xFF\xD8".to_vec()),
+            },
+        ],
+        items
+    );
+    // Truncated items are skipped
+    assert_eq!(2, parse_items(&data[..data.len() - 1], 3).len());
+}
+
+#[test]
+fn translate_items_into_vorbis_comments() {
+    let tag = Tag {
+        items: vec![
+            Item {
+                key: "Track".to_owned(),
+                value: ItemValue::Text("3/12".to_owned()),
+            },
+            Item {
+                key: "Album Artist".to_owned(),
+                value: ItemValue::Text("Various Artists".to_owned()),
+            },
+            Item {
+                key: "Genre".to_owned(),
+                value: ItemValue::Text("Deep House\0Techno".to_owned()),
+            },
+            Item {
+                key: "Cover Art (Front)".to_owned(),
+                value: ItemValue::Binary(vec![0]),
+            },
+        ],
+    };
+    assert_eq!(
+        vec![
+            ("TRACKNUMBER".to_owned(), "3/12".to_owned()),
+            ("ALBUMARTIST".to_owned(), "Various Artists".to_owned()),
+            ("GENRE".to_owned(), "Deep House".to_owned()),
+            ("GENRE".to_owned(), "Techno".to_owned()),
+        ],
+        tag.to_vorbis_comments()
+    );
+}
@@ -16,9 +16,13 @@
 ///////////////////////////////////////////////////////////////////////
 
 use crate::{
-    io::import::{self, *},
+    io::{
+        export::{self, ExportTrackConfig, ExportTrackFlags},
+        import::{self, *},
+    },
     util::{
-        digest::MediaDigest, parse_artwork_from_embedded_image, push_next_actor_role_name, serato,
+        digest::MediaDigest, parse_artwork_from_embedded_image, push_next_actor_role_name,
+        select_artwork_image_from_embedded_images, serato,
     },
     Result,
 };
@@ -35,8 +39,8 @@ use aoide_core::{
     util::{Canonical, CanonicalizeInto as _},
 };
 
-use metaflac::block::PictureType;
-use std::time::Duration;
+use metaflac::block::{Picture, PictureType};
+use std::{path::Path, time::Duration};
 
 use super::vorbis;
 
@@ -46,8 +50,42 @@ impl vorbis::CommentReader for metaflac::Tag {
     }
 }
 
+impl vorbis::CommentWriter for metaflac::Tag {
+    fn write_multiple_values(&mut self, key: String, values: Vec<String>) {
+        self.set_vorbis(key, values);
+    }
+
+    fn remove_all_values(&mut self, key: &str) {
+        self.remove_vorbis(key);
+    }
+}
+
 use triseratops::tag::{TagContainer as SeratoTagContainer, TagFormat as SeratoTagFormat};
 
+/// All pictures in the order of preference for the artwork
+fn artwork_pictures(flac_tag: &metaflac::Tag) -> impl Iterator<Item = &Picture> {
+    flac_tag
+        .pictures()
+        .filter(|p| p.picture_type == PictureType::CoverFront)
+        .chain(
+            flac_tag
+                .pictures()
+                .filter(|p| p.picture_type == PictureType::Media),
+        )
+        .chain(
+            flac_tag
+                .pictures()
+                .filter(|p| p.picture_type == PictureType::Leaflet),
+        )
+        .chain(
+            flac_tag
+                .pictures()
+                .filter(|p| p.picture_type == PictureType::Other),
+        )
+        // otherwise take the first picture that could be parsed
+        .chain(flac_tag.pictures())
+}
+
 #[derive(Debug)]
 pub struct ImportTrack;
 
@@ -89,7 +127,12 @@ impl import::ImportTrack for ImportTrack {
                     channels,
                     sample_rate,
                     bitrate: None,
+                    bitrate_mode: None,
                     loudness,
+                    loudness_range: None,
+                    true_peak: None,
+                    waveform: None,
+                    range: None,
                     encoder,
                 };
                 track.media_source.content = Content::Audio(audio_content);
@@ -251,6 +294,14 @@ impl import::ImportTrack for ImportTrack {
             track.indexes.movement = index;
         }
 
+        track.lyrics = vorbis::import_lyrics(
+            flac_tag
+                .get_vorbis("LYRICS")
+                .into_iter()
+                .flatten()
+                .chain(flac_tag.get_vorbis("UNSYNCEDLYRICS").into_iter().flatten()),
+        );
+
         if flags.contains(ImportTrackFlags::ARTWORK) {
             let mut image_digest = if flags.contains(ImportTrackFlags::ARTWORK_DIGEST) {
                 if flags.contains(ImportTrackFlags::ARTWORK_DIGEST_SHA256) {
@@ -263,26 +314,7 @@ impl import::ImportTrack for ImportTrack {
             } else {
                 Default::default()
             };
-            let artwork = flac_tag
-                .pictures()
-                .filter(|p| p.picture_type == PictureType::CoverFront)
-                .chain(
-                    flac_tag
-                        .pictures()
-                        .filter(|p| p.picture_type == PictureType::Media),
-                )
-                .chain(
-                    flac_tag
-                        .pictures()
-                        .filter(|p| p.picture_type == PictureType::Leaflet),
-                )
-                .chain(
-                    flac_tag
-                        .pictures()
-                        .filter(|p| p.picture_type == PictureType::Other),
-                )
-                // otherwise take the first picture that could be parsed
-                .chain(flac_tag.pictures())
+            let artwork = artwork_pictures(&flac_tag)
                 .filter_map(|p| parse_artwork_from_embedded_image(&p.data, None, &mut image_digest))
                 .next();
             if let Some(artwork) = artwork {
@@ -296,7 +328,11 @@ impl import::ImportTrack for ImportTrack {
         // Serato Tags
         if flags.contains(ImportTrackFlags::SERATO_TAGS) {
             let mut serato_tags = SeratoTagContainer::new();
-            vorbis::import_serato_markers2(&flac_tag, &mut serato_tags, SeratoTagFormat::FLAC);
+            if let Err(err) =
+                vorbis::import_serato_markers2(&flac_tag, &mut serato_tags, SeratoTagFormat::FLAC)
+            {
+                log::warn!("{}", err);
+            }
 
             let track_cues = serato::read_cues(&serato_tags)?;
             if !track_cues.is_empty() {
@@ -308,4 +344,37 @@ impl import::ImportTrack for ImportTrack {
 
         Ok(track)
     }
+
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        let flac_tag = metaflac::Tag::read_from(reader).map_err(anyhow::Error::from)?;
+        Ok(select_artwork_image_from_embedded_images(
+            artwork_pictures(&flac_tag).map(|p| (p.data.as_slice(), None)),
+        ))
+    }
 }
+
+#[derive(Debug)]
+pub struct ExportTrack;
+
+impl export::ExportTrack for ExportTrack {
+    fn export_track_to_path(
+        &self,
+        config: &ExportTrackConfig,
+        flags: ExportTrackFlags,
+        track: &Track,
+        path: &Path,
+    ) -> Result<()> {
+        let mut flac_tag = metaflac::Tag::read_from_path(path).map_err(anyhow::Error::from)?;
+        vorbis::export_track(&mut flac_tag, config, flags, track);
+        if flags.contains(ExportTrackFlags::SERATO_TAGS) {
+            vorbis::export_serato_markers2(&mut flac_tag, track, SeratoTagFormat::FLAC);
+        }
+        // Only the metadata blocks are rewritten, the audio frames are
+        // copied verbatim.
+        flac_tag.save().map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,76 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::{media::Source, util::clock::DateTime};
+
+use std::io::Cursor;
+
+fn new_track() -> Track {
+    Track::new_from_media_source(Source {
+        collected_at: DateTime::now_utc(),
+        synchronized_at: None,
+        path: "test.flac".to_owned().into(),
+        content_type: "audio/flac".to_owned(),
+        content_digest: None,
+        content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+        content: Content::Audio(Default::default()),
+        artwork: Default::default(),
+    })
+}
+
+#[test]
+fn export_and_reimport_track_metadata() {
+    let mut track = new_track();
+    track.set_track_title("Title");
+    track.set_track_artist("Artist");
+    track.set_album_title("Album");
+    track.indexes.track.number = Some(2);
+    track.indexes.track.total = Some(10);
+
+    let mut flac_tag = metaflac::Tag::new();
+    flac_tag.set_vorbis("CUSTOM", vec!["Unaffected"]);
+    vorbis::export_track(
+        &mut flac_tag,
+        &Default::default(),
+        ExportTrackFlags::empty(),
+        &track,
+    );
+    let mut data = Vec::new();
+    flac_tag.write_to(&mut data).unwrap();
+
+    let reread_tag = metaflac::Tag::read_from(&mut Cursor::new(data.clone())).unwrap();
+    assert_eq!(
+        Some("Unaffected"),
+        reread_tag.get_vorbis("CUSTOM").and_then(|mut i| i.next())
+    );
+
+    let mut reader: Box<dyn Reader> = Box::new(Cursor::new(data));
+    let reimported = import::ImportTrack::import_track(
+        &ImportTrack,
+        &Default::default(),
+        ImportTrackFlags::METADATA,
+        new_track(),
+        &mut reader,
+    )
+    .unwrap();
+    assert_eq!(Some("Title"), reimported.track_title());
+    assert_eq!(Some("Artist"), reimported.track_artist());
+    assert_eq!(Some("Album"), reimported.album_title());
+    assert_eq!(track.indexes.track, reimported.indexes.track);
+}
@@ -0,0 +1,846 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    io::{
+        export::{ExportTrackConfig, ExportTrackFlags},
+        import::{ArtworkImage, ImportTrackConfig, ImportTrackFlags},
+    },
+    lyrics,
+    util::{
+        digest::MediaDigest,
+        export_actor_role_names, format_key_signature, format_replay_gain, format_tempo_bpm,
+        format_tempo_bpm_integer, parse_artwork_from_embedded_image, parse_index_numbers,
+        parse_key_signature, parse_replay_gain, parse_tempo_bpm, push_next_actor_role_name,
+        select_artwork_image_from_embedded_images, serato,
+        tag::{export_faceted_tags, faceted_tags, import_faceted_tags, FacetedTagMappingConfig},
+    },
+    Result,
+};
+
+use aoide_core::{
+    audio::{signal::LoudnessLufs, PositionMs},
+    media::{concat_encoder_properties, Content},
+    tag::{Facet, Score as TagScore, Tags, TagsMap},
+    track::{
+        actor::ActorRole,
+        album::AlbumKind,
+        lyrics::{Lyrics, SyncedLine},
+        release::DateOrDateTime,
+        tag::{FACET_CGROUP, FACET_COMMENT, FACET_GENRE, FACET_MOOD},
+        title::{Title, TitleKind, Titles},
+        Track,
+    },
+    util::{
+        clock::{DateTime, DateYYYYMMDD, MonthType, YearType},
+        Canonical, CanonicalizeInto as _,
+    },
+};
+
+use aoide_core_serde::tag::Tags as SerdeTags;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use id3::{
+    self,
+    frame::{Picture, PictureType, SynchronisedLyricsType, TimestampFormat},
+};
+use mime::Mime;
+use semval::IsValid as _;
+use std::borrow::Cow;
+use triseratops::tag::{
+    format::id3::ID3Tag, Markers as SeratoMarkers, Markers2 as SeratoMarkers2,
+    TagContainer as SeratoTagContainer, TagFormat as SeratoTagFormat,
+};
+
+fn parse_timestamp(timestamp: id3::Timestamp) -> DateOrDateTime {
+    match (timestamp.month, timestamp.day) {
+        (Some(month), Some(day)) => {
+            let date = NaiveDate::from_ymd_opt(timestamp.year, month.into(), day.into());
+            if let Some(date) = date {
+                if let (Some(hour), Some(min), Some(sec)) =
+                    (timestamp.hour, timestamp.minute, timestamp.second)
+                {
+                    let time = NaiveTime::from_hms_opt(hour.into(), min.into(), sec.into());
+                    if let Some(time) = time {
+                        return DateTime::from(chrono::DateTime::<Utc>::from_utc(
+                            NaiveDateTime::new(date, time),
+                            Utc,
+                        ))
+                        .into();
+                    }
+                }
+                DateYYYYMMDD::from(date).into()
+            } else if month > 0 && month <= 12 {
+                DateYYYYMMDD::from_year_month(timestamp.year as YearType, month as MonthType).into()
+            } else {
+                DateYYYYMMDD::from_year(timestamp.year as YearType).into()
+            }
+        }
+        (Some(month), None) => {
+            if month > 0 && month <= 12 {
+                DateYYYYMMDD::from_year_month(timestamp.year as YearType, month as MonthType).into()
+            } else {
+                DateYYYYMMDD::from_year(timestamp.year as YearType).into()
+            }
+        }
+        _ => DateYYYYMMDD::from_year(timestamp.year as YearType).into(),
+    }
+}
+
+fn id3_text_frames<'a>(
+    id3_tag: &'a id3::Tag,
+    frame_id: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    id3_tag
+        .frames()
+        .filter(move |frame| frame.id() == frame_id)
+        .filter_map(|frame| {
+            if let id3::Content::Text(txt) = frame.content() {
+                Some(txt.as_str())
+            } else {
+                None
+            }
+        })
+        // All "T..."" text frames (except "TXXX") may contain multiple
+        // values separated by a NULL character
+        .flat_map(|txt| txt.split('\0'))
+}
+
+fn id3_first_text_frame<'a>(id3_tag: &'a id3::Tag, frame_id: &'a str) -> Option<&'a str> {
+    id3_text_frames(id3_tag, frame_id).next()
+}
+
+fn id3_extended_texts<'a>(
+    id3_tag: &'a id3::Tag,
+    description: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    id3_tag
+        .extended_texts()
+        .filter(move |txxx| txxx.description == description)
+        .map(|txxx| txxx.value.as_str())
+}
+
+fn id3_first_extended_text<'a>(id3_tag: &'a id3::Tag, description: &'a str) -> Option<&'a str> {
+    id3_extended_texts(id3_tag, description).next()
+}
+
+fn import_faceted_text_tags(
+    tags_map: &mut TagsMap,
+    config: &FacetedTagMappingConfig,
+    facet: &Facet,
+    id3_tag: &id3::Tag,
+    frame_id: &str,
+) {
+    let removed_tags = tags_map.remove_faceted_tags(&facet);
+    if removed_tags > 0 {
+        log::debug!("Replacing {} custom '{}' tags", removed_tags, facet.value());
+    }
+    let tag_mapping_config = config.get(facet.value());
+    let mut next_score_value = TagScore::max_value();
+    for label in id3_text_frames(id3_tag, frame_id) {
+        import_faceted_tags(
+            tags_map,
+            &mut next_score_value,
+            &facet,
+            tag_mapping_config,
+            label,
+        );
+    }
+}
+
+pub fn import_loudness(id3_tag: &id3::Tag) -> Option<LoudnessLufs> {
+    id3_first_extended_text(id3_tag, "REPLAYGAIN_TRACK_GAIN").and_then(parse_replay_gain)
+}
+
+pub fn import_encoder(id3_tag: &id3::Tag) -> Option<Cow<'_, str>> {
+    concat_encoder_properties(
+        id3_first_text_frame(id3_tag, "TENC"),
+        id3_first_text_frame(id3_tag, "TSSE"),
+    )
+}
+
+/// All pictures in the order of preference for the artwork
+fn artwork_pictures(id3_tag: &id3::Tag) -> impl Iterator<Item = &Picture> {
+    id3_tag
+        .pictures()
+        .filter(|p| p.picture_type == PictureType::CoverFront)
+        .chain(
+            id3_tag
+                .pictures()
+                .filter(|p| p.picture_type == PictureType::Media),
+        )
+        .chain(
+            id3_tag
+                .pictures()
+                .filter(|p| p.picture_type == PictureType::Leaflet),
+        )
+        .chain(
+            id3_tag
+                .pictures()
+                .filter(|p| p.picture_type == PictureType::Other),
+        )
+        // otherwise take the first picture that could be parsed
+        .chain(id3_tag.pictures())
+}
+
+pub fn load_artwork_image(id3_tag: &id3::Tag) -> Option<ArtworkImage> {
+    select_artwork_image_from_embedded_images(
+        artwork_pictures(id3_tag).map(|p| (p.data.as_slice(), None)),
+    )
+}
+
+/// Parse the Serato Markers and Markers2 frames into the tag container
+///
+/// All frames are parsed even if some of them are invalid and the
+/// last error is returned.
+fn read_serato_tags(id3_tag: &id3::Tag, serato_tags: &mut SeratoTagContainer) -> Result<()> {
+    let mut result = Ok(());
+    for geob in id3_tag.encapsulated_objects() {
+        let parsed = match geob.description.as_str() {
+            SeratoMarkers::ID3_TAG => serato_tags
+                .parse_markers(&geob.data, SeratoTagFormat::ID3)
+                .map_err(|err| anyhow::anyhow!("Failed to parse Serato Markers: {}", err)),
+            SeratoMarkers2::ID3_TAG => serato_tags
+                .parse_markers2(&geob.data, SeratoTagFormat::ID3)
+                .map_err(|err| anyhow::anyhow!("Failed to parse Serato Markers2: {}", err)),
+            _ => continue,
+        };
+        if let Err(err) = parsed {
+            result = Err(err.into());
+        }
+    }
+    result
+}
+
+/// Import unsynchronized (USLT) and time-synchronized (SYLT) lyrics
+///
+/// Only SYLT frames with lyrics and absolute time stamps in
+/// milliseconds are considered.
+pub fn import_lyrics(id3_tag: &id3::Tag) -> Canonical<Vec<Lyrics>> {
+    let unsynchronized = id3_tag.lyrics().filter_map(|uslt| {
+        lyrics::import_lyrics_text(lyrics::normalize_language(&uslt.lang), &uslt.text)
+    });
+    let synchronized = id3_tag
+        .synchronised_lyrics()
+        .filter(|sylt| {
+            if sylt.timestamp_format != TimestampFormat::Ms {
+                log::debug!("Ignoring SYLT frame with MPEG frames as time stamps");
+                return false;
+            }
+            sylt.content_type == SynchronisedLyricsType::Lyrics
+        })
+        .map(|sylt| Lyrics {
+            language: lyrics::normalize_language(&sylt.lang),
+            text: None,
+            lines: sylt
+                .content
+                .iter()
+                .map(|(millis, text)| SyncedLine {
+                    position: PositionMs(f64::from(*millis)),
+                    text: text.trim().to_owned(),
+                })
+                .collect(),
+        });
+    lyrics::collect_lyrics(unsynchronized.chain(synchronized))
+}
+
+/// Import all metadata from an ID3v2 tag into a track
+///
+/// The audio properties of the media source are not affected, they
+/// depend on the actual file format that embeds the ID3v2 tag.
+pub fn import_metadata_into_track(
+    id3_tag: &id3::Tag,
+    config: &ImportTrackConfig,
+    flags: ImportTrackFlags,
+    mut track: Track,
+) -> Result<Track> {
+    if let Some(tempo_bpm) = id3_first_extended_text(id3_tag, "BPM")
+        .and_then(parse_tempo_bpm)
+        // Alternative: Try "TEMPO" if "BPM" is missing or invalid
+        .or_else(|| id3_first_extended_text(id3_tag, "TEMPO").and_then(parse_tempo_bpm))
+        // Fallback: Parse integer BPM
+        .or_else(|| id3_first_text_frame(id3_tag, "TBPM").and_then(parse_tempo_bpm))
+    {
+        debug_assert!(tempo_bpm.is_valid());
+        track.metrics.tempo_bpm = Some(tempo_bpm);
+    }
+
+    if let Some(key_signature) = id3_first_text_frame(id3_tag, "TKEY").and_then(parse_key_signature)
+    {
+        track.metrics.key_signature = key_signature;
+    }
+
+    // Track titles
+    let mut track_titles = Vec::with_capacity(4);
+    if let Some(name) = id3_tag.title() {
+        let title = Title {
+            name: name.to_owned(),
+            kind: TitleKind::Main,
+        };
+        track_titles.push(title);
+    }
+    if let Some(name) = id3_first_text_frame(id3_tag, "TSST") {
+        let title = Title {
+            name: name.to_owned(),
+            kind: TitleKind::Sub,
+        };
+        track_titles.push(title);
+    }
+    if let Some(name) = id3_first_text_frame(id3_tag, "MVNM") {
+        let title = Title {
+            name: name.to_owned(),
+            kind: TitleKind::Movement,
+        };
+        track_titles.push(title);
+    }
+    if flags.contains(ImportTrackFlags::ITUNES_ID3V2_GROUPING_MOVEMENT_WORK) {
+        // Starting with iTunes 12.5.4 the "TIT1" text frame is used
+        // for storing the work instead of the grouping.
+        if let Some(name) = id3_first_text_frame(id3_tag, "TIT1") {
+            let title = Title {
+                name: name.to_owned(),
+                kind: TitleKind::Work,
+            };
+            track_titles.push(title);
+        }
+    } else if let Some(name) = id3_first_extended_text(id3_tag, "WORK") {
+        let title = Title {
+            name: name.to_owned(),
+            kind: TitleKind::Work,
+        };
+        track_titles.push(title);
+    }
+    let track_titles = track_titles.canonicalize_into();
+    if !track_titles.is_empty() {
+        track.titles = Canonical::tie(track_titles);
+    }
+
+    // Track actors
+    let mut track_actors = Vec::with_capacity(8);
+    if let Some(name) = id3_tag.artist() {
+        push_next_actor_role_name(&mut track_actors, ActorRole::Artist, name.to_owned());
+    }
+    for name in id3_text_frames(id3_tag, "TCOM") {
+        push_next_actor_role_name(&mut track_actors, ActorRole::Composer, name.to_owned());
+    }
+    for name in id3_text_frames(id3_tag, "TPE3") {
+        push_next_actor_role_name(&mut track_actors, ActorRole::Conductor, name.to_owned());
+    }
+    let track_actors = track_actors.canonicalize_into();
+    if !track_actors.is_empty() {
+        track.actors = Canonical::tie(track_actors);
+    }
+
+    let mut album = track.album.untie();
+
+    // Album titles
+    let mut album_titles = Vec::with_capacity(1);
+    if let Some(name) = id3_tag.album() {
+        let title = Title {
+            name: name.to_owned(),
+            kind: TitleKind::Main,
+        };
+        album_titles.push(title);
+    }
+    let album_titles = album_titles.canonicalize_into();
+    if !album_titles.is_empty() {
+        album.titles = Canonical::tie(album_titles);
+    }
+
+    // Album actors
+    let mut album_actors = Vec::with_capacity(4);
+    if let Some(name) = id3_tag.album_artist() {
+        push_next_actor_role_name(&mut album_actors, ActorRole::Artist, name.to_owned());
+    }
+    let album_actors = album_actors.canonicalize_into();
+    if !album_actors.is_empty() {
+        album.actors = Canonical::tie(album_actors);
+    }
+
+    // Album properties
+    if id3_first_text_frame(id3_tag, "TCMP")
+        .and_then(|tcmp| tcmp.parse::<u8>().ok())
+        .unwrap_or_default()
+        == 1
+    {
+        album.kind = AlbumKind::Compilation;
+    }
+
+    track.album = Canonical::tie(album);
+
+    // Release properties
+    // Instead of the release date "TDRL" most applications use the recording date "TDRC".
+    // See also https://picard-docs.musicbrainz.org/en/appendices/tag_mapping.html
+    if let Some(released_at) = id3_tag
+        .date_released()
+        .or_else(|| id3_tag.date_recorded())
+        .map(parse_timestamp)
+    {
+        track.release.released_at = Some(released_at);
+    }
+    if let Some(label) = id3_first_text_frame(id3_tag, "TPUB") {
+        track.release.released_by = Some(label.to_owned());
+    }
+    if let Some(copyright) = id3_first_text_frame(id3_tag, "TCOP") {
+        track.release.copyright = Some(copyright.to_owned());
+    }
+
+    let mut tags_map = TagsMap::default();
+    if flags.contains(ImportTrackFlags::MIXXX_CUSTOM_TAGS) {
+        for geob in id3_tag
+            .encapsulated_objects()
+            .filter(|geob| geob.description == MIXXX_CUSTOM_TAGS_GEOB_DESCRIPTION)
+        {
+            if geob
+                .mime_type
+                .parse::<Mime>()
+                .ok()
+                .as_ref()
+                .map(Mime::type_)
+                != Some(mime::APPLICATION_JSON.type_())
+            {
+                log::warn!(
+                    "Unexpected MIME type for GEOB '{}': {}",
+                    geob.description,
+                    geob.mime_type
+                );
+                continue;
+            }
+            if let Some(custom_tags) = serde_json::from_slice::<SerdeTags>(&geob.data)
+                .map_err(|err| {
+                    log::warn!("Failed to parse Mixxx custom tags: {}", err);
+                    err
+                })
+                .ok()
+                .map(Tags::from)
+            {
+                // Initialize map with all existing custom tags as starting point
+                debug_assert_eq!(0, tags_map.total_count());
+                tags_map = custom_tags.into();
+            }
+        }
+    }
+
+    // Comment tag
+    for comment in id3_tag
+        .comments()
+        .filter(|comm| comm.description.is_empty())
+        .map(|comm| comm.text.as_str())
+    {
+        let removed_comments = tags_map.remove_faceted_tags(&FACET_COMMENT);
+        if removed_comments > 0 {
+            log::debug!(
+                "Replacing {} custom '{}' tags",
+                removed_comments,
+                FACET_COMMENT.value()
+            );
+        }
+        let mut next_score_value = TagScore::default_value();
+        import_faceted_tags(
+            &mut tags_map,
+            &mut next_score_value,
+            &FACET_COMMENT,
+            None,
+            comment.to_owned(),
+        );
+    }
+
+    // Genre tags
+    import_faceted_text_tags(
+        &mut tags_map,
+        &config.faceted_tag_mapping,
+        &FACET_GENRE,
+        id3_tag,
+        "TCON",
+    );
+
+    // Mood tags
+    import_faceted_text_tags(
+        &mut tags_map,
+        &config.faceted_tag_mapping,
+        &FACET_MOOD,
+        id3_tag,
+        "TMOO",
+    );
+
+    // Grouping tags
+    // Apple decided to store the Work in the traditional ID3v2 Content Group
+    // frame (TIT1) and introduced new Grouping (GRP1) and Movement Name (MVNM)
+    // frames.
+    // https://discussions.apple.com/thread/7900430
+    // http://blog.jthink.net/2016/11/the-reason-why-is-grouping-field-no.html
+    if flags.contains(ImportTrackFlags::ITUNES_ID3V2_GROUPING_MOVEMENT_WORK) {
+        import_faceted_text_tags(
+            &mut tags_map,
+            &config.faceted_tag_mapping,
+            &FACET_CGROUP,
+            id3_tag,
+            "GRP1",
+        );
+    } else {
+        import_faceted_text_tags(
+            &mut tags_map,
+            &config.faceted_tag_mapping,
+            &FACET_CGROUP,
+            id3_tag,
+            "TIT1",
+        );
+    }
+
+    debug_assert!(track.tags.is_empty());
+    track.tags = Canonical::tie(tags_map.into());
+
+    // Indexes (in pairs)
+    if id3_tag.track().is_some() || id3_tag.total_tracks().is_some() {
+        track.indexes.track.number = id3_tag.track().map(|i| (i & 0xFFFF) as u16);
+        track.indexes.track.total = id3_tag.total_tracks().map(|i| (i & 0xFFFF) as u16);
+    }
+    if id3_tag.disc().is_some() || id3_tag.total_discs().is_some() {
+        track.indexes.disc.number = id3_tag.disc().map(|i| (i & 0xFFFF) as u16);
+        track.indexes.disc.total = id3_tag.total_discs().map(|i| (i & 0xFFFF) as u16);
+    }
+    if let Some(movement) = id3_first_text_frame(id3_tag, "MVIN").and_then(parse_index_numbers) {
+        track.indexes.movement = movement;
+    }
+
+    // Lyrics
+    track.lyrics = import_lyrics(id3_tag);
+
+    // Artwork
+    if flags.contains(ImportTrackFlags::ARTWORK) {
+        let mut image_digest = if flags.contains(ImportTrackFlags::ARTWORK_DIGEST) {
+            if flags.contains(ImportTrackFlags::ARTWORK_DIGEST_SHA256) {
+                // Compatibility
+                MediaDigest::sha256()
+            } else {
+                // Default
+                MediaDigest::new()
+            }
+        } else {
+            Default::default()
+        };
+        let artwork = artwork_pictures(id3_tag)
+            .filter_map(|p| parse_artwork_from_embedded_image(&p.data, None, &mut image_digest))
+            .next();
+        if let Some(artwork) = artwork {
+            track.media_source.artwork = artwork;
+        }
+    }
+
+    // Serato Tags
+    if flags.contains(ImportTrackFlags::SERATO_TAGS) {
+        let mut serato_tags = SeratoTagContainer::new();
+        if let Err(err) = read_serato_tags(id3_tag, &mut serato_tags) {
+            log::warn!("{}", err);
+        }
+
+        let track_cues = serato::read_cues(&serato_tags)?;
+        if !track_cues.is_empty() {
+            track.cues = Canonical::tie(track_cues);
+        }
+
+        track.color = serato::read_track_color(&serato_tags);
+    }
+
+    Ok(track)
+}
+
+pub const MIXXX_CUSTOM_TAGS_GEOB_DESCRIPTION: &str = "Mixxx CustomTags";
+
+/// Replace or remove a text frame
+///
+/// Multiple values are separated by a NULL character (ID3v2.4).
+fn export_text_frame<'a>(
+    id3_tag: &mut id3::Tag,
+    frame_id: &str,
+    values: impl IntoIterator<Item = &'a str>,
+) {
+    let values: Vec<_> = values.into_iter().collect();
+    if values.is_empty() {
+        id3_tag.remove(frame_id);
+    } else {
+        id3_tag.set_text(frame_id, values.join("\0"));
+    }
+}
+
+/// Replace or remove an extended text frame (TXXX)
+fn export_extended_text(id3_tag: &mut id3::Tag, description: &str, value: Option<&str>) {
+    id3_tag.remove_extended_text(Some(description), None);
+    if let Some(value) = value {
+        id3_tag.add_extended_text(description, value);
+    }
+}
+
+fn export_faceted_text_tags(
+    id3_tag: &mut id3::Tag,
+    config: &FacetedTagMappingConfig,
+    facet: &Facet,
+    tags: &Tags,
+    frame_id: &str,
+) {
+    let labels = export_faceted_tags(config.get(facet.value()), faceted_tags(tags, facet));
+    export_text_frame(id3_tag, frame_id, labels.iter().map(String::as_str));
+}
+
+fn export_title_text_frame(
+    id3_tag: &mut id3::Tag,
+    titles: &[Title],
+    kind: TitleKind,
+    frame_id: &str,
+) {
+    export_text_frame(
+        id3_tag,
+        frame_id,
+        Titles::filter_kind(titles, kind).map(|title| title.name.as_str()),
+    );
+}
+
+fn export_serato_geob(id3_tag: &mut id3::Tag, description: &str, data: Result<Vec<u8>>) {
+    let data = match data {
+        Ok(data) => data,
+        Err(err) => {
+            // Keep the existing frame
+            log::warn!("{}", err);
+            return;
+        }
+    };
+    id3_tag.remove_encapsulated_object(Some(description), None, None, None);
+    id3_tag.add_frame(id3::Frame::with_content(
+        "GEOB",
+        id3::Content::EncapsulatedObject(id3::frame::EncapsulatedObject {
+            mime_type: mime::APPLICATION_OCTET_STREAM.to_string(),
+            filename: "".to_owned(),
+            description: description.to_owned(),
+            data,
+        }),
+    ));
+}
+
+pub fn export_track_to_tag(
+    id3_tag: &mut id3::Tag,
+    config: &ExportTrackConfig,
+    flags: ExportTrackFlags,
+    track: &Track,
+) {
+    let loudness = match &track.media_source.content {
+        Content::Audio(audio_content) => audio_content.loudness,
+    };
+    let replay_gain = loudness.map(format_replay_gain);
+    export_extended_text(id3_tag, "REPLAYGAIN_TRACK_GAIN", replay_gain.as_deref());
+
+    if let Some(tempo_bpm) = track.metrics.tempo_bpm {
+        // Store the fractional value in a custom frame and
+        // the integer value in the standard frame
+        export_extended_text(id3_tag, "BPM", Some(&format_tempo_bpm(tempo_bpm)));
+        id3_tag.set_text("TBPM", format_tempo_bpm_integer(tempo_bpm));
+    } else {
+        export_extended_text(id3_tag, "BPM", None);
+        id3_tag.remove("TBPM");
+    }
+    export_extended_text(id3_tag, "TEMPO", None);
+
+    export_text_frame(
+        id3_tag,
+        "TKEY",
+        format_key_signature(track.metrics.key_signature),
+    );
+
+    // Track titles
+    export_title_text_frame(id3_tag, &track.titles, TitleKind::Main, "TIT2");
+    export_title_text_frame(id3_tag, &track.titles, TitleKind::Sub, "TSST");
+    export_title_text_frame(id3_tag, &track.titles, TitleKind::Movement, "MVNM");
+    if flags.contains(ExportTrackFlags::ITUNES_ID3V2_GROUPING_MOVEMENT_WORK) {
+        export_title_text_frame(id3_tag, &track.titles, TitleKind::Work, "TIT1");
+        export_extended_text(id3_tag, "WORK", None);
+    } else {
+        let work = Titles::filter_kind(track.titles.iter(), TitleKind::Work)
+            .map(|title| title.name.as_str())
+            .next();
+        export_extended_text(id3_tag, "WORK", work);
+    }
+
+    // Track actors
+    export_text_frame(
+        id3_tag,
+        "TPE1",
+        export_actor_role_names(&track.actors, ActorRole::Artist),
+    );
+    export_text_frame(
+        id3_tag,
+        "TCOM",
+        export_actor_role_names(&track.actors, ActorRole::Composer),
+    );
+    export_text_frame(
+        id3_tag,
+        "TPE3",
+        export_actor_role_names(&track.actors, ActorRole::Conductor),
+    );
+
+    // Album
+    export_title_text_frame(id3_tag, &track.album.titles, TitleKind::Main, "TALB");
+    export_text_frame(
+        id3_tag,
+        "TPE2",
+        export_actor_role_names(&track.album.actors, ActorRole::Artist),
+    );
+    if track.album.kind == AlbumKind::Compilation {
+        id3_tag.set_text("TCMP", "1");
+    } else {
+        id3_tag.remove("TCMP");
+    }
+
+    // Release
+    export_text_frame(id3_tag, "TPUB", track.release.released_by.as_deref());
+    export_text_frame(id3_tag, "TCOP", track.release.copyright.as_deref());
+
+    // Indexes (in pairs)
+    id3_tag.remove("TRCK");
+    if let Some(number) = track.indexes.track.number {
+        id3_tag.set_track(number.into());
+    }
+    if let Some(total) = track.indexes.track.total {
+        id3_tag.set_total_tracks(total.into());
+    }
+    id3_tag.remove("TPOS");
+    if let Some(number) = track.indexes.disc.number {
+        id3_tag.set_disc(number.into());
+    }
+    if let Some(total) = track.indexes.disc.total {
+        id3_tag.set_total_discs(total.into());
+    }
+    let movement = track.indexes.movement.to_string();
+    export_text_frame(
+        id3_tag,
+        "MVIN",
+        if movement.is_empty() {
+            None
+        } else {
+            Some(movement.as_str())
+        },
+    );
+
+    // Comment tag
+    id3_tag.remove_comment(Some(""), None);
+    if let Some(comment) = export_faceted_tags(None, faceted_tags(&track.tags, &FACET_COMMENT))
+        .into_iter()
+        .next()
+    {
+        id3_tag.add_comment(id3::frame::Comment {
+            lang: "eng".to_owned(),
+            description: "".to_owned(),
+            text: comment,
+        });
+    }
+
+    // Genre tags
+    export_faceted_text_tags(
+        id3_tag,
+        &config.faceted_tag_mapping,
+        &FACET_GENRE,
+        &track.tags,
+        "TCON",
+    );
+
+    // Mood tags
+    export_faceted_text_tags(
+        id3_tag,
+        &config.faceted_tag_mapping,
+        &FACET_MOOD,
+        &track.tags,
+        "TMOO",
+    );
+
+    // Grouping tags
+    if flags.contains(ExportTrackFlags::ITUNES_ID3V2_GROUPING_MOVEMENT_WORK) {
+        export_faceted_text_tags(
+            id3_tag,
+            &config.faceted_tag_mapping,
+            &FACET_CGROUP,
+            &track.tags,
+            "GRP1",
+        );
+    } else {
+        export_faceted_text_tags(
+            id3_tag,
+            &config.faceted_tag_mapping,
+            &FACET_CGROUP,
+            &track.tags,
+            "TIT1",
+        );
+    }
+
+    // Mixxx custom tags
+    if flags.contains(ExportTrackFlags::MIXXX_CUSTOM_TAGS) {
+        id3_tag.remove_encapsulated_object(
+            Some(MIXXX_CUSTOM_TAGS_GEOB_DESCRIPTION),
+            None,
+            None,
+            None,
+        );
+        if !track.tags.is_empty() {
+            let custom_tags = SerdeTags::from(track.tags.as_ref().clone());
+            match serde_json::to_vec(&custom_tags) {
+                Ok(data) => {
+                    id3_tag.add_frame(id3::Frame::with_content(
+                        "GEOB",
+                        id3::Content::EncapsulatedObject(id3::frame::EncapsulatedObject {
+                            mime_type: mime::APPLICATION_JSON.to_string(),
+                            filename: "".to_owned(),
+                            description: MIXXX_CUSTOM_TAGS_GEOB_DESCRIPTION.to_owned(),
+                            data,
+                        }),
+                    ));
+                }
+                Err(err) => {
+                    log::warn!("Failed to serialize Mixxx custom tags: {}", err);
+                }
+            }
+        }
+    }
+
+    // Serato Tags
+    if flags.contains(ExportTrackFlags::SERATO_TAGS) {
+        // Parse the existing data first to preserve all entries
+        // that are not affected by the export. Other Serato frames,
+        // e.g. BeatGrid or Overview, are left untouched.
+        let mut serato_tags = SeratoTagContainer::new();
+        match read_serato_tags(id3_tag, &mut serato_tags) {
+            Ok(()) => {
+                serato::write_cues(&mut serato_tags, &track.cues);
+                serato::write_track_color(&mut serato_tags, track.color);
+                export_serato_geob(
+                    id3_tag,
+                    SeratoMarkers::ID3_TAG,
+                    serato::dump_markers(&serato_tags, SeratoTagFormat::ID3),
+                );
+                export_serato_geob(
+                    id3_tag,
+                    SeratoMarkers2::ID3_TAG,
+                    serato::dump_markers2(&serato_tags, SeratoTagFormat::ID3),
+                );
+            }
+            Err(err) => {
+                // Overwriting the existing frames would discard all
+                // entries that could not be parsed
+                log::warn!("Skipping export of Serato tags: {}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
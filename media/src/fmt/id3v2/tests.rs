@@ -0,0 +1,77 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::{
+    media::{ContentMetadataFlags, Source},
+    music::time::TempoBpm,
+    util::clock::DateTime,
+};
+
+fn new_track() -> Track {
+    Track::new_from_media_source(Source {
+        collected_at: DateTime::now_utc(),
+        synchronized_at: None,
+        path: "test.mp3".to_owned().into(),
+        content_type: "audio/mpeg".to_owned(),
+        content_digest: None,
+        content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+        content: Content::Audio(Default::default()),
+        artwork: Default::default(),
+    })
+}
+
+#[test]
+fn export_and_reimport_track_metadata() {
+    let mut track = new_track();
+    track.set_track_title("Title");
+    track.set_track_artist("Artist");
+    track.set_track_composer("Composer");
+    track.set_album_title("Album");
+    track.set_album_artist("Album Artist");
+    track.indexes.track.number = Some(3);
+    track.indexes.track.total = Some(12);
+    track.indexes.disc.number = Some(1);
+    track.indexes.disc.total = Some(2);
+    track.metrics.tempo_bpm = Some(TempoBpm(123.5));
+    track.release.released_by = Some("Label".to_owned());
+
+    let mut id3_tag = id3::Tag::new();
+    export_track_to_tag(
+        &mut id3_tag,
+        &Default::default(),
+        ExportTrackFlags::empty(),
+        &track,
+    );
+
+    let reimported = import_metadata_into_track(
+        &id3_tag,
+        &Default::default(),
+        ImportTrackFlags::METADATA,
+        new_track(),
+    )
+    .unwrap();
+    assert_eq!(Some("Title"), reimported.track_title());
+    assert_eq!(Some("Artist"), reimported.track_artist());
+    assert_eq!(Some("Composer"), reimported.track_composer());
+    assert_eq!(Some("Album"), reimported.album_title());
+    assert_eq!(Some("Album Artist"), reimported.album_artist());
+    assert_eq!(track.indexes, reimported.indexes);
+    assert_eq!(track.metrics.tempo_bpm, reimported.metrics.tempo_bpm);
+    assert_eq!(track.release.released_by, reimported.release.released_by);
+}
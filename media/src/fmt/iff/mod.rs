@@ -0,0 +1,270 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    io::import::{ImportTrackConfig, Reader},
+    util::{
+        parse_index_numbers, parse_year_tag, push_next_actor_role_name, tag::import_faceted_tags,
+    },
+    Result,
+};
+
+use aoide_core::{
+    tag::{Score as TagScore, TagsMap},
+    track::{
+        actor::ActorRole,
+        tag::{FACET_COMMENT, FACET_GENRE},
+        title::{Title, TitleKind},
+        Track,
+    },
+    util::{Canonical, CanonicalizeInto as _},
+};
+
+use std::io::{ErrorKind as IoErrorKind, SeekFrom};
+
+pub type ChunkId = [u8; 4];
+
+/// Byte order of chunk sizes and numbers
+///
+/// Both RIFF/WAVE (little-endian) and AIFF (big-endian) files are
+/// organized as a sequence of chunks that follow a common file header,
+/// derived from the (EA) Interchange File Format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+impl ByteOrder {
+    pub fn read_u16(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            Self::LittleEndian => u16::from_le_bytes(bytes),
+            Self::BigEndian => u16::from_be_bytes(bytes),
+        }
+    }
+
+    pub fn read_u32(self, bytes: [u8; 4]) -> u32 {
+        match self {
+            Self::LittleEndian => u32::from_le_bytes(bytes),
+            Self::BigEndian => u32::from_be_bytes(bytes),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkHeader {
+    pub id: ChunkId,
+    pub size: u32,
+}
+
+impl ChunkHeader {
+    /// The size of the chunk including the optional pad byte
+    pub fn padded_size(self) -> u64 {
+        u64::from(self.size) + u64::from(self.size & 1)
+    }
+}
+
+/// Read the header of the next chunk
+///
+/// Returns `None` if the end of the file has been reached.
+pub fn read_chunk_header(
+    reader: &mut Box<dyn Reader>,
+    byte_order: ByteOrder,
+) -> Result<Option<ChunkHeader>> {
+    let mut bytes = [0u8; 8];
+    match reader.read_exact(&mut bytes) {
+        Ok(()) => (),
+        Err(err) if err.kind() == IoErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let id = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    let size = byte_order.read_u32([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    Ok(Some(ChunkHeader { id, size }))
+}
+
+/// Read the data of a chunk and consume the optional pad byte
+///
+/// The untrusted chunk size is checked against the remaining length
+/// of the stream before allocating memory for the data.
+pub fn read_chunk_data(reader: &mut Box<dyn Reader>, header: ChunkHeader) -> Result<Vec<u8>> {
+    let position = reader.seek(SeekFrom::Current(0))?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(position))?;
+    let remaining = end.saturating_sub(position);
+    if u64::from(header.size) > remaining {
+        return Err(anyhow::anyhow!(
+            "Chunk size {} exceeds the remaining stream length {}",
+            header.size,
+            remaining
+        )
+        .into());
+    }
+    let mut data = vec![0u8; header.size as usize];
+    reader.read_exact(&mut data)?;
+    if header.padded_size() > u64::from(header.size) {
+        reader.seek(SeekFrom::Current(1))?;
+    }
+    Ok(data)
+}
+
+pub fn skip_chunk_data(reader: &mut Box<dyn Reader>, header: ChunkHeader) -> Result<()> {
+    reader.seek(SeekFrom::Current(header.padded_size() as i64))?;
+    Ok(())
+}
+
+/// Read and parse the data of an embedded ID3v2 chunk
+///
+/// Malformed tags are logged and ignored.
+pub fn read_id3_chunk(
+    reader: &mut Box<dyn Reader>,
+    header: ChunkHeader,
+) -> Result<Option<id3::Tag>> {
+    let data = read_chunk_data(reader, header)?;
+    match id3::Tag::read_from(&mut data.as_slice()) {
+        Ok(tag) => Ok(Some(tag)),
+        Err(err) => {
+            log::warn!("Failed to parse embedded ID3v2 tag: {}", err);
+            Ok(None)
+        }
+    }
+}
+
+/// Skip all remaining chunks until an ID3v2 chunk is found
+pub fn find_id3_chunk(
+    reader: &mut Box<dyn Reader>,
+    byte_order: ByteOrder,
+    id3_chunk_ids: &[ChunkId],
+) -> Result<Option<id3::Tag>> {
+    while let Some(chunk_header) = read_chunk_header(reader, byte_order)? {
+        if id3_chunk_ids.contains(&chunk_header.id) {
+            if let Some(id3_tag) = read_id3_chunk(reader, chunk_header)? {
+                return Ok(Some(id3_tag));
+            }
+        } else {
+            skip_chunk_data(reader, chunk_header)?;
+        }
+    }
+    Ok(None)
+}
+
+/// Decode a text chunk that might be terminated or padded with NULL characters
+pub fn decode_text(data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(data);
+    let text = text.trim_end_matches('\0').trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_owned())
+    }
+}
+
+/// Basic, textual metadata that is stored in dedicated chunks
+///
+/// These fields are used as a fallback if no ID3v2 chunk is available.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub comment: Option<String>,
+    pub genre: Option<String>,
+    pub released_at: Option<String>,
+    pub copyright: Option<String>,
+    pub track_number: Option<String>,
+}
+
+pub fn import_text_metadata_into_track(
+    config: &ImportTrackConfig,
+    text_metadata: TextMetadata,
+    mut track: Track,
+) -> Track {
+    let TextMetadata {
+        title,
+        artist,
+        album,
+        comment,
+        genre,
+        released_at,
+        copyright,
+        track_number,
+    } = text_metadata;
+
+    if let Some(name) = title {
+        let track_titles = vec![Title {
+            name,
+            kind: TitleKind::Main,
+        }];
+        track.titles = Canonical::tie(track_titles.canonicalize_into());
+    }
+
+    if let Some(name) = artist {
+        let mut track_actors = Vec::with_capacity(1);
+        push_next_actor_role_name(&mut track_actors, ActorRole::Artist, name);
+        track.actors = Canonical::tie(track_actors.canonicalize_into());
+    }
+
+    if let Some(name) = album {
+        let mut album = track.album.untie();
+        let album_titles = vec![Title {
+            name,
+            kind: TitleKind::Main,
+        }];
+        album.titles = Canonical::tie(album_titles.canonicalize_into());
+        track.album = Canonical::tie(album);
+    }
+
+    if let Some(released_at) = released_at.as_deref().and_then(parse_year_tag) {
+        track.release.released_at = Some(released_at);
+    }
+    if let Some(copyright) = copyright {
+        track.release.copyright = Some(copyright);
+    }
+
+    if let Some(index) = track_number.as_deref().and_then(parse_index_numbers) {
+        track.indexes.track = index;
+    }
+
+    let mut tags_map = TagsMap::default();
+    if let Some(comment) = comment {
+        let mut next_score_value = TagScore::default_value();
+        import_faceted_tags(
+            &mut tags_map,
+            &mut next_score_value,
+            &FACET_COMMENT,
+            None,
+            comment,
+        );
+    }
+    if let Some(genre) = genre {
+        let tag_mapping_config = config.faceted_tag_mapping.get(FACET_GENRE.value());
+        let mut next_score_value = TagScore::max_value();
+        import_faceted_tags(
+            &mut tags_map,
+            &mut next_score_value,
+            &FACET_GENRE,
+            tag_mapping_config,
+            genre,
+        );
+    }
+    debug_assert!(track.tags.is_empty());
+    track.tags = Canonical::tie(tags_map.into());
+
+    track
+}
+
+#[cfg(test)]
+mod tests;
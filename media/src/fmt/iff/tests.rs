@@ -0,0 +1,47 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use std::io::Cursor;
+
+#[test]
+fn read_chunk_data_with_padding() {
+    let mut reader: Box<dyn Reader> = Box::new(Cursor::new(b"abc\0def".to_vec()));
+    let header = ChunkHeader {
+        id: *b"TEST",
+        size: 3,
+    };
+    assert_eq!(
+        b"abc".to_vec(),
+        read_chunk_data(&mut reader, header).unwrap()
+    );
+    assert_eq!(
+        b"def".to_vec(),
+        read_chunk_data(&mut reader, header).unwrap()
+    );
+}
+
+#[test]
+fn read_chunk_data_exceeding_stream_length() {
+    let mut reader: Box<dyn Reader> = Box::new(Cursor::new(b"abc".to_vec()));
+    let header = ChunkHeader {
+        id: *b"TEST",
+        size: u32::MAX,
+    };
+    assert!(read_chunk_data(&mut reader, header).is_err());
+}
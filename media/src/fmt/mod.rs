@@ -15,17 +15,51 @@
 
 ///////////////////////////////////////////////////////////////////////
 
+#[cfg(feature = "fmt-aiff")]
+pub mod aiff;
+
+#[cfg(feature = "fmt-ape")]
+pub mod ape;
+
+#[cfg(any(feature = "fmt-ape", feature = "fmt-mpc", feature = "fmt-wavpack"))]
+pub mod apev2;
+
 #[cfg(feature = "fmt-flac")]
 pub mod flac;
 
+#[cfg(any(feature = "fmt-aiff", feature = "fmt-mp3", feature = "fmt-wav"))]
+pub mod id3v2;
+
+#[cfg(any(feature = "fmt-aiff", feature = "fmt-wav"))]
+pub mod iff;
+
 #[cfg(feature = "fmt-mp3")]
 pub mod mp3;
 
 #[cfg(feature = "fmt-mp4")]
 pub mod mp4;
 
+#[cfg(feature = "fmt-mpc")]
+pub mod mpc;
+
 #[cfg(feature = "fmt-ogg")]
 pub mod ogg;
 
-#[cfg(any(feature = "fmt-flag", feature = "fmt-ogg"))]
+#[cfg(feature = "fmt-opus")]
+pub mod opus;
+
+#[cfg(any(
+    feature = "fmt-ape",
+    feature = "fmt-flac",
+    feature = "fmt-mpc",
+    feature = "fmt-ogg",
+    feature = "fmt-opus",
+    feature = "fmt-wavpack"
+))]
 pub mod vorbis;
+
+#[cfg(feature = "fmt-wav")]
+pub mod wav;
+
+#[cfg(feature = "fmt-wavpack")]
+pub mod wavpack;
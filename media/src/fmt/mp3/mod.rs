@@ -16,12 +16,9 @@
 ///////////////////////////////////////////////////////////////////////
 
 use crate::{
-    io::import::{self, *},
-    util::{
-        digest::MediaDigest,
-        parse_artwork_from_embedded_image, parse_index_numbers, parse_key_signature,
-        parse_replay_gain, parse_tempo_bpm, push_next_actor_role_name, serato,
-        tag::{import_faceted_tags, FacetedTagMappingConfig},
+    io::{
+        export::{self, ExportTrackConfig, ExportTrackFlags},
+        import::{self, *},
     },
     Result,
 };
@@ -29,132 +26,524 @@ use crate::{
 use aoide_core::{
     audio::{
         channel::{ChannelCount, NumberOfChannels},
-        signal::SampleRateHz,
-        AudioContent,
-    },
-    media::{concat_encoder_properties, Content, ContentMetadataFlags},
-    tag::{Facet, Score as TagScore, Tags, TagsMap},
-    track::{
-        actor::ActorRole,
-        album::AlbumKind,
-        release::DateOrDateTime,
-        tag::{FACET_CGROUP, FACET_COMMENT, FACET_GENRE, FACET_MOOD},
-        title::{Title, TitleKind},
-        Track,
-    },
-    util::{
-        clock::{DateTime, DateYYYYMMDD, MonthType, YearType},
-        Canonical, CanonicalizeInto as _,
+        signal::{BitrateBps, BitrateMode, SampleRateHz},
+        AudioContent, DurationMs,
     },
+    media::{Content, ContentMetadataFlags},
+    track::Track,
 };
 
-use aoide_core_serde::tag::Tags as SerdeTags;
-
-use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Utc};
-use id3::{self, frame::PictureType};
-use mime::Mime;
-use minimp3::Decoder;
-use semval::IsValid as _;
-use std::{borrow::Cow, io::SeekFrom, time::Duration};
-use triseratops::tag::{
-    format::id3::ID3Tag, Markers as SeratoMarkers, Markers2 as SeratoMarkers2,
-    TagContainer as SeratoTagContainer, TagFormat as SeratoTagFormat,
+use std::{
+    borrow::Cow,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+    time::Duration,
 };
 
-fn parse_timestamp(timestamp: id3::Timestamp) -> DateOrDateTime {
-    match (timestamp.month, timestamp.day) {
-        (Some(month), Some(day)) => {
-            let date = NaiveDate::from_ymd_opt(timestamp.year, month.into(), day.into());
-            if let Some(date) = date {
-                if let (Some(hour), Some(min), Some(sec)) =
-                    (timestamp.hour, timestamp.minute, timestamp.second)
-                {
-                    let time = NaiveTime::from_hms_opt(hour.into(), min.into(), sec.into());
-                    if let Some(time) = time {
-                        return DateTime::from(chrono::DateTime::<Utc>::from_utc(
-                            NaiveDateTime::new(date, time),
-                            Utc,
-                        ))
-                        .into();
-                    }
-                }
-                DateYYYYMMDD::from(date).into()
-            } else if month > 0 && month <= 12 {
-                DateYYYYMMDD::from_year_month(timestamp.year as YearType, month as MonthType).into()
-            } else {
-                DateYYYYMMDD::from_year(timestamp.year as YearType).into()
-            }
+use super::id3v2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MpegVersion {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MpegLayer {
+    Layer1,
+    Layer2,
+    Layer3,
+}
+
+const FRAME_HEADER_SIZE: usize = 4;
+
+const BITRATES_KBPS_MPEG1: [[u16; 15]; 3] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+];
+
+const BITRATES_KBPS_MPEG2: [[u16; 15]; 2] = [
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+const SAMPLE_RATES_MPEG1: [u32; 3] = [44_100, 48_000, 32_000];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameHeader {
+    version: MpegVersion,
+    layer: MpegLayer,
+    crc_protected: bool,
+    bitrate_kbps: u16,
+    sample_rate: u32,
+    padding: bool,
+    channel_count: u8,
+}
+
+impl FrameHeader {
+    /// Parse a frame header
+    ///
+    /// Free format frames without a bitrate are not supported.
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < FRAME_HEADER_SIZE || bytes[0] != 0xFF || bytes[1] & 0xE0 != 0xE0 {
+            return None;
         }
-        (Some(month), None) => {
-            if month > 0 && month <= 12 {
-                DateYYYYMMDD::from_year_month(timestamp.year as YearType, month as MonthType).into()
-            } else {
-                DateYYYYMMDD::from_year(timestamp.year as YearType).into()
+        let version = match (bytes[1] >> 3) & 0b11 {
+            0b00 => MpegVersion::Mpeg25,
+            0b10 => MpegVersion::Mpeg2,
+            0b11 => MpegVersion::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (bytes[1] >> 1) & 0b11 {
+            0b01 => MpegLayer::Layer3,
+            0b10 => MpegLayer::Layer2,
+            0b11 => MpegLayer::Layer1,
+            _ => return None,
+        };
+        let crc_protected = bytes[1] & 0b1 == 0;
+        let bitrate_index = usize::from(bytes[2] >> 4);
+        if bitrate_index == 0 || bitrate_index == 0b1111 {
+            return None;
+        }
+        let bitrate_kbps = match (version, layer) {
+            (MpegVersion::Mpeg1, MpegLayer::Layer1) => BITRATES_KBPS_MPEG1[0][bitrate_index],
+            (MpegVersion::Mpeg1, MpegLayer::Layer2) => BITRATES_KBPS_MPEG1[1][bitrate_index],
+            (MpegVersion::Mpeg1, MpegLayer::Layer3) => BITRATES_KBPS_MPEG1[2][bitrate_index],
+            (_, MpegLayer::Layer1) => BITRATES_KBPS_MPEG2[0][bitrate_index],
+            (_, _) => BITRATES_KBPS_MPEG2[1][bitrate_index],
+        };
+        let sample_rate_index = usize::from((bytes[2] >> 2) & 0b11);
+        let sample_rate = SAMPLE_RATES_MPEG1.get(sample_rate_index)?
+            / match version {
+                MpegVersion::Mpeg1 => 1,
+                MpegVersion::Mpeg2 => 2,
+                MpegVersion::Mpeg25 => 4,
+            };
+        let padding = (bytes[2] >> 1) & 0b1 != 0;
+        let channel_count = if bytes[3] >> 6 == 0b11 { 1 } else { 2 };
+        Some(Self {
+            version,
+            layer,
+            crc_protected,
+            bitrate_kbps,
+            sample_rate,
+            padding,
+            channel_count,
+        })
+    }
+
+    fn samples_per_frame(&self) -> u32 {
+        match (self.layer, self.version) {
+            (MpegLayer::Layer1, _) => 384,
+            (MpegLayer::Layer2, _) | (MpegLayer::Layer3, MpegVersion::Mpeg1) => 1152,
+            (MpegLayer::Layer3, _) => 576,
+        }
+    }
+
+    /// The size of the whole frame in bytes, including the header
+    fn frame_size(&self) -> usize {
+        let bitrate_bps = u32::from(self.bitrate_kbps) * 1000;
+        let size = if self.layer == MpegLayer::Layer1 {
+            (12 * bitrate_bps / self.sample_rate + u32::from(self.padding)) * 4
+        } else {
+            self.samples_per_frame() / 8 * bitrate_bps / self.sample_rate + u32::from(self.padding)
+        };
+        size as usize
+    }
+
+    /// The offset of the Xing/Info header within the first frame
+    fn xing_offset(&self) -> usize {
+        let side_info_size = match (self.version, self.channel_count) {
+            (MpegVersion::Mpeg1, 1) => 17,
+            (MpegVersion::Mpeg1, _) => 32,
+            (_, 1) => 9,
+            (_, _) => 17,
+        };
+        let crc_size = if self.crc_protected { 2 } else { 0 };
+        FRAME_HEADER_SIZE + crc_size + side_info_size
+    }
+
+    /// Check if the header belongs to the same stream
+    fn is_compatible(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.layer == other.layer
+            && self.sample_rate == other.sample_rate
+    }
+}
+
+// The VBRI header is always located 32 bytes after the frame header
+const VBRI_OFFSET: usize = FRAME_HEADER_SIZE + 32;
+
+/// Information from a Xing/Info or VBRI header with
+/// an optional LAME extension in the first frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InfoHeader {
+    bitrate_mode: BitrateMode,
+
+    frame_count: Option<u32>,
+
+    // Encoder and version, e.g. "LAME3.100"
+    encoder: Option<String>,
+
+    // Number of samples added by the encoder at the start and end
+    encoder_delay_padding: Option<(u32, u32)>,
+}
+
+fn read_u32_be(bytes: &[u8]) -> Option<u32> {
+    let bytes = bytes.get(..4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn parse_lame_extension(bytes: &[u8]) -> Option<(String, BitrateMode, (u32, u32))> {
+    if bytes.len() < 24 || !bytes.starts_with(b"LAME") {
+        return None;
+    }
+    let encoder = String::from_utf8_lossy(&bytes[..9])
+        .trim_end_matches(char::from(0))
+        .trim()
+        .to_owned();
+    let bitrate_mode = match bytes[9] & 0x0F {
+        // CBR and CBR with 2 passes
+        1 | 8 => BitrateMode::Constant,
+        _ => BitrateMode::Variable,
+    };
+    // 12 bits for the delay followed by 12 bits for the padding
+    let delay = (u32::from(bytes[21]) << 4) | (u32::from(bytes[22]) >> 4);
+    let padding = (u32::from(bytes[22] & 0x0F) << 8) | u32::from(bytes[23]);
+    Some((encoder, bitrate_mode, (delay, padding)))
+}
+
+/// Parse a Xing/Info or VBRI header in the first frame
+///
+/// http://gabriel.mp3-tech.org/mp3infotag.html
+fn parse_info_header(frame_header: &FrameHeader, frame: &[u8]) -> Option<InfoHeader> {
+    let xing_offset = frame_header.xing_offset();
+    if let Some(xing) = frame.get(xing_offset..) {
+        let bitrate_mode = if xing.starts_with(b"Xing") {
+            Some(BitrateMode::Variable)
+        } else if xing.starts_with(b"Info") {
+            Some(BitrateMode::Constant)
+        } else {
+            None
+        };
+        if let Some(bitrate_mode) = bitrate_mode {
+            let flags = read_u32_be(&xing[4..])?;
+            let mut offset = 8;
+            let mut frame_count = None;
+            if flags & 0x1 != 0 {
+                frame_count = read_u32_be(&xing[offset..]);
+                offset += 4;
+            }
+            if flags & 0x2 != 0 {
+                // Number of bytes
+                offset += 4;
+            }
+            if flags & 0x4 != 0 {
+                // Table of contents
+                offset += 100;
+            }
+            if flags & 0x8 != 0 {
+                // Quality indicator
+                offset += 4;
             }
+            let mut info_header = InfoHeader {
+                bitrate_mode,
+                frame_count,
+                encoder: None,
+                encoder_delay_padding: None,
+            };
+            if let Some((encoder, bitrate_mode, delay_padding)) =
+                xing.get(offset..).and_then(parse_lame_extension)
+            {
+                // The LAME extension is more specific than the Xing/Info tag
+                info_header.bitrate_mode = bitrate_mode;
+                info_header.encoder = Some(encoder);
+                info_header.encoder_delay_padding = Some(delay_padding);
+            }
+            return Some(info_header);
         }
-        _ => DateYYYYMMDD::from_year(timestamp.year as YearType).into(),
     }
+    let vbri = frame.get(VBRI_OFFSET..)?;
+    if vbri.len() < 18 || !vbri.starts_with(b"VBRI") {
+        return None;
+    }
+    Some(InfoHeader {
+        bitrate_mode: BitrateMode::Variable,
+        frame_count: read_u32_be(&vbri[14..]),
+        encoder: None,
+        encoder_delay_padding: None,
+    })
 }
 
-fn id3_text_frames<'a>(
-    id3_tag: &'a id3::Tag,
-    frame_id: &'a str,
-) -> impl Iterator<Item = &'a str> + 'a {
-    id3_tag
-        .frames()
-        .filter(move |frame| frame.id() == frame_id)
-        .filter_map(|frame| {
-            if let id3::Content::Text(txt) = frame.content() {
-                Some(txt.as_str())
+const ID3V2_HEADER_SIZE: usize = 10;
+
+/// The size of an optional ID3v2 tag at the start of the stream
+fn id3v2_tag_size(header: &[u8]) -> u64 {
+    if header.len() < ID3V2_HEADER_SIZE || !header.starts_with(b"ID3") {
+        return 0;
+    }
+    // Syncsafe integer with 7 bits per byte
+    let tag_size = header[6..10]
+        .iter()
+        .fold(0u64, |size, b| (size << 7) | u64::from(b & 0x7F));
+    // Optional footer
+    let footer_size = if header[5] & 0x10 != 0 {
+        ID3V2_HEADER_SIZE as u64
+    } else {
+        0
+    };
+    ID3V2_HEADER_SIZE as u64 + tag_size + footer_size
+}
+
+// The number of bytes that are read ahead while scanning
+const SCAN_BUFFER_SIZE: usize = 16_384;
+
+// Upper bound for the size of a single frame, i.e. the maximum
+// distance for looking back when resynchronizing
+const MAX_FRAME_SIZE: usize = 4_096;
+
+/// Random access to a bounded window of the stream
+///
+/// Data that is not needed while scanning, e.g. the payload of
+/// frames beyond the current window, is skipped by seeking.
+struct ScanBuffer<R> {
+    reader: R,
+    stream_len: u64,
+    // The stream position of the first buffered byte
+    start: u64,
+    data: Vec<u8>,
+}
+
+impl<R: Read + Seek> ScanBuffer<R> {
+    fn new(mut reader: R) -> Result<Self> {
+        let stream_len = reader.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            reader,
+            stream_len,
+            start: stream_len,
+            data: Vec::with_capacity(MAX_FRAME_SIZE + SCAN_BUFFER_SIZE),
+        })
+    }
+
+    /// Get up to `len` bytes at the given stream position
+    ///
+    /// Fewer bytes are returned at the end of the stream.
+    fn bytes_at(&mut self, pos: u64, len: usize) -> Result<&[u8]> {
+        // The reader is always positioned at the end of the buffered data
+        let end = self.start + self.data.len() as u64;
+        if pos < self.start || pos + len as u64 > end {
+            if pos >= self.start && pos <= end {
+                // Keep some of the preceding bytes and continue reading
+                let discard = (pos - self.start).saturating_sub(MAX_FRAME_SIZE as u64);
+                self.data.drain(..discard as usize);
+                self.start += discard;
             } else {
-                None
+                self.reader.seek(SeekFrom::Start(pos))?;
+                self.data.clear();
+                self.start = pos;
             }
-        })
-        // All "T..."" text frames (except "TXXX") may contain multiple
-        // values separated by a NULL character
-        .flat_map(|txt| txt.split('\0'))
+            let fill_len = (pos - self.start) as usize + len.max(SCAN_BUFFER_SIZE);
+            if self.data.len() < fill_len {
+                let read_len = (fill_len - self.data.len()) as u64;
+                (&mut self.reader)
+                    .take(read_len)
+                    .read_to_end(&mut self.data)?;
+            }
+        }
+        let offset = (pos - self.start) as usize;
+        let available = self.data.get(offset..).unwrap_or_default();
+        Ok(&available[..len.min(available.len())])
+    }
+}
+
+/// Parse a frame header at the given position that is followed
+/// by another compatible frame or the end of the stream
+fn find_frame<R: Read + Seek>(
+    buffer: &mut ScanBuffer<R>,
+    pos: u64,
+    reference: Option<&FrameHeader>,
+) -> Result<Option<FrameHeader>> {
+    let frame_header = match FrameHeader::parse(buffer.bytes_at(pos, FRAME_HEADER_SIZE)?) {
+        Some(frame_header) => frame_header,
+        None => return Ok(None),
+    };
+    if let Some(reference) = reference {
+        if !frame_header.is_compatible(reference) {
+            return Ok(None);
+        }
+    }
+    let next_pos = pos + frame_header.frame_size() as u64;
+    if next_pos == buffer.stream_len {
+        return Ok(Some(frame_header));
+    }
+    let next_header = match FrameHeader::parse(buffer.bytes_at(next_pos, FRAME_HEADER_SIZE)?) {
+        Some(next_header) => next_header,
+        None => return Ok(None),
+    };
+    if !next_header.is_compatible(&frame_header) {
+        return Ok(None);
+    }
+    Ok(Some(frame_header))
 }
 
-fn id3_first_text_frame<'a>(id3_tag: &'a id3::Tag, frame_id: &'a str) -> Option<&'a str> {
-    id3_text_frames(id3_tag, frame_id).next()
+/// Find the next frame starting at the given position
+///
+/// Returns the position and the header of the frame.
+fn find_next_frame<R: Read + Seek>(
+    buffer: &mut ScanBuffer<R>,
+    mut pos: u64,
+    reference: Option<&FrameHeader>,
+) -> Result<Option<(u64, FrameHeader)>> {
+    while pos + FRAME_HEADER_SIZE as u64 <= buffer.stream_len {
+        if let Some(frame_header) = find_frame(buffer, pos, reference)? {
+            return Ok(Some((pos, frame_header)));
+        }
+        pos += 1;
+    }
+    Ok(None)
 }
 
-fn id3_extended_texts<'a>(
-    id3_tag: &'a id3::Tag,
-    description: &'a str,
-) -> impl Iterator<Item = &'a str> + 'a {
-    id3_tag
-        .extended_texts()
-        .filter(move |txxx| txxx.description == description)
-        .map(|txxx| txxx.value.as_str())
+/// Properties of an MPEG audio stream, obtained by scanning all frames
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StreamInfo {
+    channel_count: u8,
+
+    sample_rate: u32,
+
+    frame_count: u64,
+
+    samples_per_frame: u32,
+
+    // Number of decoded samples per channel, excluding the
+    // encoder delay and padding if known
+    sample_count: u64,
+
+    // Size of all audio frames in bytes
+    audio_size: u64,
+
+    bitrate_mode: BitrateMode,
+
+    encoder: Option<String>,
 }
 
-fn id3_first_extended_text<'a>(id3_tag: &'a id3::Tag, description: &'a str) -> Option<&'a str> {
-    id3_extended_texts(id3_tag, description).next()
+impl StreamInfo {
+    fn duration(&self) -> DurationMs {
+        DurationMs::from_inner(self.sample_count as f64 * 1000.0 / f64::from(self.sample_rate))
+    }
+
+    /// The average bitrate of all audio frames
+    fn average_bitrate(&self) -> BitrateBps {
+        let secs = (self.frame_count * u64::from(self.samples_per_frame)) as f64
+            / f64::from(self.sample_rate);
+        BitrateBps::from_inner(self.audio_size as f64 * 8.0 / secs)
+    }
 }
 
-fn import_faceted_text_tags(
-    tags_map: &mut TagsMap,
-    config: &FacetedTagMappingConfig,
-    facet: &Facet,
-    id3_tag: &id3::Tag,
-    frame_id: &str,
-) {
-    let removed_tags = tags_map.remove_faceted_tags(&facet);
-    if removed_tags > 0 {
-        log::debug!("Replacing {} custom '{}' tags", removed_tags, facet.value());
+/// Walk through all frames of an MPEG audio stream
+///
+/// Garbage between frames and trailing tags like ID3v1 or APEv2
+/// are skipped by resynchronizing on the next valid frame header.
+fn scan_frames<R: Read + Seek>(reader: R) -> Result<Option<StreamInfo>> {
+    let mut buffer = ScanBuffer::new(reader)?;
+    let start_pos = id3v2_tag_size(buffer.bytes_at(0, ID3V2_HEADER_SIZE)?);
+    let (mut pos, first_header) = match find_next_frame(&mut buffer, start_pos, None)? {
+        Some(first_frame) => first_frame,
+        None => return Ok(None),
+    };
+    let info_header = parse_info_header(
+        &first_header,
+        buffer.bytes_at(pos, first_header.frame_size())?,
+    );
+    if info_header.is_some() {
+        // The first frame contains the info header instead of audio data
+        pos += first_header.frame_size() as u64;
     }
-    let tag_mapping_config = config.get(facet.value());
-    let mut next_score_value = TagScore::max_value();
-    for label in id3_text_frames(id3_tag, frame_id) {
-        import_faceted_tags(
-            tags_map,
-            &mut next_score_value,
-            &facet,
-            tag_mapping_config,
-            label,
-        );
+    let mut frame_count = 0u64;
+    let mut audio_size = 0u64;
+    let mut channel_count = first_header.channel_count;
+    let mut bitrate_kbps = None;
+    let mut variable_bitrate = false;
+    while pos + FRAME_HEADER_SIZE as u64 <= buffer.stream_len {
+        let frame_header = match FrameHeader::parse(buffer.bytes_at(pos, FRAME_HEADER_SIZE)?) {
+            Some(frame_header) if frame_header.is_compatible(&first_header) => frame_header,
+            _ => {
+                // Resynchronize
+                match find_next_frame(&mut buffer, pos + 1, Some(&first_header))? {
+                    Some((next_pos, _)) => {
+                        log::debug!("Skipped {} bytes between MP3 frames", next_pos - pos);
+                        pos = next_pos;
+                        continue;
+                    }
+                    None => break,
+                }
+            }
+        };
+        let frame_size = frame_header.frame_size() as u64;
+        if pos + frame_size > buffer.stream_len {
+            log::debug!("Ignoring truncated MP3 frame at the end");
+            break;
+        }
+        if frame_count == 0 {
+            channel_count = frame_header.channel_count;
+        }
+        if let Some(bitrate_kbps) = bitrate_kbps {
+            variable_bitrate |= bitrate_kbps != frame_header.bitrate_kbps;
+        }
+        bitrate_kbps = Some(frame_header.bitrate_kbps);
+        frame_count += 1;
+        audio_size += frame_size;
+        pos += frame_size;
+    }
+    if frame_count == 0 {
+        return Ok(None);
+    }
+    let samples_per_frame = first_header.samples_per_frame();
+    let mut sample_count = frame_count * u64::from(samples_per_frame);
+    let mut bitrate_mode = if variable_bitrate {
+        BitrateMode::Variable
+    } else {
+        BitrateMode::Constant
+    };
+    let mut encoder = None;
+    if let Some(info_header) = info_header {
+        if let Some(expected_frame_count) = info_header.frame_count {
+            if u64::from(expected_frame_count) != frame_count {
+                log::debug!(
+                    "Number of MP3 frames differs from info header: expected = {}, actual = {}",
+                    expected_frame_count,
+                    frame_count
+                );
+            }
+        }
+        if let Some((delay, padding)) = info_header.encoder_delay_padding {
+            sample_count = sample_count.saturating_sub(u64::from(delay) + u64::from(padding));
+        }
+        // A VBR encoder might have used the same bitrate for all frames
+        if !variable_bitrate {
+            bitrate_mode = info_header.bitrate_mode;
+        }
+        encoder = info_header.encoder;
     }
+    Ok(Some(StreamInfo {
+        channel_count,
+        sample_rate: first_header.sample_rate,
+        frame_count,
+        samples_per_frame,
+        sample_count,
+        audio_size,
+        bitrate_mode,
+        encoder,
+    }))
 }
 
 #[derive(Debug)]
@@ -168,43 +557,10 @@ impl import::ImportTrack for ImportTrack {
         mut track: Track,
         reader: &mut Box<dyn Reader>,
     ) -> Result<Track> {
-        // Read number of channels and sample rate from the first decoded
-        // MP3 frame. Those properties are supposed to be constant for the
-        // whole MP3 file. Decoding the whole file would take too long.
-        let mut decoder = Decoder::new(reader);
-        let mut channels = None;
-        let mut sample_rate = None;
-        loop {
-            let decoded_frame = decoder.next_frame();
-            match decoded_frame {
-                Ok(frame) => {
-                    if frame.layer != 3
-                        || frame.channels < 1
-                        || frame.channels > 2
-                        || frame.sample_rate <= 0
-                        || frame.data.is_empty()
-                    {
-                        // Silently skip invalid or empty frames
-                        log::warn!("Invalid MP3 frame: {:?}", frame);
-                        continue;
-                    }
-                    channels = Some(ChannelCount(frame.channels as NumberOfChannels).into());
-                    sample_rate = Some(SampleRateHz::from_inner(frame.sample_rate as f64));
-                    // Stop decoding
-                    break;
-                }
-                Err(minimp3::Error::Eof) => break,
-                Err(minimp3::Error::Io(err)) => return Err(err.into()),
-                Err(err) => return Err(anyhow::Error::from(err).into()),
-            }
-        }
-        let reader = decoder.into_inner();
-
-        // Restart reader after obtainig the basic audio properties from minimp3
-        let _start_pos = reader.seek(SeekFrom::Start(0))?;
-        debug_assert_eq!(0, _start_pos);
-
-        let mut duration = mp3_duration::from_read(reader).map(Into::into).ok();
+        // Scanning all frames is the only way to obtain an accurate
+        // duration and average bitrate, e.g. for VBR files without
+        // an info header
+        let stream_info = scan_frames(&mut *reader)?;
 
         // Restart reader after obtainig the basic audio properties
         let _start_pos = reader.seek(SeekFrom::Start(0))?;
@@ -212,13 +568,10 @@ impl import::ImportTrack for ImportTrack {
 
         let id3_tag = id3::Tag::read_from(reader).map_err(anyhow::Error::from)?;
 
-        let metadata_flags = if duration.is_some() {
+        let metadata_flags = if stream_info.is_some() {
             // Accurate duration
             ContentMetadataFlags::RELIABLE
         } else {
-            duration = id3_tag
-                .duration()
-                .map(|secs| Duration::from_secs(u64::from(secs)).into());
             ContentMetadataFlags::UNRELIABLE
         };
         if track
@@ -226,353 +579,94 @@ impl import::ImportTrack for ImportTrack {
             .content_metadata_flags
             .update(metadata_flags)
         {
-            // TODO: Average bitrate needs to be calculated from all MP3 frames
-            // if not stored explicitly. mp3-duration already reads the bitrate
-            // of each frame but does not calculate and return an average bitrate.
-            let bitrate = None;
-            let loudness = id3_first_extended_text(&id3_tag, "REPLAYGAIN_TRACK_GAIN")
-                .and_then(parse_replay_gain);
-            let encoder = concat_encoder_properties(
-                id3_first_text_frame(&id3_tag, "TENC"),
-                id3_first_text_frame(&id3_tag, "TSSE"),
-            )
-            .map(Cow::into_owned);
+            let mut duration = None;
+            let mut channels = None;
+            let mut sample_rate = None;
+            let mut bitrate = None;
+            let mut bitrate_mode = None;
+            let mut encoder = id3v2::import_encoder(&id3_tag).map(Cow::into_owned);
+            if let Some(stream_info) = stream_info {
+                duration = Some(stream_info.duration());
+                channels =
+                    Some(ChannelCount(NumberOfChannels::from(stream_info.channel_count)).into());
+                let sample_rate_hz = SampleRateHz::from_inner(stream_info.sample_rate.into());
+                if sample_rate_hz.is_valid() {
+                    sample_rate = Some(sample_rate_hz);
+                } else {
+                    log::warn!("Invalid sample rate: {}", sample_rate_hz);
+                }
+                let avg_bitrate = stream_info.average_bitrate();
+                if avg_bitrate.is_valid() {
+                    bitrate = Some(avg_bitrate);
+                } else {
+                    log::warn!("Invalid bitrate: {}", avg_bitrate);
+                }
+                bitrate_mode = Some(stream_info.bitrate_mode);
+                if encoder.is_none() {
+                    encoder = stream_info.encoder;
+                }
+            } else {
+                log::warn!("No valid MP3 frames found");
+                duration = id3_tag
+                    .duration()
+                    .map(|secs| Duration::from_secs(u64::from(secs)).into());
+            }
+            let loudness = id3v2::import_loudness(&id3_tag);
             let audio_content = AudioContent {
                 duration,
                 channels,
                 sample_rate,
                 bitrate,
+                bitrate_mode,
                 loudness,
+                loudness_range: None,
+                true_peak: None,
+                waveform: None,
+                range: None,
                 encoder,
             };
             track.media_source.content = Content::Audio(audio_content);
         }
 
-        if let Some(tempo_bpm) = id3_first_extended_text(&id3_tag, "BPM")
-            .and_then(parse_tempo_bpm)
-            // Alternative: Try "TEMPO" if "BPM" is missing or invalid
-            .or_else(|| id3_first_extended_text(&id3_tag, "TEMPO").and_then(parse_tempo_bpm))
-            // Fallback: Parse integer BPM
-            .or_else(|| id3_first_text_frame(&id3_tag, "TBPM").and_then(parse_tempo_bpm))
-        {
-            debug_assert!(tempo_bpm.is_valid());
-            track.metrics.tempo_bpm = Some(tempo_bpm);
-        }
-
-        if let Some(key_signature) =
-            id3_first_text_frame(&id3_tag, "TKEY").and_then(parse_key_signature)
-        {
-            track.metrics.key_signature = key_signature;
-        }
-
-        // Track titles
-        let mut track_titles = Vec::with_capacity(4);
-        if let Some(name) = id3_tag.title() {
-            let title = Title {
-                name: name.to_owned(),
-                kind: TitleKind::Main,
-            };
-            track_titles.push(title);
-        }
-        if let Some(name) = id3_first_text_frame(&id3_tag, "TSST") {
-            let title = Title {
-                name: name.to_owned(),
-                kind: TitleKind::Sub,
-            };
-            track_titles.push(title);
-        }
-        if let Some(name) = id3_first_text_frame(&id3_tag, "MVNM") {
-            let title = Title {
-                name: name.to_owned(),
-                kind: TitleKind::Movement,
-            };
-            track_titles.push(title);
-        }
-        if flags.contains(ImportTrackFlags::ITUNES_ID3V2_GROUPING_MOVEMENT_WORK) {
-            // Starting with iTunes 12.5.4 the "TIT1" text frame is used
-            // for storing the work instead of the grouping.
-            if let Some(name) = id3_first_text_frame(&id3_tag, "TIT1") {
-                let title = Title {
-                    name: name.to_owned(),
-                    kind: TitleKind::Work,
-                };
-                track_titles.push(title);
-            }
-        } else if let Some(name) = id3_first_extended_text(&id3_tag, "WORK") {
-            let title = Title {
-                name: name.to_owned(),
-                kind: TitleKind::Work,
-            };
-            track_titles.push(title);
-        }
-        let track_titles = track_titles.canonicalize_into();
-        if !track_titles.is_empty() {
-            track.titles = Canonical::tie(track_titles);
-        }
-
-        // Track actors
-        let mut track_actors = Vec::with_capacity(8);
-        if let Some(name) = id3_tag.artist() {
-            push_next_actor_role_name(&mut track_actors, ActorRole::Artist, name.to_owned());
-        }
-        for name in id3_text_frames(&id3_tag, "TCOM") {
-            push_next_actor_role_name(&mut track_actors, ActorRole::Composer, name.to_owned());
-        }
-        for name in id3_text_frames(&id3_tag, "TPE3") {
-            push_next_actor_role_name(&mut track_actors, ActorRole::Conductor, name.to_owned());
-        }
-        let track_actors = track_actors.canonicalize_into();
-        if !track_actors.is_empty() {
-            track.actors = Canonical::tie(track_actors);
-        }
-
-        let mut album = track.album.untie();
-
-        // Album titles
-        let mut album_titles = Vec::with_capacity(1);
-        if let Some(name) = id3_tag.album() {
-            let title = Title {
-                name: name.to_owned(),
-                kind: TitleKind::Main,
-            };
-            album_titles.push(title);
-        }
-        let album_titles = album_titles.canonicalize_into();
-        if !album_titles.is_empty() {
-            album.titles = Canonical::tie(album_titles);
-        }
-
-        // Album actors
-        let mut album_actors = Vec::with_capacity(4);
-        if let Some(name) = id3_tag.album_artist() {
-            push_next_actor_role_name(&mut album_actors, ActorRole::Artist, name.to_owned());
-        }
-        let album_actors = album_actors.canonicalize_into();
-        if !album_actors.is_empty() {
-            album.actors = Canonical::tie(album_actors);
-        }
-
-        // Album properties
-        if id3_first_text_frame(&id3_tag, "TCMP")
-            .and_then(|tcmp| tcmp.parse::<u8>().ok())
-            .unwrap_or_default()
-            == 1
-        {
-            album.kind = AlbumKind::Compilation;
-        }
-
-        track.album = Canonical::tie(album);
-
-        // Release properties
-        // Instead of the release date "TDRL" most applications use the recording date "TDRC".
-        // See also https://picard-docs.musicbrainz.org/en/appendices/tag_mapping.html
-        if let Some(released_at) = id3_tag
-            .date_released()
-            .or_else(|| id3_tag.date_recorded())
-            .map(parse_timestamp)
-        {
-            track.release.released_at = Some(released_at);
-        }
-        if let Some(label) = id3_first_text_frame(&id3_tag, "TPUB") {
-            track.release.released_by = Some(label.to_owned());
-        }
-        if let Some(copyright) = id3_first_text_frame(&id3_tag, "TCOP") {
-            track.release.copyright = Some(copyright.to_owned());
-        }
-
-        let mut tags_map = TagsMap::default();
-        if flags.contains(ImportTrackFlags::MIXXX_CUSTOM_TAGS) {
-            for geob in id3_tag
-                .encapsulated_objects()
-                .filter(|geob| geob.description == "Mixxx CustomTags")
-            {
-                if geob
-                    .mime_type
-                    .parse::<Mime>()
-                    .ok()
-                    .as_ref()
-                    .map(Mime::type_)
-                    != Some(mime::APPLICATION_JSON.type_())
-                {
-                    log::warn!(
-                        "Unexpected MIME type for GEOB '{}': {}",
-                        geob.description,
-                        geob.mime_type
-                    );
-                    continue;
-                }
-                if let Some(custom_tags) = serde_json::from_slice::<SerdeTags>(&geob.data)
-                    .map_err(|err| {
-                        log::warn!("Failed to parse Mixxx custom tags: {}", err);
-                        err
-                    })
-                    .ok()
-                    .map(Tags::from)
-                {
-                    // Initialize map with all existing custom tags as starting point
-                    debug_assert_eq!(0, tags_map.total_count());
-                    tags_map = custom_tags.into();
-                }
-            }
-        }
-
-        // Comment tag
-        for comment in id3_tag
-            .comments()
-            .filter(|comm| comm.description.is_empty())
-            .map(|comm| comm.text.as_str())
-        {
-            let removed_comments = tags_map.remove_faceted_tags(&FACET_COMMENT);
-            if removed_comments > 0 {
-                log::debug!(
-                    "Replacing {} custom '{}' tags",
-                    removed_comments,
-                    FACET_COMMENT.value()
-                );
-            }
-            let mut next_score_value = TagScore::default_value();
-            import_faceted_tags(
-                &mut tags_map,
-                &mut next_score_value,
-                &FACET_COMMENT,
-                None,
-                comment.to_owned(),
-            );
-        }
-
-        // Genre tags
-        import_faceted_text_tags(
-            &mut tags_map,
-            &config.faceted_tag_mapping,
-            &FACET_GENRE,
-            &id3_tag,
-            "TCON",
-        );
-
-        // Mood tags
-        import_faceted_text_tags(
-            &mut tags_map,
-            &config.faceted_tag_mapping,
-            &FACET_MOOD,
-            &id3_tag,
-            "TMOO",
-        );
-
-        // Grouping tags
-        // Apple decided to store the Work in the traditional ID3v2 Content Group
-        // frame (TIT1) and introduced new Grouping (GRP1) and Movement Name (MVNM)
-        // frames.
-        // https://discussions.apple.com/thread/7900430
-        // http://blog.jthink.net/2016/11/the-reason-why-is-grouping-field-no.html
-        if flags.contains(ImportTrackFlags::ITUNES_ID3V2_GROUPING_MOVEMENT_WORK) {
-            import_faceted_text_tags(
-                &mut tags_map,
-                &config.faceted_tag_mapping,
-                &FACET_CGROUP,
-                &id3_tag,
-                "GRP1",
-            );
-        } else {
-            import_faceted_text_tags(
-                &mut tags_map,
-                &config.faceted_tag_mapping,
-                &FACET_CGROUP,
-                &id3_tag,
-                "TIT1",
-            );
-        }
-
-        debug_assert!(track.tags.is_empty());
-        track.tags = Canonical::tie(tags_map.into());
-
-        // Indexes (in pairs)
-        if id3_tag.track().is_some() || id3_tag.total_tracks().is_some() {
-            track.indexes.track.number = id3_tag.track().map(|i| (i & 0xFFFF) as u16);
-            track.indexes.track.total = id3_tag.total_tracks().map(|i| (i & 0xFFFF) as u16);
-        }
-        if id3_tag.disc().is_some() || id3_tag.total_discs().is_some() {
-            track.indexes.disc.number = id3_tag.disc().map(|i| (i & 0xFFFF) as u16);
-            track.indexes.disc.total = id3_tag.total_discs().map(|i| (i & 0xFFFF) as u16);
-        }
-        if let Some(movement) = id3_first_text_frame(&id3_tag, "MVIN").and_then(parse_index_numbers)
-        {
-            track.indexes.movement = movement;
-        }
-
-        // Artwork
-        if flags.contains(ImportTrackFlags::ARTWORK) {
-            let mut image_digest = if flags.contains(ImportTrackFlags::ARTWORK_DIGEST) {
-                if flags.contains(ImportTrackFlags::ARTWORK_DIGEST_SHA256) {
-                    // Compatibility
-                    MediaDigest::sha256()
-                } else {
-                    // Default
-                    MediaDigest::new()
-                }
-            } else {
-                Default::default()
-            };
-            let artwork = id3_tag
-                .pictures()
-                .filter(|p| p.picture_type == PictureType::CoverFront)
-                .chain(
-                    id3_tag
-                        .pictures()
-                        .filter(|p| p.picture_type == PictureType::Media),
-                )
-                .chain(
-                    id3_tag
-                        .pictures()
-                        .filter(|p| p.picture_type == PictureType::Leaflet),
-                )
-                .chain(
-                    id3_tag
-                        .pictures()
-                        .filter(|p| p.picture_type == PictureType::Other),
-                )
-                // otherwise take the first picture that could be parsed
-                .chain(id3_tag.pictures())
-                .filter_map(|p| parse_artwork_from_embedded_image(&p.data, None, &mut image_digest))
-                .next();
-            if let Some(artwork) = artwork {
-                track.media_source.artwork = artwork;
-            }
-        }
-
-        // Serato Tags
-        if flags.contains(ImportTrackFlags::SERATO_TAGS) {
-            let mut serato_tags = SeratoTagContainer::new();
-
-            for geob in id3_tag.encapsulated_objects() {
-                match geob.description.as_str() {
-                    SeratoMarkers::ID3_TAG => {
-                        serato_tags
-                            .parse_markers(&geob.data, SeratoTagFormat::ID3)
-                            .map_err(|err| {
-                                log::warn!("Failed to parse Serato Markers: {}", err);
-                            })
-                            .ok();
-                    }
-                    SeratoMarkers2::ID3_TAG => {
-                        serato_tags
-                            .parse_markers2(&geob.data, SeratoTagFormat::ID3)
-                            .map_err(|err| {
-                                log::warn!("Failed to parse Serato Markers2: {}", err);
-                            })
-                            .ok();
-                    }
-                    _ => (),
-                }
-            }
+        id3v2::import_metadata_into_track(&id3_tag, config, flags, track)
+    }
 
-            let track_cues = serato::read_cues(&serato_tags)?;
-            if !track_cues.is_empty() {
-                track.cues = Canonical::tie(track_cues);
-            }
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        let id3_tag = id3::Tag::read_from(reader).map_err(anyhow::Error::from)?;
+        Ok(id3v2::load_artwork_image(&id3_tag))
+    }
+}
 
-            track.color = serato::read_track_color(&serato_tags);
-        }
+#[derive(Debug)]
+pub struct ExportTrack;
 
-        Ok(track)
+impl export::ExportTrack for ExportTrack {
+    fn export_track_to_path(
+        &self,
+        config: &ExportTrackConfig,
+        flags: ExportTrackFlags,
+        track: &Track,
+        path: &Path,
+    ) -> Result<()> {
+        let mut id3_tag = match id3::Tag::read_from_path(path) {
+            Ok(id3_tag) => id3_tag,
+            Err(id3::Error {
+                kind: id3::ErrorKind::NoTag,
+                ..
+            }) => id3::Tag::new(),
+            Err(err) => return Err(anyhow::Error::from(err).into()),
+        };
+        id3v2::export_track_to_tag(&mut id3_tag, config, flags, track);
+        id3_tag
+            .write_to_path(path, id3::Version::Id3v24)
+            .map_err(anyhow::Error::from)?;
+        Ok(())
     }
 }
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
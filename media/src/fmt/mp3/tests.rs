@@ -0,0 +1,147 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use std::io::Cursor;
+
+// MPEG-1 Layer III, 44.1 kHz, joint stereo, no CRC
+const HEADER_128_KBPS: [u8; 4] = [0xFF, 0xFB, 0x90, 0x40];
+const HEADER_160_KBPS: [u8; 4] = [0xFF, 0xFB, 0xA0, 0x40];
+
+fn frame(header: [u8; 4]) -> Vec<u8> {
+    let mut frame = vec![0u8; FrameHeader::parse(&header).unwrap().frame_size()];
+    frame[..header.len()].copy_from_slice(&header);
+    frame
+}
+
+#[test]
+fn parse_frame_header() {
+    let frame_header = FrameHeader::parse(&HEADER_128_KBPS).unwrap();
+    assert_eq!(
+        FrameHeader {
+            version: MpegVersion::Mpeg1,
+            layer: MpegLayer::Layer3,
+            crc_protected: false,
+            bitrate_kbps: 128,
+            sample_rate: 44_100,
+            padding: false,
+            channel_count: 2,
+        },
+        frame_header
+    );
+    assert_eq!(1152, frame_header.samples_per_frame());
+    assert_eq!(417, frame_header.frame_size());
+    assert_eq!(
+        522,
+        FrameHeader::parse(&HEADER_160_KBPS).unwrap().frame_size()
+    );
+}
+
+#[test]
+fn parse_invalid_frame_header() {
+    // Free format
+    assert!(FrameHeader::parse(&[0xFF, 0xFB, 0x00, 0x40]).is_none());
+    // Reserved sample rate
+    assert!(FrameHeader::parse(&[0xFF, 0xFB, 0x9C, 0x40]).is_none());
+    // Reserved layer
+    assert!(FrameHeader::parse(&[0xFF, 0xF9, 0x90, 0x40]).is_none());
+}
+
+#[test]
+fn scan_cbr_frames_with_tags_and_garbage() {
+    // ID3v2 tag with 20 bytes
+    let mut data = b"ID3\x04\x00\x00\x00\x00\x00\x14".to_vec();
+    data.extend_from_slice(&[0u8; 20]);
+    for i in 0..10 {
+        if i == 5 {
+            // Garbage between frames
+            data.extend_from_slice(&[0u8; 7]);
+        }
+        data.extend(frame(HEADER_128_KBPS));
+    }
+    // ID3v1 tag
+    data.extend_from_slice(b"TAG");
+    data.extend_from_slice(&[0u8; 125]);
+    let stream_info = scan_frames(Cursor::new(data)).unwrap().unwrap();
+    assert_eq!(
+        StreamInfo {
+            channel_count: 2,
+            sample_rate: 44_100,
+            frame_count: 10,
+            samples_per_frame: 1152,
+            sample_count: 11_520,
+            audio_size: 4_170,
+            bitrate_mode: BitrateMode::Constant,
+            encoder: None,
+        },
+        stream_info
+    );
+    assert_eq!(
+        127_706,
+        stream_info.average_bitrate().to_inner().round() as i64
+    );
+}
+
+#[test]
+fn scan_vbr_frames_with_xing_and_lame_header() {
+    let mut info_frame = frame(HEADER_128_KBPS);
+    let xing_offset = 36;
+    info_frame[xing_offset..xing_offset + 12]
+        .copy_from_slice(b"Xing\x00\x00\x00\x01\x00\x00\x00\x04");
+    let lame_offset = xing_offset + 12;
+    info_frame[lame_offset..lame_offset + 10].copy_from_slice(b"LAME3.100\x04");
+    // Encoder delay = 576, padding = 1000
+    info_frame[lame_offset + 21..lame_offset + 24].copy_from_slice(&[0x24, 0x03, 0xE8]);
+    let mut data = info_frame;
+    data.extend(frame(HEADER_128_KBPS));
+    data.extend(frame(HEADER_160_KBPS));
+    data.extend(frame(HEADER_160_KBPS));
+    data.extend(frame(HEADER_128_KBPS));
+    let stream_info = scan_frames(Cursor::new(data)).unwrap().unwrap();
+    assert_eq!(
+        StreamInfo {
+            channel_count: 2,
+            sample_rate: 44_100,
+            frame_count: 4,
+            samples_per_frame: 1152,
+            sample_count: 4 * 1152 - 576 - 1000,
+            audio_size: 2 * 417 + 2 * 522,
+            bitrate_mode: BitrateMode::Variable,
+            encoder: Some("LAME3.100".to_owned()),
+        },
+        stream_info
+    );
+}
+
+#[test]
+fn scan_cbr_frames_with_info_header() {
+    let mut info_frame = frame(HEADER_128_KBPS);
+    info_frame[36..44].copy_from_slice(b"Info\x00\x00\x00\x00");
+    let mut data = info_frame;
+    data.extend(frame(HEADER_128_KBPS));
+    data.extend(frame(HEADER_128_KBPS));
+    let stream_info = scan_frames(Cursor::new(data)).unwrap().unwrap();
+    assert_eq!(2, stream_info.frame_count);
+    assert_eq!(BitrateMode::Constant, stream_info.bitrate_mode);
+}
+
+#[test]
+fn scan_without_frames() {
+    assert!(scan_frames(Cursor::new(b"")).unwrap().is_none());
+    assert!(scan_frames(Cursor::new([0u8; 1000])).unwrap().is_none());
+}
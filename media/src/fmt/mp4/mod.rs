@@ -16,11 +16,18 @@
 ///////////////////////////////////////////////////////////////////////
 
 use crate::{
-    io::import::{self, *},
+    io::{
+        export::{self, ExportTrackConfig, ExportTrackFlags},
+        import::{self, *},
+    },
+    lyrics,
     util::{
-        digest::MediaDigest, parse_artwork_from_embedded_image, parse_key_signature,
-        parse_replay_gain, parse_tempo_bpm, parse_year_tag, push_next_actor_role_name, serato,
-        tag::import_faceted_tags,
+        digest::MediaDigest,
+        export_actor_role_names, format_key_signature, format_replay_gain, format_tempo_bpm,
+        parse_artwork_from_embedded_image, parse_key_signature, parse_replay_gain, parse_tempo_bpm,
+        parse_year_tag, push_next_actor_role_name, select_artwork_image_from_embedded_images,
+        serato,
+        tag::{export_faceted_tags, faceted_tags, import_faceted_tags},
     },
     Result,
 };
@@ -35,10 +42,10 @@ use aoide_core::{
     music::time::{Beats, TempoBpm},
     tag::{Score as TagScore, Tags, TagsMap},
     track::{
-        actor::ActorRole,
+        actor::{Actor, ActorRole},
         album::AlbumKind,
         tag::{FACET_CGROUP, FACET_COMMENT, FACET_GENRE, FACET_MOOD},
-        title::{Title, TitleKind},
+        title::{Title, TitleKind, Titles},
         Track,
     },
     util::{Canonical, CanonicalizeInto as _},
@@ -52,11 +59,64 @@ use mp4ameta::{
     STANDARD_GENRES,
 };
 use semval::IsValid as _;
+use std::path::Path;
 use triseratops::tag::{
     format::mp4::MP4Tag, Markers as SeratoMarkers, Markers2 as SeratoMarkers2,
     TagContainer as SeratoTagContainer, TagFormat as SeratoTagFormat,
 };
 
+/// Parse the Serato Markers and Markers2 atoms into the tag container
+///
+/// All atoms are parsed even if some of them are invalid and the
+/// last error is returned.
+fn read_serato_tags(mp4_tag: &Mp4Tag, serato_tags: &mut SeratoTagContainer) -> Result<()> {
+    let mut result = Ok(());
+
+    if let Some(data) = mp4_tag
+        .data(&FreeformIdent::new(
+            SeratoMarkers::MP4_ATOM_FREEFORM_MEAN,
+            SeratoMarkers::MP4_ATOM_FREEFORM_NAME,
+        ))
+        .next()
+    {
+        let parsed = match data {
+            Data::Utf8(input) => serato_tags
+                .parse_markers(input.as_bytes(), SeratoTagFormat::MP4)
+                .map_err(|err| anyhow::anyhow!("Failed to parse Serato Markers: {}", err)),
+            data => Err(anyhow::anyhow!(
+                "Unexpected data for Serato Markers: {:?}",
+                data
+            )),
+        };
+        if let Err(err) = parsed {
+            result = Err(err.into());
+        }
+    }
+
+    if let Some(data) = mp4_tag
+        .data(&FreeformIdent::new(
+            SeratoMarkers2::MP4_ATOM_FREEFORM_MEAN,
+            SeratoMarkers2::MP4_ATOM_FREEFORM_NAME,
+        ))
+        .next()
+    {
+        let parsed = match data {
+            Data::Utf8(input) => serato_tags
+                .parse_markers2(input.as_bytes(), SeratoTagFormat::MP4)
+                .map_err(|err| anyhow::anyhow!("Failed to parse Serato Markers2: {}", err)),
+            data => Err(anyhow::anyhow!(
+                "Unexpected data for Serato Markers2: {:?}",
+                data
+            )),
+        };
+        if let Err(err) = parsed {
+            result = Err(err.into());
+        }
+    }
+
+    result
+}
+
 #[derive(Debug)]
 pub struct ImportTrack;
 
@@ -90,6 +150,24 @@ fn read_channels(channel_config: ChannelConfig) -> Channels {
 
 const COM_APPLE_ITUNES_FREEFORM_MEAN: &str = "com.apple.iTunes";
 const ORG_MIXXX_DJ_FREEFORM_MEAN: &str = "org.mixxx.dj";
+const MIXXX_CUSTOM_TAGS_FREEFORM_NAME: &str = "CustomTags";
+
+/// All cover art images with their format, if known
+fn artwork_images(mp4_tag: &Mp4Tag) -> impl Iterator<Item = (&[u8], Option<ImageFormat>)> {
+    mp4_tag
+        .data(&FourCC(*b"covr"))
+        .map(|image_data| match image_data {
+            Data::Jpeg(bytes) => Some((bytes.as_slice(), Some(ImageFormat::Jpeg))),
+            Data::Png(bytes) => Some((bytes.as_slice(), Some(ImageFormat::Png))),
+            Data::Reserved(bytes) => Some((bytes.as_slice(), None)),
+            _ => {
+                log::warn!("Unexpected cover art data");
+                None
+            }
+        })
+        .take_while(Option::is_some)
+        .flatten()
+}
 
 impl import::ImportTrack for ImportTrack {
     fn import_track(
@@ -128,7 +206,12 @@ impl import::ImportTrack for ImportTrack {
                 channels,
                 sample_rate,
                 bitrate,
+                bitrate_mode: None,
                 loudness,
+                loudness_range: None,
+                true_peak: None,
+                waveform: None,
+                range: None,
                 encoder,
             };
             track.media_source.content = Content::Audio(audio_content);
@@ -290,7 +373,7 @@ impl import::ImportTrack for ImportTrack {
             if let Some(data) = mp4_tag
                 .data(&FreeformIdent::new(
                     ORG_MIXXX_DJ_FREEFORM_MEAN,
-                    "CustomTags",
+                    MIXXX_CUSTOM_TAGS_FREEFORM_NAME,
                 ))
                 .next()
             {
@@ -411,6 +494,14 @@ impl import::ImportTrack for ImportTrack {
             track.indexes.movement.total = mp4_tag.movement_count();
         }
 
+        // Lyrics (©lyr), either plain text or formatted as LRC
+        if let Some(imported) = mp4_tag
+            .take_lyrics()
+            .and_then(|text| lyrics::import_lyrics_text(None, &text))
+        {
+            track.lyrics = lyrics::collect_lyrics(std::iter::once(imported));
+        }
+
         // Artwork
         if flags.contains(ImportTrackFlags::ARTWORK) {
             let mut image_digest = if flags.contains(ImportTrackFlags::ARTWORK_DIGEST) {
@@ -424,16 +515,7 @@ impl import::ImportTrack for ImportTrack {
             } else {
                 Default::default()
             };
-            for image_data in mp4_tag.data(&FourCC(*b"covr")) {
-                let (image_data, image_format) = match image_data {
-                    Data::Jpeg(bytes) => (bytes, Some(ImageFormat::Jpeg)),
-                    Data::Png(bytes) => (bytes, Some(ImageFormat::Png)),
-                    Data::Reserved(bytes) => (bytes, None),
-                    _ => {
-                        log::warn!("Unexpected cover art data");
-                        break;
-                    }
-                };
+            for (image_data, image_format) in artwork_images(&mp4_tag) {
                 if let Some(artwork) =
                     parse_artwork_from_embedded_image(image_data, image_format, &mut image_digest)
                 {
@@ -446,49 +528,8 @@ impl import::ImportTrack for ImportTrack {
         // Serato Tags
         if flags.contains(ImportTrackFlags::SERATO_TAGS) {
             let mut serato_tags = SeratoTagContainer::new();
-
-            if let Some(data) = mp4_tag
-                .data(&FreeformIdent::new(
-                    SeratoMarkers::MP4_ATOM_FREEFORM_MEAN,
-                    SeratoMarkers::MP4_ATOM_FREEFORM_NAME,
-                ))
-                .next()
-            {
-                match data {
-                    Data::Utf8(input) => {
-                        serato_tags
-                            .parse_markers(input.as_bytes(), SeratoTagFormat::MP4)
-                            .map_err(|err| {
-                                log::warn!("Failed to parse Serato Markers: {}", err);
-                            })
-                            .ok();
-                    }
-                    data => {
-                        log::warn!("Unexpected data for Serato Markers: {:?}", data);
-                    }
-                }
-            }
-
-            if let Some(data) = mp4_tag
-                .data(&FreeformIdent::new(
-                    SeratoMarkers2::MP4_ATOM_FREEFORM_MEAN,
-                    SeratoMarkers2::MP4_ATOM_FREEFORM_NAME,
-                ))
-                .next()
-            {
-                match data {
-                    Data::Utf8(input) => {
-                        serato_tags
-                            .parse_markers2(input.as_bytes(), SeratoTagFormat::MP4)
-                            .map_err(|err| {
-                                log::warn!("Failed to parse Serato Markers2: {}", err);
-                            })
-                            .ok();
-                    }
-                    data => {
-                        log::warn!("Unexpected data for Serato Markers2: {:?}", data);
-                    }
-                }
+            if let Err(err) = read_serato_tags(&mp4_tag, &mut serato_tags) {
+                log::warn!("{}", err);
             }
 
             let track_cues = serato::read_cues(&serato_tags)?;
@@ -501,4 +542,344 @@ impl import::ImportTrack for ImportTrack {
 
         Ok(track)
     }
+
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        let mp4_tag = Mp4Tag::read_from(reader).map_err(anyhow::Error::from)?;
+        Ok(select_artwork_image_from_embedded_images(artwork_images(
+            &mp4_tag,
+        )))
+    }
+}
+
+fn export_freeform_strings(mp4_tag: &mut Mp4Tag, mean: &str, name: &str, values: Vec<String>) {
+    let ident = FreeformIdent::new(mean, name);
+    if values.is_empty() {
+        mp4_tag.remove_data_of(&ident);
+    } else {
+        mp4_tag.set_all_data(ident, values.into_iter().map(Data::Utf8));
+    }
+}
+
+fn export_serato_freeform_string(
+    mp4_tag: &mut Mp4Tag,
+    mean: &str,
+    name: &str,
+    data: Result<Vec<u8>>,
+) {
+    match data
+        .and_then(|data| String::from_utf8(data).map_err(|err| anyhow::Error::from(err).into()))
+    {
+        Ok(value) => {
+            export_freeform_strings(mp4_tag, mean, name, vec![value]);
+        }
+        Err(err) => {
+            // Keep the existing atom
+            log::warn!("{}", err);
+        }
+    }
+}
+
+fn export_itunes_freeform_strings(mp4_tag: &mut Mp4Tag, name: &str, values: Vec<String>) {
+    export_freeform_strings(mp4_tag, COM_APPLE_ITUNES_FREEFORM_MEAN, name, values);
+}
+
+fn export_actor_names(actors: &[Actor], role: ActorRole) -> Vec<String> {
+    export_actor_role_names(actors, role)
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+fn export_title_name(titles: &[Title], kind: TitleKind) -> Option<String> {
+    Titles::filter_kind(titles, kind)
+        .map(|title| title.name.to_owned())
+        .next()
+}
+
+fn export_track_to_tag(
+    mp4_tag: &mut Mp4Tag,
+    config: &ExportTrackConfig,
+    flags: ExportTrackFlags,
+    track: &Track,
+) {
+    let loudness = match &track.media_source.content {
+        Content::Audio(audio_content) => audio_content.loudness,
+    };
+    export_itunes_freeform_strings(
+        mp4_tag,
+        "replaygain_track_gain",
+        loudness.map(format_replay_gain).into_iter().collect(),
+    );
+
+    // Store the fractional value in a freeform atom and
+    // the rounded integer value in the standard atom
+    export_itunes_freeform_strings(
+        mp4_tag,
+        "BPM",
+        track
+            .metrics
+            .tempo_bpm
+            .map(format_tempo_bpm)
+            .into_iter()
+            .collect(),
+    );
+    if let Some(bpm) = track
+        .metrics
+        .tempo_bpm
+        .map(|tempo_bpm| tempo_bpm.0.round())
+        .filter(|bpm| *bpm >= 1.0 && *bpm <= f64::from(u16::MAX))
+    {
+        mp4_tag.set_bpm(bpm as u16);
+    } else {
+        mp4_tag.remove_bpm();
+    }
+
+    export_itunes_freeform_strings(
+        mp4_tag,
+        "initialkey",
+        format_key_signature(track.metrics.key_signature)
+            .map(ToOwned::to_owned)
+            .into_iter()
+            .collect(),
+    );
+    export_itunes_freeform_strings(mp4_tag, "KEY", vec![]);
+
+    // Track titles
+    if let Some(title) = export_title_name(&track.titles, TitleKind::Main) {
+        mp4_tag.set_title(title);
+    } else {
+        mp4_tag.remove_title();
+    }
+    export_itunes_freeform_strings(
+        mp4_tag,
+        "SUBTITLE",
+        export_title_name(&track.titles, TitleKind::Sub)
+            .into_iter()
+            .collect(),
+    );
+    if let Some(work) = export_title_name(&track.titles, TitleKind::Work) {
+        mp4_tag.set_work(work);
+    } else {
+        mp4_tag.remove_work();
+    }
+    if let Some(movement) = export_title_name(&track.titles, TitleKind::Movement) {
+        mp4_tag.set_movement(movement);
+    } else {
+        mp4_tag.remove_movement();
+    }
+
+    // Track actors
+    let artists = export_actor_names(&track.actors, ActorRole::Artist);
+    if artists.is_empty() {
+        mp4_tag.remove_artists();
+    } else {
+        mp4_tag.set_artists(artists);
+    }
+    let composers = export_actor_names(&track.actors, ActorRole::Composer);
+    if composers.is_empty() {
+        mp4_tag.remove_composers();
+    } else {
+        mp4_tag.set_composers(composers);
+    }
+    export_itunes_freeform_strings(
+        mp4_tag,
+        "REMIXER",
+        export_actor_names(&track.actors, ActorRole::Remixer),
+    );
+    export_itunes_freeform_strings(
+        mp4_tag,
+        "LYRICIST",
+        export_actor_names(&track.actors, ActorRole::Lyricist),
+    );
+    export_itunes_freeform_strings(
+        mp4_tag,
+        "CONDUCTOR",
+        export_actor_names(&track.actors, ActorRole::Conductor),
+    );
+
+    // Album
+    if let Some(album) = export_title_name(&track.album.titles, TitleKind::Main) {
+        mp4_tag.set_album(album);
+    } else {
+        mp4_tag.remove_album();
+    }
+    let album_artists = export_actor_names(&track.album.actors, ActorRole::Artist);
+    if album_artists.is_empty() {
+        mp4_tag.remove_album_artists();
+    } else {
+        mp4_tag.set_album_artists(album_artists);
+    }
+    if track.album.kind == AlbumKind::Compilation {
+        mp4_tag.set_compilation();
+    } else {
+        mp4_tag.remove_compilation();
+    }
+
+    // Release
+    if let Some(released_at) = track.release.released_at {
+        mp4_tag.set_year(released_at.to_string());
+    } else {
+        mp4_tag.remove_year();
+    }
+    if let Some(copyright) = &track.release.copyright {
+        mp4_tag.set_copyright(copyright);
+    } else {
+        mp4_tag.remove_copyright();
+    }
+    export_itunes_freeform_strings(
+        mp4_tag,
+        "LABEL",
+        track.release.released_by.iter().cloned().collect(),
+    );
+
+    // Indexes (in pairs)
+    if let Some(number) = track.indexes.track.number {
+        mp4_tag.set_track_number(number);
+    } else {
+        mp4_tag.remove_track_number();
+    }
+    if let Some(total) = track.indexes.track.total {
+        mp4_tag.set_total_tracks(total);
+    } else {
+        mp4_tag.remove_total_tracks();
+    }
+    if let Some(number) = track.indexes.disc.number {
+        mp4_tag.set_disc_number(number);
+    } else {
+        mp4_tag.remove_disc_number();
+    }
+    if let Some(total) = track.indexes.disc.total {
+        mp4_tag.set_total_discs(total);
+    } else {
+        mp4_tag.remove_total_discs();
+    }
+    if let Some(number) = track.indexes.movement.number {
+        mp4_tag.set_movement_index(number);
+    } else {
+        mp4_tag.remove_movement_index();
+    }
+    if let Some(total) = track.indexes.movement.total {
+        mp4_tag.set_movement_count(total);
+    } else {
+        mp4_tag.remove_movement_count();
+    }
+
+    // Comment tag
+    if let Some(comment) = export_faceted_tags(None, faceted_tags(&track.tags, &FACET_COMMENT))
+        .into_iter()
+        .next()
+    {
+        mp4_tag.set_comment(comment);
+    } else {
+        mp4_tag.remove_comments();
+    }
+
+    // Genre tags
+    // Legacy/standard genres are replaced by custom genres
+    mp4_tag.remove_standard_genres();
+    let genres = export_faceted_tags(
+        config.faceted_tag_mapping.get(FACET_GENRE.value()),
+        faceted_tags(&track.tags, &FACET_GENRE),
+    );
+    if genres.is_empty() {
+        mp4_tag.remove_custom_genres();
+    } else {
+        mp4_tag.set_custom_genres(genres);
+    }
+
+    // Mood tags
+    export_itunes_freeform_strings(
+        mp4_tag,
+        "MOOD",
+        export_faceted_tags(
+            config.faceted_tag_mapping.get(FACET_MOOD.value()),
+            faceted_tags(&track.tags, &FACET_MOOD),
+        ),
+    );
+
+    // Grouping tags
+    let groupings = export_faceted_tags(
+        config.faceted_tag_mapping.get(FACET_CGROUP.value()),
+        faceted_tags(&track.tags, &FACET_CGROUP),
+    );
+    if groupings.is_empty() {
+        mp4_tag.remove_groupings();
+    } else {
+        mp4_tag.set_groupings(groupings);
+    }
+
+    // Mixxx CustomTags
+    if flags.contains(ExportTrackFlags::MIXXX_CUSTOM_TAGS) {
+        let custom_tags = if track.tags.is_empty() {
+            None
+        } else {
+            let custom_tags = SerdeTags::from(track.tags.as_ref().clone());
+            serde_json::to_string(&custom_tags)
+                .map_err(|err| {
+                    log::warn!("Failed to serialize Mixxx custom tags: {}", err);
+                    err
+                })
+                .ok()
+        };
+        export_freeform_strings(
+            mp4_tag,
+            ORG_MIXXX_DJ_FREEFORM_MEAN,
+            MIXXX_CUSTOM_TAGS_FREEFORM_NAME,
+            custom_tags.into_iter().collect(),
+        );
+    }
+
+    // Serato Tags
+    if flags.contains(ExportTrackFlags::SERATO_TAGS) {
+        // Parse the existing data first to preserve all entries
+        // that are not affected by the export. Other Serato atoms,
+        // e.g. BeatGrid or Overview, are left untouched.
+        let mut serato_tags = SeratoTagContainer::new();
+        match read_serato_tags(mp4_tag, &mut serato_tags) {
+            Ok(()) => {
+                serato::write_cues(&mut serato_tags, &track.cues);
+                serato::write_track_color(&mut serato_tags, track.color);
+                export_serato_freeform_string(
+                    mp4_tag,
+                    SeratoMarkers::MP4_ATOM_FREEFORM_MEAN,
+                    SeratoMarkers::MP4_ATOM_FREEFORM_NAME,
+                    serato::dump_markers(&serato_tags, SeratoTagFormat::MP4),
+                );
+                export_serato_freeform_string(
+                    mp4_tag,
+                    SeratoMarkers2::MP4_ATOM_FREEFORM_MEAN,
+                    SeratoMarkers2::MP4_ATOM_FREEFORM_NAME,
+                    serato::dump_markers2(&serato_tags, SeratoTagFormat::MP4),
+                );
+            }
+            Err(err) => {
+                // Overwriting the existing atoms would discard all
+                // entries that could not be parsed
+                log::warn!("Skipping export of Serato tags: {}", err);
+            }
+        }
+    }
 }
+
+#[derive(Debug)]
+pub struct ExportTrack;
+
+impl export::ExportTrack for ExportTrack {
+    fn export_track_to_path(
+        &self,
+        config: &ExportTrackConfig,
+        flags: ExportTrackFlags,
+        track: &Track,
+        path: &Path,
+    ) -> Result<()> {
+        let mut mp4_tag = Mp4Tag::read_from_path(path).map_err(anyhow::Error::from)?;
+        export_track_to_tag(&mut mp4_tag, config, flags, track);
+        // Only the metadata atoms are rewritten, the media data
+        // is left untouched.
+        mp4_tag.write_to_path(path).map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests;
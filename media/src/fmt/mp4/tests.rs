@@ -0,0 +1,98 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::{media::Source, util::clock::DateTime};
+
+fn new_track() -> Track {
+    Track::new_from_media_source(Source {
+        collected_at: DateTime::now_utc(),
+        synchronized_at: None,
+        path: "test.m4a".to_owned().into(),
+        content_type: "audio/m4a".to_owned(),
+        content_digest: None,
+        content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+        content: Content::Audio(Default::default()),
+        artwork: Default::default(),
+    })
+}
+
+fn itunes_freeform_string<'a>(mp4_tag: &'a Mp4Tag, name: &str) -> Option<&'a str> {
+    mp4_tag
+        .string(&FreeformIdent::new(COM_APPLE_ITUNES_FREEFORM_MEAN, name))
+        .next()
+}
+
+#[test]
+fn export_track_to_tag_sets_metadata() {
+    let mut track = new_track();
+    track.set_track_title("Title");
+    track.set_track_artist("Artist");
+    track.set_album_title("Album");
+    track.set_album_artist("Album Artist");
+    track.album.kind = AlbumKind::Compilation;
+    track.metrics.tempo_bpm = Some(TempoBpm(123.5));
+
+    let mut mp4_tag = Mp4Tag::default();
+    export_track_to_tag(
+        &mut mp4_tag,
+        &Default::default(),
+        ExportTrackFlags::empty(),
+        &track,
+    );
+
+    assert_eq!(Some("Title"), mp4_tag.title());
+    assert_eq!(vec!["Artist"], mp4_tag.artists().collect::<Vec<_>>());
+    assert_eq!(Some("Album"), mp4_tag.album());
+    assert_eq!(
+        vec!["Album Artist"],
+        mp4_tag.album_artists().collect::<Vec<_>>()
+    );
+    assert!(mp4_tag.compilation());
+    // The fractional tempo is stored in a freeform atom and
+    // the rounded integer value in the standard atom
+    assert_eq!(Some("123.5"), itunes_freeform_string(&mp4_tag, "BPM"));
+    assert_eq!(Some(124), mp4_tag.bpm());
+}
+
+#[test]
+fn export_track_to_tag_removes_missing_metadata() {
+    let mut mp4_tag = Mp4Tag::default();
+    mp4_tag.set_title("Title");
+    mp4_tag.set_artists(vec!["Artist".to_owned()]);
+    mp4_tag.set_album("Album");
+    mp4_tag.set_compilation();
+    mp4_tag.set_bpm(120);
+    export_itunes_freeform_strings(&mut mp4_tag, "BPM", vec!["120".to_owned()]);
+    export_itunes_freeform_strings(&mut mp4_tag, "KEY", vec!["Am".to_owned()]);
+
+    export_track_to_tag(
+        &mut mp4_tag,
+        &Default::default(),
+        ExportTrackFlags::empty(),
+        &new_track(),
+    );
+
+    assert!(mp4_tag.title().is_none());
+    assert!(mp4_tag.artists().next().is_none());
+    assert!(mp4_tag.album().is_none());
+    assert!(!mp4_tag.compilation());
+    assert!(mp4_tag.bpm().is_none());
+    assert!(itunes_freeform_string(&mp4_tag, "BPM").is_none());
+    assert!(itunes_freeform_string(&mp4_tag, "KEY").is_none());
+}
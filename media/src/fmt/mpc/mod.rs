@@ -0,0 +1,180 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    io::import::{self, *},
+    Result,
+};
+
+use aoide_core::track::Track;
+
+use std::io::SeekFrom;
+
+use super::apev2::{self, StreamProperties};
+
+const SV7_MAGIC: &[u8] = b"MP+";
+const SV8_MAGIC: &[u8] = b"MPCK";
+
+const SAMPLE_RATES: [u32; 4] = [44_100, 48_000, 37_800, 32_000];
+
+// Number of samples per frame in SV7 files
+const SV7_FRAME_SAMPLES: u64 = 1_152;
+
+const SV7_HEADER_SIZE: usize = 12;
+
+// Upper bound for the size of the stream header packet in SV8 files
+const SV8_MAX_STREAM_HEADER_SIZE: u64 = 1_024;
+
+/// Parse the stream header of a stream version 7 file
+///
+/// https://trac.musepack.net/musepack/wiki/SV7Specification
+fn parse_sv7_header(header: &[u8]) -> Option<StreamProperties> {
+    if header.len() < SV7_HEADER_SIZE || !header.starts_with(SV7_MAGIC) || header[3] & 0x0F != 7 {
+        return None;
+    }
+    let frame_count = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let sample_rate_index =
+        (u32::from_le_bytes([header[8], header[9], header[10], header[11]]) >> 16) & 0b11;
+    Some(StreamProperties {
+        // Always stereo
+        channel_count: 2,
+        sample_rate: SAMPLE_RATES[sample_rate_index as usize],
+        // The actual number of samples in the last frame is ignored
+        sample_count: Some(u64::from(frame_count) * SV7_FRAME_SAMPLES),
+    })
+}
+
+/// Decode a variable length integer with 7 bits per byte
+fn read_sv8_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    while let Some((byte, remainder)) = data.split_first() {
+        *data = remainder;
+        value = (value << 7) | u64::from(byte & 0x7F);
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Parse the payload of the stream header packet of a stream version 8 file
+///
+/// https://trac.musepack.net/musepack/wiki/SV8Specification
+fn parse_sv8_stream_header(mut payload: &[u8]) -> Option<StreamProperties> {
+    // Skip the CRC
+    if payload.len() < 5 || payload[4] != 8 {
+        return None;
+    }
+    payload = &payload[5..];
+    let sample_count = read_sv8_varint(&mut payload)?;
+    let beginning_silence = read_sv8_varint(&mut payload)?;
+    if payload.len() < 2 {
+        return None;
+    }
+    let sample_rate_index = payload[0] >> 5;
+    let channel_count = (payload[1] >> 4) + 1;
+    Some(StreamProperties {
+        channel_count: channel_count.into(),
+        sample_rate: *SAMPLE_RATES.get(usize::from(sample_rate_index))?,
+        sample_count: Some(sample_count.saturating_sub(beginning_silence)),
+    })
+}
+
+/// Read the key and the payload size of the next packet
+fn read_sv8_packet_header(reader: &mut Box<dyn Reader>) -> Result<([u8; 2], u64)> {
+    let mut key = [0u8; 2];
+    reader.read_exact(&mut key)?;
+    // The packet size includes both the key and the size itself
+    // and is encoded with at most 9 size bytes
+    let mut packet_size = 0u64;
+    let mut header_size = key.len() as u64;
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        header_size += 1;
+        packet_size = (packet_size << 7) | u64::from(byte[0] & 0x7F);
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+        if header_size >= 11 {
+            return Err(anyhow::anyhow!("Invalid Musepack packet size").into());
+        }
+    }
+    let payload_size = packet_size
+        .checked_sub(header_size)
+        .ok_or_else(|| anyhow::anyhow!("Invalid Musepack packet size"))?;
+    Ok((key, payload_size))
+}
+
+#[derive(Debug)]
+pub struct ImportTrack;
+
+impl import::ImportTrack for ImportTrack {
+    fn import_track(
+        &self,
+        config: &ImportTrackConfig,
+        flags: ImportTrackFlags,
+        track: Track,
+        reader: &mut Box<dyn Reader>,
+    ) -> Result<Track> {
+        apev2::skip_id3v2_tag(reader)?;
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        let stream_props = if magic.starts_with(SV8_MAGIC) {
+            // Read packets until the stream header has been found
+            let mut stream_props = None;
+            loop {
+                let (key, payload_size) = read_sv8_packet_header(reader)?;
+                match &key {
+                    b"SH" if payload_size <= SV8_MAX_STREAM_HEADER_SIZE => {
+                        let mut payload = vec![0u8; payload_size as usize];
+                        reader.read_exact(&mut payload)?;
+                        stream_props = parse_sv8_stream_header(&payload);
+                        break;
+                    }
+                    b"SH" | b"AP" | b"SE" => {
+                        // Invalid stream header or no stream header
+                        // before the first audio packet
+                        break;
+                    }
+                    _ => {
+                        reader.seek(SeekFrom::Current(payload_size as i64))?;
+                    }
+                }
+            }
+            stream_props
+        } else {
+            let mut header = [0u8; SV7_HEADER_SIZE];
+            header[..magic.len()].copy_from_slice(&magic);
+            reader.read_exact(&mut header[magic.len()..])?;
+            parse_sv7_header(&header)
+        }
+        .ok_or_else(|| anyhow::anyhow!("Unsupported or invalid Musepack stream header"))?;
+        apev2::import_track(reader, stream_props, config, flags, track)
+    }
+
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        apev2::load_artwork_image(reader)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,61 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+#[test]
+fn read_sv8_varint_multiple_bytes() {
+    let data = [0x81, 0x00, 0x7F];
+    let mut input = &data[..];
+    assert_eq!(Some(128), read_sv8_varint(&mut input));
+    assert_eq!(Some(127), read_sv8_varint(&mut input));
+    assert_eq!(None, read_sv8_varint(&mut input));
+}
+
+#[test]
+fn parse_sv8_stream_header_stereo() {
+    // CRC, stream version, sample count (39_056), beginning silence (64),
+    // sample rate (48 kHz) with max used bands, and 2 channels with M/S
+    let payload = [
+        0x00, 0x00, 0x00, 0x00, 0x08, 0x82, 0xB1, 0x10, 0x40, 0x3F, 0x19,
+    ];
+    assert_eq!(
+        Some(StreamProperties {
+            channel_count: 2,
+            sample_rate: 48_000,
+            sample_count: Some(38_992),
+        }),
+        parse_sv8_stream_header(&payload)
+    );
+}
+
+#[test]
+fn parse_sv7_header_frames() {
+    let header = [
+        b'M', b'P', b'+', 0x17, // magic and version
+        0x64, 0x00, 0x00, 0x00, // 100 frames
+        0x00, 0x00, 0x01, 0x00, // 48 kHz
+    ];
+    assert_eq!(
+        Some(StreamProperties {
+            channel_count: 2,
+            sample_rate: 48_000,
+            sample_count: Some(115_200),
+        }),
+        parse_sv7_header(&header)
+    );
+}
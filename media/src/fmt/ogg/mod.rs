@@ -16,9 +16,9 @@
 ///////////////////////////////////////////////////////////////////////
 
 use crate::{
-    io::import::{self, *},
-    util::{
-        digest::MediaDigest, parse_artwork_from_embedded_image, push_next_actor_role_name, serato,
+    io::{
+        export::{self, ExportTrackConfig, ExportTrackFlags},
+        import::{self, *},
     },
     Result,
 };
@@ -30,48 +30,21 @@ use aoide_core::{
         AudioContent,
     },
     media::{Content, ContentMetadataFlags},
-    tag::TagsMap,
-    track::{
-        actor::ActorRole,
-        tag::{FACET_CGROUP, FACET_COMMENT, FACET_GENRE, FACET_MOOD},
-        Track,
-    },
-    util::{Canonical, CanonicalizeInto as _},
+    track::Track,
 };
 
-use lewton::inside_ogg::OggStreamReader;
-use metaflac::block::PictureType;
+use lewton::{header::read_header_comment, inside_ogg::OggStreamReader};
+use ogg::{PacketReader, PacketWriteEndInfo, PacketWriter};
 use semval::IsValid as _;
-
-use triseratops::tag::{TagContainer as SeratoTagContainer, TagFormat as SeratoTagFormat};
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write as _},
+    path::{Path, PathBuf},
+};
+use triseratops::tag::TagFormat as SeratoTagFormat;
 
 use super::vorbis;
 
-impl vorbis::CommentReader for Vec<(String, String)> {
-    fn read_first_value(&self, key: &str) -> Option<&str> {
-        self.iter().find_map(|(k, v)| {
-            if k.eq_ignore_ascii_case(key) {
-                Some(v.as_str())
-            } else {
-                None
-            }
-        })
-    }
-}
-
-fn filter_vorbis_comment_values<'a>(
-    vorbis_comments: &'a [(String, String)],
-    key: &'a str,
-) -> impl Iterator<Item = &'a str> + 'a {
-    vorbis_comments.iter().filter_map(move |(k, v)| {
-        if k.eq_ignore_ascii_case(key) {
-            Some(v.as_str())
-        } else {
-            None
-        }
-    })
-}
-
 #[derive(Debug)]
 pub struct ImportTrack;
 
@@ -123,219 +96,148 @@ impl import::ImportTrack for ImportTrack {
                 channels,
                 sample_rate,
                 bitrate,
+                bitrate_mode: None,
                 loudness,
+                loudness_range: None,
+                true_peak: None,
+                waveform: None,
+                range: None,
                 encoder,
             };
             track.media_source.content = Content::Audio(audio_content);
         }
 
-        if let Some(tempo_bpm) = vorbis::import_tempo_bpm(vorbis_comments) {
-            track.metrics.tempo_bpm = Some(tempo_bpm);
-        }
-
-        if let Some(key_signature) = vorbis::import_key_signature(vorbis_comments) {
-            track.metrics.key_signature = key_signature;
-        }
-
-        // Track titles
-        let track_titles = vorbis::import_track_titles(vorbis_comments);
-        if !track_titles.is_empty() {
-            track.titles = Canonical::tie(track_titles);
-        }
-
-        // Track actors
-        let mut track_actors = Vec::with_capacity(8);
-        for name in filter_vorbis_comment_values(vorbis_comments, "ARTIST") {
-            push_next_actor_role_name(&mut track_actors, ActorRole::Artist, name.to_owned());
-        }
-        for name in filter_vorbis_comment_values(vorbis_comments, "COMPOSER") {
-            push_next_actor_role_name(&mut track_actors, ActorRole::Composer, name.to_owned());
-        }
-        for name in filter_vorbis_comment_values(vorbis_comments, "CONDUCTOR") {
-            push_next_actor_role_name(&mut track_actors, ActorRole::Conductor, name.to_owned());
-        }
-        for name in filter_vorbis_comment_values(vorbis_comments, "PRODUCER") {
-            push_next_actor_role_name(&mut track_actors, ActorRole::Producer, name.to_owned());
-        }
-        for name in filter_vorbis_comment_values(vorbis_comments, "REMIXER") {
-            push_next_actor_role_name(&mut track_actors, ActorRole::Remixer, name.to_owned());
-        }
-        let track_actors = track_actors.canonicalize_into();
-        if !track_actors.is_empty() {
-            track.actors = Canonical::tie(track_actors);
-        }
-
-        let mut album = track.album.untie();
-
-        // Album titles
-        let album_titles = vorbis::import_album_titles(vorbis_comments);
-        if !album_titles.is_empty() {
-            album.titles = Canonical::tie(album_titles);
-        }
+        vorbis::import_metadata_into_track(vorbis_comments, config, flags, track)
+    }
 
-        // Album actors
-        let mut album_actors = Vec::with_capacity(4);
-        for name in filter_vorbis_comment_values(vorbis_comments, "ALBUMARTIST")
-            .chain(filter_vorbis_comment_values(
-                vorbis_comments,
-                "ALBUM_ARTIST",
-            ))
-            .chain(filter_vorbis_comment_values(
-                vorbis_comments,
-                "ALBUM ARTIST",
-            ))
-            .chain(filter_vorbis_comment_values(vorbis_comments, "ENSEMBLE"))
-        {
-            push_next_actor_role_name(&mut album_actors, ActorRole::Artist, name.to_owned());
-        }
-        let album_actors = album_actors.canonicalize_into();
-        if !album_actors.is_empty() {
-            album.actors = Canonical::tie(album_actors);
-        }
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        let ogg_reader = OggStreamReader::new(reader).map_err(anyhow::Error::from)?;
+        Ok(vorbis::load_artwork_image(
+            &ogg_reader.comment_hdr.comment_list,
+        ))
+    }
+}
 
-        // Album properties
-        if let Some(album_kind) = vorbis::import_album_kind(vorbis_comments) {
-            album.kind = album_kind;
-        }
+const VORBIS_IDENT_HEADER_PREFIX: &[u8] = b"\x01vorbis";
 
-        track.album = Canonical::tie(album);
+fn push_length_prefixed_bytes(packet: &mut Vec<u8>, bytes: &[u8]) {
+    packet.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    packet.extend_from_slice(bytes);
+}
 
-        // Release properties
-        if let Some(released_at) = vorbis::import_released_at(vorbis_comments) {
-            track.release.released_at = Some(released_at);
-        }
-        if let Some(released_by) = vorbis::import_released_by(vorbis_comments) {
-            track.release.released_by = Some(released_by);
-        }
-        if let Some(copyright) = vorbis::import_release_copyright(vorbis_comments) {
-            track.release.copyright = Some(copyright);
-        }
+/// Encode the comment header packet of a Vorbis stream
+///
+/// https://xiph.org/vorbis/doc/Vorbis_I_spec.html#x1-610004.2
+fn encode_vorbis_comment_header(vendor: &str, vorbis_comments: &[(String, String)]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(1024);
+    packet.push(0x03); // packet type
+    packet.extend_from_slice(b"vorbis");
+    push_length_prefixed_bytes(&mut packet, vendor.as_bytes());
+    packet.extend_from_slice(&(vorbis_comments.len() as u32).to_le_bytes());
+    for (key, value) in vorbis_comments {
+        push_length_prefixed_bytes(&mut packet, format!("{}={}", key, value).as_bytes());
+    }
+    packet.push(0x01); // framing bit
+    packet
+}
 
-        let mut tags_map = TagsMap::default();
-        if flags.contains(ImportTrackFlags::MIXXX_CUSTOM_TAGS) {
-            if let Some(custom_tags) = vorbis::import_mixxx_custom_tags(vorbis_comments) {
-                // Initialize map with all existing custom tags as starting point
-                debug_assert_eq!(0, tags_map.total_count());
-                tags_map = custom_tags.into();
+/// Copy all Ogg packets from the source into the target file while
+/// replacing the comment header of the first Vorbis stream.
+///
+/// The audio packets are copied verbatim and their page boundaries
+/// and granule positions are preserved. Only the page sequence numbers
+/// and checksums of the stream are recalculated.
+fn rewrite_vorbis_comment_header(
+    source_path: &Path,
+    target_path: &Path,
+    mut update_vorbis_comments: impl FnMut(&mut Vec<(String, String)>),
+) -> Result<()> {
+    let mut packet_reader = PacketReader::new(BufReader::new(File::open(source_path)?));
+    let mut packet_writer = PacketWriter::new(BufWriter::new(File::create(target_path)?));
+    let mut vorbis_stream_serial = None;
+    let mut comment_header_rewritten = false;
+    while let Some(packet) = packet_reader.read_packet().map_err(anyhow::Error::from)? {
+        let stream_serial = packet.stream_serial();
+        let absgp_page = packet.absgp_page();
+        let end_info = if packet.last_in_stream() {
+            PacketWriteEndInfo::EndStream
+        } else if packet.last_in_page() {
+            PacketWriteEndInfo::EndPage
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        let data = if vorbis_stream_serial.is_none() {
+            if packet.first_in_stream() && packet.data.starts_with(VORBIS_IDENT_HEADER_PREFIX) {
+                vorbis_stream_serial = Some(stream_serial);
             }
-        }
-
-        // Comment tag
-        // The original specification only defines a "DESCRIPTION" field,
-        // while MusicBrainz recommends to use "COMMENT". Mixxx follows
-        // MusicBrainz.
-        // http://www.xiph.org/vorbis/doc/v-comment.html
-        // https://picard.musicbrainz.org/docs/mappings
-        {
-            vorbis::import_faceted_text_tags(
-                &mut tags_map,
-                &config.faceted_tag_mapping,
-                &FACET_COMMENT,
-                filter_vorbis_comment_values(vorbis_comments, "COMMENT")
-                    .chain(filter_vorbis_comment_values(vorbis_comments, "DESCRIPTION")),
-            );
-        }
-
-        // Genre tags
-        vorbis::import_faceted_text_tags(
-            &mut tags_map,
-            &config.faceted_tag_mapping,
-            &FACET_GENRE,
-            filter_vorbis_comment_values(vorbis_comments, "GENRE"),
-        );
-
-        // Mood tags
-        vorbis::import_faceted_text_tags(
-            &mut tags_map,
-            &config.faceted_tag_mapping,
-            &FACET_MOOD,
-            filter_vorbis_comment_values(vorbis_comments, "MOOD"),
-        );
+            packet.data
+        } else if !comment_header_rewritten && vorbis_stream_serial == Some(stream_serial) {
+            // The comment header is the 2nd packet of the stream
+            let comment_hdr = read_header_comment(&packet.data).map_err(anyhow::Error::from)?;
+            let mut vorbis_comments = comment_hdr.comment_list;
+            update_vorbis_comments(&mut vorbis_comments);
+            comment_header_rewritten = true;
+            encode_vorbis_comment_header(&comment_hdr.vendor, &vorbis_comments)
+        } else {
+            packet.data
+        };
+        packet_writer.write_packet(data, stream_serial, end_info, absgp_page)?;
+    }
+    if !comment_header_rewritten {
+        return Err(anyhow::anyhow!("No Vorbis comment header found").into());
+    }
+    packet_writer.into_inner().flush()?;
+    Ok(())
+}
 
-        // Grouping tags
-        vorbis::import_faceted_text_tags(
-            &mut tags_map,
-            &config.faceted_tag_mapping,
-            &FACET_CGROUP,
-            filter_vorbis_comment_values(vorbis_comments, "GROUPING"),
-        );
+fn temp_file_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".aoide-tmp");
+    path.with_file_name(file_name)
+}
 
-        if let Some(index) = vorbis::import_track_index(vorbis_comments) {
-            track.indexes.track = index;
-        }
-        if let Some(index) = vorbis::import_disc_index(vorbis_comments) {
-            track.indexes.disc = index;
-        }
-        if let Some(index) = vorbis::import_movement_index(vorbis_comments) {
-            track.indexes.movement = index;
-        }
+#[derive(Debug)]
+pub struct ExportTrack;
 
-        if flags.contains(ImportTrackFlags::ARTWORK) {
-            let mut image_digest = if flags.contains(ImportTrackFlags::ARTWORK_DIGEST) {
-                if flags.contains(ImportTrackFlags::ARTWORK_DIGEST_SHA256) {
-                    // Compatibility
-                    MediaDigest::sha256()
-                } else {
-                    // Default
-                    MediaDigest::new()
-                }
-            } else {
-                Default::default()
-            };
-            // https://wiki.xiph.org/index.php/VorbisComment#Cover_art
-            // The unofficial COVERART field in a VorbisComment tag is deprecated:
-            // https://wiki.xiph.org/VorbisComment#Unofficial_COVERART_field_.28deprecated.29
-            let picture_iter_by_type = |picture_type| {
-                filter_vorbis_comment_values(vorbis_comments, "METADATA_BLOCK_PICTURE")
-                    .chain(filter_vorbis_comment_values(vorbis_comments, "COVERART"))
-                    .filter_map(|base64_data| {
-                        base64::decode(base64_data)
-                            .map_err(|err| {
-                                log::warn!(
-                                    "Failed to decode base64 encoded picture block: {}",
-                                    err
-                                );
-                                err
-                            })
-                            .ok()
-                    })
-                    .filter_map(|decoded| {
-                        metaflac::block::Picture::from_bytes(&decoded[..])
-                            .map_err(|err| {
-                                log::warn!("Failed to decode FLAC picture block: {}", err);
-                                err
-                            })
-                            .ok()
-                    })
-                    .filter(move |picture| picture.picture_type == picture_type)
-            };
-            // Decoding and discarding the blocks multiple times is inefficient
-            // but expected to occur only infrequently. Most files will include
-            // just a front cover and nothing else.
-            if let Some(artwork) = picture_iter_by_type(PictureType::CoverFront)
-                .chain(picture_iter_by_type(PictureType::Media))
-                .chain(picture_iter_by_type(PictureType::Leaflet))
-                .chain(picture_iter_by_type(PictureType::Other))
-                .filter_map(|p| parse_artwork_from_embedded_image(&p.data, None, &mut image_digest))
-                .next()
-            {
-                track.media_source.artwork = artwork;
+impl export::ExportTrack for ExportTrack {
+    fn export_track_to_path(
+        &self,
+        config: &ExportTrackConfig,
+        flags: ExportTrackFlags,
+        track: &Track,
+        path: &Path,
+    ) -> Result<()> {
+        // The comment header is embedded into the Ogg bitstream and
+        // its size usually changes. The whole file needs to be rewritten
+        // into a temporary file that finally replaces the original file.
+        let temp_path = temp_file_path(path);
+        if let Err(err) = rewrite_vorbis_comment_header(path, &temp_path, |vorbis_comments| {
+            vorbis::export_track(vorbis_comments, config, flags, track);
+            if flags.contains(ExportTrackFlags::SERATO_TAGS) {
+                vorbis::export_serato_markers2(vorbis_comments, track, SeratoTagFormat::Ogg);
             }
-        }
-
-        // Serato Tags
-        if flags.contains(ImportTrackFlags::SERATO_TAGS) {
-            let mut serato_tags = SeratoTagContainer::new();
-            vorbis::import_serato_markers2(vorbis_comments, &mut serato_tags, SeratoTagFormat::Ogg);
-
-            let track_cues = serato::read_cues(&serato_tags)?;
-            if !track_cues.is_empty() {
-                track.cues = Canonical::tie(track_cues);
+        }) {
+            if let Err(err) = fs::remove_file(&temp_path) {
+                log::warn!(
+                    "Failed to remove temporary file {}: {}",
+                    temp_path.display(),
+                    err
+                );
             }
-
-            track.color = serato::read_track_color(&serato_tags);
-        }
-
-        Ok(track)
+            return Err(err);
+        }
+        // Preserve the permissions of the original file that
+        // is replaced
+        let permissions = fs::metadata(path)?.permissions();
+        fs::set_permissions(&temp_path, permissions)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
     }
 }
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,152 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use vorbis::CommentWriter as _;
+
+#[test]
+fn write_vorbis_comments_case_insensitive() {
+    let mut vorbis_comments = vec![
+        ("Artist".to_owned(), "Artist 1".to_owned()),
+        ("TITLE".to_owned(), "Title".to_owned()),
+        ("artist".to_owned(), "Artist 2".to_owned()),
+    ];
+    vorbis_comments.write_multiple_values(
+        "ARTIST".to_owned(),
+        vec!["Artist A".to_owned(), "Artist B".to_owned()],
+    );
+    assert_eq!(
+        vec![
+            ("TITLE".to_owned(), "Title".to_owned()),
+            ("ARTIST".to_owned(), "Artist A".to_owned()),
+            ("ARTIST".to_owned(), "Artist B".to_owned()),
+        ],
+        vorbis_comments
+    );
+    vorbis_comments.remove_all_values("title");
+    assert_eq!(2, vorbis_comments.len());
+}
+
+#[test]
+fn encode_vorbis_comment_header_roundtrip() {
+    let vendor = "Xiph.Org libVorbis I 20200704 (Reducing Environment)";
+    let vorbis_comments = vec![
+        ("TITLE".to_owned(), "Title".to_owned()),
+        ("GENRE".to_owned(), "Deep House".to_owned()),
+        ("GENRE".to_owned(), "Techno".to_owned()),
+        ("COMMENT".to_owned(), "Ümläüts & =".to_owned()),
+    ];
+    let packet = encode_vorbis_comment_header(vendor, &vorbis_comments);
+    let comment_hdr = read_header_comment(&packet).unwrap();
+    assert_eq!(vendor, comment_hdr.vendor);
+    assert_eq!(vorbis_comments, comment_hdr.comment_list);
+}
+
+fn write_test_file(path: &Path, vorbis_comments: &[(String, String)]) {
+    let serial = 1;
+    let mut packet_writer = PacketWriter::new(BufWriter::new(File::create(path).unwrap()));
+    let mut ident_header = VORBIS_IDENT_HEADER_PREFIX.to_vec();
+    ident_header.extend_from_slice(&[0u8; 23]);
+    packet_writer
+        .write_packet(ident_header, serial, PacketWriteEndInfo::EndPage, 0)
+        .unwrap();
+    packet_writer
+        .write_packet(
+            encode_vorbis_comment_header("vendor", vorbis_comments),
+            serial,
+            PacketWriteEndInfo::NormalPacket,
+            0,
+        )
+        .unwrap();
+    packet_writer
+        .write_packet(
+            b"\x05vorbis setup".to_vec(),
+            serial,
+            PacketWriteEndInfo::EndStream,
+            0,
+        )
+        .unwrap();
+    packet_writer.into_inner().flush().unwrap();
+}
+
+fn read_test_file(path: &Path) -> Vec<(String, String)> {
+    let mut packet_reader = PacketReader::new(BufReader::new(File::open(path).unwrap()));
+    packet_reader.read_packet().unwrap().unwrap();
+    let packet = packet_reader.read_packet().unwrap().unwrap();
+    read_header_comment(&packet.data).unwrap().comment_list
+}
+
+#[test]
+fn export_track_rewrites_comment_header() {
+    let path =
+        std::env::temp_dir().join(format!("aoide-ogg-export-test-{}.ogg", std::process::id()));
+    write_test_file(
+        &path,
+        &[
+            ("TITLE".to_owned(), "Old Title".to_owned()),
+            ("CUSTOM".to_owned(), "Unaffected".to_owned()),
+        ],
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+    }
+
+    let mut track = Track::new_from_media_source(aoide_core::media::Source {
+        collected_at: aoide_core::util::clock::DateTime::now_utc(),
+        synchronized_at: None,
+        path: "test.ogg".to_owned().into(),
+        content_type: "audio/ogg".to_owned(),
+        content_digest: None,
+        content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+        content: Content::Audio(Default::default()),
+        artwork: Default::default(),
+    });
+    track.set_track_title("New Title");
+    track.set_track_artist("Artist");
+    let result = export::ExportTrack::export_track_to_path(
+        &ExportTrack,
+        &Default::default(),
+        ExportTrackFlags::empty(),
+        &track,
+        &path,
+    );
+    let vorbis_comments = read_test_file(&path);
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt as _;
+        fs::metadata(&path).unwrap().permissions().mode()
+    };
+    fs::remove_file(&path).unwrap();
+    result.unwrap();
+    assert!(!temp_file_path(&path).exists());
+    #[cfg(unix)]
+    assert_eq!(0o640, mode & 0o777);
+
+    let reimported = vorbis::import_metadata_into_track(
+        &vorbis_comments,
+        &Default::default(),
+        ImportTrackFlags::METADATA,
+        Track::new_from_media_source(track.media_source.clone()),
+    )
+    .unwrap();
+    assert_eq!(Some("New Title"), reimported.track_title());
+    assert_eq!(Some("Artist"), reimported.track_artist());
+    assert!(vorbis_comments.contains(&("CUSTOM".to_owned(), "Unaffected".to_owned())));
+}
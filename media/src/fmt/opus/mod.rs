@@ -0,0 +1,290 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    io::import::{self, *},
+    util::parse_r128_gain,
+    Result,
+};
+
+use aoide_core::{
+    audio::{
+        channel::ChannelCount,
+        signal::{BitrateBps, LoudnessLufs, SampleRateHz},
+        AudioContent,
+    },
+    media::{Content, ContentMetadataFlags},
+    track::Track,
+};
+
+use ogg::PacketReader;
+use semval::IsValid as _;
+use std::{io::SeekFrom, time::Duration};
+
+use super::vorbis::{self, CommentReader as _};
+
+const OPUS_HEAD_MAGIC: &[u8] = b"OpusHead";
+const OPUS_TAGS_MAGIC: &[u8] = b"OpusTags";
+
+// Opus always decodes at 48 kHz, independent of the input sample rate
+// that is stored in the identification header
+const OPUS_OUTPUT_SAMPLE_RATE_HZ: u32 = 48_000;
+
+// The maximum size of an Ogg page including the header
+const OGG_MAX_PAGE_SIZE: u64 = 65_307;
+
+const OGG_CAPTURE_PATTERN: &[u8] = b"OggS";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct IdentificationHeader {
+    channel_count: u8,
+    pre_skip: u16,
+    /// Q7.8 fixed-point gain in dB that must be applied when decoding
+    output_gain: i16,
+}
+
+/// Parse the loudness from the R128 track gain tag
+///
+/// The track gain is applied in addition to the output gain from
+/// the identification header, i.e. the total gain relative to the
+/// R128 reference level is the sum of both values.
+///
+/// https://tools.ietf.org/html/rfc7845#section-5.2.1
+fn parse_r128_track_gain(input: &str, output_gain: i16) -> Option<LoudnessLufs> {
+    let LoudnessLufs(lufs) = parse_r128_gain(input)?;
+    let loudness = LoudnessLufs(lufs - f64::from(output_gain) / 256.0);
+    if !loudness.is_valid() {
+        log::warn!(
+            "Invalid loudness with R128 track gain '{}' and output gain {}: {}",
+            input,
+            output_gain,
+            loudness
+        );
+        return None;
+    }
+    Some(loudness)
+}
+
+/// Parse the identification header packet
+///
+/// https://tools.ietf.org/html/rfc7845#section-5.1
+fn parse_identification_header(packet: &[u8]) -> Option<IdentificationHeader> {
+    if packet.len() < 19 || !packet.starts_with(OPUS_HEAD_MAGIC) {
+        return None;
+    }
+    let version = packet[8];
+    if version >> 4 != 0 {
+        // Incompatible major version
+        return None;
+    }
+    Some(IdentificationHeader {
+        channel_count: packet[9],
+        pre_skip: u16::from_le_bytes([packet[10], packet[11]]),
+        output_gain: i16::from_le_bytes([packet[16], packet[17]]),
+    })
+}
+
+fn read_length_prefixed_str(data: &mut &[u8]) -> Option<String> {
+    if data.len() < 4 {
+        return None;
+    }
+    let len = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    if data.len() < 4 + len {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&data[4..4 + len]).into_owned();
+    *data = &data[4 + len..];
+    Some(text)
+}
+
+/// Parse the comment header packet into a list of Vorbis comments
+///
+/// The comment header uses the same format as in Ogg Vorbis, but
+/// starts with a different magic signature and omits the framing bit.
+///
+/// https://tools.ietf.org/html/rfc7845#section-5.2
+fn parse_comment_header(packet: &[u8]) -> Option<Vec<(String, String)>> {
+    if !packet.starts_with(OPUS_TAGS_MAGIC) {
+        return None;
+    }
+    let mut data = &packet[OPUS_TAGS_MAGIC.len()..];
+    let _vendor = read_length_prefixed_str(&mut data)?;
+    if data.len() < 4 {
+        return None;
+    }
+    let count = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    data = &data[4..];
+    let mut vorbis_comments = Vec::with_capacity(count.min(256));
+    for _ in 0..count {
+        let comment = read_length_prefixed_str(&mut data)?;
+        if let Some(pos) = comment.find('=') {
+            let (key, value) = comment.split_at(pos);
+            vorbis_comments.push((key.to_owned(), value[1..].to_owned()));
+        } else {
+            log::warn!("Invalid Vorbis comment: {}", comment);
+        }
+    }
+    Some(vorbis_comments)
+}
+
+/// Find the granule position of the last Ogg page of a stream
+///
+/// Only the tail of the file is scanned, starting at the last page.
+fn find_last_granule_position(
+    reader: &mut Box<dyn Reader>,
+    stream_serial: u32,
+) -> Result<Option<u64>> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let tail_len = file_len.min(2 * OGG_MAX_PAGE_SIZE);
+    reader.seek(SeekFrom::Start(file_len - tail_len))?;
+    let mut tail = vec![0u8; tail_len as usize];
+    reader.read_exact(&mut tail)?;
+    let mut end = tail.len();
+    while let Some(pos) = tail[..end]
+        .windows(OGG_CAPTURE_PATTERN.len())
+        .rposition(|window| window == OGG_CAPTURE_PATTERN)
+    {
+        end = pos;
+        let header = &tail[pos..];
+        if header.len() < 27 || header[4] != 0 {
+            // Incomplete header or unsupported version
+            continue;
+        }
+        let granule_position = u64::from_le_bytes([
+            header[6], header[7], header[8], header[9], header[10], header[11], header[12],
+            header[13],
+        ]);
+        let serial = u32::from_le_bytes([header[14], header[15], header[16], header[17]]);
+        // A granule position of -1 indicates that no packet finishes on this page
+        if serial == stream_serial && granule_position != u64::MAX {
+            return Ok(Some(granule_position));
+        }
+    }
+    Ok(None)
+}
+
+/// Read the identification and comment header packets
+///
+/// Returns the parsed identification header, the serial number
+/// of the Opus stream, and the Vorbis comments.
+fn read_header_packets(
+    reader: &mut Box<dyn Reader>,
+) -> Result<(IdentificationHeader, u32, Vec<(String, String)>)> {
+    let mut packet_reader = PacketReader::new(reader);
+    let ident_packet = packet_reader
+        .read_packet()
+        .map_err(anyhow::Error::from)?
+        .ok_or_else(|| anyhow::anyhow!("Missing Opus identification header"))?;
+    let ident_hdr = parse_identification_header(&ident_packet.data)
+        .ok_or_else(|| anyhow::anyhow!("Invalid Opus identification header"))?;
+    let stream_serial = ident_packet.stream_serial();
+    let comment_packet = packet_reader
+        .read_packet()
+        .map_err(anyhow::Error::from)?
+        .filter(|packet| packet.stream_serial() == stream_serial)
+        .ok_or_else(|| anyhow::anyhow!("Missing Opus comment header"))?;
+    let vorbis_comments = parse_comment_header(&comment_packet.data)
+        .ok_or_else(|| anyhow::anyhow!("Invalid Opus comment header"))?;
+    Ok((ident_hdr, stream_serial, vorbis_comments))
+}
+
+#[derive(Debug)]
+pub struct ImportTrack;
+
+impl import::ImportTrack for ImportTrack {
+    fn import_track(
+        &self,
+        config: &ImportTrackConfig,
+        flags: ImportTrackFlags,
+        mut track: Track,
+        reader: &mut Box<dyn Reader>,
+    ) -> Result<Track> {
+        let (ident_hdr, stream_serial, vorbis_comments) = read_header_packets(reader)?;
+        // The comment header is followed by a page break
+        let audio_start = reader.stream_position()?;
+
+        if track
+            .media_source
+            .content_metadata_flags
+            .update(ContentMetadataFlags::RELIABLE)
+        {
+            let channel_count = ChannelCount(ident_hdr.channel_count.into());
+            let channels = if channel_count.is_valid() {
+                Some(channel_count.into())
+            } else {
+                log::warn!("Invalid channel count: {}", channel_count.0);
+                None
+            };
+            let sample_rate = Some(SampleRateHz::from_inner(OPUS_OUTPUT_SAMPLE_RATE_HZ.into()));
+            let mut duration = None;
+            let mut bitrate = None;
+            if let Some(granule_position) = find_last_granule_position(reader, stream_serial)? {
+                let sample_count = granule_position.saturating_sub(ident_hdr.pre_skip.into());
+                let secs = sample_count as f64 / f64::from(OPUS_OUTPUT_SAMPLE_RATE_HZ);
+                duration = Some(Duration::from_secs_f64(secs).into());
+                if secs > 0.0 {
+                    // The average bitrate of all audio pages, i.e. including
+                    // the (small) overhead of the Ogg container
+                    let audio_len = reader.seek(SeekFrom::End(0))?.saturating_sub(audio_start);
+                    let avg_bitrate = BitrateBps::from_inner(audio_len as f64 * 8.0 / secs);
+                    if avg_bitrate.is_valid() {
+                        bitrate = Some(avg_bitrate);
+                    } else {
+                        log::warn!("Invalid bitrate: {}", avg_bitrate);
+                    }
+                }
+            } else {
+                log::warn!("Failed to determine the duration of the Opus stream");
+            }
+            let loudness = vorbis_comments
+                .read_first_value("R128_TRACK_GAIN")
+                .and_then(|input| parse_r128_track_gain(input, ident_hdr.output_gain))
+                // Fallback: Some applications write ReplayGain tags
+                // although this is prohibited by the specification
+                .or_else(|| vorbis::import_loudness(&vorbis_comments));
+            let encoder = vorbis::import_encoder(&vorbis_comments).map(Into::into);
+            let audio_content = AudioContent {
+                duration,
+                channels,
+                sample_rate,
+                bitrate,
+                bitrate_mode: None,
+                loudness,
+                loudness_range: None,
+                true_peak: None,
+                waveform: None,
+                range: None,
+                encoder,
+            };
+            track.media_source.content = Content::Audio(audio_content);
+        }
+
+        vorbis::import_metadata_into_track(&vorbis_comments, config, flags, track)
+    }
+
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        let (_, _, vorbis_comments) = read_header_packets(reader)?;
+        Ok(vorbis::load_artwork_image(&vorbis_comments))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,82 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+#[test]
+fn parse_identification_header_stereo() {
+    let packet = [
+        b'O', b'p', b'u', b's', b'H', b'e', b'a', b'd', // magic
+        0x01, // version
+        0x02, // channel count
+        0x38, 0x01, // pre-skip: 312
+        0x80, 0xBB, 0x00, 0x00, // input sample rate: 48000 Hz
+        0x00, 0xFA, // output gain: -6 dB
+        0x00, // channel mapping family
+    ];
+    assert_eq!(
+        Some(IdentificationHeader {
+            channel_count: 2,
+            pre_skip: 312,
+            output_gain: -1536,
+        }),
+        parse_identification_header(&packet)
+    );
+    assert_eq!(None, parse_identification_header(&packet[..18]));
+}
+
+#[test]
+fn parse_r128_track_gain_with_output_gain() {
+    assert_eq!(Some(LoudnessLufs(-17.0)), parse_r128_track_gain("-1536", 0));
+    // -6 dB track gain + -6 dB output gain
+    assert_eq!(
+        Some(LoudnessLufs(-11.0)),
+        parse_r128_track_gain("-1536", -1536)
+    );
+    // +1.5 dB track gain + -6 dB output gain
+    assert_eq!(
+        Some(LoudnessLufs(-18.5)),
+        parse_r128_track_gain("384", -1536)
+    );
+    assert_eq!(None, parse_r128_track_gain("invalid", -1536));
+}
+
+#[test]
+fn parse_comment_header_without_framing_bit() {
+    let mut packet = b"OpusTags".to_vec();
+    let vendor = b"libopus 1.3.1";
+    packet.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    packet.extend_from_slice(vendor);
+    packet.extend_from_slice(&3u32.to_le_bytes());
+    for comment in &[
+        "TITLE=Title".as_bytes(),
+        "R128_TRACK_GAIN=-1536".as_bytes(),
+        "invalid".as_bytes(),
+    ] {
+        packet.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        packet.extend_from_slice(comment);
+    }
+    assert_eq!(
+        Some(vec![
+            ("TITLE".to_owned(), "Title".to_owned()),
+            ("R128_TRACK_GAIN".to_owned(), "-1536".to_owned()),
+        ]),
+        parse_comment_header(&packet)
+    );
+    // Truncated
+    assert_eq!(None, parse_comment_header(&packet[..packet.len() - 1]));
+}
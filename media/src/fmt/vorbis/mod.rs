@@ -15,27 +15,44 @@
 
 ///////////////////////////////////////////////////////////////////////
 
-use crate::util::{
-    parse_index_numbers, parse_key_signature, parse_replay_gain, parse_tempo_bpm, parse_year_tag,
-    tag::{import_faceted_tags, FacetedTagMappingConfig},
+use crate::{
+    io::{
+        export::{ExportTrackConfig, ExportTrackFlags},
+        import::{ArtworkImage, ImportTrackConfig, ImportTrackFlags},
+    },
+    lyrics,
+    util::{
+        digest::MediaDigest,
+        export_actor_role_names, format_key_signature, format_replay_gain, format_tempo_bpm,
+        parse_artwork_from_embedded_image, parse_index_numbers, parse_key_signature,
+        parse_replay_gain, parse_tempo_bpm, parse_year_tag, push_next_actor_role_name,
+        select_artwork_image_from_embedded_images, serato,
+        tag::{export_faceted_tags, faceted_tags, import_faceted_tags, FacetedTagMappingConfig},
+    },
+    Result,
 };
 
 use aoide_core::{
     audio::signal::LoudnessLufs,
-    media::concat_encoder_properties,
+    media::{concat_encoder_properties, Content},
     music::{key::KeySignature, time::TempoBpm},
     tag::{Facet, Score as TagScore, Tags, TagsMap},
     track::{
+        actor::{Actor, ActorRole},
         album::AlbumKind,
         index::Index,
+        lyrics::Lyrics,
         release::DateOrDateTime,
-        title::{Title, TitleKind},
+        tag::{FACET_CGROUP, FACET_COMMENT, FACET_GENRE, FACET_MOOD},
+        title::{Title, TitleKind, Titles},
+        Track,
     },
-    util::CanonicalizeInto as _,
+    util::{Canonical, CanonicalizeInto as _},
 };
 
 use aoide_core_serde::tag::Tags as SerdeTags;
 
+use metaflac::block::{Picture, PictureType};
 use semval::IsValid as _;
 use std::borrow::Cow;
 use triseratops::tag::{
@@ -47,6 +64,61 @@ pub trait CommentReader {
     fn read_first_value(&self, key: &str) -> Option<&str>;
 }
 
+pub trait CommentWriter {
+    /// Replace all existing values of the given key
+    fn write_multiple_values(&mut self, key: String, values: Vec<String>);
+
+    fn remove_all_values(&mut self, key: &str);
+
+    fn write_multiple_values_or_remove(&mut self, key: &str, values: Vec<String>) {
+        if values.is_empty() {
+            self.remove_all_values(key);
+        } else {
+            self.write_multiple_values(key.to_owned(), values);
+        }
+    }
+
+    fn write_single_value_or_remove(&mut self, key: &str, value: Option<String>) {
+        self.write_multiple_values_or_remove(key, value.into_iter().collect());
+    }
+}
+
+impl CommentReader for Vec<(String, String)> {
+    fn read_first_value(&self, key: &str) -> Option<&str> {
+        self.iter().find_map(|(k, v)| {
+            if k.eq_ignore_ascii_case(key) {
+                Some(v.as_str())
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl CommentWriter for Vec<(String, String)> {
+    fn write_multiple_values(&mut self, key: String, values: Vec<String>) {
+        self.remove_all_values(&key);
+        self.extend(values.into_iter().map(|value| (key.clone(), value)));
+    }
+
+    fn remove_all_values(&mut self, key: &str) {
+        self.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+    }
+}
+
+pub fn filter_vorbis_comment_values<'a>(
+    vorbis_comments: &'a [(String, String)],
+    key: &'a str,
+) -> impl Iterator<Item = &'a str> + 'a {
+    vorbis_comments.iter().filter_map(move |(k, v)| {
+        if k.eq_ignore_ascii_case(key) {
+            Some(v.as_str())
+        } else {
+            None
+        }
+    })
+}
+
 pub fn import_faceted_text_tags<'a>(
     tags_map: &mut TagsMap,
     config: &FacetedTagMappingConfig,
@@ -236,9 +308,22 @@ pub fn import_album_titles(reader: &impl CommentReader) -> Vec<Title> {
     album_titles.canonicalize_into()
 }
 
+/// Import lyrics from the values of "LYRICS" and "UNSYNCEDLYRICS"
+/// comments
+///
+/// Values that are formatted as LRC are imported as time-synchronized
+/// lyrics.
+pub fn import_lyrics<'a>(values: impl IntoIterator<Item = &'a str>) -> Canonical<Vec<Lyrics>> {
+    lyrics::collect_lyrics(
+        values
+            .into_iter()
+            .filter_map(|value| lyrics::import_lyrics_text(None, value)),
+    )
+}
+
 pub fn import_mixxx_custom_tags(reader: &impl CommentReader) -> Option<Tags> {
     reader
-        .read_first_value("MIXXX_CUSTOM_TAGS")
+        .read_first_value(MIXXX_CUSTOM_TAGS_KEY)
         .and_then(|json| {
             serde_json::from_str::<SerdeTags>(json)
                 .map_err(|err| {
@@ -250,10 +335,38 @@ pub fn import_mixxx_custom_tags(reader: &impl CommentReader) -> Option<Tags> {
         .map(Into::into)
 }
 
+/// Parse the Serato Markers2 comment into the tag container
+///
+/// Fails if the existing comment could not be parsed.
 pub fn import_serato_markers2(
     reader: &impl CommentReader,
     serato_tags: &mut SeratoTagContainer,
     format: SeratoTagFormat,
+) -> Result<()> {
+    let vorbis_comment = match format {
+        SeratoTagFormat::FLAC => SeratoMarkers2::FLAC_COMMENT,
+        SeratoTagFormat::Ogg => SeratoMarkers2::OGG_COMMENT,
+        _ => {
+            return Ok(());
+        }
+    };
+
+    if let Some(data) = reader.read_first_value(vorbis_comment) {
+        serato_tags
+            .parse_markers2(&data.as_bytes(), format)
+            .map_err(|err| anyhow::anyhow!("Failed to parse Serato Markers2: {}", err))?;
+    }
+    Ok(())
+}
+
+/// Replace the cues and the track color in the Serato Markers2 comment
+///
+/// The existing data is parsed first to preserve all entries that
+/// are not affected by the export.
+pub fn export_serato_markers2(
+    vorbis_comments: &mut (impl CommentReader + CommentWriter),
+    track: &Track,
+    format: SeratoTagFormat,
 ) {
     let vorbis_comment = match format {
         SeratoTagFormat::FLAC => SeratoMarkers2::FLAC_COMMENT,
@@ -263,7 +376,430 @@ pub fn import_serato_markers2(
         }
     };
 
-    reader
-        .read_first_value(vorbis_comment)
-        .and_then(|data| serato_tags.parse_markers2(&data.as_bytes(), format).ok());
+    let mut serato_tags = SeratoTagContainer::new();
+    if let Err(err) = import_serato_markers2(&*vorbis_comments, &mut serato_tags, format) {
+        // Overwriting the existing comment would discard all
+        // entries that could not be parsed
+        log::warn!("Skipping export of Serato tags: {}", err);
+        return;
+    }
+    serato::write_cues(&mut serato_tags, &track.cues);
+    serato::write_track_color(&mut serato_tags, track.color);
+
+    match serato::dump_markers2(&serato_tags, format)
+        .and_then(|data| String::from_utf8(data).map_err(|err| anyhow::Error::from(err).into()))
+    {
+        Ok(value) => {
+            vorbis_comments.write_single_value_or_remove(vorbis_comment, Some(value));
+        }
+        Err(err) => {
+            // Keep the existing comment
+            log::warn!("{}", err);
+        }
+    }
+}
+
+/// All pictures in the order of preference for the artwork
+fn artwork_pictures(vorbis_comments: &[(String, String)]) -> impl Iterator<Item = Picture> + '_ {
+    // https://wiki.xiph.org/index.php/VorbisComment#Cover_art
+    // The unofficial COVERART field in a VorbisComment tag is deprecated:
+    // https://wiki.xiph.org/VorbisComment#Unofficial_COVERART_field_.28deprecated.29
+    let picture_iter_by_type = move |picture_type| {
+        filter_vorbis_comment_values(vorbis_comments, "METADATA_BLOCK_PICTURE")
+            .chain(filter_vorbis_comment_values(vorbis_comments, "COVERART"))
+            .filter_map(|base64_data| {
+                base64::decode(base64_data)
+                    .map_err(|err| {
+                        log::warn!("Failed to decode base64 encoded picture block: {}", err);
+                        err
+                    })
+                    .ok()
+            })
+            .filter_map(|decoded| {
+                Picture::from_bytes(&decoded[..])
+                    .map_err(|err| {
+                        log::warn!("Failed to decode FLAC picture block: {}", err);
+                        err
+                    })
+                    .ok()
+            })
+            .filter(move |picture| picture.picture_type == picture_type)
+    };
+    // Decoding and discarding the blocks multiple times is inefficient
+    // but expected to occur only infrequently. Most files will include
+    // just a front cover and nothing else.
+    picture_iter_by_type(PictureType::CoverFront)
+        .chain(picture_iter_by_type(PictureType::Media))
+        .chain(picture_iter_by_type(PictureType::Leaflet))
+        .chain(picture_iter_by_type(PictureType::Other))
+}
+
+pub fn load_artwork_image(vorbis_comments: &[(String, String)]) -> Option<ArtworkImage> {
+    artwork_pictures(vorbis_comments).find_map(|p| {
+        select_artwork_image_from_embedded_images(std::iter::once((p.data.as_slice(), None)))
+    })
+}
+
+/// Import all metadata from Vorbis comments into a track
+///
+/// Shared by all Ogg formats that store their metadata as a list
+/// of Vorbis comments, i.e. Ogg Vorbis and Ogg Opus. The audio
+/// properties of the media source are not affected.
+pub fn import_metadata_into_track(
+    vorbis_comments: &Vec<(String, String)>,
+    config: &ImportTrackConfig,
+    flags: ImportTrackFlags,
+    mut track: Track,
+) -> Result<Track> {
+    if let Some(tempo_bpm) = import_tempo_bpm(vorbis_comments) {
+        track.metrics.tempo_bpm = Some(tempo_bpm);
+    }
+
+    if let Some(key_signature) = import_key_signature(vorbis_comments) {
+        track.metrics.key_signature = key_signature;
+    }
+
+    // Track titles
+    let track_titles = import_track_titles(vorbis_comments);
+    if !track_titles.is_empty() {
+        track.titles = Canonical::tie(track_titles);
+    }
+
+    // Track actors
+    let mut track_actors = Vec::with_capacity(8);
+    for name in filter_vorbis_comment_values(vorbis_comments, "ARTIST") {
+        push_next_actor_role_name(&mut track_actors, ActorRole::Artist, name.to_owned());
+    }
+    for name in filter_vorbis_comment_values(vorbis_comments, "COMPOSER") {
+        push_next_actor_role_name(&mut track_actors, ActorRole::Composer, name.to_owned());
+    }
+    for name in filter_vorbis_comment_values(vorbis_comments, "CONDUCTOR") {
+        push_next_actor_role_name(&mut track_actors, ActorRole::Conductor, name.to_owned());
+    }
+    for name in filter_vorbis_comment_values(vorbis_comments, "PRODUCER") {
+        push_next_actor_role_name(&mut track_actors, ActorRole::Producer, name.to_owned());
+    }
+    for name in filter_vorbis_comment_values(vorbis_comments, "REMIXER") {
+        push_next_actor_role_name(&mut track_actors, ActorRole::Remixer, name.to_owned());
+    }
+    let track_actors = track_actors.canonicalize_into();
+    if !track_actors.is_empty() {
+        track.actors = Canonical::tie(track_actors);
+    }
+
+    let mut album = track.album.untie();
+
+    // Album titles
+    let album_titles = import_album_titles(vorbis_comments);
+    if !album_titles.is_empty() {
+        album.titles = Canonical::tie(album_titles);
+    }
+
+    // Album actors
+    let mut album_actors = Vec::with_capacity(4);
+    for name in filter_vorbis_comment_values(vorbis_comments, "ALBUMARTIST")
+        .chain(filter_vorbis_comment_values(
+            vorbis_comments,
+            "ALBUM_ARTIST",
+        ))
+        .chain(filter_vorbis_comment_values(
+            vorbis_comments,
+            "ALBUM ARTIST",
+        ))
+        .chain(filter_vorbis_comment_values(vorbis_comments, "ENSEMBLE"))
+    {
+        push_next_actor_role_name(&mut album_actors, ActorRole::Artist, name.to_owned());
+    }
+    let album_actors = album_actors.canonicalize_into();
+    if !album_actors.is_empty() {
+        album.actors = Canonical::tie(album_actors);
+    }
+
+    // Album properties
+    if let Some(album_kind) = import_album_kind(vorbis_comments) {
+        album.kind = album_kind;
+    }
+
+    track.album = Canonical::tie(album);
+
+    // Release properties
+    if let Some(released_at) = import_released_at(vorbis_comments) {
+        track.release.released_at = Some(released_at);
+    }
+    if let Some(released_by) = import_released_by(vorbis_comments) {
+        track.release.released_by = Some(released_by);
+    }
+    if let Some(copyright) = import_release_copyright(vorbis_comments) {
+        track.release.copyright = Some(copyright);
+    }
+
+    let mut tags_map = TagsMap::default();
+    if flags.contains(ImportTrackFlags::MIXXX_CUSTOM_TAGS) {
+        if let Some(custom_tags) = import_mixxx_custom_tags(vorbis_comments) {
+            // Initialize map with all existing custom tags as starting point
+            debug_assert_eq!(0, tags_map.total_count());
+            tags_map = custom_tags.into();
+        }
+    }
+
+    // Comment tag
+    // The original specification only defines a "DESCRIPTION" field,
+    // while MusicBrainz recommends to use "COMMENT". Mixxx follows
+    // MusicBrainz.
+    // http://www.xiph.org/vorbis/doc/v-comment.html
+    // https://picard.musicbrainz.org/docs/mappings
+    {
+        import_faceted_text_tags(
+            &mut tags_map,
+            &config.faceted_tag_mapping,
+            &FACET_COMMENT,
+            filter_vorbis_comment_values(vorbis_comments, "COMMENT")
+                .chain(filter_vorbis_comment_values(vorbis_comments, "DESCRIPTION")),
+        );
+    }
+
+    // Genre tags
+    import_faceted_text_tags(
+        &mut tags_map,
+        &config.faceted_tag_mapping,
+        &FACET_GENRE,
+        filter_vorbis_comment_values(vorbis_comments, "GENRE"),
+    );
+
+    // Mood tags
+    import_faceted_text_tags(
+        &mut tags_map,
+        &config.faceted_tag_mapping,
+        &FACET_MOOD,
+        filter_vorbis_comment_values(vorbis_comments, "MOOD"),
+    );
+
+    // Grouping tags
+    import_faceted_text_tags(
+        &mut tags_map,
+        &config.faceted_tag_mapping,
+        &FACET_CGROUP,
+        filter_vorbis_comment_values(vorbis_comments, "GROUPING"),
+    );
+
+    if let Some(index) = import_track_index(vorbis_comments) {
+        track.indexes.track = index;
+    }
+    if let Some(index) = import_disc_index(vorbis_comments) {
+        track.indexes.disc = index;
+    }
+    if let Some(index) = import_movement_index(vorbis_comments) {
+        track.indexes.movement = index;
+    }
+
+    track.lyrics = import_lyrics(
+        filter_vorbis_comment_values(vorbis_comments, "LYRICS").chain(
+            filter_vorbis_comment_values(vorbis_comments, "UNSYNCEDLYRICS"),
+        ),
+    );
+
+    if flags.contains(ImportTrackFlags::ARTWORK) {
+        let mut image_digest = if flags.contains(ImportTrackFlags::ARTWORK_DIGEST) {
+            if flags.contains(ImportTrackFlags::ARTWORK_DIGEST_SHA256) {
+                // Compatibility
+                MediaDigest::sha256()
+            } else {
+                // Default
+                MediaDigest::new()
+            }
+        } else {
+            Default::default()
+        };
+        if let Some(artwork) = artwork_pictures(vorbis_comments)
+            .filter_map(|p| parse_artwork_from_embedded_image(&p.data, None, &mut image_digest))
+            .next()
+        {
+            track.media_source.artwork = artwork;
+        }
+    }
+
+    // Serato Tags
+    if flags.contains(ImportTrackFlags::SERATO_TAGS) {
+        let mut serato_tags = SeratoTagContainer::new();
+        if let Err(err) =
+            import_serato_markers2(vorbis_comments, &mut serato_tags, SeratoTagFormat::Ogg)
+        {
+            log::warn!("{}", err);
+        }
+
+        let track_cues = serato::read_cues(&serato_tags)?;
+        if !track_cues.is_empty() {
+            track.cues = Canonical::tie(track_cues);
+        }
+
+        track.color = serato::read_track_color(&serato_tags);
+    }
+
+    Ok(track)
+}
+
+pub const MIXXX_CUSTOM_TAGS_KEY: &str = "MIXXX_CUSTOM_TAGS";
+
+fn export_faceted_text_tags(
+    writer: &mut impl CommentWriter,
+    config: &FacetedTagMappingConfig,
+    facet: &Facet,
+    tags: &Tags,
+    key: &str,
+) {
+    let labels = export_faceted_tags(config.get(facet.value()), faceted_tags(tags, facet));
+    writer.write_multiple_values_or_remove(key, labels);
+}
+
+fn export_titles(writer: &mut impl CommentWriter, titles: &[Title], kind: TitleKind, key: &str) {
+    writer.write_multiple_values_or_remove(
+        key,
+        Titles::filter_kind(titles, kind)
+            .map(|title| title.name.to_owned())
+            .collect(),
+    );
+}
+
+fn export_actors(writer: &mut impl CommentWriter, actors: &[Actor], role: ActorRole, key: &str) {
+    writer.write_multiple_values_or_remove(
+        key,
+        export_actor_role_names(actors, role)
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect(),
+    );
+}
+
+fn export_index(writer: &mut impl CommentWriter, index: Index, number_key: &str, total_key: &str) {
+    writer.write_single_value_or_remove(number_key, index.number.map(|number| number.to_string()));
+    writer.write_single_value_or_remove(total_key, index.total.map(|total| total.to_string()));
+}
+
+/// Replace all Vorbis comments that are populated by the import
+///
+/// Alternative fields that are only considered during the import
+/// are removed to prevent ambiguities when re-importing the
+/// metadata. Unrelated fields are preserved.
+pub fn export_track(
+    writer: &mut impl CommentWriter,
+    config: &ExportTrackConfig,
+    flags: ExportTrackFlags,
+    track: &Track,
+) {
+    let loudness = match &track.media_source.content {
+        Content::Audio(audio_content) => audio_content.loudness,
+    };
+    writer.write_single_value_or_remove("REPLAYGAIN_TRACK_GAIN", loudness.map(format_replay_gain));
+
+    writer.write_single_value_or_remove("BPM", track.metrics.tempo_bpm.map(format_tempo_bpm));
+    writer.remove_all_values("TEMPO");
+
+    writer.write_single_value_or_remove(
+        "INITIALKEY",
+        format_key_signature(track.metrics.key_signature).map(ToOwned::to_owned),
+    );
+    writer.remove_all_values("KEY");
+
+    // Track titles
+    export_titles(writer, &track.titles, TitleKind::Main, "TITLE");
+    export_titles(writer, &track.titles, TitleKind::Sub, "SUBTITLE");
+    export_titles(writer, &track.titles, TitleKind::Work, "WORK");
+    export_titles(writer, &track.titles, TitleKind::Movement, "MOVEMENTNAME");
+
+    // Track actors
+    export_actors(writer, &track.actors, ActorRole::Artist, "ARTIST");
+    export_actors(writer, &track.actors, ActorRole::Composer, "COMPOSER");
+    export_actors(writer, &track.actors, ActorRole::Conductor, "CONDUCTOR");
+    export_actors(writer, &track.actors, ActorRole::Producer, "PRODUCER");
+    export_actors(writer, &track.actors, ActorRole::Remixer, "REMIXER");
+
+    // Album
+    export_titles(writer, &track.album.titles, TitleKind::Main, "ALBUM");
+    export_actors(
+        writer,
+        &track.album.actors,
+        ActorRole::Artist,
+        "ALBUMARTIST",
+    );
+    writer.remove_all_values("ALBUM_ARTIST");
+    writer.remove_all_values("ALBUM ARTIST");
+    writer.remove_all_values("ENSEMBLE");
+    writer.write_single_value_or_remove(
+        "COMPILATION",
+        if track.album.kind == AlbumKind::Compilation {
+            Some("1".to_owned())
+        } else {
+            None
+        },
+    );
+
+    // Release
+    writer.write_single_value_or_remove(
+        "DATE",
+        track
+            .release
+            .released_at
+            .map(|released_at| released_at.to_string()),
+    );
+    writer.write_single_value_or_remove("LABEL", track.release.released_by.clone());
+    writer.write_single_value_or_remove("COPYRIGHT", track.release.copyright.clone());
+
+    // Indexes
+    export_index(writer, track.indexes.track, "TRACKNUMBER", "TRACKTOTAL");
+    writer.remove_all_values("TOTALTRACKS");
+    export_index(writer, track.indexes.disc, "DISCNUMBER", "DISCTOTAL");
+    writer.remove_all_values("TOTALDISCS");
+    export_index(writer, track.indexes.movement, "MOVEMENT", "MOVEMENTTOTAL");
+
+    // Comment tag
+    // Follow MusicBrainz and Mixxx by writing "COMMENT" instead
+    // of "DESCRIPTION", see also the import.
+    writer.write_multiple_values_or_remove(
+        "COMMENT",
+        export_faceted_tags(None, faceted_tags(&track.tags, &FACET_COMMENT)),
+    );
+    writer.remove_all_values("DESCRIPTION");
+
+    // Genre tags
+    export_faceted_text_tags(
+        writer,
+        &config.faceted_tag_mapping,
+        &FACET_GENRE,
+        &track.tags,
+        "GENRE",
+    );
+
+    // Mood tags
+    export_faceted_text_tags(
+        writer,
+        &config.faceted_tag_mapping,
+        &FACET_MOOD,
+        &track.tags,
+        "MOOD",
+    );
+
+    // Grouping tags
+    export_faceted_text_tags(
+        writer,
+        &config.faceted_tag_mapping,
+        &FACET_CGROUP,
+        &track.tags,
+        "GROUPING",
+    );
+
+    // Mixxx custom tags
+    if flags.contains(ExportTrackFlags::MIXXX_CUSTOM_TAGS) {
+        let custom_tags = if track.tags.is_empty() {
+            None
+        } else {
+            let custom_tags = SerdeTags::from(track.tags.as_ref().clone());
+            serde_json::to_string(&custom_tags)
+                .map_err(|err| {
+                    log::warn!("Failed to serialize Mixxx custom tags: {}", err);
+                    err
+                })
+                .ok()
+        };
+        writer.write_single_value_or_remove(MIXXX_CUSTOM_TAGS_KEY, custom_tags);
+    }
 }
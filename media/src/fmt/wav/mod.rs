@@ -0,0 +1,273 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    io::import::{self, *},
+    Result,
+};
+
+use aoide_core::{
+    audio::{
+        channel::ChannelCount,
+        signal::{BitrateBps, SampleRateHz},
+        AudioContent,
+    },
+    media::{Content, ContentMetadataFlags},
+    track::Track,
+};
+
+use semval::IsValid as _;
+use std::{borrow::Cow, time::Duration};
+
+use super::{
+    id3v2,
+    iff::{
+        self, decode_text, read_chunk_data, read_chunk_header, read_id3_chunk, skip_chunk_data,
+        ByteOrder, ChunkId, TextMetadata,
+    },
+};
+
+const RIFF_CHUNK_ID: ChunkId = *b"RIFF";
+const WAVE_FORM_TYPE: ChunkId = *b"WAVE";
+const FMT_CHUNK_ID: ChunkId = *b"fmt ";
+const DATA_CHUNK_ID: ChunkId = *b"data";
+const LIST_CHUNK_ID: ChunkId = *b"LIST";
+const INFO_LIST_TYPE: ChunkId = *b"INFO";
+
+// Both lower and upper case variants are used in the wild
+const ID3_CHUNK_IDS: [ChunkId; 2] = [*b"id3 ", *b"ID3 "];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FormatChunk {
+    channel_count: u16,
+    sample_rate: u32,
+    byte_rate: u32,
+    bits_per_sample: u16,
+}
+
+fn parse_format_chunk(data: &[u8]) -> Option<FormatChunk> {
+    if data.len() < 16 {
+        return None;
+    }
+    let le_u16 = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+    let le_u32 = |offset: usize| {
+        u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ])
+    };
+    // The audio format tag at offset 0 and the block alignment
+    // at offset 12 are not needed
+    Some(FormatChunk {
+        channel_count: le_u16(2),
+        sample_rate: le_u32(4),
+        byte_rate: le_u32(8),
+        bits_per_sample: le_u16(14),
+    })
+}
+
+/// Parse the sub-chunks of a LIST chunk of type INFO
+///
+/// https://www.recordingblogs.com/wiki/list-chunk-of-a-wave-file
+fn parse_info_list_chunk(mut data: &[u8]) -> Vec<(ChunkId, String)> {
+    let mut info = Vec::with_capacity(8);
+    while data.len() >= 8 {
+        let id = [data[0], data[1], data[2], data[3]];
+        let size = ByteOrder::LittleEndian.read_u32([data[4], data[5], data[6], data[7]]) as usize;
+        data = &data[8..];
+        if size > data.len() {
+            log::warn!("Truncated INFO sub-chunk: {}", String::from_utf8_lossy(&id));
+            break;
+        }
+        if let Some(text) = decode_text(&data[..size]) {
+            info.push((id, text));
+        }
+        let padded_size = size + (size & 1);
+        data = &data[padded_size.min(data.len())..];
+    }
+    info
+}
+
+fn text_metadata_from_info(info: &[(ChunkId, String)]) -> TextMetadata {
+    let find_text = |id: &ChunkId| {
+        info.iter()
+            .find(|(info_id, _)| info_id == id)
+            .map(|(_, text)| text.to_owned())
+    };
+    TextMetadata {
+        title: find_text(b"INAM"),
+        artist: find_text(b"IART"),
+        album: find_text(b"IPRD"),
+        comment: find_text(b"ICMT"),
+        genre: find_text(b"IGNR"),
+        released_at: find_text(b"ICRD"),
+        copyright: find_text(b"ICOP"),
+        track_number: find_text(b"ITRK").or_else(|| find_text(b"IPRT")),
+    }
+}
+
+/// Read and verify the file header
+fn read_file_header(reader: &mut Box<dyn Reader>) -> Result<()> {
+    let mut file_header = [0u8; 12];
+    reader.read_exact(&mut file_header)?;
+    if file_header[0..4] != RIFF_CHUNK_ID || file_header[8..12] != WAVE_FORM_TYPE {
+        return Err(anyhow::anyhow!("Not a RIFF/WAVE file").into());
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct ImportTrack;
+
+impl import::ImportTrack for ImportTrack {
+    fn import_track(
+        &self,
+        config: &ImportTrackConfig,
+        flags: ImportTrackFlags,
+        mut track: Track,
+        reader: &mut Box<dyn Reader>,
+    ) -> Result<Track> {
+        read_file_header(reader)?;
+
+        let mut format_chunk = None;
+        let mut data_size = None;
+        let mut id3_tag = None;
+        let mut info = Vec::new();
+        while let Some(chunk_header) = read_chunk_header(reader, ByteOrder::LittleEndian)? {
+            match chunk_header.id {
+                FMT_CHUNK_ID => {
+                    format_chunk = parse_format_chunk(&read_chunk_data(reader, chunk_header)?);
+                }
+                DATA_CHUNK_ID => {
+                    data_size = Some(chunk_header.size);
+                    skip_chunk_data(reader, chunk_header)?;
+                }
+                LIST_CHUNK_ID => {
+                    let data = read_chunk_data(reader, chunk_header)?;
+                    if data.starts_with(&INFO_LIST_TYPE) {
+                        info = parse_info_list_chunk(&data[INFO_LIST_TYPE.len()..]);
+                    }
+                }
+                id if ID3_CHUNK_IDS.contains(&id) => {
+                    if let Some(tag) = read_id3_chunk(reader, chunk_header)? {
+                        id3_tag = Some(tag);
+                    }
+                }
+                _ => {
+                    skip_chunk_data(reader, chunk_header)?;
+                }
+            }
+        }
+
+        let format_chunk = format_chunk.ok_or_else(|| anyhow::anyhow!("Missing format chunk"))?;
+
+        if track
+            .media_source
+            .content_metadata_flags
+            .update(ContentMetadataFlags::RELIABLE)
+        {
+            let channel_count = ChannelCount(format_chunk.channel_count);
+            let channels = if channel_count.is_valid() {
+                Some(channel_count.into())
+            } else {
+                log::warn!("Invalid channel count: {}", channel_count.0);
+                None
+            };
+            let sample_rate = SampleRateHz::from_inner(format_chunk.sample_rate.into());
+            let sample_rate = if sample_rate.is_valid() {
+                Some(sample_rate)
+            } else {
+                log::warn!("Invalid sample rate: {}", sample_rate);
+                None
+            };
+            // The byte rate is only an average for compressed formats and
+            // might be missing. Uncompressed PCM data is then assumed.
+            // Calculated with 64-bit integers to prevent overflows.
+            let byte_rate = if format_chunk.byte_rate > 0 {
+                u64::from(format_chunk.byte_rate)
+            } else {
+                u64::from(format_chunk.sample_rate)
+                    * u64::from(format_chunk.channel_count)
+                    * u64::from(format_chunk.bits_per_sample)
+                    / 8
+            };
+            let bitrate = BitrateBps::from_inner(byte_rate as f64 * 8.0);
+            let bitrate = if bitrate.is_valid() {
+                Some(bitrate)
+            } else {
+                log::warn!("Invalid bitrate: {}", bitrate);
+                None
+            };
+            let duration = match data_size {
+                Some(data_size) if byte_rate > 0 => {
+                    Some(Duration::from_secs_f64(f64::from(data_size) / byte_rate as f64).into())
+                }
+                _ => None,
+            };
+            let loudness = id3_tag.as_ref().and_then(id3v2::import_loudness);
+            let encoder = id3_tag
+                .as_ref()
+                .and_then(id3v2::import_encoder)
+                .map(Cow::into_owned)
+                .or_else(|| {
+                    info.iter()
+                        .find(|(id, _)| id == b"ISFT")
+                        .map(|(_, text)| text.to_owned())
+                });
+            let audio_content = AudioContent {
+                duration,
+                channels,
+                sample_rate,
+                bitrate,
+                bitrate_mode: None,
+                loudness,
+                loudness_range: None,
+                true_peak: None,
+                waveform: None,
+                range: None,
+                encoder,
+            };
+            track.media_source.content = Content::Audio(audio_content);
+        }
+
+        // Prefer the more expressive ID3v2 tag over the RIFF INFO chunk
+        if let Some(id3_tag) = id3_tag {
+            id3v2::import_metadata_into_track(&id3_tag, config, flags, track)
+        } else {
+            Ok(iff::import_text_metadata_into_track(
+                config,
+                text_metadata_from_info(&info),
+                track,
+            ))
+        }
+    }
+
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        read_file_header(reader)?;
+        let id3_tag = iff::find_id3_chunk(reader, ByteOrder::LittleEndian, &ID3_CHUNK_IDS)?;
+        Ok(id3_tag.as_ref().and_then(id3v2::load_artwork_image))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
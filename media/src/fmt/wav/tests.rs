@@ -0,0 +1,57 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+#[test]
+fn parse_format_chunk_pcm() {
+    let data = [
+        0x01, 0x00, // PCM
+        0x02, 0x00, // 2 channels
+        0x44, 0xAC, 0x00, 0x00, // 44100 Hz
+        0x10, 0xB1, 0x02, 0x00, // 176400 bytes/s
+        0x04, 0x00, // block align
+        0x10, 0x00, // 16 bits/sample
+    ];
+    assert_eq!(
+        Some(FormatChunk {
+            channel_count: 2,
+            sample_rate: 44100,
+            byte_rate: 176_400,
+            bits_per_sample: 16,
+        }),
+        parse_format_chunk(&data)
+    );
+    assert_eq!(None, parse_format_chunk(&data[..14]));
+}
+
+#[test]
+fn parse_info_list_chunk_with_padding() {
+    let data = b"INAM\x06\x00\x00\x00Title\0IART\x07\x00\x00\x00Artist\0\0ICMT\x00\x00\x00\x00";
+    let info = parse_info_list_chunk(data);
+    assert_eq!(
+        vec![
+            (*b"INAM", "Title".to_owned()),
+            (*b"IART", "Artist".to_owned())
+        ],
+        info
+    );
+    let text_metadata = text_metadata_from_info(&info);
+    assert_eq!(Some("Title"), text_metadata.title.as_deref());
+    assert_eq!(Some("Artist"), text_metadata.artist.as_deref());
+    assert_eq!(None, text_metadata.album);
+}
@@ -0,0 +1,169 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    io::import::{self, *},
+    Result,
+};
+
+use aoide_core::track::Track;
+
+use super::apev2::{self, StreamProperties};
+
+const BLOCK_HEADER_SIZE: usize = 32;
+
+// WavPack blocks never exceed 1 MiB, larger sizes indicate a corrupt
+// or malicious file
+const MAX_BLOCK_SIZE: usize = 1 << 20;
+
+const MONO_FLAG: u32 = 0x0000_0004;
+const SRATE_LSB: u32 = 23;
+const SRATE_MASK: u32 = 0xF;
+
+const ID_LARGE: u8 = 0x80;
+const ID_ODD_SIZE: u8 = 0x40;
+const ID_FUNCTION_MASK: u8 = 0x3F;
+const ID_CHANNEL_INFO: u8 = 0x0D;
+const ID_SAMPLE_RATE: u8 = 0x27;
+
+const SAMPLE_RATES: [u32; 15] = [
+    6_000, 8_000, 9_600, 11_025, 12_000, 16_000, 22_050, 24_000, 32_000, 44_100, 48_000, 64_000,
+    88_200, 96_000, 192_000,
+];
+
+/// Iterate over all metadata sub-blocks of a block
+///
+/// Returns the function id and the data of each sub-block.
+fn sub_blocks(mut data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    std::iter::from_fn(move || {
+        if data.len() < 2 {
+            return None;
+        }
+        let id = data[0];
+        let (word_count, header_size) = if id & ID_LARGE == 0 {
+            (usize::from(data[1]), 2)
+        } else {
+            if data.len() < 4 {
+                return None;
+            }
+            (
+                usize::from(data[1]) | usize::from(data[2]) << 8 | usize::from(data[3]) << 16,
+                4,
+            )
+        };
+        let padded_size = word_count * 2;
+        if data.len() < header_size + padded_size {
+            return None;
+        }
+        let size = if id & ID_ODD_SIZE == 0 || padded_size == 0 {
+            padded_size
+        } else {
+            padded_size - 1
+        };
+        let sub_block_data = &data[header_size..header_size + size];
+        data = &data[header_size + padded_size..];
+        Some((id & ID_FUNCTION_MASK, sub_block_data))
+    })
+}
+
+/// Parse the header and metadata of the first block
+///
+/// http://www.wavpack.com/WavPack5FileFormat.pdf
+fn parse_first_block(block: &[u8]) -> Option<StreamProperties> {
+    if block.len() < BLOCK_HEADER_SIZE || !block.starts_with(b"wvpk") {
+        return None;
+    }
+    let total_samples_u8 = block[11];
+    let total_samples = u32::from_le_bytes([block[12], block[13], block[14], block[15]]);
+    let flags = u32::from_le_bytes([block[24], block[25], block[26], block[27]]);
+    let sample_count = if total_samples == u32::MAX {
+        // Unknown
+        None
+    } else {
+        Some(
+            ((u64::from(total_samples_u8) << 32) + u64::from(total_samples))
+                - u64::from(total_samples_u8),
+        )
+    };
+    let mut channel_count = if flags & MONO_FLAG == 0 { 2 } else { 1 };
+    let mut sample_rate = SAMPLE_RATES
+        .get(((flags >> SRATE_LSB) & SRATE_MASK) as usize)
+        .copied()
+        .unwrap_or_default();
+    for (function_id, data) in sub_blocks(&block[BLOCK_HEADER_SIZE..]) {
+        match function_id {
+            ID_CHANNEL_INFO => {
+                if let Some(count) = data.first() {
+                    channel_count = u16::from(*count);
+                }
+            }
+            // Only present for non-standard sample rates
+            ID_SAMPLE_RATE if data.len() >= 3 => {
+                sample_rate = u32::from_le_bytes([data[0], data[1], data[2], 0]);
+            }
+            _ => (),
+        }
+    }
+    Some(StreamProperties {
+        channel_count,
+        sample_rate,
+        sample_count,
+    })
+}
+
+#[derive(Debug)]
+pub struct ImportTrack;
+
+impl import::ImportTrack for ImportTrack {
+    fn import_track(
+        &self,
+        config: &ImportTrackConfig,
+        flags: ImportTrackFlags,
+        track: Track,
+        reader: &mut Box<dyn Reader>,
+    ) -> Result<Track> {
+        apev2::skip_id3v2_tag(reader)?;
+        let mut block_header = [0u8; 8];
+        reader.read_exact(&mut block_header)?;
+        if &block_header[..4] != b"wvpk" {
+            return Err(anyhow::anyhow!("Not a WavPack file").into());
+        }
+        // The block size excludes the preamble and the block size itself
+        let block_size = u32::from_le_bytes([
+            block_header[4],
+            block_header[5],
+            block_header[6],
+            block_header[7],
+        ]) as usize;
+        if block_size > MAX_BLOCK_SIZE {
+            return Err(anyhow::anyhow!("Invalid WavPack block size: {}", block_size).into());
+        }
+        let mut block = block_header.to_vec();
+        block.resize(block_header.len() + block_size, 0);
+        reader.read_exact(&mut block[block_header.len()..])?;
+        let stream_props = parse_first_block(&block)
+            .ok_or_else(|| anyhow::anyhow!("Invalid WavPack block header"))?;
+        apev2::import_track(reader, stream_props, config, flags, track)
+    }
+
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>> {
+        apev2::load_artwork_image(reader)
+    }
+}
+
+#[cfg(test)]
+mod tests;
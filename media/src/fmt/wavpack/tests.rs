@@ -0,0 +1,141 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::{
+    media::{Content, ContentMetadataFlags, Source},
+    util::clock::DateTime,
+};
+
+use std::io::Cursor;
+
+fn new_block(flags: u32, total_samples: u32, sub_blocks: &[u8]) -> Vec<u8> {
+    let mut block = b"wvpk".to_vec();
+    let block_size = (BLOCK_HEADER_SIZE - 8 + sub_blocks.len()) as u32;
+    block.extend_from_slice(&block_size.to_le_bytes());
+    block.extend_from_slice(&[0x10, 0x04]); // version
+    block.push(0x00); // block index (upper 8 bits)
+    block.push(0x00); // total samples (upper 8 bits)
+    block.extend_from_slice(&total_samples.to_le_bytes());
+    block.extend_from_slice(&0u32.to_le_bytes()); // block index
+    block.extend_from_slice(&0u32.to_le_bytes()); // block samples
+    block.extend_from_slice(&flags.to_le_bytes());
+    block.extend_from_slice(&0u32.to_le_bytes()); // CRC
+    block.extend_from_slice(sub_blocks);
+    block
+}
+
+#[test]
+fn sub_blocks_with_odd_size() {
+    let data = [
+        ID_CHANNEL_INFO | ID_ODD_SIZE,
+        0x01, // 1 word
+        0x06,
+        0x00, // padding
+        ID_SAMPLE_RATE,
+        0x02, // 2 words
+        0x01,
+        0x02,
+        0x03,
+        0x04,
+    ];
+    assert_eq!(
+        vec![
+            (ID_CHANNEL_INFO, &[0x06][..]),
+            (ID_SAMPLE_RATE, &[0x01, 0x02, 0x03, 0x04][..])
+        ],
+        sub_blocks(&data).collect::<Vec<_>>()
+    );
+    // Truncated
+    assert_eq!(0, sub_blocks(&data[..3]).count());
+}
+
+#[test]
+fn parse_first_block_stereo_44100() {
+    let flags = 9 << SRATE_LSB;
+    let block = new_block(flags, 44_100, &[]);
+    assert_eq!(
+        Some(StreamProperties {
+            channel_count: 2,
+            sample_rate: 44_100,
+            sample_count: Some(44_100),
+        }),
+        parse_first_block(&block)
+    );
+    // Unknown number of samples
+    let block = new_block(flags | MONO_FLAG, u32::MAX, &[]);
+    assert_eq!(
+        Some(StreamProperties {
+            channel_count: 1,
+            sample_rate: 44_100,
+            sample_count: None,
+        }),
+        parse_first_block(&block)
+    );
+    assert_eq!(None, parse_first_block(&block[..BLOCK_HEADER_SIZE - 1]));
+}
+
+#[test]
+fn parse_first_block_non_standard_sample_rate() {
+    let flags = SRATE_MASK << SRATE_LSB;
+    let sub_blocks = [
+        ID_SAMPLE_RATE | ID_ODD_SIZE,
+        0x02, // 2 words
+        0x10,
+        0x27,
+        0x00, // 10_000 Hz
+        0x00, // padding
+        ID_CHANNEL_INFO | ID_ODD_SIZE,
+        0x01, // 1 word
+        0x06,
+        0x00, // padding
+    ];
+    let block = new_block(flags, 10_000, &sub_blocks);
+    assert_eq!(
+        Some(StreamProperties {
+            channel_count: 6,
+            sample_rate: 10_000,
+            sample_count: Some(10_000),
+        }),
+        parse_first_block(&block)
+    );
+}
+
+#[test]
+fn import_track_with_oversized_block() {
+    let mut data = b"wvpk".to_vec();
+    data.extend_from_slice(&u32::MAX.to_le_bytes());
+    let mut reader: Box<dyn Reader> = Box::new(Cursor::new(data));
+    assert!(import::ImportTrack::import_track(
+        &ImportTrack,
+        &Default::default(),
+        ImportTrackFlags::empty(),
+        Track::new_from_media_source(Source {
+            collected_at: DateTime::now_utc(),
+            synchronized_at: None,
+            path: "test.wv".to_owned().into(),
+            content_type: "audio/x-wavpack".to_owned(),
+            content_digest: None,
+            content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+            content: Content::Audio(Default::default()),
+            artwork: Default::default(),
+        }),
+        &mut reader,
+    )
+    .is_err());
+}
@@ -0,0 +1,399 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{io::import::ArtworkImage, util::digest::MediaDigest, Error, Result};
+
+use aoide_core::media::{Digest, ImageDimension};
+
+use image::{
+    guess_format, imageops::FilterType, load_from_memory, GenericImageView as _, ImageFormat,
+    ImageOutputFormat,
+};
+use mime::{IMAGE_JPEG, IMAGE_PNG, IMAGE_STAR};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    ffi::OsStr,
+    fmt::Write as _,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::SystemTime,
+};
+
+const ORIGINAL_FILE_NAME: &str = "original";
+
+const TEMP_FILE_EXTENSION: &str = "tmp";
+
+// Resized variants are encoded as JPEG unless they contain
+// an alpha channel
+const RESIZED_JPEG_QUALITY: u8 = 85;
+
+/// A cached variant of an artwork image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArtworkVariant {
+    /// The original, full-resolution image
+    Original,
+
+    /// Scaled down to fit into a square with the given edge length
+    /// while preserving the aspect ratio
+    Resized(ImageDimension),
+}
+
+impl ArtworkVariant {
+    fn file_name(self) -> Cow<'static, str> {
+        match self {
+            Self::Original => ORIGINAL_FILE_NAME.into(),
+            Self::Resized(max_dimension) => max_dimension.to_string().into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtworkCacheConfig {
+    /// The root directory of all cached files
+    pub root_dir: PathBuf,
+
+    /// The maximum total size of all cached files in bytes
+    /// that is restored by eviction
+    pub max_total_size: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EvictionOutcome {
+    pub total_size_before: u64,
+    pub total_size_after: u64,
+    pub evicted_files: usize,
+}
+
+/// A content-addressed cache of artwork images in the local file system
+///
+/// Entries are keyed by the digest of the original image, i.e. the
+/// same digest that is stored for the artwork of media sources. Each
+/// entry contains the original image and all resized variants that
+/// have been requested so far.
+#[derive(Debug, Clone)]
+pub struct ArtworkCache {
+    config: ArtworkCacheConfig,
+}
+
+fn encode_hex(digest: &Digest) -> String {
+    digest
+        .iter()
+        .fold(String::with_capacity(2 * digest.len()), |mut hex, byte| {
+            write!(hex, "{:02x}", byte).expect("infallible");
+            hex
+        })
+}
+
+fn decode_hex(hex: &str) -> Option<Digest> {
+    if hex.len() != 2 * std::mem::size_of::<Digest>() || !hex.bytes().all(|b| b.is_ascii_hexdigit())
+    {
+        return None;
+    }
+    let mut digest = Digest::default();
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+fn media_type_of_image(image_data: &[u8]) -> String {
+    match guess_format(image_data) {
+        Ok(ImageFormat::Jpeg) => IMAGE_JPEG.to_string(),
+        Ok(ImageFormat::Png) => IMAGE_PNG.to_string(),
+        Ok(ImageFormat::Gif) => mime::IMAGE_GIF.to_string(),
+        Ok(ImageFormat::Bmp) => mime::IMAGE_BMP.to_string(),
+        Ok(ImageFormat::WebP) => "image/webp".to_string(),
+        Ok(ImageFormat::Tiff) => "image/tiff".to_string(),
+        _ => IMAGE_STAR.to_string(),
+    }
+}
+
+/// Scale down an image to fit into a square with the given edge length
+///
+/// Images that already fit are returned unmodified.
+pub fn resize_artwork_image(
+    image: &ArtworkImage,
+    max_dimension: ImageDimension,
+) -> Result<ArtworkImage> {
+    let decoded = load_from_memory(&image.data).map_err(anyhow::Error::from)?;
+    let max_dimension = u32::from(max_dimension);
+    let (width, height) = decoded.dimensions();
+    if width <= max_dimension && height <= max_dimension {
+        return Ok(image.clone());
+    }
+    let resized = decoded.resize(max_dimension, max_dimension, FilterType::Lanczos3);
+    let (media_type, output_format) = if resized.color().has_alpha() {
+        (IMAGE_PNG, ImageOutputFormat::Png)
+    } else {
+        (IMAGE_JPEG, ImageOutputFormat::Jpeg(RESIZED_JPEG_QUALITY))
+    };
+    let mut data = Vec::new();
+    resized
+        .write_to(&mut data, output_format)
+        .map_err(anyhow::Error::from)?;
+    Ok(ArtworkImage {
+        media_type: media_type.to_string(),
+        data,
+    })
+}
+
+// Distinguishes concurrent writers of the same file
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Write a file atomically by renaming a temporary file
+fn write_file(file_path: &Path, data: &[u8]) -> Result<()> {
+    let temp_file_path = file_path.with_extension(format!(
+        "{}.{}.{}",
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed),
+        TEMP_FILE_EXTENSION,
+    ));
+    fs::write(&temp_file_path, data)?;
+    fs::rename(&temp_file_path, file_path).map_err(|err| {
+        let _ = fs::remove_file(&temp_file_path);
+        Error::from(err)
+    })
+}
+
+/// Group directories are named by the first byte of the digest
+fn is_group_dir_name(file_name: &OsStr) -> bool {
+    file_name
+        .to_str()
+        .map(|name| name.len() == 2 && name.bytes().all(|b| b.is_ascii_hexdigit()))
+        .unwrap_or(false)
+}
+
+/// Parse the variant of an image file within an entry directory
+fn parse_variant_file_name(file_name: &OsStr) -> Option<ArtworkVariant> {
+    let file_name = file_name.to_str()?;
+    if file_name == ORIGINAL_FILE_NAME {
+        return Some(ArtworkVariant::Original);
+    }
+    let variant = ArtworkVariant::Resized(file_name.parse().ok()?);
+    // Reject ambiguous names, e.g. with leading zeros
+    Some(variant).filter(|variant| variant.file_name() == file_name)
+}
+
+/// Remove a directory that might have become empty
+fn remove_empty_dir(dir_path: &Path) {
+    // Fails if the directory is not empty
+    let _ = fs::remove_dir(dir_path);
+}
+
+impl ArtworkCache {
+    pub fn new(config: ArtworkCacheConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ArtworkCacheConfig {
+        &self.config
+    }
+
+    fn entry_dir(&self, digest: &Digest) -> PathBuf {
+        let hex = encode_hex(digest);
+        // Group entries by the first byte to keep directories small
+        self.config.root_dir.join(&hex[..2]).join(hex)
+    }
+
+    fn file_path(&self, digest: &Digest, variant: ArtworkVariant) -> PathBuf {
+        self.entry_dir(digest).join(variant.file_name().as_ref())
+    }
+
+    /// Load a cached variant
+    pub fn load(&self, digest: &Digest, variant: ArtworkVariant) -> Result<Option<ArtworkImage>> {
+        let data = match fs::read(self.file_path(digest, variant)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(ArtworkImage {
+            media_type: media_type_of_image(&data),
+            data,
+        }))
+    }
+
+    /// Store the original image
+    ///
+    /// The image is only stored if the digest matches the image data.
+    /// Otherwise the file might have been modified after the artwork
+    /// has been imported and `false` is returned.
+    pub fn store_original(&self, digest: &Digest, image: &ArtworkImage) -> Result<bool> {
+        if MediaDigest::new().digest_content(&image.data).as_ref() != Some(digest) {
+            log::debug!("Digest mismatch: Not caching the artwork image");
+            return Ok(false);
+        }
+        fs::create_dir_all(self.entry_dir(digest))?;
+        write_file(
+            &self.file_path(digest, ArtworkVariant::Original),
+            &image.data,
+        )?;
+        Ok(true)
+    }
+
+    /// Load a resized variant or create it from the original image
+    ///
+    /// Returns `None` if neither the requested variant nor the
+    /// original image is cached.
+    pub fn load_or_resize(
+        &self,
+        digest: &Digest,
+        max_dimension: ImageDimension,
+    ) -> Result<Option<ArtworkImage>> {
+        let variant = ArtworkVariant::Resized(max_dimension);
+        if let Some(resized) = self.load(digest, variant)? {
+            return Ok(Some(resized));
+        }
+        let original = if let Some(original) = self.load(digest, ArtworkVariant::Original)? {
+            original
+        } else {
+            return Ok(None);
+        };
+        let resized = resize_artwork_image(&original, max_dimension)?;
+        write_file(&self.file_path(digest, variant), &resized.data)?;
+        Ok(Some(resized))
+    }
+
+    /// Collect the directories of all entries
+    ///
+    /// Files and directories that don't match the layout of the cache
+    /// are skipped and left untouched.
+    fn entry_dirs(&self) -> Result<Vec<(Digest, PathBuf)>> {
+        let group_dirs = match fs::read_dir(&self.config.root_dir) {
+            Ok(group_dirs) => group_dirs,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut entry_dirs = Vec::new();
+        for group_dir in group_dirs {
+            let group_dir = group_dir?;
+            if !group_dir.file_type()?.is_dir() || !is_group_dir_name(&group_dir.file_name()) {
+                log::warn!(
+                    "Skipping unexpected entry {} in artwork cache",
+                    group_dir.path().display()
+                );
+                continue;
+            }
+            for entry_dir in fs::read_dir(group_dir.path())? {
+                let entry_dir = entry_dir?;
+                let digest = entry_dir
+                    .file_name()
+                    .to_str()
+                    .and_then(decode_hex)
+                    .filter(|digest| self.entry_dir(digest) == entry_dir.path());
+                match digest {
+                    Some(digest) if entry_dir.file_type()?.is_dir() => {
+                        entry_dirs.push((digest, entry_dir.path()));
+                    }
+                    _ => {
+                        log::warn!(
+                            "Skipping unexpected entry {} in artwork cache",
+                            entry_dir.path().display()
+                        );
+                    }
+                }
+            }
+        }
+        Ok(entry_dirs)
+    }
+
+    /// Delete all entries with digests that are no longer referenced
+    ///
+    /// Returns the number of deleted entries.
+    pub fn purge_unreferenced(&self, referenced_digests: &HashSet<Digest>) -> Result<usize> {
+        let mut purged_count = 0;
+        for (digest, entry_dir) in self.entry_dirs()? {
+            if referenced_digests.contains(&digest) {
+                continue;
+            }
+            fs::remove_dir_all(&entry_dir)?;
+            purged_count += 1;
+            if let Some(group_dir) = entry_dir.parent() {
+                remove_empty_dir(group_dir);
+            }
+        }
+        Ok(purged_count)
+    }
+
+    /// Delete the least recently used files until the total size of
+    /// all files does not exceed the configured maximum
+    ///
+    /// Only the image files of valid entries are considered.
+    ///
+    /// Files are evicted in the order of their last access time if
+    /// provided by the file system and otherwise in the order of
+    /// their last modification time.
+    pub fn evict(&self) -> Result<EvictionOutcome> {
+        if !self.config.root_dir.is_dir() {
+            // Nothing has been cached yet
+            return Ok(Default::default());
+        }
+        let mut files = Vec::new();
+        let mut total_size = 0;
+        for (_, entry_dir) in self.entry_dirs()? {
+            for file in fs::read_dir(&entry_dir)? {
+                let file = file?;
+                if !file.file_type()?.is_file()
+                    || parse_variant_file_name(&file.file_name()).is_none()
+                {
+                    log::warn!(
+                        "Skipping unexpected entry {} in artwork cache",
+                        file.path().display()
+                    );
+                    continue;
+                }
+                let metadata = file.metadata()?;
+                let last_used_at = metadata
+                    .accessed()
+                    .or_else(|_| metadata.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                total_size += metadata.len();
+                files.push((last_used_at, metadata.len(), file.path()));
+            }
+        }
+        let mut outcome = EvictionOutcome {
+            total_size_before: total_size,
+            total_size_after: total_size,
+            evicted_files: 0,
+        };
+        if total_size <= self.config.max_total_size {
+            return Ok(outcome);
+        }
+        files.sort_unstable_by_key(|(last_used_at, _, _)| *last_used_at);
+        for (_, file_size, file_path) in files {
+            if outcome.total_size_after <= self.config.max_total_size {
+                break;
+            }
+            fs::remove_file(&file_path)?;
+            outcome.total_size_after -= file_size;
+            outcome.evicted_files += 1;
+            if let Some(entry_dir) = file_path.parent() {
+                remove_empty_dir(entry_dir);
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,158 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+#[test]
+fn encode_decode_hex_roundtrip() {
+    let mut digest = Digest::default();
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = (i * 8) as u8;
+    }
+    let hex = encode_hex(&digest);
+    assert_eq!(64, hex.len());
+    assert!(hex.starts_with("0008101820"));
+    assert_eq!(Some(digest), decode_hex(&hex));
+}
+
+#[test]
+fn decode_hex_invalid() {
+    assert_eq!(None, decode_hex(""));
+    assert_eq!(None, decode_hex("0a"));
+    assert_eq!(None, decode_hex(&"xy".repeat(32)));
+    assert_eq!(None, decode_hex(&"0".repeat(65)));
+}
+
+#[test]
+fn entry_dirs_are_grouped_by_first_byte() {
+    let cache = ArtworkCache::new(ArtworkCacheConfig {
+        root_dir: PathBuf::from("/cache"),
+        max_total_size: 0,
+    });
+    let digest = [0xab; 32];
+    assert_eq!(
+        Path::new("/cache/ab")
+            .join("ab".repeat(32))
+            .join("original"),
+        cache.file_path(&digest, ArtworkVariant::Original)
+    );
+    assert_eq!(
+        Path::new("/cache/ab").join("ab".repeat(32)).join("256"),
+        cache.file_path(&digest, ArtworkVariant::Resized(256))
+    );
+}
+
+#[test]
+fn purge_unreferenced_skips_unexpected_entries() -> Result<()> {
+    let root_dir = std::env::temp_dir().join(format!(
+        "aoide-artwork-cache-purge-test-{}",
+        std::process::id()
+    ));
+    let cache = ArtworkCache::new(ArtworkCacheConfig {
+        root_dir: root_dir.clone(),
+        max_total_size: 0,
+    });
+    let referenced = [0xab; 32];
+    let unreferenced = [0xcd; 32];
+    for digest in &[referenced, unreferenced] {
+        fs::create_dir_all(cache.entry_dir(digest))?;
+        write_file(&cache.file_path(digest, ArtworkVariant::Original), b"image")?;
+    }
+    // Unexpected entries in the root directory and a group directory
+    let unexpected_dir = root_dir.join("unexpected");
+    fs::create_dir_all(unexpected_dir.join("ef".repeat(32)))?;
+    let unexpected_file = root_dir.join("ab").join("unexpected.txt");
+    write_file(&unexpected_file, b"text")?;
+    let mismatching_dir = root_dir.join("ab").join("cd".repeat(32));
+    fs::create_dir_all(&mismatching_dir)?;
+
+    let purged_count =
+        cache.purge_unreferenced(&std::iter::once(referenced).collect::<HashSet<_>>());
+    let existing = [
+        cache.entry_dir(&referenced).exists(),
+        cache.entry_dir(&unreferenced).exists(),
+        unexpected_dir.exists(),
+        unexpected_file.exists(),
+        mismatching_dir.exists(),
+    ];
+    let _ = fs::remove_dir_all(&root_dir);
+
+    assert_eq!(1, purged_count?);
+    assert_eq!([true, false, true, true, true], existing);
+    Ok(())
+}
+
+#[test]
+fn parse_variant_file_names() {
+    assert_eq!(
+        Some(ArtworkVariant::Original),
+        parse_variant_file_name(OsStr::new("original"))
+    );
+    assert_eq!(
+        Some(ArtworkVariant::Resized(256)),
+        parse_variant_file_name(OsStr::new("256"))
+    );
+    assert_eq!(None, parse_variant_file_name(OsStr::new("0256")));
+    assert_eq!(None, parse_variant_file_name(OsStr::new("+256")));
+    assert_eq!(None, parse_variant_file_name(OsStr::new("256.tmp")));
+    assert_eq!(None, parse_variant_file_name(OsStr::new("unexpected.txt")));
+}
+
+#[test]
+fn evict_skips_unexpected_files() -> Result<()> {
+    let root_dir = std::env::temp_dir().join(format!(
+        "aoide-artwork-cache-evict-test-{}",
+        std::process::id()
+    ));
+    let cache = ArtworkCache::new(ArtworkCacheConfig {
+        root_dir: root_dir.clone(),
+        max_total_size: 0,
+    });
+    let digest = [0xab; 32];
+    fs::create_dir_all(cache.entry_dir(&digest))?;
+    write_file(
+        &cache.file_path(&digest, ArtworkVariant::Original),
+        b"image",
+    )?;
+    write_file(
+        &cache.file_path(&digest, ArtworkVariant::Resized(256)),
+        b"resized",
+    )?;
+    // Foreign files outside of and within entry directories
+    let foreign_files = [
+        root_dir.join("foreign.txt"),
+        root_dir.join("ab").join("foreign.txt"),
+        cache.entry_dir(&digest).join("foreign.txt"),
+    ];
+    for foreign_file in &foreign_files {
+        write_file(foreign_file, b"text")?;
+    }
+
+    let outcome = cache.evict();
+    let existing: Vec<_> = foreign_files
+        .iter()
+        .map(|foreign_file| foreign_file.exists())
+        .collect();
+    let _ = fs::remove_dir_all(&root_dir);
+
+    let outcome = outcome?;
+    assert_eq!(2, outcome.evicted_files);
+    assert_eq!(12, outcome.total_size_before);
+    assert_eq!(0, outcome.total_size_after);
+    assert_eq!(vec![true; 3], existing);
+    Ok(())
+}
@@ -15,6 +15,7 @@
 
 ///////////////////////////////////////////////////////////////////////
 
+pub mod artwork_cache;
 pub mod digest;
 
 use super::{Error, IoError, Result};
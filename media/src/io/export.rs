@@ -0,0 +1,64 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{util::tag::FacetedTagMappingConfig, Result};
+
+use aoide_core::track::Track;
+
+use bitflags::bitflags;
+use std::path::Path;
+
+#[rustfmt::skip]
+bitflags! {
+    pub struct ExportTrackFlags: u16 {
+        const METADATA                            = 0b0000000000000001;
+        // Custom application metadata
+        const ITUNES_ID3V2_GROUPING_MOVEMENT_WORK = 0b0000000100000000; // ID3v2 with iTunes v12.5.4 and newer
+        const MIXXX_CUSTOM_TAGS                   = 0b0000001000000001; // implies METADATA
+        const SERATO_TAGS                         = 0b0001000000000001; // implies METADATA
+    }
+}
+
+impl ExportTrackFlags {
+    pub fn is_valid(self) -> bool {
+        Self::all().contains(self)
+    }
+}
+
+impl Default for ExportTrackFlags {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportTrackConfig {
+    pub faceted_tag_mapping: FacetedTagMappingConfig,
+}
+
+/// Write the metadata of a track into an existing file
+///
+/// Only the metadata is replaced, the audio data is left untouched.
+pub trait ExportTrack {
+    fn export_track_to_path(
+        &self,
+        config: &ExportTrackConfig,
+        flags: ExportTrackFlags,
+        track: &Track,
+        path: &Path,
+    ) -> Result<()>;
+}
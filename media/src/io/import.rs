@@ -35,12 +35,19 @@ bitflags! {
         const CONTENT_DIGEST                      = 0b0000000000000100;
         const ARTWORK_DIGEST                      = 0b0000000000001010; // implies ARTWORK
         const ARTWORK_DIGEST_SHA256               = 0b0000000000011010; // Use SHA-256 instead of BLAKE3 (e.g. for Mixxx)
+        // Audio analysis of the decoded stream
+        const ANALYZE_LOUDNESS                    = 0b0000000000100000; // EBU R128
+        const ANALYZE_TEMPO                       = 0b0000000001000000; // unless tagged or locked
+        const ANALYZE_KEY_SIGNATURE               = 0b0000000010000000; // unless tagged or locked
         // Custom application metadata
         const ITUNES_ID3V2_GROUPING_MOVEMENT_WORK = 0b0000000100000000; // ID3v2 with iTunes v12.5.4 and newer
         const MIXXX_CUSTOM_TAGS                   = 0b0000001000000001; // implies METADATA
         const MIXXX_KEEP_CUSTOM_GENRE_TAGS        = 0b0000010000000000;
         const MIXXX_KEEP_CUSTOM_MOOD_TAGS         = 0b0000100000000000;
         const SERATO_TAGS                         = 0b0001000000000001; // implies METADATA
+        // Acoustic fingerprint of the decoded stream, stored separately
+        const ANALYZE_FINGERPRINT                 = 0b0010000000000000;
+        const ANALYZE_WAVEFORM                    = 0b0100000000000000; // overview with peak/RMS bins
     }
 }
 
@@ -56,9 +63,31 @@ impl Default for ImportTrackFlags {
     }
 }
 
+/// Common file names of artwork images that are stored side by side
+/// with the audio files in the same directory, in order of precedence
+pub const DEFAULT_SIDECAR_ARTWORK_FILE_NAMES: &[&str] = &[
+    "cover.jpg",
+    "cover.jpeg",
+    "cover.png",
+    "folder.jpg",
+    "folder.jpeg",
+    "folder.png",
+    "front.jpg",
+    "front.jpeg",
+    "front.png",
+    "album.jpg",
+    "album.jpeg",
+    "album.png",
+];
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct ImportTrackConfig {
     pub faceted_tag_mapping: FacetedTagMappingConfig,
+
+    /// File names of artwork images in the directory of a track
+    /// that are picked up if no artwork is embedded, in order of
+    /// precedence. File names are matched case-insensitive.
+    pub sidecar_artwork_file_names: Vec<String>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -87,6 +116,13 @@ impl NewTrackInput {
     }
 }
 
+/// The encoded image data of embedded artwork
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArtworkImage {
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
 pub trait Reader: Read + Seek + 'static {}
 
 impl<T> Reader for T where T: Read + Seek + 'static {}
@@ -99,4 +135,10 @@ pub trait ImportTrack {
         track: Track,
         reader: &mut Box<dyn Reader>,
     ) -> Result<Track>;
+
+    /// Load the embedded artwork image in full resolution
+    ///
+    /// Selects the same image that is used for the artwork when
+    /// importing the track.
+    fn load_artwork_image(&self, reader: &mut Box<dyn Reader>) -> Result<Option<ArtworkImage>>;
 }
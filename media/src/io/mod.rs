@@ -15,4 +15,5 @@
 
 ///////////////////////////////////////////////////////////////////////
 
+pub mod export;
 pub mod import;
@@ -18,9 +18,16 @@
 #![deny(missing_debug_implementations)]
 #![deny(rust_2018_idioms)]
 
+#[cfg(feature = "analyze")]
+pub mod analyze;
+
+pub mod cue;
 pub mod fmt;
 pub mod fs;
 pub mod io;
+pub mod library;
+pub mod lyrics;
+pub mod playlist;
 pub mod util;
 
 use mime::Mime;
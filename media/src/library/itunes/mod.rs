@@ -0,0 +1,358 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{
+    import_faceted_text_tag, import_rating, parse_location, xml::child_elements, CollectionTrack,
+    Library, Playlist,
+};
+
+use crate::{
+    util::{
+        guess_mime_from_path, parse_tempo_bpm, parse_year_tag, push_next_actor_role_name,
+        tag::FacetedTagMappingConfig,
+    },
+    Error, Result,
+};
+
+use aoide_core::{
+    audio::{
+        signal::{BitrateBps, SampleRateHz},
+        AudioContent, DurationMs,
+    },
+    media::{resolver::SourcePathResolver, Content, ContentMetadataFlags, Source},
+    tag::TagsMap,
+    track::{
+        actor::ActorRole,
+        index::Index,
+        tag::{FACET_CGROUP, FACET_COMMENT, FACET_GENRE},
+        title::{Title, TitleKind},
+        Track,
+    },
+    util::{clock::DateTime, Canonical, CanonicalizeInto as _},
+};
+
+use anyhow::anyhow;
+use roxmltree::{Document, Node};
+use std::{collections::HashMap, str::FromStr};
+
+///////////////////////////////////////////////////////////////////////
+
+/// The entries of a plist `<dict>` element, i.e. the value
+/// elements by their preceding `<key>`
+type Dict<'a, 'input> = HashMap<&'a str, Node<'a, 'input>>;
+
+fn dict_entries<'a, 'input>(node: Node<'a, 'input>) -> Dict<'a, 'input> {
+    let mut entries = HashMap::new();
+    let mut key = None;
+    for child in node.children().filter(Node::is_element) {
+        if child.has_tag_name("key") {
+            key = child.text();
+        } else if let Some(key) = key.take() {
+            entries.insert(key, child);
+        }
+    }
+    entries
+}
+
+/// The text content of a `<string>`, `<integer>`, `<real>`,
+/// or `<date>` value
+fn text<'a>(dict: &Dict<'a, '_>, key: &str) -> Option<&'a str> {
+    dict.get(key)
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+fn parse<T: FromStr>(dict: &Dict<'_, '_>, key: &str) -> Option<T> {
+    text(dict, key).and_then(|text| text.parse().ok())
+}
+
+/// A `<true/>` or `<false/>` value, missing values are `false`
+fn flag(dict: &Dict<'_, '_>, key: &str) -> bool {
+    dict.get(key)
+        .map_or(false, |node| node.has_tag_name("true"))
+}
+
+fn index(dict: &Dict<'_, '_>, number_key: &str, total_key: &str) -> Index {
+    Index {
+        number: parse::<u16>(dict, number_key).filter(|number| *number > 0),
+        total: parse::<u16>(dict, total_key).filter(|total| *total > 0),
+    }
+}
+
+fn import_track(
+    dict: &Dict<'_, '_>,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+    collected_at: DateTime,
+) -> std::result::Result<Track, String> {
+    let location = text(dict, "Location").unwrap_or_default();
+    let url = parse_location(location).ok_or_else(|| location.to_owned())?;
+    let path = source_path_resolver
+        .resolve_path_from_url(&url)
+        .map_err(|err| {
+            log::info!("Skipping track at {}: {}", url, err);
+            location.to_owned()
+        })?;
+    let mime = guess_mime_from_path(url.path()).map_err(|err| {
+        log::info!("Skipping track at {}: {}", url, err);
+        location.to_owned()
+    })?;
+
+    let audio_content = AudioContent {
+        duration: parse::<f64>(dict, "Total Time")
+            .filter(|ms| *ms > 0.0)
+            .map(DurationMs::from_inner),
+        sample_rate: parse::<f64>(dict, "Sample Rate")
+            .filter(|hz| *hz > 0.0)
+            .map(SampleRateHz::from_inner),
+        bitrate: parse::<f64>(dict, "Bit Rate")
+            .filter(|kbps| *kbps > 0.0)
+            .map(|kbps| BitrateBps::from_inner(kbps * 1000.0)),
+        ..Default::default()
+    };
+    let media_source = Source {
+        collected_at: parse(dict, "Date Added").unwrap_or(collected_at),
+        // The file needs to be synchronized
+        synchronized_at: None,
+        path,
+        content_type: mime.to_string(),
+        content_digest: None,
+        content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+        content: Content::Audio(audio_content),
+        artwork: Default::default(),
+    };
+    let mut track = Track::new_from_media_source(media_source);
+
+    // Track titles
+    let mut track_titles = Vec::with_capacity(3);
+    for (kind, key) in [
+        (TitleKind::Main, "Name"),
+        (TitleKind::Work, "Work"),
+        (TitleKind::Movement, "Movement Name"),
+    ]
+    .iter()
+    {
+        if let Some(name) = text(dict, key) {
+            track_titles.push(Title {
+                name: name.to_owned(),
+                kind: *kind,
+            });
+        }
+    }
+    track.titles = Canonical::tie(track_titles.canonicalize_into());
+
+    // Track actors
+    let mut track_actors = Vec::with_capacity(4);
+    for (role, key) in [
+        (ActorRole::Artist, "Artist"),
+        (ActorRole::Composer, "Composer"),
+    ]
+    .iter()
+    {
+        if let Some(name) = text(dict, key) {
+            push_next_actor_role_name(&mut track_actors, *role, name.to_owned());
+        }
+    }
+    track.actors = Canonical::tie(track_actors.canonicalize_into());
+
+    if let Some(album_title) = text(dict, "Album") {
+        track.set_album_title(album_title);
+    }
+    if let Some(album_artist) = text(dict, "Album Artist") {
+        track.set_album_artist(album_artist);
+    }
+
+    track.release.released_at = text(dict, "Year").and_then(parse_year_tag);
+
+    track.indexes.track = index(dict, "Track Number", "Track Count");
+    track.indexes.disc = index(dict, "Disc Number", "Disc Count");
+    track.indexes.movement = index(dict, "Movement Number", "Movement Count");
+
+    track.metrics.tempo_bpm = text(dict, "BPM")
+        .and_then(parse_tempo_bpm)
+        .filter(|tempo_bpm| tempo_bpm.0 > 0.0);
+
+    let mut tags_map = TagsMap::default();
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_COMMENT,
+        text(dict, "Comments"),
+    );
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_GENRE,
+        text(dict, "Genre"),
+    );
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_CGROUP,
+        text(dict, "Grouping"),
+    );
+    // Ratings are stored in steps of 20 per star, computed ratings
+    // are inherited from the album and ignored
+    if !flag(dict, "Rating Computed") {
+        if let Some(rating) = parse::<u16>(dict, "Rating").filter(|rating| *rating <= 100) {
+            import_rating(&mut tags_map, (rating * 51 / 20) as u8);
+        }
+    }
+    track.tags = Canonical::tie(tags_map.into());
+
+    track.play_counter.times_played = parse(dict, "Play Count");
+    track.play_counter.last_played_at = parse(dict, "Play Date UTC");
+
+    Ok(track)
+}
+
+/// System playlists like the whole library or the purchased music
+/// are marked as distinguished, smart playlists contain their criteria
+fn is_user_playlist(dict: &Dict<'_, '_>) -> bool {
+    !flag(dict, "Master")
+        && !dict.contains_key("Distinguished Kind")
+        && !dict.contains_key("Smart Info")
+        && !dict.contains_key("Smart Criteria")
+        && dict
+            .get("Visible")
+            .map_or(true, |node| node.has_tag_name("true"))
+}
+
+/// Resolve the names of all enclosing folders, starting at the top level
+fn playlist_folders<'a>(
+    dict: &Dict<'a, '_>,
+    folders_by_persistent_id: &HashMap<&'a str, (&'a str, Option<&'a str>)>,
+) -> Vec<String> {
+    let mut folders = Vec::new();
+    let mut parent_id = text(dict, "Parent Persistent ID");
+    while let Some((name, grand_parent_id)) =
+        parent_id.and_then(|id| folders_by_persistent_id.get(id))
+    {
+        folders.push((*name).to_owned());
+        // Guard against cyclic references
+        if folders.len() > folders_by_persistent_id.len() {
+            log::warn!("Cyclic playlist folders");
+            break;
+        }
+        parent_id = *grand_parent_id;
+    }
+    folders.reverse();
+    folders
+}
+
+/// Import the tracks and the user playlists from an
+/// `iTunes Music Library.xml` file
+///
+/// Tracks are matched by their file location that is resolved into
+/// the path of a media source by the given resolver. Remote tracks
+/// and tracks that could not be resolved are skipped and reported.
+/// Smart playlists and system playlists are not imported.
+pub fn import_library(
+    xml: &str,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+    collected_at: DateTime,
+) -> Result<Library> {
+    let document =
+        Document::parse(xml).map_err(|err| anyhow!("Failed to parse iTunes XML: {}", err))?;
+    let root = document.root_element();
+    let root_dict = if let Some(root_dict) = child_elements(root, "dict")
+        .next()
+        .filter(|_| root.has_tag_name("plist"))
+    {
+        dict_entries(root_dict)
+    } else {
+        return Err(Error::Other(anyhow!(
+            "Unexpected root element {} in iTunes XML",
+            root.tag_name().name()
+        )));
+    };
+
+    let mut library = Library::default();
+    if let Some(tracks) = root_dict.get("Tracks") {
+        for node in child_elements(*tracks, "dict") {
+            let dict = dict_entries(node);
+            let track_id = if let Some(track_id) = text(&dict, "Track ID") {
+                track_id
+            } else {
+                log::warn!("Skipping track without Track ID");
+                continue;
+            };
+            if text(&dict, "Track Type").map_or(false, |track_type| track_type != "File") {
+                log::debug!("Skipping track {} that is not a file", track_id);
+                continue;
+            }
+            match import_track(
+                &dict,
+                faceted_tag_mapping,
+                source_path_resolver,
+                collected_at,
+            ) {
+                Ok(track) => library.tracks.push(CollectionTrack {
+                    track_id: track_id.to_owned(),
+                    track,
+                }),
+                Err(location) => library.not_imported.push(location),
+            }
+        }
+    }
+
+    if let Some(playlists) = root_dict.get("Playlists") {
+        let playlist_dicts: Vec<_> = child_elements(*playlists, "dict")
+            .map(dict_entries)
+            .collect();
+        let folders_by_persistent_id: HashMap<_, _> = playlist_dicts
+            .iter()
+            .filter(|dict| flag(dict, "Folder"))
+            .filter_map(|dict| {
+                let persistent_id = text(dict, "Playlist Persistent ID")?;
+                let name = text(dict, "Name").unwrap_or_default();
+                Some((persistent_id, (name, text(dict, "Parent Persistent ID"))))
+            })
+            .collect();
+        for dict in &playlist_dicts {
+            if flag(dict, "Folder") || !is_user_playlist(dict) {
+                continue;
+            }
+            let track_ids = dict
+                .get("Playlist Items")
+                .map(|items| {
+                    child_elements(*items, "dict")
+                        .filter_map(|item| {
+                            text(&dict_entries(item), "Track ID").map(ToOwned::to_owned)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            library.playlists.push(Playlist {
+                folders: playlist_folders(dict, &folders_by_persistent_id),
+                name: text(dict, "Name").unwrap_or_default().to_owned(),
+                track_ids,
+            });
+        }
+    }
+
+    Ok(library)
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
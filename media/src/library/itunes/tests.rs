@@ -0,0 +1,211 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::library::tests::{collected_at, import_test_library, resolver};
+
+use aoide_core::{music::time::TempoBpm, tag::Score as TagScore, track::tag::LABEL_RATING};
+
+const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple Computer//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Major Version</key><integer>1</integer>
+	<key>Tracks</key>
+	<dict>
+		<key>101</key>
+		<dict>
+			<key>Track ID</key><integer>101</integer>
+			<key>Name</key><string>Allegro</string>
+			<key>Artist</key><string>Track Artist</string>
+			<key>Album Artist</key><string>Album Artist</string>
+			<key>Composer</key><string>Composer</string>
+			<key>Album</key><string>Album Title</string>
+			<key>Grouping</key><string>Grouping</string>
+			<key>Work</key><string>Symphony No. 1</string>
+			<key>Movement Number</key><integer>1</integer>
+			<key>Movement Count</key><integer>4</integer>
+			<key>Movement Name</key><string>Allegro</string>
+			<key>Genre</key><string>Classical</string>
+			<key>Total Time</key><integer>300000</integer>
+			<key>Disc Number</key><integer>1</integer>
+			<key>Disc Count</key><integer>2</integer>
+			<key>Track Number</key><integer>3</integer>
+			<key>Track Count</key><integer>12</integer>
+			<key>Year</key><integer>2019</integer>
+			<key>BPM</key><integer>120</integer>
+			<key>Date Added</key><date>2020-01-31T12:00:00Z</date>
+			<key>Bit Rate</key><integer>320</integer>
+			<key>Sample Rate</key><integer>44100</integer>
+			<key>Comments</key><string>Comment</string>
+			<key>Play Count</key><integer>7</integer>
+			<key>Play Date</key><integer>3663865500</integer>
+			<key>Play Date UTC</key><date>2020-02-05T20:25:00Z</date>
+			<key>Rating</key><integer>80</integer>
+			<key>Track Type</key><string>File</string>
+			<key>Location</key><string>file://localhost/music/Composer/Allegro.m4a</string>
+		</dict>
+		<key>102</key>
+		<dict>
+			<key>Track ID</key><integer>102</integer>
+			<key>Name</key><string>Outside</string>
+			<key>Rating</key><integer>60</integer>
+			<key>Rating Computed</key><true/>
+			<key>Track Type</key><string>File</string>
+			<key>Location</key><string>file://localhost/other/Outside.mp3</string>
+		</dict>
+		<key>103</key>
+		<dict>
+			<key>Track ID</key><integer>103</integer>
+			<key>Name</key><string>Stream</string>
+			<key>Track Type</key><string>URL</string>
+			<key>Location</key><string>http://radio.example.com/stream</string>
+		</dict>
+	</dict>
+	<key>Playlists</key>
+	<array>
+		<dict>
+			<key>Name</key><string>Library</string>
+			<key>Master</key><true/>
+			<key>Playlist ID</key><integer>1</integer>
+			<key>Playlist Persistent ID</key><string>0000000000000001</string>
+			<key>Visible</key><false/>
+			<key>Playlist Items</key>
+			<array>
+				<dict><key>Track ID</key><integer>101</integer></dict>
+				<dict><key>Track ID</key><integer>102</integer></dict>
+			</array>
+		</dict>
+		<dict>
+			<key>Name</key><string>Music</string>
+			<key>Playlist ID</key><integer>2</integer>
+			<key>Playlist Persistent ID</key><string>0000000000000002</string>
+			<key>Distinguished Kind</key><integer>4</integer>
+			<key>Music</key><true/>
+		</dict>
+		<dict>
+			<key>Name</key><string>Folder</string>
+			<key>Playlist ID</key><integer>3</integer>
+			<key>Playlist Persistent ID</key><string>0000000000000003</string>
+			<key>Folder</key><true/>
+		</dict>
+		<dict>
+			<key>Name</key><string>Sub Folder</string>
+			<key>Playlist ID</key><integer>4</integer>
+			<key>Playlist Persistent ID</key><string>0000000000000004</string>
+			<key>Parent Persistent ID</key><string>0000000000000003</string>
+			<key>Folder</key><true/>
+		</dict>
+		<dict>
+			<key>Name</key><string>List</string>
+			<key>Playlist ID</key><integer>5</integer>
+			<key>Playlist Persistent ID</key><string>0000000000000005</string>
+			<key>Parent Persistent ID</key><string>0000000000000004</string>
+			<key>Playlist Items</key>
+			<array>
+				<dict><key>Track ID</key><integer>102</integer></dict>
+				<dict><key>Track ID</key><integer>101</integer></dict>
+			</array>
+		</dict>
+		<dict>
+			<key>Name</key><string>Smart</string>
+			<key>Playlist ID</key><integer>6</integer>
+			<key>Playlist Persistent ID</key><string>0000000000000006</string>
+			<key>Smart Info</key><data>AQEAAwAAAAIAAAAZAAAAAAAAAAcAAAABAAAAAAAAAAAAAAAAAAAAAAAA</data>
+			<key>Smart Criteria</key><data>U0xzdAABAAEAAAADAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA</data>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#;
+
+#[test]
+fn import_tracks() {
+    let library = import_test_library(import_library, XML);
+    assert_eq!(1, library.tracks.len());
+    assert_eq!(
+        vec!["file://localhost/other/Outside.mp3".to_owned()],
+        library.not_imported
+    );
+
+    let CollectionTrack { track_id, track } = &library.tracks[0];
+    assert_eq!("101", track_id);
+    assert_eq!("Composer/Allegro.m4a", track.media_source.path.as_str());
+    assert_eq!(
+        "2020-01-31T12:00:00Z".parse::<DateTime>().unwrap(),
+        track.media_source.collected_at
+    );
+    assert_eq!(Some("Allegro"), track.track_title());
+    assert_eq!(Some("Track Artist"), track.track_artist());
+    assert_eq!(Some("Album Title"), track.album_title());
+    assert_eq!(Some("Album Artist"), track.album_artist());
+    assert_eq!(Some(3), track.indexes.track.number);
+    assert_eq!(Some(12), track.indexes.track.total);
+    assert_eq!(Some(2), track.indexes.disc.total);
+    assert_eq!(Some(TempoBpm(120.0)), track.metrics.tempo_bpm);
+    assert_eq!(Some(7), track.play_counter.times_played);
+    assert_eq!(
+        Some("2020-02-05T20:25:00Z".parse::<DateTime>().unwrap()),
+        track.play_counter.last_played_at
+    );
+    let rating = track
+        .tags
+        .plain
+        .iter()
+        .find(|tag| tag.label.as_ref() == Some(&*LABEL_RATING))
+        .unwrap();
+    assert_eq!(TagScore::new(0.8), rating.score);
+}
+
+#[test]
+fn import_work_and_movement() {
+    let library = import_test_library(import_library, XML);
+    let track = &library.tracks[0].track;
+    let work = track
+        .titles
+        .iter()
+        .find(|title| title.kind == TitleKind::Work)
+        .unwrap();
+    assert_eq!("Symphony No. 1", work.name);
+    let movement = track
+        .titles
+        .iter()
+        .find(|title| title.kind == TitleKind::Movement)
+        .unwrap();
+    assert_eq!("Allegro", movement.name);
+    assert_eq!(
+        Index {
+            number: Some(1),
+            total: Some(4),
+        },
+        track.indexes.movement
+    );
+}
+
+#[test]
+fn import_user_playlists_with_folders() {
+    let library = import_test_library(import_library, XML);
+    assert_eq!(
+        vec![Playlist {
+            folders: vec!["Folder".to_owned(), "Sub Folder".to_owned()],
+            name: "List".to_owned(),
+            track_ids: vec!["102".to_owned(), "101".to_owned()],
+        }],
+        library.playlists
+    );
+}
@@ -0,0 +1,327 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{CueRecord, Database, PlaylistRecord, TrackRecord};
+
+use crate::Result;
+
+use diesel::{
+    sql_query,
+    sql_types::{BigInt, Binary, Double, Integer, Nullable, Text},
+    Connection as _, QueryableByName, RunQueryDsl as _, SqliteConnection,
+};
+use std::{collections::HashMap, path::Path};
+use url::Url;
+
+#[derive(QueryableByName)]
+struct TrackRow {
+    #[sql_type = "BigInt"]
+    id: i64,
+    #[sql_type = "Text"]
+    location: String,
+    #[sql_type = "Nullable<Text>"]
+    title: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    artist: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    album: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    album_artist: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    composer: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    grouping: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    genre: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    comment: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    year: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    tracknumber: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    tracktotal: Option<String>,
+    #[sql_type = "Nullable<Double>"]
+    duration: Option<f64>,
+    #[sql_type = "Nullable<Integer>"]
+    samplerate: Option<i32>,
+    #[sql_type = "Nullable<Integer>"]
+    channels: Option<i32>,
+    #[sql_type = "Nullable<Integer>"]
+    bitrate: Option<i32>,
+    #[sql_type = "Nullable<Double>"]
+    bpm: Option<f64>,
+    #[sql_type = "Nullable<Integer>"]
+    bpm_lock: Option<i32>,
+    #[sql_type = "Nullable<Text>"]
+    beats_version: Option<String>,
+    #[sql_type = "Nullable<Binary>"]
+    beats: Option<Vec<u8>>,
+    #[sql_type = "Nullable<Text>"]
+    key: Option<String>,
+    #[sql_type = "Nullable<Integer>"]
+    key_id: Option<i32>,
+    #[sql_type = "Nullable<Text>"]
+    keys_version: Option<String>,
+    #[sql_type = "Nullable<Binary>"]
+    keys: Option<Vec<u8>>,
+    #[sql_type = "Nullable<Integer>"]
+    rating: Option<i32>,
+    #[sql_type = "Nullable<Integer>"]
+    timesplayed: Option<i32>,
+    #[sql_type = "Nullable<Text>"]
+    last_played_at: Option<String>,
+    #[sql_type = "Nullable<Text>"]
+    datetime_added: Option<String>,
+    #[sql_type = "Nullable<Integer>"]
+    color: Option<i32>,
+}
+
+impl From<TrackRow> for TrackRecord {
+    fn from(from: TrackRow) -> Self {
+        let TrackRow {
+            id,
+            location,
+            title,
+            artist,
+            album,
+            album_artist,
+            composer,
+            grouping,
+            genre,
+            comment,
+            year,
+            tracknumber,
+            tracktotal,
+            duration,
+            samplerate,
+            channels,
+            bitrate,
+            bpm,
+            bpm_lock,
+            beats_version,
+            beats,
+            key,
+            key_id,
+            keys_version,
+            keys,
+            rating,
+            timesplayed,
+            last_played_at,
+            datetime_added,
+            color,
+        } = from;
+        Self {
+            id,
+            location,
+            title,
+            artist,
+            album,
+            album_artist,
+            composer,
+            grouping,
+            genre,
+            comment,
+            year,
+            tracknumber,
+            tracktotal,
+            duration,
+            samplerate,
+            channels,
+            bitrate,
+            bpm,
+            bpm_lock: bpm_lock.unwrap_or_default() != 0,
+            beats_version,
+            beats,
+            key,
+            key_id,
+            keys_version,
+            keys,
+            rating,
+            timesplayed,
+            last_played_at,
+            datetime_added,
+            color,
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct CueRow {
+    #[sql_type = "BigInt"]
+    track_id: i64,
+    #[sql_type = "Integer"]
+    cue_type: i32,
+    #[sql_type = "Double"]
+    position: f64,
+    #[sql_type = "Double"]
+    length: f64,
+    #[sql_type = "Integer"]
+    hotcue: i32,
+    #[sql_type = "Nullable<Text>"]
+    label: Option<String>,
+    #[sql_type = "Nullable<Integer>"]
+    color: Option<i32>,
+}
+
+impl From<CueRow> for CueRecord {
+    fn from(from: CueRow) -> Self {
+        let CueRow {
+            track_id,
+            cue_type,
+            position,
+            length,
+            hotcue,
+            label,
+            color,
+        } = from;
+        Self {
+            track_id,
+            cue_type,
+            position,
+            length,
+            hotcue,
+            label,
+            color,
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct ListRow {
+    #[sql_type = "BigInt"]
+    id: i64,
+    #[sql_type = "Text"]
+    name: String,
+}
+
+#[derive(QueryableByName)]
+struct ListTrackRow {
+    #[sql_type = "BigInt"]
+    list_id: i64,
+    #[sql_type = "BigInt"]
+    track_id: i64,
+}
+
+// The history (hidden = 2) is only used for determining when
+// tracks have been played for the last time
+const SELECT_TRACKS: &str = r#"
+SELECT library.id, track_locations.location,
+    library.title, library.artist, library.album, library.album_artist,
+    library.composer, library.grouping, library.genre, library.comment,
+    library.year, library.tracknumber, library.tracktotal,
+    library.duration, library.samplerate, library.channels, library.bitrate,
+    library.bpm, library.bpm_lock, library.beats_version, library.beats,
+    library."key", library.key_id, library.keys_version, library.keys,
+    library.rating, library.timesplayed,
+    (SELECT MAX(PlaylistTracks.pl_datetime_added) FROM PlaylistTracks
+        JOIN Playlists ON Playlists.id=PlaylistTracks.playlist_id
+        WHERE PlaylistTracks.track_id=library.id AND Playlists.hidden=2) AS last_played_at,
+    library.datetime_added, library.color
+FROM library
+JOIN track_locations ON track_locations.id=library.location
+WHERE library.mixxx_deleted=0 AND track_locations.fs_deleted=0
+ORDER BY library.id
+"#;
+
+const SELECT_CUES: &str = r#"
+SELECT track_id, type AS cue_type, position, length, hotcue, label, color
+FROM cues
+ORDER BY track_id, id
+"#;
+
+// Neither the Auto DJ queue (hidden = 1) nor the history (hidden = 2)
+// are imported
+const SELECT_PLAYLISTS: &str = r#"
+SELECT id, name FROM Playlists WHERE hidden=0 ORDER BY position
+"#;
+
+const SELECT_PLAYLIST_TRACKS: &str = r#"
+SELECT playlist_id AS list_id, track_id FROM PlaylistTracks ORDER BY playlist_id, position
+"#;
+
+const SELECT_CRATES: &str = r#"
+SELECT id, name FROM crates ORDER BY name
+"#;
+
+const SELECT_CRATE_TRACKS: &str = r#"
+SELECT crate_id AS list_id, track_id FROM crate_tracks ORDER BY crate_id, track_id
+"#;
+
+fn load_lists(
+    connection: &SqliteConnection,
+    select_lists: &str,
+    select_list_tracks: &str,
+) -> Result<Vec<PlaylistRecord>> {
+    let mut track_ids: HashMap<i64, Vec<i64>> = HashMap::new();
+    for ListTrackRow { list_id, track_id } in sql_query(select_list_tracks)
+        .load::<ListTrackRow>(connection)
+        .map_err(anyhow::Error::from)?
+    {
+        track_ids.entry(list_id).or_default().push(track_id);
+    }
+    Ok(sql_query(select_lists)
+        .load::<ListRow>(connection)
+        .map_err(anyhow::Error::from)?
+        .into_iter()
+        .map(|ListRow { id, name }| PlaylistRecord {
+            name,
+            track_ids: track_ids.remove(&id).unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Read the library, the visible playlists, and the crates from
+/// the database file of Mixxx 2.3 or newer
+fn load_database(connection: &SqliteConnection) -> Result<Database> {
+    let tracks = sql_query(SELECT_TRACKS)
+        .load::<TrackRow>(connection)
+        .map_err(anyhow::Error::from)?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let cues = sql_query(SELECT_CUES)
+        .load::<CueRow>(connection)
+        .map_err(anyhow::Error::from)?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let playlists = load_lists(connection, SELECT_PLAYLISTS, SELECT_PLAYLIST_TRACKS)?;
+    let crates = load_lists(connection, SELECT_CRATES, SELECT_CRATE_TRACKS)?;
+    Ok(Database {
+        tracks,
+        cues,
+        playlists,
+        crates,
+    })
+}
+
+/// Open the database file of Mixxx read-only and load its contents
+pub fn load_database_file(database_file_path: &Path) -> Result<Database> {
+    // The Mixxx database must not be modified
+    let database_url = Url::from_file_path(database_file_path)
+        .map_err(|()| anyhow::anyhow!("Invalid file path: {}", database_file_path.display()))?;
+    let connection =
+        SqliteConnection::establish(&format!("{}?mode=ro", database_url)).map_err(|err| {
+            anyhow::anyhow!(
+                "Failed to open Mixxx database {}: {}",
+                database_file_path.display(),
+                err
+            )
+        })?;
+    load_database(&connection)
+}
@@ -0,0 +1,559 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{
+    import_faceted_text_tag, import_rating, CollectionTrack, Library, Playlist, CHROMATIC_KEY_CODES,
+};
+
+use crate::{
+    util::{
+        guess_mime_from_path, parse_index_numbers, parse_key_signature, parse_year_tag,
+        push_next_actor_role_name, tag::FacetedTagMappingConfig,
+    },
+    Result,
+};
+
+use aoide_core::{
+    audio::{
+        channel::{ChannelCount, NumberOfChannels},
+        signal::{BitrateBps, SampleRateHz},
+        AudioContent, DurationMs, PositionMs,
+    },
+    media::{resolver::SourcePathResolver, Content, ContentMetadataFlags, Source},
+    music::{key::KeySignature, time::TempoBpm},
+    tag::TagsMap,
+    track::{
+        actor::ActorRole,
+        cue::{BankIndex, Cue, CueFlags, OutMode, SlotIndex},
+        metric::MetricsFlags,
+        tag::{FACET_CGROUP, FACET_COMMENT, FACET_GENRE},
+        title::{Title, TitleKind},
+        Track,
+    },
+    util::{
+        clock::DateTime,
+        color::{Color, RgbColor},
+        Canonical, CanonicalizeInto as _,
+    },
+};
+
+use chrono::{NaiveDateTime, Utc};
+use std::{collections::HashMap, path::Path};
+use url::Url;
+
+#[cfg(feature = "library-mixxx-db")]
+pub mod db;
+
+///////////////////////////////////////////////////////////////////////
+
+/// Hot cues and saved loops are numbered by their pad
+pub const HOT_CUE_BANK_INDEX: BankIndex = 0;
+
+/// The main cue, the intro and outro ranges, and the last active loop
+pub const MAIN_CUE_BANK_INDEX: BankIndex = 1;
+
+pub const MAIN_CUE_SLOT_INDEX: SlotIndex = 0;
+
+pub const INTRO_SLOT_INDEX: SlotIndex = 1;
+
+pub const OUTRO_SLOT_INDEX: SlotIndex = 2;
+
+pub const LOOP_SLOT_INDEX: SlotIndex = 3;
+
+/// Crates are imported as playlists within this folder
+pub const CRATES_FOLDER: &str = "Crates";
+
+/// A row of the `library` table joined with `track_locations`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackRecord {
+    pub id: i64,
+
+    /// The absolute file path
+    pub location: String,
+
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub composer: Option<String>,
+    pub grouping: Option<String>,
+    pub genre: Option<String>,
+    pub comment: Option<String>,
+    pub year: Option<String>,
+    pub tracknumber: Option<String>,
+    pub tracktotal: Option<String>,
+
+    /// The duration in seconds
+    pub duration: Option<f64>,
+    pub samplerate: Option<i32>,
+    pub channels: Option<i32>,
+
+    /// The bitrate in kbps
+    pub bitrate: Option<i32>,
+
+    pub bpm: Option<f64>,
+    pub bpm_lock: bool,
+    pub beats_version: Option<String>,
+    pub beats: Option<Vec<u8>>,
+
+    /// The key as text, used if `key_id` is missing
+    pub key: Option<String>,
+
+    /// The chromatic key, starting at 1 for C major
+    pub key_id: Option<i32>,
+    pub keys_version: Option<String>,
+    pub keys: Option<Vec<u8>>,
+
+    /// 0 = no rating, 1..=5 stars
+    pub rating: Option<i32>,
+    pub timesplayed: Option<i32>,
+
+    /// The most recent time the track has been added to the history
+    pub last_played_at: Option<String>,
+    pub datetime_added: Option<String>,
+
+    /// An RGB color code
+    pub color: Option<i32>,
+}
+
+/// A row of the `cues` table
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueRecord {
+    pub track_id: i64,
+
+    /// 1 = hot cue, 2 = main cue, 4 = loop, 6 = intro, 7 = outro
+    pub cue_type: i32,
+
+    /// The position in interleaved stereo samples, i.e. 2 per frame
+    pub position: f64,
+
+    /// The length in interleaved stereo samples
+    pub length: f64,
+
+    /// The index of the hot cue pad or -1
+    pub hotcue: i32,
+
+    pub label: Option<String>,
+
+    /// An RGB color code
+    pub color: Option<i32>,
+}
+
+/// A playlist or crate with the ordered IDs of its tracks
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistRecord {
+    pub name: String,
+
+    pub track_ids: Vec<i64>,
+}
+
+/// The contents of a Mixxx library database that are imported
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Database {
+    pub tracks: Vec<TrackRecord>,
+
+    pub cues: Vec<CueRecord>,
+
+    /// All visible playlists, i.e. excluding the Auto DJ queue
+    /// and the history
+    pub playlists: Vec<PlaylistRecord>,
+
+    pub crates: Vec<PlaylistRecord>,
+}
+
+/// The origin of beats and keys as stored in their serialized
+/// Protocol Buffers messages
+const SOURCE_ANALYZER: u64 = 1;
+const SOURCE_USER: u64 = 3;
+
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, remainder) = bytes.split_first()?;
+        *bytes = remainder;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Find the value of a top-level varint field in a serialized
+/// Protocol Buffers message without decoding the whole message
+fn find_varint_field(mut bytes: &[u8], field_number: u64) -> Option<u64> {
+    while !bytes.is_empty() {
+        let key = read_varint(&mut bytes)?;
+        match key & 0x7 {
+            0 => {
+                let value = read_varint(&mut bytes)?;
+                if key >> 3 == field_number {
+                    return Some(value);
+                }
+            }
+            1 => bytes = bytes.get(8..)?,
+            2 => {
+                let len = read_varint(&mut bytes)? as usize;
+                bytes = bytes.get(len..)?;
+            }
+            5 => bytes = bytes.get(4..)?,
+            wire_type => {
+                log::warn!("Unsupported wire type {}", wire_type);
+                return None;
+            }
+        }
+    }
+    None
+}
+
+fn beats_source(version: &str, beats: &[u8]) -> Option<u64> {
+    if version.starts_with("BeatGrid") {
+        find_varint_field(beats, 3)
+    } else if version.starts_with("BeatMap") {
+        find_varint_field(beats, 2)
+    } else {
+        None
+    }
+}
+
+fn keys_source(version: &str, keys: &[u8]) -> Option<u64> {
+    if version.starts_with("KeyMap") {
+        find_varint_field(keys, 3)
+    } else {
+        None
+    }
+}
+
+/// Mixxx stores timestamps in UTC either as ISO 8601 strings
+/// or in the SQLite format `YYYY-MM-DD HH:MM:SS`
+fn parse_timestamp(input: &str) -> Option<DateTime> {
+    if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(input) {
+        return Some(datetime.into());
+    }
+    let input = input.trim_end_matches('Z');
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .map(|datetime| chrono::DateTime::<Utc>::from_utc(datetime, Utc).into())
+        .or_else(|| {
+            log::warn!("Invalid timestamp {}", input);
+            None
+        })
+}
+
+fn parse_rgb_color(code: i32) -> Option<RgbColor> {
+    Some(code)
+        .filter(|code| *code >= 0)
+        .map(|code| RgbColor(code as u32))
+        .filter(|color| color.0 <= RgbColor::max_code())
+}
+
+fn import_cues<'a>(
+    cues: impl Iterator<Item = &'a CueRecord>,
+    sample_rate: SampleRateHz,
+) -> Vec<Cue> {
+    let position_ms = |samples: f64| PositionMs(samples / (2.0 * sample_rate.to_inner()) * 1000.0);
+    let mut imported = Vec::new();
+    for cue in cues {
+        if cue.position < 0.0 {
+            log::debug!("Skipping cue without position");
+            continue;
+        }
+        let end = Some(cue.position + cue.length).filter(|_| cue.length > 0.0);
+        let (bank_index, slot_index, out_mode) = match cue.cue_type {
+            1 if cue.hotcue >= 0 => (HOT_CUE_BANK_INDEX, cue.hotcue as SlotIndex, None),
+            2 => (MAIN_CUE_BANK_INDEX, MAIN_CUE_SLOT_INDEX, None),
+            4 if cue.hotcue >= 0 => (
+                HOT_CUE_BANK_INDEX,
+                cue.hotcue as SlotIndex,
+                Some(OutMode::Loop),
+            ),
+            4 => (MAIN_CUE_BANK_INDEX, LOOP_SLOT_INDEX, Some(OutMode::Loop)),
+            6 => (MAIN_CUE_BANK_INDEX, INTRO_SLOT_INDEX, Some(OutMode::Cont)),
+            7 => (MAIN_CUE_BANK_INDEX, OUTRO_SLOT_INDEX, Some(OutMode::Cont)),
+            cue_type => {
+                log::debug!("Skipping cue of type {}", cue_type);
+                continue;
+            }
+        };
+        imported.push(Cue {
+            bank_index,
+            slot_index: Some(slot_index),
+            in_position: Some(position_ms(cue.position)),
+            out_position: end.filter(|_| out_mode.is_some()).map(position_ms),
+            out_mode: out_mode.filter(|_| end.is_some()),
+            label: cue
+                .label
+                .as_deref()
+                .filter(|label| !label.is_empty())
+                .map(ToOwned::to_owned),
+            color: cue.color.and_then(parse_rgb_color).map(Color::Rgb),
+            flags: CueFlags::empty(),
+        });
+    }
+    imported.canonicalize_into()
+}
+
+fn import_metrics_flags(record: &TrackRecord) -> MetricsFlags {
+    let mut flags = MetricsFlags::empty();
+    if record.bpm_lock {
+        flags |= MetricsFlags::TEMPO_BPM_LOCKED;
+    }
+    if let (Some(version), Some(beats)) = (&record.beats_version, &record.beats) {
+        if beats_source(version, beats) == Some(SOURCE_ANALYZER) {
+            flags |= MetricsFlags::TEMPO_BPM_ANALYZED;
+        }
+    }
+    if let (Some(version), Some(keys)) = (&record.keys_version, &record.keys) {
+        match keys_source(version, keys) {
+            Some(SOURCE_ANALYZER) => flags |= MetricsFlags::KEY_SIGNATURE_ANALYZED,
+            Some(SOURCE_USER) => flags |= MetricsFlags::KEY_SIGNATURE_LOCKED,
+            _ => {}
+        }
+    }
+    flags
+}
+
+fn import_track<'a>(
+    record: &TrackRecord,
+    cues: impl Iterator<Item = &'a CueRecord>,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+    collected_at: DateTime,
+) -> std::result::Result<Track, String> {
+    let location = &record.location;
+    let url = Url::from_file_path(Path::new(location)).map_err(|()| {
+        log::warn!("Invalid location {}", location);
+        location.to_owned()
+    })?;
+    let path = source_path_resolver
+        .resolve_path_from_url(&url)
+        .map_err(|err| {
+            log::info!("Skipping track at {}: {}", url, err);
+            location.to_owned()
+        })?;
+    let mime = guess_mime_from_path(location).map_err(|err| {
+        log::info!("Skipping track at {}: {}", url, err);
+        location.to_owned()
+    })?;
+
+    let sample_rate = record
+        .samplerate
+        .filter(|hz| *hz > 0)
+        .map(|hz| SampleRateHz::from_inner(f64::from(hz)));
+    let audio_content = AudioContent {
+        duration: record
+            .duration
+            .filter(|secs| *secs > 0.0)
+            .map(|secs| DurationMs::from_inner(secs * 1000.0)),
+        channels: record
+            .channels
+            .filter(|count| *count > 0)
+            .map(|count| ChannelCount(count as NumberOfChannels).into()),
+        sample_rate,
+        bitrate: record
+            .bitrate
+            .filter(|kbps| *kbps > 0)
+            .map(|kbps| BitrateBps::from_inner(f64::from(kbps) * 1000.0)),
+        ..Default::default()
+    };
+    let media_source = Source {
+        collected_at: record
+            .datetime_added
+            .as_deref()
+            .and_then(parse_timestamp)
+            .unwrap_or(collected_at),
+        // The file needs to be synchronized
+        synchronized_at: None,
+        path,
+        content_type: mime.to_string(),
+        content_digest: None,
+        content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+        content: Content::Audio(audio_content),
+        artwork: Default::default(),
+    };
+    let mut track = Track::new_from_media_source(media_source);
+
+    if let Some(title) = record.title.as_deref().filter(|title| !title.is_empty()) {
+        track.titles = Canonical::tie(vec![Title {
+            name: title.to_owned(),
+            kind: TitleKind::Main,
+        }]);
+    }
+
+    let mut track_actors = Vec::with_capacity(4);
+    for (role, name) in [
+        (ActorRole::Artist, &record.artist),
+        (ActorRole::Composer, &record.composer),
+    ]
+    .iter()
+    {
+        if let Some(name) = name.as_deref().filter(|name| !name.is_empty()) {
+            push_next_actor_role_name(&mut track_actors, *role, name.to_owned());
+        }
+    }
+    track.actors = Canonical::tie(track_actors.canonicalize_into());
+
+    if let Some(album_title) = record.album.as_deref().filter(|title| !title.is_empty()) {
+        track.set_album_title(album_title);
+    }
+    if let Some(album_artist) = record
+        .album_artist
+        .as_deref()
+        .filter(|name| !name.is_empty())
+    {
+        track.set_album_artist(album_artist);
+    }
+
+    track.release.released_at = record.year.as_deref().and_then(parse_year_tag);
+
+    if let Some(index) = record.tracknumber.as_deref().and_then(parse_index_numbers) {
+        track.indexes.track = index;
+    }
+    if let Some(total) = record
+        .tracktotal
+        .as_deref()
+        .and_then(|total| total.parse().ok())
+    {
+        track.indexes.track.total = Some(total);
+    }
+
+    track.metrics.tempo_bpm = record
+        .bpm
+        .map(TempoBpm)
+        .filter(|tempo_bpm| tempo_bpm.0 > 0.0);
+    if let Some(key_code) = record
+        .key_id
+        .filter(|key_id| *key_id > 0)
+        .and_then(|key_id| CHROMATIC_KEY_CODES.get(key_id as usize - 1))
+    {
+        track.metrics.key_signature = KeySignature::new(*key_code);
+    } else if let Some(key_signature) = record.key.as_deref().and_then(parse_key_signature) {
+        track.metrics.key_signature = key_signature;
+    }
+    track.metrics.flags = import_metrics_flags(record);
+
+    let mut tags_map = TagsMap::default();
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_COMMENT,
+        record.comment.as_deref(),
+    );
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_GENRE,
+        record.genre.as_deref(),
+    );
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_CGROUP,
+        record.grouping.as_deref(),
+    );
+    if let Some(stars) = record.rating.filter(|stars| (1..=5).contains(stars)) {
+        import_rating(&mut tags_map, stars as u8 * 51);
+    }
+    track.tags = Canonical::tie(tags_map.into());
+
+    track.color = record.color.and_then(parse_rgb_color).map(Color::Rgb);
+
+    track.play_counter.times_played = record
+        .timesplayed
+        .filter(|count| *count > 0)
+        .map(|count| count as u64);
+    track.play_counter.last_played_at = record.last_played_at.as_deref().and_then(parse_timestamp);
+
+    if let Some(sample_rate) = sample_rate {
+        track.cues = Canonical::tie(import_cues(cues, sample_rate));
+    }
+
+    Ok(track)
+}
+
+fn import_playlist(folders: &[&str], record: PlaylistRecord) -> Playlist {
+    let PlaylistRecord { name, track_ids } = record;
+    Playlist {
+        folders: folders.iter().map(|folder| (*folder).to_owned()).collect(),
+        name,
+        track_ids: track_ids.iter().map(ToString::to_string).collect(),
+    }
+}
+
+/// Import the library, the playlists, and the crates that have
+/// been read from a Mixxx database
+///
+/// The locations of tracks are resolved into paths of media sources
+/// by the given resolver. Tracks that could not be resolved are
+/// skipped and reported. Crates are imported as playlists within
+/// [`CRATES_FOLDER`].
+pub fn import_library(
+    database: Database,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+    collected_at: DateTime,
+) -> Result<Library> {
+    let Database {
+        tracks,
+        cues,
+        playlists,
+        crates,
+    } = database;
+    let mut cues_by_track_id: HashMap<i64, Vec<CueRecord>> = HashMap::new();
+    for cue in cues {
+        cues_by_track_id.entry(cue.track_id).or_default().push(cue);
+    }
+    let mut library = Library::default();
+    for record in &tracks {
+        let track_cues = cues_by_track_id
+            .get(&record.id)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        match import_track(
+            record,
+            track_cues.iter(),
+            faceted_tag_mapping,
+            source_path_resolver,
+            collected_at,
+        ) {
+            Ok(track) => library.tracks.push(CollectionTrack {
+                track_id: record.id.to_string(),
+                track,
+            }),
+            Err(location) => library.not_imported.push(location),
+        }
+    }
+    library.playlists.extend(
+        playlists
+            .into_iter()
+            .map(|record| import_playlist(&[], record)),
+    );
+    library.playlists.extend(
+        crates
+            .into_iter()
+            .map(|record| import_playlist(&[CRATES_FOLDER], record)),
+    );
+    Ok(library)
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,212 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::library::tests::{collected_at, import_test_library, resolver};
+
+use aoide_core::{music::key::KeyCode, tag::Score as TagScore, track::tag::LABEL_RATING};
+
+fn test_database() -> Database {
+    Database {
+        tracks: vec![
+            TrackRecord {
+                id: 1,
+                location: "/music/Artist/Track Title.mp3".to_owned(),
+                title: Some("Track Title".to_owned()),
+                artist: Some("Track Artist".to_owned()),
+                album: Some("Album Title".to_owned()),
+                album_artist: Some("Album Artist".to_owned()),
+                genre: Some("House".to_owned()),
+                year: Some("2019".to_owned()),
+                tracknumber: Some("3".to_owned()),
+                tracktotal: Some("12".to_owned()),
+                duration: Some(300.0),
+                samplerate: Some(44100),
+                channels: Some(2),
+                bitrate: Some(320),
+                bpm: Some(123.45),
+                bpm_lock: true,
+                key: Some("Am".to_owned()),
+                key_id: Some(22),
+                keys_version: Some("KeyMap-1.0".to_owned()),
+                // global_key = 22, source = USER
+                keys: Some(vec![0x08, 22, 0x18, 3]),
+                rating: Some(4),
+                timesplayed: Some(7),
+                last_played_at: Some("2020-02-01 20:15:00".to_owned()),
+                datetime_added: Some("2020-01-31T12:00:00.000Z".to_owned()),
+                color: Some(0x00FF00),
+                ..Default::default()
+            },
+            TrackRecord {
+                id: 2,
+                location: "/other/Outside.mp3".to_owned(),
+                ..Default::default()
+            },
+        ],
+        cues: vec![
+            CueRecord {
+                track_id: 1,
+                cue_type: 2,
+                position: 88200.0,
+                length: 0.0,
+                hotcue: -1,
+                label: None,
+                color: None,
+            },
+            CueRecord {
+                track_id: 1,
+                cue_type: 1,
+                position: 441000.0,
+                length: 0.0,
+                hotcue: 2,
+                label: Some("Drop".to_owned()),
+                color: Some(0xFF0000),
+            },
+            CueRecord {
+                track_id: 1,
+                cue_type: 4,
+                position: 882000.0,
+                length: 88200.0,
+                hotcue: -1,
+                label: Some(String::new()),
+                color: None,
+            },
+            CueRecord {
+                track_id: 2,
+                cue_type: 1,
+                position: 0.0,
+                length: 0.0,
+                hotcue: 0,
+                label: None,
+                color: None,
+            },
+        ],
+        playlists: vec![PlaylistRecord {
+            name: "List".to_owned(),
+            track_ids: vec![2, 1],
+        }],
+        crates: vec![PlaylistRecord {
+            name: "Crate".to_owned(),
+            track_ids: vec![1],
+        }],
+    }
+}
+
+#[test]
+fn find_varint_fields_of_serialized_messages() {
+    // bpm = { bpm = 120.0 } (length-delimited), source = ANALYZER
+    let beat_grid = [0x0a, 0x09, 0x09, 0, 0, 0, 0, 0, 0, 0x5e, 0x40, 0x18, 1];
+    assert_eq!(
+        Some(SOURCE_ANALYZER),
+        beats_source("BeatGrid-2.0", &beat_grid)
+    );
+    assert_eq!(None, beats_source("BeatMap-1.0", &beat_grid));
+    assert_eq!(Some(300), find_varint_field(&[0x10, 0xac, 0x02], 2));
+    // Truncated messages are rejected
+    assert_eq!(None, find_varint_field(&[0x0a, 0x09, 0x09], 3));
+}
+
+#[test]
+fn import_tracks() {
+    let library = import_test_library(import_library, test_database());
+    assert_eq!(1, library.tracks.len());
+    assert_eq!(vec!["/other/Outside.mp3".to_owned()], library.not_imported);
+
+    let CollectionTrack { track_id, track } = &library.tracks[0];
+    assert_eq!("1", track_id);
+    assert_eq!("Artist/Track Title.mp3", track.media_source.path.as_str());
+    assert_eq!("audio/mpeg", track.media_source.content_type);
+    assert_eq!(
+        parse_timestamp("2020-01-31T12:00:00Z"),
+        Some(track.media_source.collected_at)
+    );
+    assert_eq!(Some("Track Title"), track.track_title());
+    assert_eq!(Some("Track Artist"), track.track_artist());
+    assert_eq!(Some("Album Title"), track.album_title());
+    assert_eq!(Some("Album Artist"), track.album_artist());
+    assert_eq!(Some(3), track.indexes.track.number);
+    assert_eq!(Some(12), track.indexes.track.total);
+    assert_eq!(Some(TempoBpm(123.45)), track.metrics.tempo_bpm);
+    assert_eq!(KeyCode::Amin, track.metrics.key_signature.code());
+    assert_eq!(
+        MetricsFlags::TEMPO_BPM_LOCKED | MetricsFlags::KEY_SIGNATURE_LOCKED,
+        track.metrics.flags
+    );
+    assert_eq!(Some(Color::Rgb(RgbColor(0x00FF00))), track.color);
+    assert_eq!(Some(7), track.play_counter.times_played);
+    assert_eq!(
+        parse_timestamp("2020-02-01T20:15:00Z"),
+        track.play_counter.last_played_at
+    );
+    let rating = track
+        .tags
+        .plain
+        .iter()
+        .find(|tag| tag.label.as_ref() == Some(&*LABEL_RATING))
+        .unwrap();
+    assert_eq!(TagScore::new(0.8), rating.score);
+}
+
+#[test]
+fn import_main_cue_hot_cues_and_loops() {
+    let library = import_test_library(import_library, test_database());
+    let cues = &library.tracks[0].track.cues;
+    assert_eq!(3, cues.len());
+
+    let hot_cue = &cues[0];
+    assert_eq!(HOT_CUE_BANK_INDEX, hot_cue.bank_index);
+    assert_eq!(Some(2), hot_cue.slot_index);
+    assert_eq!(Some(PositionMs(5000.0)), hot_cue.in_position);
+    assert_eq!(Some("Drop"), hot_cue.label.as_deref());
+    assert_eq!(Some(Color::Rgb(RgbColor(0xFF0000))), hot_cue.color);
+
+    let main_cue = &cues[1];
+    assert_eq!(MAIN_CUE_BANK_INDEX, main_cue.bank_index);
+    assert_eq!(Some(MAIN_CUE_SLOT_INDEX), main_cue.slot_index);
+    assert_eq!(Some(PositionMs(1000.0)), main_cue.in_position);
+    assert_eq!(None, main_cue.out_position);
+
+    let last_loop = &cues[2];
+    assert_eq!(MAIN_CUE_BANK_INDEX, last_loop.bank_index);
+    assert_eq!(Some(LOOP_SLOT_INDEX), last_loop.slot_index);
+    assert_eq!(Some(PositionMs(10000.0)), last_loop.in_position);
+    assert_eq!(Some(PositionMs(11000.0)), last_loop.out_position);
+    assert_eq!(Some(OutMode::Loop), last_loop.out_mode);
+    assert_eq!(None, last_loop.label);
+}
+
+#[test]
+fn import_playlists_and_crates() {
+    let library = import_test_library(import_library, test_database());
+    assert_eq!(
+        vec![
+            Playlist {
+                folders: vec![],
+                name: "List".to_owned(),
+                track_ids: vec!["2".to_owned(), "1".to_owned()],
+            },
+            Playlist {
+                folders: vec![CRATES_FOLDER.to_owned()],
+                name: "Crate".to_owned(),
+                track_ids: vec!["1".to_owned()],
+            },
+        ],
+        library.playlists
+    );
+}
@@ -0,0 +1,251 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::util::tag::{
+    export_faceted_tags, faceted_tags, import_faceted_tags, FacetedTagMappingConfig,
+};
+
+use aoide_core::{
+    music::key::KeyCode,
+    tag::{Facet, FacetKey, PlainTag, Score as TagScore, TagsMap},
+    track::{tag::LABEL_RATING, Track},
+};
+
+use url::Url;
+
+pub mod itunes;
+pub mod mixxx;
+pub mod rekordbox;
+pub mod traktor;
+
+pub(crate) mod xml;
+
+/// A track from the collection of a DJ library
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionTrack {
+    /// The library-specific identifier that is referenced by
+    /// playlist entries
+    pub track_id: String,
+
+    pub track: Track,
+}
+
+/// A playlist from the nested playlist tree of a DJ library
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Playlist {
+    /// The names of all enclosing folders, starting at the top level
+    pub folders: Vec<String>,
+
+    pub name: String,
+
+    /// The track IDs of all entries
+    pub track_ids: Vec<String>,
+}
+
+impl Playlist {
+    pub const FOLDER_SEPARATOR: &'static str = " / ";
+
+    /// The name prefixed with the names of all enclosing folders
+    pub fn flattened_title(&self) -> String {
+        self.folders
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(self.name.as_str()))
+            .collect::<Vec<_>>()
+            .join(Self::FOLDER_SEPARATOR)
+    }
+
+    /// The inverse of [`Playlist::flattened_title`]
+    pub fn from_flattened_title(flattened_title: &str, track_ids: Vec<String>) -> Self {
+        let mut folders: Vec<_> = flattened_title
+            .split(Self::FOLDER_SEPARATOR)
+            .map(ToOwned::to_owned)
+            .collect();
+        let name = folders.pop().unwrap_or_default();
+        Self {
+            folders,
+            name,
+            track_ids,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Library {
+    pub tracks: Vec<CollectionTrack>,
+
+    pub playlists: Vec<Playlist>,
+
+    /// Locations of tracks that have been skipped, e.g. because
+    /// they are outside of the collection's root directory
+    pub not_imported: Vec<String>,
+}
+
+/// All keys in chromatic order, starting with the major keys at C
+/// followed by the minor keys at C
+pub(crate) const CHROMATIC_KEY_CODES: [KeyCode; 24] = [
+    KeyCode::Cmaj,
+    KeyCode::Dbmaj,
+    KeyCode::Dmaj,
+    KeyCode::Ebmaj,
+    KeyCode::Emaj,
+    KeyCode::Fmaj,
+    KeyCode::Gbmaj,
+    KeyCode::Gmaj,
+    KeyCode::Abmaj,
+    KeyCode::Amaj,
+    KeyCode::Bbmaj,
+    KeyCode::Bmaj,
+    KeyCode::Cmin,
+    KeyCode::Dbmin,
+    KeyCode::Dmin,
+    KeyCode::Ebmin,
+    KeyCode::Emin,
+    KeyCode::Fmin,
+    KeyCode::Gbmin,
+    KeyCode::Gmin,
+    KeyCode::Abmin,
+    KeyCode::Amin,
+    KeyCode::Bbmin,
+    KeyCode::Bmin,
+];
+
+/// A node in the playlist tree, i.e. either a folder or a playlist
+#[derive(Debug)]
+pub(crate) enum PlaylistNode<'a> {
+    Folder {
+        name: &'a str,
+        children: Vec<PlaylistNode<'a>>,
+    },
+    Playlist(&'a Playlist),
+}
+
+fn insert_playlist_node<'a>(
+    nodes: &mut Vec<PlaylistNode<'a>>,
+    folders: &'a [String],
+    playlist: &'a Playlist,
+) {
+    let (folder_name, sub_folders) = if let Some(split) = folders.split_first() {
+        split
+    } else {
+        nodes.push(PlaylistNode::Playlist(playlist));
+        return;
+    };
+    let existing_folder = nodes.iter_mut().find_map(|node| match node {
+        PlaylistNode::Folder { name, children } if *name == folder_name => Some(children),
+        _ => None,
+    });
+    if let Some(children) = existing_folder {
+        insert_playlist_node(children, sub_folders, playlist);
+    } else {
+        let mut children = Vec::new();
+        insert_playlist_node(&mut children, sub_folders, playlist);
+        nodes.push(PlaylistNode::Folder {
+            name: folder_name,
+            children,
+        });
+    }
+}
+
+/// Nest playlists into their folders while preserving the order
+/// of first appearance
+pub(crate) fn build_playlist_tree(playlists: &[Playlist]) -> Vec<PlaylistNode<'_>> {
+    let mut nodes = Vec::new();
+    for playlist in playlists {
+        insert_playlist_node(&mut nodes, &playlist.folders, playlist);
+    }
+    nodes
+}
+
+/// Rekordbox and iTunes encode local files as `file://localhost/...`
+pub(crate) fn parse_location(location: &str) -> Option<Url> {
+    let url = Url::parse(location)
+        .map_err(|err| {
+            log::warn!("Invalid location {}: {}", location, err);
+        })
+        .ok()?;
+    if url.scheme() == "file" && url.host_str().is_some() {
+        // Drop the host by converting the URL into a path and back
+        return url
+            .to_file_path()
+            .ok()
+            .and_then(|path| Url::from_file_path(path).ok());
+    }
+    Some(url)
+}
+
+pub(crate) fn import_faceted_text_tag(
+    tags_map: &mut TagsMap,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    facet: &Facet,
+    label: Option<&str>,
+) {
+    if let Some(label) = label {
+        let mut next_score_value = TagScore::max_value();
+        import_faceted_tags(
+            tags_map,
+            &mut next_score_value,
+            facet,
+            faceted_tag_mapping.get(facet.value()),
+            label,
+        );
+    }
+}
+
+/// DJ libraries store ratings as a byte in steps of 51 per star,
+/// i.e. 0 = no rating and 255 = 5 stars
+pub(crate) fn import_rating(tags_map: &mut TagsMap, rating: u8) {
+    if rating == 0 {
+        return;
+    }
+    let plain_tag = PlainTag {
+        label: Some(LABEL_RATING.clone()),
+        score: TagScore::clamp_from(f64::from(rating) / f64::from(u8::MAX)),
+    };
+    tags_map.insert(FacetKey::new(None), plain_tag);
+}
+
+pub(crate) fn export_faceted_text_tag(
+    track: &Track,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    facet: &Facet,
+) -> Option<String> {
+    export_faceted_tags(
+        faceted_tag_mapping.get(facet.value()),
+        faceted_tags(&track.tags, facet),
+    )
+    .into_iter()
+    .next()
+}
+
+/// The inverse of [`import_rating`], rounded to whole stars
+pub(crate) fn export_rating(track: &Track) -> Option<u8> {
+    track
+        .tags
+        .plain
+        .iter()
+        .find(|plain_tag| plain_tag.label.as_ref() == Some(&*LABEL_RATING))
+        .map(|plain_tag| (plain_tag.score.value() * 5.0).round() as u8 * 51)
+        .filter(|rating| *rating > 0)
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
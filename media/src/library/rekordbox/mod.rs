@@ -0,0 +1,663 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{
+    build_playlist_tree, export_faceted_text_tag, export_rating, import_faceted_text_tag,
+    import_rating, parse_location,
+    xml::{
+        attribute, child_elements, parse_attribute, write_close_element, write_empty_element,
+        write_open_element, Attributes, DECLARATION,
+    },
+    CollectionTrack, Library, Playlist, PlaylistNode,
+};
+
+use crate::{
+    util::{
+        format_key_signature, guess_mime_from_path, parse_key_signature, parse_tempo_bpm,
+        parse_year_tag, push_next_actor_role_name, tag::FacetedTagMappingConfig,
+    },
+    Error, Result,
+};
+
+use aoide_core::{
+    audio::{
+        signal::{BitrateBps, SampleRateHz},
+        AudioContent, DurationMs, PositionMs,
+    },
+    media::{resolver::SourcePathResolver, Content, ContentMetadataFlags, Source},
+    tag::TagsMap,
+    track::{
+        actor::{ActorRole, Actors},
+        cue::{BankIndex, Cue, CueFlags, OutMode, SlotIndex},
+        index::Index,
+        tag::{FACET_CGROUP, FACET_COMMENT, FACET_GENRE},
+        title::{Title, TitleKind, Titles},
+        Track,
+    },
+    util::{
+        clock::{DateTime, DateYYYYMMDD},
+        color::{Color, RgbColor},
+        Canonical, CanonicalizeInto as _,
+    },
+};
+
+use anyhow::anyhow;
+use chrono::{NaiveDate, Utc};
+use roxmltree::{Document, Node};
+use std::{borrow::Cow, collections::HashMap};
+use url::Url;
+
+///////////////////////////////////////////////////////////////////////
+
+/// Hot cues and hot loops are numbered by their pad
+pub const HOT_CUE_BANK_INDEX: BankIndex = 0;
+
+/// Memory cues and memory loops are numbered in order of appearance
+pub const MEMORY_CUE_BANK_INDEX: BankIndex = 1;
+
+fn parse_date_added(input: &str) -> Option<DateTime> {
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .map_err(|err| {
+            log::warn!("Invalid date {}: {}", input, err);
+        })
+        .ok()
+        .map(|date| chrono::DateTime::<Utc>::from_utc(date.and_hms(0, 0, 0), Utc).into())
+}
+
+fn parse_rgb_color(input: &str) -> Option<RgbColor> {
+    let hex_digits = input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+        .or_else(|| input.strip_prefix('#'))
+        .unwrap_or(input);
+    u32::from_str_radix(hex_digits, 16)
+        .map_err(|err| {
+            log::warn!("Invalid color {}: {}", input, err);
+        })
+        .ok()
+        .filter(|code| *code <= RgbColor::max_code())
+        .map(RgbColor)
+}
+
+fn parse_position_mark_color(node: Node<'_, '_>) -> Option<Color> {
+    let red = parse_attribute::<u8>(node, "Red")?;
+    let green = parse_attribute::<u8>(node, "Green")?;
+    let blue = parse_attribute::<u8>(node, "Blue")?;
+    let code = u32::from(red) << 16 | u32::from(green) << 8 | u32::from(blue);
+    Some(Color::Rgb(RgbColor(code)))
+}
+
+fn position_ms_from_secs(secs: f64) -> PositionMs {
+    PositionMs(secs * 1000.0)
+}
+
+fn import_cues<'a, 'input: 'a>(position_marks: impl Iterator<Item = Node<'a, 'input>>) -> Vec<Cue> {
+    let mut next_memory_slot_index: SlotIndex = 0;
+    let mut cues = Vec::new();
+    for node in position_marks {
+        let start = if let Some(start) = parse_attribute::<f64>(node, "Start") {
+            start
+        } else {
+            log::warn!("Skipping position mark without start");
+            continue;
+        };
+        // 0 = cue, 1 = fade-in, 2 = fade-out, 3 = load, 4 = loop
+        let is_loop = parse_attribute::<u8>(node, "Type") == Some(4);
+        let end = if is_loop {
+            parse_attribute::<f64>(node, "End")
+        } else {
+            None
+        };
+        let num = parse_attribute::<i16>(node, "Num").unwrap_or(-1);
+        let (bank_index, slot_index) = if num >= 0 {
+            (HOT_CUE_BANK_INDEX, num)
+        } else {
+            let slot_index = next_memory_slot_index;
+            next_memory_slot_index += 1;
+            (MEMORY_CUE_BANK_INDEX, slot_index)
+        };
+        cues.push(Cue {
+            bank_index,
+            slot_index: Some(slot_index),
+            in_position: Some(position_ms_from_secs(start)),
+            out_position: end.map(position_ms_from_secs),
+            out_mode: end.map(|_| OutMode::Loop),
+            label: attribute(node, "Name").map(ToOwned::to_owned),
+            color: parse_position_mark_color(node),
+            flags: CueFlags::empty(),
+        });
+    }
+    cues.canonicalize_into()
+}
+
+fn import_track(
+    node: Node<'_, '_>,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+    collected_at: DateTime,
+) -> std::result::Result<Track, String> {
+    let location = attribute(node, "Location").unwrap_or_default();
+    let url = parse_location(location).ok_or_else(|| location.to_owned())?;
+    let path = source_path_resolver
+        .resolve_path_from_url(&url)
+        .map_err(|err| {
+            log::info!("Skipping track at {}: {}", url, err);
+            location.to_owned()
+        })?;
+    let mime = guess_mime_from_path(url.path()).map_err(|err| {
+        log::info!("Skipping track at {}: {}", url, err);
+        location.to_owned()
+    })?;
+
+    let audio_content = AudioContent {
+        duration: parse_attribute::<f64>(node, "TotalTime")
+            .filter(|secs| *secs > 0.0)
+            .map(|secs| DurationMs::from_inner(secs * 1000.0)),
+        sample_rate: parse_attribute::<f64>(node, "SampleRate")
+            .filter(|hz| *hz > 0.0)
+            .map(SampleRateHz::from_inner),
+        bitrate: parse_attribute::<f64>(node, "BitRate")
+            .filter(|kbps| *kbps > 0.0)
+            .map(|kbps| BitrateBps::from_inner(kbps * 1000.0)),
+        ..Default::default()
+    };
+    let media_source = Source {
+        collected_at: attribute(node, "DateAdded")
+            .and_then(parse_date_added)
+            .unwrap_or(collected_at),
+        // The file needs to be synchronized
+        synchronized_at: None,
+        path,
+        content_type: mime.to_string(),
+        content_digest: None,
+        content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+        content: Content::Audio(audio_content),
+        artwork: Default::default(),
+    };
+    let mut track = Track::new_from_media_source(media_source);
+
+    // Track titles
+    let mut track_titles = Vec::with_capacity(2);
+    if let Some(name) = attribute(node, "Name") {
+        track_titles.push(Title {
+            name: name.to_owned(),
+            kind: TitleKind::Main,
+        });
+    }
+    if let Some(name) = attribute(node, "Mix") {
+        track_titles.push(Title {
+            name: name.to_owned(),
+            kind: TitleKind::Sub,
+        });
+    }
+    track.titles = Canonical::tie(track_titles.canonicalize_into());
+
+    // Track actors
+    let mut track_actors = Vec::with_capacity(4);
+    for (role, name) in [
+        (ActorRole::Artist, attribute(node, "Artist")),
+        (ActorRole::Composer, attribute(node, "Composer")),
+        (ActorRole::Remixer, attribute(node, "Remixer")),
+    ]
+    .iter()
+    {
+        if let Some(name) = name {
+            push_next_actor_role_name(&mut track_actors, *role, (*name).to_owned());
+        }
+    }
+    track.actors = Canonical::tie(track_actors.canonicalize_into());
+
+    if let Some(album_title) = attribute(node, "Album") {
+        track.set_album_title(album_title);
+    }
+
+    track.release.released_at = attribute(node, "Year").and_then(parse_year_tag);
+    track.release.released_by = attribute(node, "Label").map(ToOwned::to_owned);
+
+    track.indexes.track = Index {
+        number: parse_attribute::<u16>(node, "TrackNumber").filter(|number| *number > 0),
+        total: None,
+    };
+    track.indexes.disc = Index {
+        number: parse_attribute::<u16>(node, "DiscNumber").filter(|number| *number > 0),
+        total: None,
+    };
+
+    track.metrics.tempo_bpm = attribute(node, "AverageBpm")
+        .and_then(parse_tempo_bpm)
+        .filter(|tempo_bpm| tempo_bpm.0 > 0.0);
+    if let Some(key_signature) = attribute(node, "Tonality").and_then(parse_key_signature) {
+        track.metrics.key_signature = key_signature;
+    }
+
+    let mut tags_map = TagsMap::default();
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_COMMENT,
+        attribute(node, "Comments"),
+    );
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_GENRE,
+        attribute(node, "Genre"),
+    );
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_CGROUP,
+        attribute(node, "Grouping"),
+    );
+    if let Some(rating) = parse_attribute::<u8>(node, "Rating") {
+        import_rating(&mut tags_map, rating);
+    }
+    track.tags = Canonical::tie(tags_map.into());
+
+    track.color = attribute(node, "Colour")
+        .and_then(parse_rgb_color)
+        .map(Color::Rgb);
+
+    track.play_counter.times_played = parse_attribute(node, "PlayCount");
+
+    track.cues = Canonical::tie(import_cues(child_elements(node, "POSITION_MARK")));
+
+    Ok(track)
+}
+
+fn import_playlist_nodes(
+    parent: Node<'_, '_>,
+    folders: &mut Vec<String>,
+    track_ids_by_location: &HashMap<&str, &str>,
+    playlists: &mut Vec<Playlist>,
+) {
+    for node in child_elements(parent, "NODE") {
+        let name = attribute(node, "Name").unwrap_or_default().to_owned();
+        // 0 = folder, 1 = playlist
+        match parse_attribute::<u8>(node, "Type") {
+            Some(0) => {
+                folders.push(name);
+                import_playlist_nodes(node, folders, track_ids_by_location, playlists);
+                folders.pop();
+            }
+            Some(1) => {
+                // 0 = TrackID, 1 = Location
+                let key_is_location = parse_attribute::<u8>(node, "KeyType") == Some(1);
+                let track_ids = child_elements(node, "TRACK")
+                    .filter_map(|entry| {
+                        let key = attribute(entry, "Key")?;
+                        if key_is_location {
+                            track_ids_by_location.get(key).map(|id| (*id).to_owned())
+                        } else {
+                            Some(key.to_owned())
+                        }
+                    })
+                    .collect();
+                playlists.push(Playlist {
+                    folders: folders.clone(),
+                    name,
+                    track_ids,
+                });
+            }
+            node_type => {
+                log::warn!("Skipping playlist node of unknown type {:?}", node_type);
+            }
+        }
+    }
+}
+
+/// Import the collection and the playlists from an exported
+/// `rekordbox.xml` file
+///
+/// The locations of tracks are resolved into paths of media sources
+/// by the given resolver. Tracks that could not be resolved are
+/// skipped and reported. Playlist folders are flattened, i.e. only
+/// their names are preserved.
+pub fn import_library(
+    xml: &str,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+    collected_at: DateTime,
+) -> Result<Library> {
+    let document =
+        Document::parse(xml).map_err(|err| anyhow!("Failed to parse Rekordbox XML: {}", err))?;
+    let root = document.root_element();
+    if !root.has_tag_name("DJ_PLAYLISTS") {
+        return Err(Error::Other(anyhow!(
+            "Unexpected root element {} in Rekordbox XML",
+            root.tag_name().name()
+        )));
+    }
+
+    let mut library = Library::default();
+    let mut track_ids_by_location = HashMap::new();
+    for collection in child_elements(root, "COLLECTION") {
+        for node in child_elements(collection, "TRACK") {
+            let track_id = if let Some(track_id) = attribute(node, "TrackID") {
+                track_id
+            } else {
+                log::warn!("Skipping track without TrackID");
+                continue;
+            };
+            if let Some(location) = attribute(node, "Location") {
+                track_ids_by_location.insert(location, track_id);
+            }
+            match import_track(
+                node,
+                faceted_tag_mapping,
+                source_path_resolver,
+                collected_at,
+            ) {
+                Ok(track) => library.tracks.push(CollectionTrack {
+                    track_id: track_id.to_owned(),
+                    track,
+                }),
+                Err(location) => library.not_imported.push(location),
+            }
+        }
+    }
+
+    let mut folders = Vec::new();
+    for playlists in child_elements(root, "PLAYLISTS") {
+        // The single top-level folder is named "ROOT"
+        for root_folder in child_elements(playlists, "NODE") {
+            import_playlist_nodes(
+                root_folder,
+                &mut folders,
+                &track_ids_by_location,
+                &mut library.playlists,
+            );
+        }
+    }
+
+    Ok(library)
+}
+
+/// Rekordbox expects local files as `file://localhost/...`
+fn format_location(url: &Url) -> String {
+    if url.scheme() == "file" && url.host_str().is_none() {
+        format!("file://localhost{}", url.path())
+    } else {
+        url.to_string()
+    }
+}
+
+fn format_secs_from_position_ms(position: PositionMs) -> String {
+    format!("{:.3}", position.0 / 1000.0)
+}
+
+fn export_cue_attributes(cue: &Cue) -> Option<Attributes<'_>> {
+    let in_position = cue.in_position?;
+    let num = match cue.bank_index {
+        HOT_CUE_BANK_INDEX => cue.slot_index.unwrap_or(-1),
+        _ => -1,
+    };
+    let loop_end = cue
+        .out_position
+        .filter(|_| cue.out_mode == Some(OutMode::Loop));
+    let mut attributes: Attributes<'_> = Vec::with_capacity(8);
+    attributes.push((
+        "Name",
+        cue.label.as_deref().map(Cow::Borrowed).unwrap_or_default(),
+    ));
+    // 0 = cue, 4 = loop
+    attributes.push((
+        "Type",
+        Cow::Borrowed(if loop_end.is_some() { "4" } else { "0" }),
+    ));
+    attributes.push(("Start", format_secs_from_position_ms(in_position).into()));
+    if let Some(end) = loop_end {
+        attributes.push(("End", format_secs_from_position_ms(end).into()));
+    }
+    attributes.push(("Num", num.to_string().into()));
+    if let Some(Color::Rgb(RgbColor(code))) = cue.color {
+        attributes.push(("Red", (code >> 16 & 0xff).to_string().into()));
+        attributes.push(("Green", (code >> 8 & 0xff).to_string().into()));
+        attributes.push(("Blue", (code & 0xff).to_string().into()));
+    }
+    Some(attributes)
+}
+
+fn export_track_attributes<'a>(
+    track_id: &'a str,
+    track: &'a Track,
+    location: String,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+) -> Attributes<'a> {
+    let mut attributes: Attributes<'a> = Vec::with_capacity(32);
+    attributes.push(("TrackID", Cow::Borrowed(track_id)));
+    if let Some(name) = track.track_title() {
+        attributes.push(("Name", name.into()));
+    }
+    if let Some(artist) = track.track_artist() {
+        attributes.push(("Artist", artist.into()));
+    }
+    if let Some(composer) = track.track_composer() {
+        attributes.push(("Composer", composer.into()));
+    }
+    if let Some(album) = track.album_title() {
+        attributes.push(("Album", album.into()));
+    }
+    if let Some(grouping) = export_faceted_text_tag(track, faceted_tag_mapping, &FACET_CGROUP) {
+        attributes.push(("Grouping", grouping.into()));
+    }
+    if let Some(genre) = export_faceted_text_tag(track, faceted_tag_mapping, &FACET_GENRE) {
+        attributes.push(("Genre", genre.into()));
+    }
+    if let Content::Audio(audio_content) = &track.media_source.content {
+        if let Some(duration) = audio_content.duration {
+            attributes.push((
+                "TotalTime",
+                format!("{:.0}", duration.to_inner() / 1000.0).into(),
+            ));
+        }
+    }
+    if let Some(number) = track.indexes.disc.number {
+        attributes.push(("DiscNumber", number.to_string().into()));
+    }
+    if let Some(number) = track.indexes.track.number {
+        attributes.push(("TrackNumber", number.to_string().into()));
+    }
+    if let Some(released_at) = track.release.released_at {
+        let year = DateYYYYMMDD::from(released_at).year();
+        attributes.push(("Year", year.to_string().into()));
+    }
+    if let Some(tempo_bpm) = track.metrics.tempo_bpm {
+        attributes.push(("AverageBpm", format!("{:.2}", tempo_bpm.0).into()));
+    }
+    attributes.push((
+        "DateAdded",
+        track
+            .media_source
+            .collected_at
+            .naive_date()
+            .format("%Y-%m-%d")
+            .to_string()
+            .into(),
+    ));
+    if let Content::Audio(audio_content) = &track.media_source.content {
+        if let Some(bitrate) = audio_content.bitrate {
+            attributes.push((
+                "BitRate",
+                format!("{:.0}", bitrate.to_inner() / 1000.0).into(),
+            ));
+        }
+        if let Some(sample_rate) = audio_content.sample_rate {
+            attributes.push((
+                "SampleRate",
+                format!("{:.0}", sample_rate.to_inner()).into(),
+            ));
+        }
+    }
+    if let Some(comments) = export_faceted_text_tag(track, faceted_tag_mapping, &FACET_COMMENT) {
+        attributes.push(("Comments", comments.into()));
+    }
+    if let Some(times_played) = track.play_counter.times_played {
+        attributes.push(("PlayCount", times_played.to_string().into()));
+    }
+    if let Some(rating) = export_rating(track) {
+        attributes.push(("Rating", rating.to_string().into()));
+    }
+    attributes.push(("Location", location.into()));
+    if let Some(remixer) = Actors::main_actor(track.actors.iter(), ActorRole::Remixer) {
+        attributes.push(("Remixer", remixer.name.as_str().into()));
+    }
+    if let Some(tonality) = format_key_signature(track.metrics.key_signature) {
+        attributes.push(("Tonality", tonality.into()));
+    }
+    if let Some(label) = track.release.released_by.as_deref() {
+        attributes.push(("Label", label.into()));
+    }
+    if let Some(mix) = Titles::filter_kind(track.titles.iter(), TitleKind::Sub).next() {
+        attributes.push(("Mix", mix.name.as_str().into()));
+    }
+    if let Some(Color::Rgb(RgbColor(code))) = track.color {
+        attributes.push(("Colour", format!("0x{:06X}", code).into()));
+    }
+    attributes
+}
+
+fn write_playlist_nodes(xml: &mut String, depth: usize, nodes: &[PlaylistNode<'_>]) {
+    for node in nodes {
+        match node {
+            PlaylistNode::Folder { name, children } => {
+                write_open_element(
+                    xml,
+                    depth,
+                    "NODE",
+                    &[
+                        ("Type", "0".into()),
+                        ("Name", Cow::Borrowed(*name)),
+                        ("Count", children.len().to_string().into()),
+                    ],
+                );
+                write_playlist_nodes(xml, depth + 1, children);
+                write_close_element(xml, depth, "NODE");
+            }
+            PlaylistNode::Playlist(playlist) => {
+                write_open_element(
+                    xml,
+                    depth,
+                    "NODE",
+                    &[
+                        ("Name", playlist.name.as_str().into()),
+                        ("Type", "1".into()),
+                        // 0 = TrackID
+                        ("KeyType", "0".into()),
+                        ("Entries", playlist.track_ids.len().to_string().into()),
+                    ],
+                );
+                for track_id in &playlist.track_ids {
+                    write_empty_element(
+                        xml,
+                        depth + 1,
+                        "TRACK",
+                        &[("Key", track_id.as_str().into())],
+                    );
+                }
+                write_close_element(xml, depth, "NODE");
+            }
+        }
+    }
+}
+
+/// Export tracks and playlists into the format of a `rekordbox.xml`
+/// file
+///
+/// The paths of media sources are resolved into locations by the given
+/// resolver. Playlists are nested into their folders and refer to tracks
+/// by their TrackID. This is the inverse of [`import_library`].
+pub fn export_library(
+    tracks: &[CollectionTrack],
+    playlists: &[Playlist],
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+) -> Result<String> {
+    let mut xml = String::with_capacity(1024 * (1 + tracks.len()));
+    xml.push_str(DECLARATION);
+    write_open_element(&mut xml, 0, "DJ_PLAYLISTS", &[("Version", "1.0.0".into())]);
+    write_empty_element(
+        &mut xml,
+        1,
+        "PRODUCT",
+        &[
+            ("Name", "aoide".into()),
+            ("Version", env!("CARGO_PKG_VERSION").into()),
+        ],
+    );
+
+    write_open_element(
+        &mut xml,
+        1,
+        "COLLECTION",
+        &[("Entries", tracks.len().to_string().into())],
+    );
+    for CollectionTrack { track_id, track } in tracks {
+        let url = source_path_resolver
+            .resolve_url_from_path(&track.media_source.path)
+            .map_err(|err| {
+                anyhow!(
+                    "Failed to resolve location of {}: {:?}",
+                    track.media_source.path,
+                    err
+                )
+            })?;
+        let attributes =
+            export_track_attributes(track_id, track, format_location(&url), faceted_tag_mapping);
+        let position_marks: Vec<_> = track
+            .cues
+            .iter()
+            .filter_map(export_cue_attributes)
+            .collect();
+        if position_marks.is_empty() {
+            write_empty_element(&mut xml, 2, "TRACK", &attributes);
+            continue;
+        }
+        write_open_element(&mut xml, 2, "TRACK", &attributes);
+        for attributes in &position_marks {
+            write_empty_element(&mut xml, 3, "POSITION_MARK", attributes);
+        }
+        write_close_element(&mut xml, 2, "TRACK");
+    }
+    write_close_element(&mut xml, 1, "COLLECTION");
+
+    let nodes = build_playlist_tree(playlists);
+    write_open_element(&mut xml, 1, "PLAYLISTS", &[]);
+    write_open_element(
+        &mut xml,
+        2,
+        "NODE",
+        &[
+            ("Type", "0".into()),
+            ("Name", "ROOT".into()),
+            ("Count", nodes.len().to_string().into()),
+        ],
+    );
+    write_playlist_nodes(&mut xml, 3, &nodes);
+    write_close_element(&mut xml, 2, "NODE");
+    write_close_element(&mut xml, 1, "PLAYLISTS");
+
+    write_close_element(&mut xml, 0, "DJ_PLAYLISTS");
+    Ok(xml)
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
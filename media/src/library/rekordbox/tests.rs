@@ -0,0 +1,179 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::library::tests::{collected_at, import_test_library, resolver};
+
+use aoide_core::{
+    media::resolver::VirtualFilePathResolver, music::time::TempoBpm, tag::Score as TagScore,
+    track::tag::LABEL_RATING,
+};
+
+const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<DJ_PLAYLISTS Version="1.0.0">
+  <PRODUCT Name="rekordbox" Version="6.5.0" Company="AlphaTheta"/>
+  <COLLECTION Entries="2">
+    <TRACK TrackID="11" Name="Track Title" Artist="Track Artist" Composer=""
+      Album="Album Title" Genre="House" Kind="MP3 File" TotalTime="300"
+      TrackNumber="3" DiscNumber="0" Year="2019" AverageBpm="123.45"
+      DateAdded="2020-01-31" BitRate="320" SampleRate="44100" PlayCount="7"
+      Rating="153" Location="file://localhost/music/Artist/Track%20Title.mp3"
+      Remixer="Remixer" Tonality="Am" Label="Label" Mix="Extended Mix" Colour="0xFF007F">
+      <TEMPO Inizio="0.025" Bpm="123.45" Metro="4/4" Battito="1"/>
+      <POSITION_MARK Name="" Type="0" Start="0.025" Num="-1"/>
+      <POSITION_MARK Name="Drop" Type="0" Start="60.5" Num="2" Red="40" Green="226" Blue="20"/>
+      <POSITION_MARK Name="" Type="4" Start="10.0" End="17.5" Num="-1"/>
+    </TRACK>
+    <TRACK TrackID="12" Name="Outside" Location="file://localhost/other/Outside.mp3"/>
+  </COLLECTION>
+  <PLAYLISTS>
+    <NODE Type="0" Name="ROOT" Count="2">
+      <NODE Name="Folder" Type="0" Count="1">
+        <NODE Name="By ID" Type="1" KeyType="0" Entries="2">
+          <TRACK Key="11"/>
+          <TRACK Key="12"/>
+        </NODE>
+      </NODE>
+      <NODE Name="By Location" Type="1" KeyType="1" Entries="1">
+        <TRACK Key="file://localhost/music/Artist/Track%20Title.mp3"/>
+      </NODE>
+    </NODE>
+  </PLAYLISTS>
+</DJ_PLAYLISTS>
+"#;
+
+#[test]
+fn import_tracks() {
+    let library = import_test_library(import_library, XML);
+    assert_eq!(1, library.tracks.len());
+    assert_eq!(
+        vec!["file://localhost/other/Outside.mp3".to_owned()],
+        library.not_imported
+    );
+
+    let CollectionTrack { track_id, track } = &library.tracks[0];
+    assert_eq!("11", track_id);
+    assert_eq!("Artist/Track Title.mp3", track.media_source.path.as_str());
+    assert_eq!("audio/mpeg", track.media_source.content_type);
+    assert!(track.media_source.synchronized_at.is_none());
+    assert_eq!(Some("Track Title"), track.track_title());
+    assert_eq!(Some("Track Artist"), track.track_artist());
+    assert_eq!(Some("Album Title"), track.album_title());
+    assert_eq!(Some(3), track.indexes.track.number);
+    assert_eq!(None, track.indexes.disc.number);
+    assert_eq!(Some("Label"), track.release.released_by.as_deref());
+    assert_eq!(Some(TempoBpm(123.45)), track.metrics.tempo_bpm);
+    assert_eq!(
+        parse_key_signature("Am").unwrap(),
+        track.metrics.key_signature
+    );
+    assert_eq!(Some(Color::Rgb(RgbColor(0xFF_00_7F))), track.color);
+    assert_eq!(Some(7), track.play_counter.times_played);
+}
+
+#[test]
+fn import_rating_as_score() {
+    let library = import_test_library(import_library, XML);
+    let track = &library.tracks[0].track;
+    let rating = track
+        .tags
+        .plain
+        .iter()
+        .find(|tag| tag.label.as_ref() == Some(&*LABEL_RATING))
+        .unwrap();
+    assert_eq!(TagScore::clamp_from(153.0 / 255.0), rating.score);
+}
+
+#[test]
+fn import_hot_and_memory_cues() {
+    let library = import_test_library(import_library, XML);
+    let cues = library.tracks[0].track.cues.as_ref();
+    assert_eq!(3, cues.len());
+
+    let hot_cue = &cues[0];
+    assert_eq!(HOT_CUE_BANK_INDEX, hot_cue.bank_index);
+    assert_eq!(Some(2), hot_cue.slot_index);
+    assert_eq!(Some(PositionMs(60_500.0)), hot_cue.in_position);
+    assert_eq!(Some("Drop"), hot_cue.label.as_deref());
+    assert_eq!(Some(Color::Rgb(RgbColor(0x28_E2_14))), hot_cue.color);
+
+    let memory_cue = &cues[1];
+    assert_eq!(MEMORY_CUE_BANK_INDEX, memory_cue.bank_index);
+    assert_eq!(Some(0), memory_cue.slot_index);
+    assert!(memory_cue.out_position.is_none());
+    assert!(memory_cue.label.is_none());
+
+    let memory_loop = &cues[2];
+    assert_eq!(MEMORY_CUE_BANK_INDEX, memory_loop.bank_index);
+    assert_eq!(Some(1), memory_loop.slot_index);
+    assert_eq!(Some(PositionMs(10_000.0)), memory_loop.in_position);
+    assert_eq!(Some(PositionMs(17_500.0)), memory_loop.out_position);
+    assert_eq!(Some(OutMode::Loop), memory_loop.out_mode);
+}
+
+#[test]
+fn import_playlists_with_flattened_folders() {
+    let library = import_test_library(import_library, XML);
+    assert_eq!(
+        vec![
+            Playlist {
+                folders: vec!["Folder".to_owned()],
+                name: "By ID".to_owned(),
+                track_ids: vec!["11".to_owned(), "12".to_owned()],
+            },
+            Playlist {
+                folders: vec![],
+                name: "By Location".to_owned(),
+                track_ids: vec!["11".to_owned()],
+            },
+        ],
+        library.playlists
+    );
+    assert_eq!("Folder / By ID", library.playlists[0].flattened_title());
+}
+
+#[test]
+fn reject_unexpected_root_element() {
+    assert!(import_library(
+        "<NML/>",
+        &Default::default(),
+        &VirtualFilePathResolver::new(),
+        collected_at(),
+    )
+    .is_err());
+}
+
+#[test]
+fn export_and_reimport_library() {
+    let library = import_test_library(import_library, XML);
+    let xml = export_library(
+        &library.tracks,
+        &library.playlists,
+        &Default::default(),
+        &resolver(),
+    )
+    .unwrap();
+    assert!(xml.contains(r#"Location="file://localhost/music/Artist/Track%20Title.mp3""#));
+    assert!(xml.contains(r#"Rating="153""#));
+    assert!(xml.contains(r#"Colour="0xFF007F""#));
+
+    let reimported =
+        import_library(&xml, &Default::default(), &resolver(), collected_at()).unwrap();
+    assert_eq!(library.tracks, reimported.tracks);
+    assert_eq!(library.playlists, reimported.playlists);
+}
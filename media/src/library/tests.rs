@@ -0,0 +1,106 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::Result;
+
+use xml::escape_attribute_value;
+
+use aoide_core::{media::resolver::VirtualFilePathResolver, util::clock::DateTime};
+
+use std::borrow::Cow;
+
+/// The base URL of the music directory of all test libraries
+pub(super) fn resolver() -> VirtualFilePathResolver {
+    VirtualFilePathResolver::with_base_url(Url::parse("file:///music/").unwrap())
+}
+
+pub(super) fn collected_at() -> DateTime {
+    DateTime::new_timestamp_millis(0)
+}
+
+/// Import a test library with the default tag mapping
+pub(super) fn import_test_library<T>(
+    import_library: impl FnOnce(
+        T,
+        &FacetedTagMappingConfig,
+        &VirtualFilePathResolver,
+        DateTime,
+    ) -> Result<Library>,
+    input: T,
+) -> Library {
+    import_library(input, &Default::default(), &resolver(), collected_at()).unwrap()
+}
+
+fn playlist(folders: &[&str], name: &str) -> Playlist {
+    Playlist {
+        folders: folders.iter().copied().map(ToOwned::to_owned).collect(),
+        name: name.to_owned(),
+        track_ids: vec![],
+    }
+}
+
+#[test]
+fn flattened_title_roundtrip() {
+    let nested = playlist(&["Gigs", "2020"], "Warmup");
+    assert_eq!("Gigs / 2020 / Warmup", nested.flattened_title());
+    assert_eq!(
+        nested,
+        Playlist::from_flattened_title(&nested.flattened_title(), vec![])
+    );
+    let top_level = playlist(&[], "Favorites");
+    assert_eq!(
+        top_level,
+        Playlist::from_flattened_title("Favorites", vec![])
+    );
+}
+
+#[test]
+fn build_playlist_tree_with_shared_folders() {
+    let playlists = vec![
+        playlist(&["Gigs"], "Warmup"),
+        playlist(&[], "Favorites"),
+        playlist(&["Gigs"], "Peak Time"),
+    ];
+    let nodes = build_playlist_tree(&playlists);
+    assert_eq!(2, nodes.len());
+    match &nodes[0] {
+        PlaylistNode::Folder { name, children } => {
+            assert_eq!("Gigs", *name);
+            assert_eq!(2, children.len());
+            assert!(
+                matches!(children[1], PlaylistNode::Playlist(playlist) if playlist.name == "Peak Time")
+            );
+        }
+        PlaylistNode::Playlist(_) => panic!("expected folder"),
+    }
+    assert!(matches!(nodes[1], PlaylistNode::Playlist(playlist) if playlist.name == "Favorites"));
+}
+
+#[test]
+fn escape_attribute_values() {
+    assert_eq!("Drum &amp; Bass", escape_attribute_value("Drum & Bass"));
+    assert_eq!(
+        "&lt;Tom&apos;s &quot;Mix&quot;&gt;&#10;",
+        escape_attribute_value("<Tom's \"Mix\">\n")
+    );
+    assert!(matches!(
+        escape_attribute_value("Plain"),
+        Cow::Borrowed("Plain")
+    ));
+}
@@ -0,0 +1,810 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{
+    build_playlist_tree, export_faceted_text_tag, export_rating, import_faceted_text_tag,
+    import_rating,
+    xml::{
+        attribute, child_elements, parse_attribute, write_close_element, write_empty_element,
+        write_open_element, Attributes, DECLARATION,
+    },
+    CollectionTrack, Library, Playlist, PlaylistNode, CHROMATIC_KEY_CODES,
+};
+
+use crate::{
+    util::{
+        format_key_signature, guess_mime_from_path, parse_key_signature, parse_tempo_bpm,
+        push_next_actor_role_name, tag::FacetedTagMappingConfig,
+    },
+    Error, Result,
+};
+
+use aoide_core::{
+    audio::{signal::BitrateBps, AudioContent, DurationMs, PositionMs},
+    media::{resolver::SourcePathResolver, Content, ContentMetadataFlags, Source},
+    music::key::KeySignature,
+    tag::TagsMap,
+    track::{
+        actor::{ActorRole, Actors},
+        cue::{BankIndex, Cue, CueFlags, OutMode, SlotIndex},
+        index::Index,
+        metric::MetricsFlags,
+        release::DateOrDateTime,
+        tag::{FACET_COMMENT, FACET_GENRE},
+        title::{Title, TitleKind, Titles},
+        Track,
+    },
+    util::{
+        clock::{DateTime, DateYYYYMMDD},
+        color::{Color, RgbColor},
+        Canonical, CanonicalizeInto as _,
+    },
+};
+
+use anyhow::anyhow;
+use chrono::{Datelike as _, NaiveDate, Utc};
+use percent_encoding::percent_decode_str;
+use roxmltree::{Document, Node};
+use std::{borrow::Cow, collections::HashMap};
+use url::Url;
+
+///////////////////////////////////////////////////////////////////////
+
+/// Hot cues and hot loops are numbered by their pad
+pub const HOT_CUE_BANK_INDEX: BankIndex = 0;
+
+/// Stored cues and loops without a pad are numbered in order of appearance
+pub const MEMORY_CUE_BANK_INDEX: BankIndex = 1;
+
+/// Beat grid anchors are numbered in order of appearance
+pub const GRID_CUE_BANK_INDEX: BankIndex = 2;
+
+/// The name of unnamed cues
+const UNNAMED_CUE: &str = "n.n.";
+
+/// Traktor separates all directories by `/:`, e.g. `/:Users/:me/:Music/:`
+const DIR_SEPARATOR: &str = "/:";
+
+/// The fixed track color palette, starting at index 1
+/// with red, orange, yellow, green, blue, violet, magenta
+const TRACK_COLORS: [RgbColor; 7] = [
+    RgbColor(0xFF0000),
+    RgbColor(0xFF8000),
+    RgbColor(0xFFFF00),
+    RgbColor(0x00FF00),
+    RgbColor(0x0000FF),
+    RgbColor(0x8000FF),
+    RgbColor(0xFF00FF),
+];
+
+fn is_drive_volume(volume: &str) -> bool {
+    let mut chars = volume.chars();
+    matches!(
+        (chars.next(), chars.next(), chars.next()),
+        (Some(drive), Some(':'), None) if drive.is_ascii_alphabetic()
+    )
+}
+
+fn file_url_from_path(path: &str) -> Url {
+    let mut url = Url::parse("file:///").expect("valid URL");
+    url.set_path(path);
+    url
+}
+
+/// All file URLs that a volume-relative location might refer to,
+/// in order of preference
+///
+/// Drive letters are used as a prefix on Windows. On macOS the volume
+/// might either be the root volume or a volume that is mounted below
+/// `/Volumes`.
+fn location_url_candidates(volume: &str, dir: &str, file: &str) -> Vec<Url> {
+    let path = format!("{}{}", dir.replace(DIR_SEPARATOR, "/"), file);
+    if is_drive_volume(volume) {
+        return vec![file_url_from_path(&format!("/{}{}", volume, path))];
+    }
+    let mut candidates = vec![file_url_from_path(&path)];
+    if !volume.is_empty() {
+        candidates.push(file_url_from_path(&format!("/Volumes/{}{}", volume, path)));
+    }
+    candidates
+}
+
+/// The primary key that is used for referencing tracks from playlists
+fn primary_key(volume: &str, dir: &str, file: &str) -> String {
+    format!("{}{}{}", volume, dir, file)
+}
+
+/// The volume, directory, and file name of a file URL, i.e. the
+/// inverse of [`location_url_candidates`]
+fn split_location<'a>(url: &Url, root_volume: &'a str) -> Option<(Cow<'a, str>, String, String)> {
+    let path = percent_decode_str(url.path()).decode_utf8().ok()?;
+    let (dir_path, file) = path.rsplit_once('/')?;
+    let (volume, dir_path) = if let Some(volume_path) = dir_path.strip_prefix("/Volumes/") {
+        let (volume, dir_path) = volume_path
+            .split_once('/')
+            .map(|(volume, dir_path)| (volume, format!("/{}", dir_path)))
+            .unwrap_or((volume_path, String::new()));
+        (Cow::Owned(volume.to_owned()), dir_path)
+    } else if let Some(drive) = dir_path.get(1..3).filter(|drive| is_drive_volume(drive)) {
+        (Cow::Owned(drive.to_owned()), dir_path[3..].to_owned())
+    } else {
+        (Cow::Borrowed(root_volume), dir_path.to_owned())
+    };
+    let dir = format!("{}{}", dir_path.replace('/', DIR_SEPARATOR), DIR_SEPARATOR);
+    Some((volume, dir, file.to_owned()))
+}
+
+fn parse_date(input: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(input, "%Y/%m/%d")
+        .map_err(|err| {
+            log::warn!("Invalid date {}: {}", input, err);
+        })
+        .ok()
+}
+
+fn format_date(date: NaiveDate) -> String {
+    format!("{}/{}/{}", date.year(), date.month(), date.day())
+}
+
+fn date_time_from_date(date: NaiveDate) -> DateTime {
+    chrono::DateTime::<Utc>::from_utc(date.and_hms(0, 0, 0), Utc).into()
+}
+
+fn parse_track_color(index: u8) -> Option<Color> {
+    TRACK_COLORS
+        .get(usize::from(index).checked_sub(1)?)
+        .copied()
+        .map(Color::Rgb)
+}
+
+/// The index of the closest color in the fixed palette
+fn format_track_color(color: Color) -> Option<usize> {
+    let RgbColor(code) = match color {
+        Color::Rgb(rgb_color) => rgb_color,
+        Color::Index(_) => return None,
+    };
+    let distance = |&RgbColor(other): &RgbColor| {
+        [16, 8, 0]
+            .iter()
+            .map(|&shift| {
+                let delta = i32::from((code >> shift) as u8) - i32::from((other >> shift) as u8);
+                delta * delta
+            })
+            .sum::<i32>()
+    };
+    TRACK_COLORS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, color)| distance(color))
+        .map(|(index, _)| index + 1)
+}
+
+fn import_cues<'a, 'input: 'a>(cue_nodes: impl Iterator<Item = Node<'a, 'input>>) -> Vec<Cue> {
+    let mut next_memory_slot_index: SlotIndex = 0;
+    let mut next_grid_slot_index: SlotIndex = 0;
+    let mut cues = Vec::new();
+    for node in cue_nodes {
+        let start = if let Some(start) = parse_attribute::<f64>(node, "START") {
+            start
+        } else {
+            log::warn!("Skipping cue without start");
+            continue;
+        };
+        // 0 = cue, 1 = fade-in, 2 = fade-out, 3 = load, 4 = grid, 5 = loop
+        let cue_type = parse_attribute::<u8>(node, "TYPE").unwrap_or_default();
+        let end = if cue_type == 5 {
+            parse_attribute::<f64>(node, "LEN")
+                .filter(|len| *len > 0.0)
+                .map(|len| start + len)
+        } else {
+            None
+        };
+        let hot_cue = parse_attribute::<i16>(node, "HOTCUE").unwrap_or(-1);
+        let (bank_index, slot_index) = if cue_type == 4 {
+            let slot_index = next_grid_slot_index;
+            next_grid_slot_index += 1;
+            (GRID_CUE_BANK_INDEX, slot_index)
+        } else if hot_cue >= 0 {
+            (HOT_CUE_BANK_INDEX, hot_cue)
+        } else {
+            let slot_index = next_memory_slot_index;
+            next_memory_slot_index += 1;
+            (MEMORY_CUE_BANK_INDEX, slot_index)
+        };
+        cues.push(Cue {
+            bank_index,
+            slot_index: Some(slot_index),
+            in_position: Some(PositionMs(start)),
+            out_position: end.map(PositionMs),
+            out_mode: end.map(|_| OutMode::Loop),
+            label: attribute(node, "NAME")
+                .filter(|name| *name != UNNAMED_CUE)
+                .map(ToOwned::to_owned),
+            color: None,
+            flags: CueFlags::empty(),
+        });
+    }
+    cues.canonicalize_into()
+}
+
+fn import_track(
+    node: Node<'_, '_>,
+    location: Node<'_, '_>,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+    collected_at: DateTime,
+) -> std::result::Result<Track, String> {
+    let volume = attribute(location, "VOLUME").unwrap_or_default();
+    let dir = attribute(location, "DIR").unwrap_or_default();
+    let file = attribute(location, "FILE").unwrap_or_default();
+    let key = primary_key(volume, dir, file);
+    let (url, path) = location_url_candidates(volume, dir, file)
+        .into_iter()
+        .find_map(|url| {
+            source_path_resolver
+                .resolve_path_from_url(&url)
+                .ok()
+                .map(|path| (url, path))
+        })
+        .ok_or_else(|| {
+            log::info!("Skipping track at {}", key);
+            key.clone()
+        })?;
+    let mime = guess_mime_from_path(url.path()).map_err(|err| {
+        log::info!("Skipping track at {}: {}", url, err);
+        key.clone()
+    })?;
+
+    let info = child_elements(node, "INFO").next();
+    let info_attribute = |name| info.and_then(|info| attribute(info, name));
+    let parse_info_attribute = |name| info.and_then(|info| parse_attribute::<f64>(info, name));
+
+    let audio_content = AudioContent {
+        duration: parse_info_attribute("PLAYTIME_FLOAT")
+            .or_else(|| parse_info_attribute("PLAYTIME"))
+            .filter(|secs| *secs > 0.0)
+            .map(|secs| DurationMs::from_inner(secs * 1000.0)),
+        bitrate: parse_info_attribute("BITRATE")
+            .filter(|bps| *bps > 0.0)
+            .map(BitrateBps::from_inner),
+        ..Default::default()
+    };
+    let media_source = Source {
+        collected_at: info_attribute("IMPORT_DATE")
+            .and_then(parse_date)
+            .map(date_time_from_date)
+            .unwrap_or(collected_at),
+        // The file needs to be synchronized
+        synchronized_at: None,
+        path,
+        content_type: mime.to_string(),
+        content_digest: None,
+        content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+        content: Content::Audio(audio_content),
+        artwork: Default::default(),
+    };
+    let mut track = Track::new_from_media_source(media_source);
+
+    // Track titles
+    let mut track_titles = Vec::with_capacity(2);
+    if let Some(name) = attribute(node, "TITLE") {
+        track_titles.push(Title {
+            name: name.to_owned(),
+            kind: TitleKind::Main,
+        });
+    }
+    if let Some(name) = info_attribute("MIX") {
+        track_titles.push(Title {
+            name: name.to_owned(),
+            kind: TitleKind::Sub,
+        });
+    }
+    track.titles = Canonical::tie(track_titles.canonicalize_into());
+
+    // Track actors
+    let mut track_actors = Vec::with_capacity(3);
+    for (role, name) in [
+        (ActorRole::Artist, attribute(node, "ARTIST")),
+        (ActorRole::Producer, info_attribute("PRODUCER")),
+        (ActorRole::Remixer, info_attribute("REMIXER")),
+    ]
+    .iter()
+    {
+        if let Some(name) = name {
+            push_next_actor_role_name(&mut track_actors, *role, (*name).to_owned());
+        }
+    }
+    track.actors = Canonical::tie(track_actors.canonicalize_into());
+
+    if let Some(album) = child_elements(node, "ALBUM").next() {
+        if let Some(album_title) = attribute(album, "TITLE") {
+            track.set_album_title(album_title);
+        }
+        track.indexes.track = Index {
+            number: parse_attribute::<u16>(album, "TRACK").filter(|number| *number > 0),
+            total: parse_attribute::<u16>(album, "OF_TRACKS").filter(|total| *total > 0),
+        };
+    }
+
+    track.release.released_at = info_attribute("RELEASE_DATE")
+        .and_then(parse_date)
+        .map(|date| DateOrDateTime::Date(DateYYYYMMDD::from(date)));
+    track.release.released_by = info_attribute("LABEL").map(ToOwned::to_owned);
+
+    track.metrics.tempo_bpm = child_elements(node, "TEMPO")
+        .next()
+        .and_then(|tempo| attribute(tempo, "BPM"))
+        .and_then(parse_tempo_bpm)
+        .filter(|tempo_bpm| tempo_bpm.0 > 0.0);
+    if let Some(key_code) = child_elements(node, "MUSICAL_KEY")
+        .next()
+        .and_then(|musical_key| parse_attribute::<usize>(musical_key, "VALUE"))
+        .and_then(|value| CHROMATIC_KEY_CODES.get(value))
+    {
+        track.metrics.key_signature = KeySignature::new(*key_code);
+    } else if let Some(key_signature) = info_attribute("KEY").and_then(parse_key_signature) {
+        track.metrics.key_signature = key_signature;
+    }
+    if parse_attribute::<u8>(node, "LOCK") == Some(1) {
+        track.metrics.flags |= MetricsFlags::TEMPO_BPM_LOCKED;
+    }
+
+    let mut tags_map = TagsMap::default();
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_COMMENT,
+        info_attribute("COMMENT"),
+    );
+    import_faceted_text_tag(
+        &mut tags_map,
+        faceted_tag_mapping,
+        &FACET_GENRE,
+        info_attribute("GENRE"),
+    );
+    if let Some(ranking) = info.and_then(|info| parse_attribute::<u8>(info, "RANKING")) {
+        import_rating(&mut tags_map, ranking);
+    }
+    track.tags = Canonical::tie(tags_map.into());
+
+    track.color = info
+        .and_then(|info| parse_attribute::<u8>(info, "COLOR"))
+        .and_then(parse_track_color);
+
+    track.play_counter.times_played = info.and_then(|info| parse_attribute(info, "PLAYCOUNT"));
+    track.play_counter.last_played_at = info_attribute("LAST_PLAYED")
+        .and_then(parse_date)
+        .map(date_time_from_date);
+
+    track.cues = Canonical::tie(import_cues(child_elements(node, "CUE_V2")));
+
+    Ok(track)
+}
+
+fn import_playlist_nodes(
+    parent: Node<'_, '_>,
+    folders: &mut Vec<String>,
+    playlists: &mut Vec<Playlist>,
+) {
+    for subnodes in child_elements(parent, "SUBNODES") {
+        for node in child_elements(subnodes, "NODE") {
+            let name = attribute(node, "NAME").unwrap_or_default().to_owned();
+            match attribute(node, "TYPE") {
+                Some("FOLDER") => {
+                    folders.push(name);
+                    import_playlist_nodes(node, folders, playlists);
+                    folders.pop();
+                }
+                Some("PLAYLIST") => {
+                    if name.starts_with('_') {
+                        log::debug!("Skipping system playlist {}", name);
+                        continue;
+                    }
+                    let track_ids = child_elements(node, "PLAYLIST")
+                        .flat_map(|playlist| child_elements(playlist, "ENTRY"))
+                        .filter_map(|entry| child_elements(entry, "PRIMARYKEY").next())
+                        .filter(|primary_key| attribute(*primary_key, "TYPE") == Some("TRACK"))
+                        .filter_map(|primary_key| attribute(primary_key, "KEY"))
+                        .map(ToOwned::to_owned)
+                        .collect();
+                    playlists.push(Playlist {
+                        folders: folders.clone(),
+                        name,
+                        track_ids,
+                    });
+                }
+                node_type => {
+                    // Smart lists are not supported
+                    log::debug!("Skipping playlist node of type {:?}", node_type);
+                }
+            }
+        }
+    }
+}
+
+/// Import the collection and the playlists from a Traktor
+/// `collection.nml` file
+///
+/// Tracks are referenced from playlists by their volume-relative
+/// location that is used as the track ID. The locations are resolved
+/// into paths of media sources by the given resolver. Tracks that
+/// could not be resolved are skipped and reported. Playlist folders
+/// are flattened, i.e. only their names are preserved.
+pub fn import_library(
+    xml: &str,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+    collected_at: DateTime,
+) -> Result<Library> {
+    let document =
+        Document::parse(xml).map_err(|err| anyhow!("Failed to parse Traktor NML: {}", err))?;
+    let root = document.root_element();
+    if !root.has_tag_name("NML") {
+        return Err(Error::Other(anyhow!(
+            "Unexpected root element {} in Traktor NML",
+            root.tag_name().name()
+        )));
+    }
+
+    let mut library = Library::default();
+    for collection in child_elements(root, "COLLECTION") {
+        for node in child_elements(collection, "ENTRY") {
+            let location = if let Some(location) = child_elements(node, "LOCATION").next() {
+                location
+            } else {
+                log::warn!("Skipping entry without location");
+                continue;
+            };
+            let track_id = primary_key(
+                attribute(location, "VOLUME").unwrap_or_default(),
+                attribute(location, "DIR").unwrap_or_default(),
+                attribute(location, "FILE").unwrap_or_default(),
+            );
+            match import_track(
+                node,
+                location,
+                faceted_tag_mapping,
+                source_path_resolver,
+                collected_at,
+            ) {
+                Ok(track) => library.tracks.push(CollectionTrack { track_id, track }),
+                Err(location) => library.not_imported.push(location),
+            }
+        }
+    }
+
+    let mut folders = Vec::new();
+    for playlists in child_elements(root, "PLAYLISTS") {
+        // The single top-level folder is named "$ROOT"
+        for root_folder in child_elements(playlists, "NODE") {
+            import_playlist_nodes(root_folder, &mut folders, &mut library.playlists);
+        }
+    }
+
+    Ok(library)
+}
+
+fn format_ms(position: PositionMs) -> String {
+    format!("{:.6}", position.0)
+}
+
+fn export_cue_attributes(cue: &Cue) -> Option<Attributes<'_>> {
+    let in_position = cue.in_position?;
+    let loop_end = cue
+        .out_position
+        .filter(|_| cue.out_mode == Some(OutMode::Loop));
+    // 0 = cue, 4 = grid, 5 = loop
+    let (cue_type, hot_cue) = match cue.bank_index {
+        GRID_CUE_BANK_INDEX => ("4", -1),
+        HOT_CUE_BANK_INDEX => (
+            if loop_end.is_some() { "5" } else { "0" },
+            cue.slot_index.unwrap_or(-1),
+        ),
+        _ => (if loop_end.is_some() { "5" } else { "0" }, -1),
+    };
+    let len = loop_end
+        .map(|end| PositionMs(end.0 - in_position.0))
+        .unwrap_or(PositionMs(0.0));
+    Some(vec![
+        ("NAME", cue.label.as_deref().unwrap_or(UNNAMED_CUE).into()),
+        ("DISPL_ORDER", "0".into()),
+        ("TYPE", cue_type.into()),
+        ("START", format_ms(in_position).into()),
+        ("LEN", format_ms(len).into()),
+        ("REPEATS", "-1".into()),
+        ("HOTCUE", hot_cue.to_string().into()),
+    ])
+}
+
+fn write_track_entry(
+    xml: &mut String,
+    track: &Track,
+    location: (&str, &str, &str),
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+) {
+    let (volume, dir, file) = location;
+
+    let mut entry_attributes: Attributes<'_> = Vec::with_capacity(3);
+    if let Some(title) = track.track_title() {
+        entry_attributes.push(("TITLE", title.into()));
+    }
+    if let Some(artist) = track.track_artist() {
+        entry_attributes.push(("ARTIST", artist.into()));
+    }
+    if track.metrics.flags.contains(MetricsFlags::TEMPO_BPM_LOCKED) {
+        entry_attributes.push(("LOCK", "1".into()));
+    }
+    write_open_element(xml, 2, "ENTRY", &entry_attributes);
+
+    write_empty_element(
+        xml,
+        3,
+        "LOCATION",
+        &[
+            ("DIR", dir.into()),
+            ("FILE", file.into()),
+            ("VOLUME", volume.into()),
+            ("VOLUMEID", volume.into()),
+        ],
+    );
+
+    let mut album_attributes: Attributes<'_> = Vec::with_capacity(3);
+    if let Some(number) = track.indexes.track.number {
+        album_attributes.push(("TRACK", number.to_string().into()));
+    }
+    if let Some(total) = track.indexes.track.total {
+        album_attributes.push(("OF_TRACKS", total.to_string().into()));
+    }
+    if let Some(album_title) = track.album_title() {
+        album_attributes.push(("TITLE", album_title.into()));
+    }
+    if !album_attributes.is_empty() {
+        write_empty_element(xml, 3, "ALBUM", &album_attributes);
+    }
+
+    let mut info_attributes: Attributes<'_> = Vec::with_capacity(16);
+    if let Content::Audio(audio_content) = &track.media_source.content {
+        if let Some(bitrate) = audio_content.bitrate {
+            info_attributes.push(("BITRATE", format!("{:.0}", bitrate.to_inner()).into()));
+        }
+    }
+    if let Some(genre) = export_faceted_text_tag(track, faceted_tag_mapping, &FACET_GENRE) {
+        info_attributes.push(("GENRE", genre.into()));
+    }
+    if let Some(label) = track.release.released_by.as_deref() {
+        info_attributes.push(("LABEL", label.into()));
+    }
+    if let Some(comment) = export_faceted_text_tag(track, faceted_tag_mapping, &FACET_COMMENT) {
+        info_attributes.push(("COMMENT", comment.into()));
+    }
+    if let Some(remixer) = Actors::main_actor(track.actors.iter(), ActorRole::Remixer) {
+        info_attributes.push(("REMIXER", remixer.name.as_str().into()));
+    }
+    if let Some(producer) = Actors::main_actor(track.actors.iter(), ActorRole::Producer) {
+        info_attributes.push(("PRODUCER", producer.name.as_str().into()));
+    }
+    if let Some(mix) = Titles::filter_kind(track.titles.iter(), TitleKind::Sub).next() {
+        info_attributes.push(("MIX", mix.name.as_str().into()));
+    }
+    if let Some(key) = format_key_signature(track.metrics.key_signature) {
+        info_attributes.push(("KEY", key.into()));
+    }
+    if let Some(times_played) = track.play_counter.times_played {
+        info_attributes.push(("PLAYCOUNT", times_played.to_string().into()));
+    }
+    if let Content::Audio(audio_content) = &track.media_source.content {
+        if let Some(duration) = audio_content.duration {
+            let secs = duration.to_inner() / 1000.0;
+            info_attributes.push(("PLAYTIME", format!("{:.0}", secs).into()));
+            info_attributes.push(("PLAYTIME_FLOAT", format!("{:.6}", secs).into()));
+        }
+    }
+    info_attributes.push((
+        "IMPORT_DATE",
+        format_date(track.media_source.collected_at.naive_date()).into(),
+    ));
+    if let Some(last_played_at) = track.play_counter.last_played_at {
+        info_attributes.push((
+            "LAST_PLAYED",
+            format_date(last_played_at.naive_date()).into(),
+        ));
+    }
+    if let Some(released_at) = track.release.released_at {
+        let date = DateYYYYMMDD::from(released_at);
+        let month = date.month().max(1);
+        let day = date.day_of_month().max(1);
+        info_attributes.push((
+            "RELEASE_DATE",
+            format!("{}/{}/{}", date.year(), month, day).into(),
+        ));
+    }
+    if let Some(color_index) = track.color.and_then(format_track_color) {
+        info_attributes.push(("COLOR", color_index.to_string().into()));
+    }
+    if let Some(rating) = export_rating(track) {
+        info_attributes.push(("RANKING", rating.to_string().into()));
+    }
+    write_empty_element(xml, 3, "INFO", &info_attributes);
+
+    if let Some(tempo_bpm) = track.metrics.tempo_bpm {
+        write_empty_element(
+            xml,
+            3,
+            "TEMPO",
+            &[
+                ("BPM", format!("{:.6}", tempo_bpm.0).into()),
+                ("BPM_QUALITY", "100.000000".into()),
+            ],
+        );
+    }
+
+    if let Some(value) = CHROMATIC_KEY_CODES
+        .iter()
+        .position(|key_code| *key_code == track.metrics.key_signature.code())
+    {
+        write_empty_element(
+            xml,
+            3,
+            "MUSICAL_KEY",
+            &[("VALUE", value.to_string().into())],
+        );
+    }
+
+    for attributes in track.cues.iter().filter_map(export_cue_attributes) {
+        write_empty_element(xml, 3, "CUE_V2", &attributes);
+    }
+
+    write_close_element(xml, 2, "ENTRY");
+}
+
+fn write_playlist_nodes(
+    xml: &mut String,
+    depth: usize,
+    nodes: &[PlaylistNode<'_>],
+    primary_keys: &HashMap<&str, String>,
+) {
+    write_open_element(
+        xml,
+        depth,
+        "SUBNODES",
+        &[("COUNT", nodes.len().to_string().into())],
+    );
+    for node in nodes {
+        match node {
+            PlaylistNode::Folder { name, children } => {
+                write_open_element(
+                    xml,
+                    depth + 1,
+                    "NODE",
+                    &[("TYPE", "FOLDER".into()), ("NAME", Cow::Borrowed(*name))],
+                );
+                write_playlist_nodes(xml, depth + 2, children, primary_keys);
+                write_close_element(xml, depth + 1, "NODE");
+            }
+            PlaylistNode::Playlist(playlist) => {
+                write_open_element(
+                    xml,
+                    depth + 1,
+                    "NODE",
+                    &[
+                        ("TYPE", "PLAYLIST".into()),
+                        ("NAME", playlist.name.as_str().into()),
+                    ],
+                );
+                let keys: Vec<_> = playlist
+                    .track_ids
+                    .iter()
+                    .filter_map(|track_id| primary_keys.get(track_id.as_str()))
+                    .collect();
+                write_open_element(
+                    xml,
+                    depth + 2,
+                    "PLAYLIST",
+                    &[
+                        ("ENTRIES", keys.len().to_string().into()),
+                        ("TYPE", "LIST".into()),
+                    ],
+                );
+                for key in keys {
+                    write_open_element(xml, depth + 3, "ENTRY", &[]);
+                    write_empty_element(
+                        xml,
+                        depth + 4,
+                        "PRIMARYKEY",
+                        &[("TYPE", "TRACK".into()), ("KEY", key.as_str().into())],
+                    );
+                    write_close_element(xml, depth + 3, "ENTRY");
+                }
+                write_close_element(xml, depth + 2, "PLAYLIST");
+                write_close_element(xml, depth + 1, "NODE");
+            }
+        }
+    }
+    write_close_element(xml, depth, "SUBNODES");
+}
+
+/// Export tracks and playlists into the format of a Traktor
+/// `collection.nml` file
+///
+/// The paths of media sources are resolved into file URLs by the given
+/// resolver and then split into volume-relative locations. Files that
+/// are not located on a drive (Windows) or below `/Volumes` (macOS) are
+/// considered to reside on the given root volume. This is the inverse
+/// of [`import_library`].
+pub fn export_library(
+    tracks: &[CollectionTrack],
+    playlists: &[Playlist],
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    source_path_resolver: &impl SourcePathResolver,
+    root_volume: &str,
+) -> Result<String> {
+    let mut xml = String::with_capacity(1024 * (1 + tracks.len()));
+    xml.push_str(DECLARATION);
+    write_open_element(&mut xml, 0, "NML", &[("VERSION", "19".into())]);
+    write_empty_element(
+        &mut xml,
+        1,
+        "HEAD",
+        &[("COMPANY", "".into()), ("PROGRAM", "aoide".into())],
+    );
+
+    write_open_element(
+        &mut xml,
+        1,
+        "COLLECTION",
+        &[("ENTRIES", tracks.len().to_string().into())],
+    );
+    let mut primary_keys = HashMap::with_capacity(tracks.len());
+    for CollectionTrack { track_id, track } in tracks {
+        let url = source_path_resolver
+            .resolve_url_from_path(&track.media_source.path)
+            .map_err(|err| {
+                anyhow!(
+                    "Failed to resolve location of {}: {:?}",
+                    track.media_source.path,
+                    err
+                )
+            })?;
+        let (volume, dir, file) = split_location(&url, root_volume)
+            .ok_or_else(|| anyhow!("Unsupported location {}", url))?;
+        write_track_entry(&mut xml, track, (&volume, &dir, &file), faceted_tag_mapping);
+        primary_keys.insert(track_id.as_str(), primary_key(&volume, &dir, &file));
+    }
+    write_close_element(&mut xml, 1, "COLLECTION");
+
+    let nodes = build_playlist_tree(playlists);
+    write_open_element(&mut xml, 1, "PLAYLISTS", &[]);
+    write_open_element(
+        &mut xml,
+        2,
+        "NODE",
+        &[("TYPE", "FOLDER".into()), ("NAME", "$ROOT".into())],
+    );
+    write_playlist_nodes(&mut xml, 3, &nodes, &primary_keys);
+    write_close_element(&mut xml, 2, "NODE");
+    write_close_element(&mut xml, 1, "PLAYLISTS");
+
+    write_close_element(&mut xml, 0, "NML");
+    Ok(xml)
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,210 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::library::tests::{collected_at, import_test_library, resolver};
+
+use aoide_core::{
+    music::{key::KeyCode, time::TempoBpm},
+    tag::Score as TagScore,
+    track::tag::LABEL_RATING,
+};
+
+const NML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="no" ?>
+<NML VERSION="19">
+  <HEAD COMPANY="www.native-instruments.com" PROGRAM="Traktor"></HEAD>
+  <COLLECTION ENTRIES="2">
+    <ENTRY TITLE="Track Title" ARTIST="Track Artist" LOCK="1">
+      <LOCATION DIR="/:music/:Artist/:" FILE="Track Title.mp3" VOLUME="Macintosh HD" VOLUMEID="Macintosh HD"></LOCATION>
+      <ALBUM TRACK="3" OF_TRACKS="12" TITLE="Album Title"></ALBUM>
+      <INFO BITRATE="320000" GENRE="House" LABEL="Label" COMMENT="Comment" REMIXER="Remixer"
+        MIX="Extended Mix" KEY="Am" PLAYCOUNT="7" PLAYTIME="300" PLAYTIME_FLOAT="300.000000"
+        IMPORT_DATE="2020/1/31" LAST_PLAYED="2020/2/1" RELEASE_DATE="2019/5/17" COLOR="4" RANKING="204"></INFO>
+      <TEMPO BPM="123.450000" BPM_QUALITY="100.000000"></TEMPO>
+      <MUSICAL_KEY VALUE="21"></MUSICAL_KEY>
+      <CUE_V2 NAME="AutoGrid" DISPL_ORDER="0" TYPE="4" START="25.000000" LEN="0.000000" REPEATS="-1" HOTCUE="-1"></CUE_V2>
+      <CUE_V2 NAME="Drop" DISPL_ORDER="0" TYPE="0" START="60500.000000" LEN="0.000000" REPEATS="-1" HOTCUE="2"></CUE_V2>
+      <CUE_V2 NAME="n.n." DISPL_ORDER="0" TYPE="5" START="10000.000000" LEN="7500.000000" REPEATS="-1" HOTCUE="-1"></CUE_V2>
+    </ENTRY>
+    <ENTRY TITLE="Outside">
+      <LOCATION DIR="/:other/:" FILE="Outside.mp3" VOLUME="External" VOLUMEID="External"></LOCATION>
+    </ENTRY>
+  </COLLECTION>
+  <PLAYLISTS>
+    <NODE TYPE="FOLDER" NAME="$ROOT">
+      <SUBNODES COUNT="3">
+        <NODE TYPE="PLAYLIST" NAME="_LOOPS">
+          <PLAYLIST ENTRIES="0" TYPE="LIST" UUID="1"></PLAYLIST>
+        </NODE>
+        <NODE TYPE="FOLDER" NAME="Folder">
+          <SUBNODES COUNT="1">
+            <NODE TYPE="PLAYLIST" NAME="List">
+              <PLAYLIST ENTRIES="2" TYPE="LIST" UUID="2">
+                <ENTRY><PRIMARYKEY TYPE="TRACK" KEY="Macintosh HD/:music/:Artist/:Track Title.mp3"></PRIMARYKEY></ENTRY>
+                <ENTRY><PRIMARYKEY TYPE="TRACK" KEY="External/:other/:Outside.mp3"></PRIMARYKEY></ENTRY>
+              </PLAYLIST>
+            </NODE>
+          </SUBNODES>
+        </NODE>
+        <NODE TYPE="SMARTLIST" NAME="Smart">
+          <SMARTLIST UUID="3"></SMARTLIST>
+        </NODE>
+      </SUBNODES>
+    </NODE>
+  </PLAYLISTS>
+</NML>
+"#;
+
+const TRACK_ID: &str = "Macintosh HD/:music/:Artist/:Track Title.mp3";
+
+#[test]
+fn import_tracks() {
+    let library = import_test_library(import_library, NML);
+    assert_eq!(1, library.tracks.len());
+    assert_eq!(
+        vec!["External/:other/:Outside.mp3".to_owned()],
+        library.not_imported
+    );
+
+    let CollectionTrack { track_id, track } = &library.tracks[0];
+    assert_eq!(TRACK_ID, track_id);
+    assert_eq!("Artist/Track Title.mp3", track.media_source.path.as_str());
+    assert_eq!("audio/mpeg", track.media_source.content_type);
+    assert_eq!(Some("Track Title"), track.track_title());
+    assert_eq!(Some("Track Artist"), track.track_artist());
+    assert_eq!(Some("Album Title"), track.album_title());
+    assert_eq!(Some(3), track.indexes.track.number);
+    assert_eq!(Some(12), track.indexes.track.total);
+    assert_eq!(Some("Label"), track.release.released_by.as_deref());
+    assert_eq!(
+        Some(DateOrDateTime::Date(DateYYYYMMDD::new(20190517))),
+        track.release.released_at
+    );
+    assert_eq!(Some(TempoBpm(123.45)), track.metrics.tempo_bpm);
+    assert_eq!(KeyCode::Amin, track.metrics.key_signature.code());
+    assert!(track.metrics.flags.contains(MetricsFlags::TEMPO_BPM_LOCKED));
+    assert_eq!(Some(Color::Rgb(RgbColor(0x00FF00))), track.color);
+    assert_eq!(Some(7), track.play_counter.times_played);
+    assert!(track.play_counter.last_played_at.is_some());
+    let rating = track
+        .tags
+        .plain
+        .iter()
+        .find(|tag| tag.label.as_ref() == Some(&*LABEL_RATING))
+        .unwrap();
+    assert_eq!(TagScore::clamp_from(204.0 / 255.0), rating.score);
+}
+
+#[test]
+fn import_hot_cues_loops_and_grid_anchors() {
+    let library = import_test_library(import_library, NML);
+    let cues = &library.tracks[0].track.cues;
+    assert_eq!(3, cues.len());
+
+    let hot_cue = &cues[0];
+    assert_eq!(HOT_CUE_BANK_INDEX, hot_cue.bank_index);
+    assert_eq!(Some(2), hot_cue.slot_index);
+    assert_eq!(Some(PositionMs(60_500.0)), hot_cue.in_position);
+    assert_eq!(Some("Drop"), hot_cue.label.as_deref());
+
+    let stored_loop = &cues[1];
+    assert_eq!(MEMORY_CUE_BANK_INDEX, stored_loop.bank_index);
+    assert_eq!(Some(PositionMs(10_000.0)), stored_loop.in_position);
+    assert_eq!(Some(PositionMs(17_500.0)), stored_loop.out_position);
+    assert_eq!(Some(OutMode::Loop), stored_loop.out_mode);
+    assert!(stored_loop.label.is_none());
+
+    let grid_anchor = &cues[2];
+    assert_eq!(GRID_CUE_BANK_INDEX, grid_anchor.bank_index);
+    assert_eq!(Some(PositionMs(25.0)), grid_anchor.in_position);
+}
+
+#[test]
+fn import_playlists_without_system_and_smart_lists() {
+    let library = import_test_library(import_library, NML);
+    assert_eq!(
+        vec![Playlist {
+            folders: vec!["Folder".to_owned()],
+            name: "List".to_owned(),
+            track_ids: vec![
+                TRACK_ID.to_owned(),
+                "External/:other/:Outside.mp3".to_owned()
+            ],
+        }],
+        library.playlists
+    );
+}
+
+#[test]
+fn split_locations_of_volumes() {
+    assert_eq!(
+        Some((
+            Cow::Borrowed("Macintosh HD"),
+            "/:Users/:me/:".to_owned(),
+            "a b.mp3".to_owned()
+        )),
+        split_location(
+            &Url::parse("file:///Users/me/a%20b.mp3").unwrap(),
+            "Macintosh HD"
+        )
+    );
+    assert_eq!(
+        Some((
+            Cow::Owned("External".to_owned()),
+            "/:Music/:".to_owned(),
+            "a.mp3".to_owned()
+        )),
+        split_location(
+            &Url::parse("file:///Volumes/External/Music/a.mp3").unwrap(),
+            "Macintosh HD"
+        )
+    );
+    assert_eq!(
+        Some((
+            Cow::Owned("C:".to_owned()),
+            "/:Music/:".to_owned(),
+            "a.mp3".to_owned()
+        )),
+        split_location(&Url::parse("file:///C:/Music/a.mp3").unwrap(), "")
+    );
+    assert_eq!(
+        vec![Url::parse("file:///C:/Music/a.mp3").unwrap()],
+        location_url_candidates("C:", "/:Music/:", "a.mp3")
+    );
+}
+
+#[test]
+fn export_and_reimport_library() {
+    let library = import_test_library(import_library, NML);
+    let xml = export_library(
+        &library.tracks,
+        &library.playlists,
+        &Default::default(),
+        &resolver(),
+        "Macintosh HD",
+    )
+    .unwrap();
+    assert!(xml.contains(r#"DIR="/:music/:Artist/:" FILE="Track Title.mp3" VOLUME="Macintosh HD""#));
+    assert!(xml.contains(r#"<MUSICAL_KEY VALUE="21"/>"#));
+
+    let reimported =
+        import_library(&xml, &Default::default(), &resolver(), collected_at()).unwrap();
+    assert_eq!(library.tracks, reimported.tracks);
+    // Entries of tracks that are not exported are dropped
+    assert_eq!(vec![TRACK_ID.to_owned()], reimported.playlists[0].track_ids);
+}
@@ -0,0 +1,146 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use roxmltree::Node;
+use std::borrow::Cow;
+
+///////////////////////////////////////////////////////////////////////
+// Reading
+///////////////////////////////////////////////////////////////////////
+
+/// The trimmed value of an attribute, `None` if missing or empty
+pub(crate) fn attribute<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.attribute(name)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+}
+
+pub(crate) fn parse_attribute<T: std::str::FromStr>(node: Node<'_, '_>, name: &str) -> Option<T> {
+    attribute(node, name).and_then(|value| {
+        value
+            .parse()
+            .map_err(|_| {
+                log::warn!("Invalid value for attribute {}: {}", name, value);
+            })
+            .ok()
+    })
+}
+
+pub(crate) fn child_elements<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag_name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.is_element() && child.has_tag_name(tag_name))
+}
+
+///////////////////////////////////////////////////////////////////////
+// Writing
+///////////////////////////////////////////////////////////////////////
+
+pub(crate) const DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+
+pub(crate) fn escape_attribute_value(value: &str) -> Cow<'_, str> {
+    if !value
+        .chars()
+        .any(|c| matches!(c, '&' | '<' | '>' | '"' | '\'' | '\n' | '\r' | '\t'))
+    {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len() + 8);
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+pub(crate) type Attributes<'a> = Vec<(&'static str, Cow<'a, str>)>;
+
+fn write_start_tag(
+    xml: &mut String,
+    depth: usize,
+    name: &str,
+    attributes: &[(&str, Cow<'_, str>)],
+) {
+    for _ in 0..depth {
+        xml.push_str("  ");
+    }
+    xml.push('<');
+    xml.push_str(name);
+    for (name, value) in attributes {
+        xml.push(' ');
+        xml.push_str(name);
+        xml.push_str("=\"");
+        xml.push_str(&escape_attribute_value(value));
+        xml.push('"');
+    }
+}
+
+pub(crate) fn write_empty_element(
+    xml: &mut String,
+    depth: usize,
+    name: &str,
+    attributes: &[(&str, Cow<'_, str>)],
+) {
+    write_start_tag(xml, depth, name, attributes);
+    xml.push_str("/>\n");
+}
+
+pub(crate) fn write_open_element(
+    xml: &mut String,
+    depth: usize,
+    name: &str,
+    attributes: &[(&str, Cow<'_, str>)],
+) {
+    write_start_tag(xml, depth, name, attributes);
+    xml.push_str(">\n");
+}
+
+pub(crate) fn write_close_element(xml: &mut String, depth: usize, name: &str) {
+    for _ in 0..depth {
+        xml.push_str("  ");
+    }
+    xml.push_str("</");
+    xml.push_str(name);
+    xml.push_str(">\n");
+}
+
+/// A single line element with text content, e.g. `<title>Text</title>`
+pub(crate) fn write_text_element(
+    xml: &mut String,
+    depth: usize,
+    name: &str,
+    attributes: &[(&str, Cow<'_, str>)],
+    text: &str,
+) {
+    write_start_tag(xml, depth, name, attributes);
+    xml.push('>');
+    xml.push_str(&escape_attribute_value(text));
+    xml.push_str("</");
+    xml.push_str(name);
+    xml.push_str(">\n");
+}
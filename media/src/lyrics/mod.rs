@@ -0,0 +1,211 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use aoide_core::{
+    audio::PositionMs,
+    track::lyrics::{Lyrics, SyncedLine},
+    util::{Canonical, CanonicalizeInto as _},
+};
+
+///////////////////////////////////////////////////////////////////////
+
+/// The file extension of sidecar files with time-synchronized lyrics
+pub const LRC_FILE_EXTENSION: &str = "lrc";
+
+/// Normalize a language code
+///
+/// ID3v2 frames use the placeholder "XXX" (or NUL characters) if the
+/// language is unknown. ISO 639-2 defines "und" for an undetermined
+/// language. All of them are mapped to `None`.
+pub fn normalize_language(language: &str) -> Option<String> {
+    let language = language.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    if language.is_empty()
+        || language.eq_ignore_ascii_case("xxx")
+        || language.eq_ignore_ascii_case("und")
+    {
+        return None;
+    }
+    Some(language.to_ascii_lowercase())
+}
+
+/// Parse a time tag `mm:ss`, `mm:ss.xx`, or `mm:ss:xx`
+fn parse_time_tag(input: &str) -> Option<f64> {
+    let (minutes, rest) = input.split_once(':')?;
+    let (seconds, fraction) = match rest.find(|c: char| c == '.' || c == ':') {
+        Some(index) => (&rest[..index], Some(&rest[index + 1..])),
+        None => (rest, None),
+    };
+    if minutes.is_empty()
+        || seconds.len() != 2
+        || !minutes
+            .bytes()
+            .chain(seconds.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let minutes = minutes.parse::<u32>().ok()?;
+    let seconds = seconds.parse::<u32>().ok()?;
+    if seconds >= 60 {
+        return None;
+    }
+    let mut millis = (f64::from(minutes) * 60.0 + f64::from(seconds)) * 1000.0;
+    if let Some(fraction) = fraction {
+        if fraction.is_empty()
+            || fraction.len() > 3
+            || !fraction.bytes().all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+        millis += fraction.parse::<f64>().ok()? * 1000.0 / 10f64.powi(fraction.len() as i32);
+    }
+    Some(millis)
+}
+
+/// Parse time-synchronized lyrics in the LRC format
+///
+/// Each line may start with one or more time tags `[mm:ss.xx]`.
+/// The ID tags `[la:...]` (language) and `[offset:...]` are respected,
+/// all other ID tags are ignored. Lines without a time tag are ignored.
+///
+/// Returns `None` if the input does not contain any time-synchronized
+/// lines.
+pub fn parse_lrc(input: &str) -> Option<Lyrics> {
+    let mut language = None;
+    let mut offset_ms = 0.0;
+    let mut lines = Vec::new();
+    for line in input.trim_start_matches('\u{feff}').lines() {
+        let mut rest = line.trim();
+        let mut positions = Vec::new();
+        while let Some(tag) = rest.strip_prefix('[') {
+            let (tag, tail) = if let Some(split) = tag.split_once(']') {
+                split
+            } else {
+                break;
+            };
+            if let Some(millis) = parse_time_tag(tag.trim()) {
+                positions.push(millis);
+                rest = tail.trim_start();
+                continue;
+            }
+            if positions.is_empty() {
+                if let Some((key, value)) = tag.split_once(':') {
+                    match key.trim().to_ascii_lowercase().as_str() {
+                        "la" | "lang" | "language" => {
+                            language = normalize_language(value);
+                        }
+                        "offset" => {
+                            if let Ok(offset) = value.trim().parse::<f64>() {
+                                offset_ms = offset;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            break;
+        }
+        if positions.is_empty() {
+            continue;
+        }
+        let text = rest.trim();
+        lines.extend(positions.into_iter().map(|millis| (millis, text)));
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    // A positive offset shifts the lines up, i.e. they appear earlier
+    let lines = lines
+        .into_iter()
+        .map(|(millis, text)| SyncedLine {
+            position: PositionMs((millis - offset_ms).max(0.0)),
+            text: text.to_owned(),
+        })
+        .collect();
+    Some(
+        Lyrics {
+            language,
+            text: None,
+            lines,
+        }
+        .canonicalize_into(),
+    )
+}
+
+/// Import lyrics from a text that is either formatted as LRC or
+/// contains the unsynchronized plain text
+///
+/// The language of LRC lyrics takes precedence over the given
+/// language.
+pub fn import_lyrics_text(language: Option<String>, input: &str) -> Option<Lyrics> {
+    if let Some(mut lyrics) = parse_lrc(input) {
+        if lyrics.language.is_none() {
+            lyrics.language = language;
+        }
+        return Some(lyrics);
+    }
+    let text = input.trim();
+    if text.is_empty() {
+        return None;
+    }
+    Some(Lyrics {
+        language,
+        text: Some(text.to_owned()),
+        lines: Vec::new(),
+    })
+}
+
+/// Merge lyrics with the same language
+///
+/// Both the unsynchronized text and the synchronized lines are only
+/// imported from the first source that provides them, e.g. an ID3v2
+/// USLT and SYLT frame with the same language are merged.
+pub fn merge_lyrics(all: &mut Vec<Lyrics>, next: Lyrics) {
+    let Lyrics {
+        language,
+        text,
+        lines,
+    } = next;
+    if let Some(existing) = all.iter_mut().find(|lyrics| lyrics.language == language) {
+        if existing.text.is_none() {
+            existing.text = text;
+        }
+        if existing.lines.is_empty() {
+            existing.lines = lines;
+        }
+    } else {
+        all.push(Lyrics {
+            language,
+            text,
+            lines,
+        });
+    }
+}
+
+/// Collect and merge lyrics into their canonical representation
+pub fn collect_lyrics(lyrics: impl IntoIterator<Item = Lyrics>) -> Canonical<Vec<Lyrics>> {
+    let mut all = Vec::new();
+    for next in lyrics {
+        if !next.is_empty() {
+            merge_lyrics(&mut all, next);
+        }
+    }
+    Canonical::tie(all.canonicalize_into())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,112 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+#[test]
+fn parse_lrc_with_multiple_time_tags_and_offset() {
+    let input = "\u{feff}[ar:Artist]\r\n\
+        [la:ENG]\r\n\
+        [offset:+500]\r\n\
+        [00:12.00]First line\r\n\
+        [00:17.20][01:02.5]Chorus\r\n\
+        Ignored line without time tag\r\n\
+        [01:00:50]Before the second chorus\r\n";
+    let lyrics = parse_lrc(input).unwrap();
+    assert_eq!(Some("eng"), lyrics.language.as_deref());
+    assert!(lyrics.text.is_none());
+    assert_eq!(
+        vec![
+            SyncedLine {
+                position: PositionMs(11_500.0),
+                text: "First line".into(),
+            },
+            SyncedLine {
+                position: PositionMs(16_700.0),
+                text: "Chorus".into(),
+            },
+            SyncedLine {
+                position: PositionMs(60_000.0),
+                text: "Before the second chorus".into(),
+            },
+            SyncedLine {
+                position: PositionMs(62_000.0),
+                text: "Chorus".into(),
+            },
+        ],
+        lyrics.lines
+    );
+}
+
+#[test]
+fn parse_time_tag_with_huge_minutes() {
+    assert_eq!(
+        Some(f64::from(u32::MAX) * 60_000.0 + 59_000.0),
+        parse_time_tag("4294967295:59")
+    );
+}
+
+#[test]
+fn parse_lrc_without_time_tags() {
+    assert!(parse_lrc("[ti:Title]\nJust some [plain] text").is_none());
+}
+
+#[test]
+fn import_lyrics_text_plain() {
+    let lyrics = import_lyrics_text(Some("deu".into()), "  Erste Zeile\nZweite Zeile\n").unwrap();
+    assert_eq!(Some("deu"), lyrics.language.as_deref());
+    assert_eq!(Some("Erste Zeile\nZweite Zeile"), lyrics.text.as_deref());
+    assert!(lyrics.lines.is_empty());
+    assert!(import_lyrics_text(None, " \n ").is_none());
+}
+
+#[test]
+fn normalize_unknown_language() {
+    assert_eq!(None, normalize_language("XXX"));
+    assert_eq!(None, normalize_language("\0\0\0"));
+    assert_eq!(None, normalize_language("und"));
+    assert_eq!(Some("eng".to_owned()), normalize_language("Eng"));
+}
+
+#[test]
+fn collect_lyrics_merges_same_language() {
+    let lyrics = collect_lyrics(vec![
+        Lyrics {
+            language: Some("eng".into()),
+            text: Some("plain".into()),
+            lines: vec![],
+        },
+        Lyrics {
+            language: None,
+            text: Some("unknown".into()),
+            lines: vec![],
+        },
+        Lyrics {
+            language: Some("eng".into()),
+            text: None,
+            lines: vec![SyncedLine {
+                position: PositionMs(0.0),
+                text: "synced".into(),
+            }],
+        },
+    ]);
+    assert_eq!(2, lyrics.len());
+    assert_eq!(None, lyrics[0].language);
+    assert_eq!(Some("eng"), lyrics[1].language.as_deref());
+    assert_eq!(Some("plain"), lyrics[1].text.as_deref());
+    assert_eq!(1, lyrics[1].lines.len());
+}
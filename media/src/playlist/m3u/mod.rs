@@ -0,0 +1,104 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{duration_from_secs, duration_secs, separator_comment, single_line, Item};
+
+use aoide_core::audio::DurationMs;
+
+///////////////////////////////////////////////////////////////////////
+
+const HEADER: &str = "#EXTM3U";
+
+const EXTINF_PREFIX: &str = "#EXTINF:";
+
+/// Parse `<seconds> [<attributes>],<title>`
+fn parse_extinf(input: &str) -> (Option<DurationMs>, Option<String>) {
+    let (head, title) = input.split_once(',').unwrap_or((input, ""));
+    let duration = head.split_whitespace().next().and_then(duration_from_secs);
+    let title = Some(title.trim())
+        .filter(|title| !title.is_empty())
+        .map(ToOwned::to_owned);
+    (duration, title)
+}
+
+/// Import all tracks from an extended M3U or M3U8 playlist
+///
+/// The duration and title of the preceding `#EXTINF` directive
+/// are assigned to each track. All other directives and comments
+/// are ignored.
+pub fn import_items(input: &str) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut extinf = None;
+    for line in input.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(extinf_line) = line.strip_prefix(EXTINF_PREFIX) {
+            extinf = Some(parse_extinf(extinf_line));
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        let (duration, title) = extinf.take().unwrap_or_default();
+        items.push(Item::Track {
+            location: line.to_owned(),
+            title,
+            duration,
+        });
+    }
+    items
+}
+
+/// Export an extended M3U playlist, encoded as UTF-8 (M3U8)
+pub fn export_items(items: &[Item]) -> String {
+    let mut m3u = String::new();
+    m3u.push_str(HEADER);
+    m3u.push('\n');
+    for item in items {
+        match item {
+            Item::Track {
+                location,
+                title,
+                duration,
+            } => {
+                if title.is_some() || duration.is_some() {
+                    m3u.push_str(&format!(
+                        "{}{},{}\n",
+                        EXTINF_PREFIX,
+                        duration_secs(*duration),
+                        title.as_deref().map(single_line).unwrap_or_default()
+                    ));
+                }
+                m3u.push_str(location);
+                m3u.push('\n');
+            }
+            Item::Separator { title } => {
+                m3u.push_str(&format!("# {}\n", separator_comment(title.as_deref())));
+            }
+        }
+    }
+    m3u
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
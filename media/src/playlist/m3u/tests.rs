@@ -0,0 +1,113 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::playlist::resolve_location_url;
+
+use std::path::Path;
+
+const M3U: &str = "\u{feff}#EXTM3U
+#EXTINF:300,Artist - Title
+Artist/Title.mp3
+
+#PLAYLIST:Ignored
+# A comment
+#EXTINF:-1 tvg-logo=\"logo.png\",Stream
+http://radio.example.com/stream
+/music/Other.flac
+";
+
+#[test]
+fn import_extended_m3u() {
+    assert_eq!(
+        vec![
+            Item::Track {
+                location: "Artist/Title.mp3".to_owned(),
+                title: Some("Artist - Title".to_owned()),
+                duration: Some(DurationMs::from_inner(300_000.0)),
+            },
+            Item::Track {
+                location: "http://radio.example.com/stream".to_owned(),
+                title: Some("Stream".to_owned()),
+                duration: None,
+            },
+            Item::Track {
+                location: "/music/Other.flac".to_owned(),
+                title: None,
+                duration: None,
+            },
+        ],
+        import_items(M3U)
+    );
+}
+
+#[test]
+fn export_with_separator_comments_and_reimport() {
+    let items = vec![
+        Item::Track {
+            location: "/music/Artist/Title.mp3".to_owned(),
+            title: Some("Artist - Title".to_owned()),
+            duration: Some(DurationMs::from_inner(299_600.0)),
+        },
+        Item::Separator {
+            title: Some("Break".to_owned()),
+        },
+        Item::Track {
+            location: "/music/Other.flac".to_owned(),
+            title: None,
+            duration: None,
+        },
+    ];
+    let m3u = export_items(&items);
+    assert_eq!(
+        "#EXTM3U
+#EXTINF:300,Artist - Title
+/music/Artist/Title.mp3
+# --- Break ---
+/music/Other.flac
+",
+        m3u
+    );
+    let reimported = import_items(&m3u);
+    assert_eq!(2, reimported.len());
+    assert_eq!(items[2], reimported[1]);
+}
+
+#[test]
+fn resolve_relative_and_absolute_locations() {
+    let base_dir = Path::new("/music/Playlists");
+    assert_eq!(
+        "file:///music/Artist/Title.mp3",
+        resolve_location_url("../Artist/Title.mp3", Some(base_dir))
+            .unwrap()
+            .as_str()
+    );
+    assert_eq!(
+        "file:///music/Other%20Track.flac",
+        resolve_location_url("/music/Other Track.flac", None)
+            .unwrap()
+            .as_str()
+    );
+    assert_eq!(
+        "file:///music/Title.mp3",
+        resolve_location_url("file:///music/Title.mp3", None)
+            .unwrap()
+            .as_str()
+    );
+    assert!(resolve_location_url("Title.mp3", None).is_none());
+}
@@ -0,0 +1,137 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::Result;
+
+use aoide_core::audio::DurationMs;
+
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+pub mod m3u;
+pub mod pls;
+pub mod xspf;
+
+/// An item of a playlist file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    /// A track that is referenced by a file path or URL
+    Track {
+        location: String,
+        title: Option<String>,
+        duration: Option<DurationMs>,
+    },
+
+    /// Separators are exported as comments and skipped on import
+    Separator { title: Option<String> },
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FileFormat {
+    /// Extended M3U, including the UTF-8 variant M3U8
+    M3u,
+
+    Pls,
+}
+
+impl FileFormat {
+    /// Detect the format by the file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "m3u" | "m3u8" => Some(Self::M3u),
+            "pls" => Some(Self::Pls),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::M3u => "audio/x-mpegurl",
+            Self::Pls => "audio/x-scpls",
+        }
+    }
+
+    pub fn import_items(self, input: &str) -> Result<Vec<Item>> {
+        match self {
+            Self::M3u => Ok(m3u::import_items(input)),
+            Self::Pls => pls::import_items(input),
+        }
+    }
+
+    pub fn export_items(self, items: &[Item]) -> String {
+        match self {
+            Self::M3u => m3u::export_items(items),
+            Self::Pls => pls::export_items(items),
+        }
+    }
+}
+
+/// Resolve the location of an item into a URL
+///
+/// Locations are either URLs or file paths. Relative file paths
+/// are resolved against the directory of the playlist file.
+pub fn resolve_location_url(location: &str, base_dir: Option<&Path>) -> Option<Url> {
+    if let Ok(url) = Url::parse(location) {
+        // Single letter schemes are drive letters on Windows
+        if url.scheme().len() > 1 {
+            return Some(url);
+        }
+    }
+    let path = Path::new(location);
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir?.join(path)
+    };
+    let mut normalized = PathBuf::with_capacity(path.as_os_str().len());
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    Url::from_file_path(normalized).ok()
+}
+
+/// Titles and comments must not span multiple lines
+fn single_line(text: &str) -> String {
+    text.lines().map(str::trim).collect::<Vec<_>>().join(" ")
+}
+
+fn duration_secs(duration: Option<DurationMs>) -> i64 {
+    duration.map_or(-1, |duration| (duration.to_inner() / 1000.0).round() as i64)
+}
+
+fn duration_from_secs(secs: &str) -> Option<DurationMs> {
+    secs.trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| *secs > 0.0)
+        .map(|secs| DurationMs::from_inner(secs * 1000.0))
+}
+
+fn separator_comment(title: Option<&str>) -> String {
+    if let Some(title) = title {
+        format!("--- {} ---", single_line(title))
+    } else {
+        "---".to_owned()
+    }
+}
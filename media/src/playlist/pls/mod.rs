@@ -0,0 +1,131 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{duration_from_secs, duration_secs, separator_comment, single_line, Item};
+
+use crate::{Error, Result};
+
+use anyhow::anyhow;
+use std::collections::BTreeMap;
+
+///////////////////////////////////////////////////////////////////////
+
+const SECTION: &str = "[playlist]";
+
+#[derive(Default)]
+struct Entry {
+    file: Option<String>,
+    title: Option<String>,
+    length: Option<String>,
+}
+
+/// Import all tracks from a PLS playlist in the order of their
+/// numbered `File` keys
+pub fn import_items(input: &str) -> Result<Vec<Item>> {
+    let mut has_section = false;
+    let mut entries: BTreeMap<usize, Entry> = BTreeMap::new();
+    for line in input.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            has_section |= line.eq_ignore_ascii_case(SECTION);
+            continue;
+        }
+        let (key, value) = if let Some(key_value) = line.split_once('=') {
+            key_value
+        } else {
+            log::warn!("Skipping invalid line: {}", line);
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let (name, number) =
+            key.split_at(key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len()));
+        let number = if let Ok(number) = number.parse() {
+            number
+        } else {
+            // NumberOfEntries, Version
+            continue;
+        };
+        let entry = entries.entry(number).or_default();
+        match name {
+            "file" => entry.file = Some(value.to_owned()),
+            "title" => entry.title = Some(value.to_owned()).filter(|title| !title.is_empty()),
+            "length" => entry.length = Some(value.to_owned()),
+            _ => log::debug!("Ignoring key {}", key),
+        }
+    }
+    if !has_section {
+        return Err(Error::Other(anyhow!("Missing {} section in PLS", SECTION)));
+    }
+    Ok(entries
+        .into_iter()
+        .filter_map(|(number, entry)| {
+            let Entry {
+                file,
+                title,
+                length,
+            } = entry;
+            if file.is_none() {
+                log::warn!("Skipping entry {} without file", number);
+            }
+            Some(Item::Track {
+                location: file?,
+                title,
+                duration: length.as_deref().and_then(duration_from_secs),
+            })
+        })
+        .collect())
+}
+
+/// Export a PLS playlist (version 2)
+pub fn export_items(items: &[Item]) -> String {
+    let mut pls = String::new();
+    pls.push_str(SECTION);
+    pls.push('\n');
+    let mut number = 0;
+    for item in items {
+        match item {
+            Item::Track {
+                location,
+                title,
+                duration,
+            } => {
+                number += 1;
+                pls.push_str(&format!("File{}={}\n", number, location));
+                if let Some(title) = title {
+                    pls.push_str(&format!("Title{}={}\n", number, single_line(title)));
+                }
+                pls.push_str(&format!("Length{}={}\n", number, duration_secs(*duration)));
+            }
+            Item::Separator { title } => {
+                pls.push_str(&format!("; {}\n", separator_comment(title.as_deref())));
+            }
+        }
+    }
+    pls.push_str(&format!("NumberOfEntries={}\nVersion=2\n", number));
+    pls
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,90 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::audio::DurationMs;
+
+const PLS: &str = "[playlist]
+; A comment
+File2=/music/Other.flac
+File1=Artist/Title.mp3
+Title1=Artist - Title
+Length1=300
+Title3=Missing File
+NumberOfEntries=2
+Version=2
+";
+
+#[test]
+fn import_entries_in_numbered_order() {
+    assert_eq!(
+        vec![
+            Item::Track {
+                location: "Artist/Title.mp3".to_owned(),
+                title: Some("Artist - Title".to_owned()),
+                duration: Some(DurationMs::from_inner(300_000.0)),
+            },
+            Item::Track {
+                location: "/music/Other.flac".to_owned(),
+                title: None,
+                duration: None,
+            },
+        ],
+        import_items(PLS).unwrap()
+    );
+}
+
+#[test]
+fn reject_missing_section() {
+    assert!(import_items("File1=/music/Title.mp3\n").is_err());
+}
+
+#[test]
+fn export_with_separator_comments_and_reimport() {
+    let items = vec![
+        Item::Track {
+            location: "/music/Artist/Title.mp3".to_owned(),
+            title: Some("Artist - Title".to_owned()),
+            duration: Some(DurationMs::from_inner(300_000.0)),
+        },
+        Item::Separator { title: None },
+        Item::Track {
+            location: "/music/Other.flac".to_owned(),
+            title: None,
+            duration: None,
+        },
+    ];
+    let pls = export_items(&items);
+    assert_eq!(
+        "[playlist]
+File1=/music/Artist/Title.mp3
+Title1=Artist - Title
+Length1=300
+; ---
+File2=/music/Other.flac
+Length2=-1
+NumberOfEntries=2
+Version=2
+",
+        pls
+    );
+    assert_eq!(
+        vec![items[0].clone(), items[2].clone()],
+        import_items(&pls).unwrap()
+    );
+}
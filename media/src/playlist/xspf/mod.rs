@@ -0,0 +1,233 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::{
+    library::xml::{
+        child_elements, write_close_element, write_open_element, write_text_element, DECLARATION,
+    },
+    Error, Result,
+};
+
+use aoide_core::audio::DurationMs;
+
+use anyhow::anyhow;
+use roxmltree::{Document, Node};
+use std::borrow::Cow;
+
+///////////////////////////////////////////////////////////////////////
+
+const NAMESPACE: &str = "http://xspf.org/ns/0/";
+
+pub const CONTENT_TYPE: &str = "application/xspf+xml";
+
+/// The `<meta>` relation for entry titles that differ from the track title
+pub const META_REL_ENTRY_TITLE: &str = "https://aoide.org/xspf/entry/title";
+
+/// The `<meta>` relation that marks a `<track>` without a location as separator
+pub const META_REL_SEPARATOR: &str = "https://aoide.org/xspf/separator";
+
+/// A track, referenced by its location and identified by its metadata
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Track {
+    /// An absolute or relative URI
+    pub location: Option<String>,
+
+    /// The artist
+    pub creator: Option<String>,
+
+    pub title: Option<String>,
+
+    pub duration: Option<DurationMs>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Track(Track),
+    Separator,
+}
+
+/// A `<track>` of the `<trackList>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub title: Option<String>,
+
+    /// Stored as `<annotation>`
+    pub notes: Option<String>,
+
+    pub item: Item,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Playlist {
+    pub title: Option<String>,
+
+    /// Stored as `<annotation>`
+    pub notes: Option<String>,
+
+    pub entries: Vec<Entry>,
+}
+
+fn child_text<'a>(node: Node<'a, '_>, tag_name: &str) -> Option<&'a str> {
+    child_elements(node, tag_name)
+        .next()
+        .and_then(|child| child.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+fn meta_text<'a>(node: Node<'a, '_>, rel: &str) -> Option<&'a str> {
+    child_elements(node, "meta")
+        .find(|meta| meta.attribute("rel") == Some(rel))
+        .and_then(|meta| meta.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+fn import_entry(node: Node<'_, '_>) -> Entry {
+    let notes = child_text(node, "annotation").map(ToOwned::to_owned);
+    let location = child_text(node, "location");
+    if location.is_none() && meta_text(node, META_REL_SEPARATOR) == Some("true") {
+        return Entry {
+            title: child_text(node, "title").map(ToOwned::to_owned),
+            notes,
+            item: Item::Separator,
+        };
+    }
+    let track = Track {
+        location: location.map(ToOwned::to_owned),
+        creator: child_text(node, "creator").map(ToOwned::to_owned),
+        title: child_text(node, "title").map(ToOwned::to_owned),
+        duration: child_text(node, "duration")
+            .and_then(|ms| ms.parse::<f64>().ok())
+            .filter(|ms| *ms > 0.0)
+            .map(DurationMs::from_inner),
+    };
+    Entry {
+        title: meta_text(node, META_REL_ENTRY_TITLE).map(ToOwned::to_owned),
+        notes,
+        item: Item::Track(track),
+    }
+}
+
+/// Import an XSPF playlist
+///
+/// Only the first `<location>` of each track is considered. Any
+/// other identifiers, links, and extensions are ignored.
+pub fn import_playlist(input: &str) -> Result<Playlist> {
+    let document =
+        Document::parse(input).map_err(|err| anyhow!("Failed to parse XSPF: {}", err))?;
+    let root = document.root_element();
+    if !root.has_tag_name("playlist") {
+        return Err(Error::Other(anyhow!("Missing XSPF element: playlist")));
+    }
+    let entries = child_elements(root, "trackList")
+        .flat_map(|track_list| child_elements(track_list, "track"))
+        .map(import_entry)
+        .collect();
+    Ok(Playlist {
+        title: child_text(root, "title").map(ToOwned::to_owned),
+        notes: child_text(root, "annotation").map(ToOwned::to_owned),
+        entries,
+    })
+}
+
+fn write_optional_text_element(xml: &mut String, depth: usize, name: &str, text: Option<&str>) {
+    if let Some(text) = text {
+        write_text_element(xml, depth, name, &[], text);
+    }
+}
+
+fn write_meta_element(xml: &mut String, depth: usize, rel: &'static str, text: &str) {
+    write_text_element(xml, depth, "meta", &[("rel", Cow::Borrowed(rel))], text);
+}
+
+fn export_entry(xml: &mut String, depth: usize, entry: &Entry) {
+    let Entry { title, notes, item } = entry;
+    write_open_element(xml, depth, "track", &[]);
+    match item {
+        Item::Track(track) => {
+            let Track {
+                location,
+                creator,
+                title: track_title,
+                duration,
+            } = track;
+            write_optional_text_element(xml, depth + 1, "location", location.as_deref());
+            write_optional_text_element(xml, depth + 1, "creator", creator.as_deref());
+            // Other applications display the title of the track
+            write_optional_text_element(
+                xml,
+                depth + 1,
+                "title",
+                track_title.as_deref().or_else(|| title.as_deref()),
+            );
+            write_optional_text_element(xml, depth + 1, "annotation", notes.as_deref());
+            if let Some(duration) = duration {
+                let duration_ms = format!("{}", duration.to_inner().round() as u64);
+                write_text_element(xml, depth + 1, "duration", &[], &duration_ms);
+            }
+            if let Some(title) = title {
+                write_meta_element(xml, depth + 1, META_REL_ENTRY_TITLE, title);
+            }
+        }
+        Item::Separator => {
+            write_optional_text_element(xml, depth + 1, "title", title.as_deref());
+            write_optional_text_element(xml, depth + 1, "annotation", notes.as_deref());
+            write_meta_element(xml, depth + 1, META_REL_SEPARATOR, "true");
+        }
+    }
+    write_close_element(xml, depth, "track");
+}
+
+/// Export an XSPF playlist
+///
+/// Entry titles and separators are stored as `<meta>` elements
+/// that are ignored by other applications.
+pub fn export_playlist(playlist: &Playlist) -> String {
+    let Playlist {
+        title,
+        notes,
+        entries,
+    } = playlist;
+    let mut xml = String::with_capacity(1024 + entries.len() * 256);
+    xml.push_str(DECLARATION);
+    write_open_element(
+        &mut xml,
+        0,
+        "playlist",
+        &[
+            ("version", Cow::Borrowed("1")),
+            ("xmlns", Cow::Borrowed(NAMESPACE)),
+        ],
+    );
+    write_optional_text_element(&mut xml, 1, "title", title.as_deref());
+    write_optional_text_element(&mut xml, 1, "annotation", notes.as_deref());
+    write_open_element(&mut xml, 1, "trackList", &[]);
+    for entry in entries {
+        export_entry(&mut xml, 2, entry);
+    }
+    write_close_element(&mut xml, 1, "trackList");
+    write_close_element(&mut xml, 0, "playlist");
+    xml
+}
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,116 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+const XSPF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <title>Warm-up</title>
+  <annotation>Friday night</annotation>
+  <trackList>
+    <track>
+      <location>file:///music/Artist%20-%20Title.mp3</location>
+      <location>http://example.com/ignored.mp3</location>
+      <creator>Artist</creator>
+      <title>Title</title>
+      <duration>300500</duration>
+      <meta rel="https://aoide.org/xspf/entry/title">Opener</meta>
+    </track>
+    <track>
+      <title>Peak time</title>
+      <meta rel="https://aoide.org/xspf/separator">true</meta>
+    </track>
+    <track>
+      <creator>Other Artist</creator>
+      <title>Other Title</title>
+      <annotation>Request</annotation>
+    </track>
+  </trackList>
+</playlist>
+"#;
+
+#[test]
+fn import_xspf() {
+    let playlist = import_playlist(XSPF).unwrap();
+    assert_eq!(Some("Warm-up"), playlist.title.as_deref());
+    assert_eq!(Some("Friday night"), playlist.notes.as_deref());
+    assert_eq!(
+        vec![
+            Entry {
+                title: Some("Opener".to_owned()),
+                notes: None,
+                item: Item::Track(Track {
+                    location: Some("file:///music/Artist%20-%20Title.mp3".to_owned()),
+                    creator: Some("Artist".to_owned()),
+                    title: Some("Title".to_owned()),
+                    duration: Some(DurationMs::from_inner(300_500.0)),
+                }),
+            },
+            Entry {
+                title: Some("Peak time".to_owned()),
+                notes: None,
+                item: Item::Separator,
+            },
+            Entry {
+                title: None,
+                notes: Some("Request".to_owned()),
+                item: Item::Track(Track {
+                    location: None,
+                    creator: Some("Other Artist".to_owned()),
+                    title: Some("Other Title".to_owned()),
+                    duration: None,
+                }),
+            },
+        ],
+        playlist.entries
+    );
+}
+
+#[test]
+fn import_without_playlist_element() {
+    assert!(import_playlist("<?xml version=\"1.0\"?><trackList/>").is_err());
+}
+
+#[test]
+fn export_and_reimport_xspf() {
+    let playlist = Playlist {
+        title: Some("Rock & Roll <Live>".to_owned()),
+        notes: Some("First line\nSecond line".to_owned()),
+        entries: vec![
+            Entry {
+                title: None,
+                notes: Some("Loud".to_owned()),
+                item: Item::Track(Track {
+                    location: Some("Artist/Title.flac".to_owned()),
+                    creator: Some("Artist".to_owned()),
+                    title: Some("Title".to_owned()),
+                    duration: Some(DurationMs::from_inner(123_000.0)),
+                }),
+            },
+            Entry {
+                title: None,
+                notes: None,
+                item: Item::Separator,
+            },
+        ],
+    };
+    let xspf = export_playlist(&playlist);
+    assert!(xspf.starts_with(DECLARATION));
+    assert!(xspf.contains("<title>Rock &amp; Roll &lt;Live&gt;</title>"));
+    assert!(xspf.contains("<duration>123000</duration>"));
+    assert_eq!(playlist, import_playlist(&xspf).unwrap());
+}
@@ -23,6 +23,8 @@ use crate::prelude::*;
 
 use self::digest::MediaDigest;
 
+use crate::io::import::ArtworkImage;
+
 use aoide_core::{
     audio::signal::LoudnessLufs,
     media::{Artwork, ImageDimension, ImageSize, Thumbnail4x4Rgb8},
@@ -53,7 +55,28 @@ use nom::{
 use semval::IsValid as _;
 use std::{convert::TryFrom as _, path::Path};
 
+// Audio file extensions that are either unknown or ambiguous
+// for mime_guess.
+const AUDIO_MIME_TYPES_BY_EXTENSION: &[(&str, &str)] = &[
+    ("ape", "audio/x-ape"),
+    ("mpc", "audio/x-musepack"),
+    // Ogg Opus files are not distinguished from Ogg Vorbis files
+    // by the generic MIME type "audio/ogg"
+    // https://tools.ietf.org/html/rfc7845#section-9
+    ("opus", "audio/opus"),
+    ("wv", "audio/x-wavpack"),
+];
+
 pub fn guess_mime_from_path(path: impl AsRef<Path>) -> Result<Mime> {
+    let path = path.as_ref();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        if let Some((_, mime_type)) = AUDIO_MIME_TYPES_BY_EXTENSION
+            .iter()
+            .find(|(known_ext, _)| known_ext.eq_ignore_ascii_case(ext))
+        {
+            return Ok(mime_type.parse().expect("valid MIME type"));
+        }
+    }
     let mime_guess = mime_guess::from_path(path);
     if mime_guess.first().is_none() {
         return Err(Error::UnknownContentType);
@@ -102,6 +125,24 @@ pub fn push_next_actor_role_name(actors: &mut Vec<Actor>, role: ActorRole, name:
     actors.push(actor);
 }
 
+/// Collects the names of all actors with the given role for exporting.
+///
+/// This is the inverse of [`push_next_actor_role_name`]: A singular
+/// summary actor takes precedence over all primary actors.
+pub fn export_actor_role_names(actors: &[Actor], role: ActorRole) -> Vec<&str> {
+    if let Some(summary_actor) = actors
+        .iter()
+        .find(|actor| actor.role == role && actor.kind == ActorKind::Summary)
+    {
+        return vec![summary_actor.name.as_str()];
+    }
+    actors
+        .iter()
+        .filter(|actor| actor.role == role && actor.kind == ActorKind::Primary)
+        .map(|actor| actor.name.as_str())
+        .collect()
+}
+
 // Assumption: Gain has been calculated with the EBU R128 algorithm
 const EBU_R128_REFERENCE_LUFS: f64 = -18.0;
 
@@ -110,6 +151,15 @@ fn db2lufs(relative_gain_db: f64) -> LoudnessLufs {
     LoudnessLufs(EBU_R128_REFERENCE_LUFS - relative_gain_db)
 }
 
+fn lufs2db(loudness: LoudnessLufs) -> f64 {
+    // Calculate the relative gain from the LUFS value
+    EBU_R128_REFERENCE_LUFS - loudness.0
+}
+
+pub fn format_replay_gain(loudness: LoudnessLufs) -> String {
+    format!("{:.2} dB", lufs2db(loudness))
+}
+
 fn parse_replay_gain_db(input: &str) -> IResult<&str, f64> {
     let mut parser = separated_pair(
         preceded(space0, double),
@@ -157,6 +207,39 @@ pub fn parse_replay_gain(input: &str) -> Option<LoudnessLufs> {
     }
 }
 
+// The reference level of R128 gain tags in Ogg Opus files
+// https://tools.ietf.org/html/rfc7845#section-5.2.1
+const R128_GAIN_REFERENCE_LUFS: f64 = -23.0;
+
+/// Parse the loudness from an R128 gain tag
+///
+/// The value is a signed integer in Q7.8 fixed-point format that
+/// stores the gain relative to the R128 reference level in dB.
+pub fn parse_r128_gain(input: &str) -> Option<LoudnessLufs> {
+    let q78_gain = match input.trim().parse::<i16>() {
+        Ok(q78_gain) => q78_gain,
+        Err(err) => {
+            log::warn!(
+                "Failed to parse R128 gain (Q7.8) from input '{}': {}",
+                input,
+                err
+            );
+            return None;
+        }
+    };
+    let relative_gain_db = f64::from(q78_gain) / 256.0;
+    let loudness_lufs = LoudnessLufs(R128_GAIN_REFERENCE_LUFS - relative_gain_db);
+    if !loudness_lufs.is_valid() {
+        log::warn!(
+            "Invalid loudness parsed from R128 gain input '{}': {}",
+            input,
+            loudness_lufs
+        );
+        return None;
+    }
+    Some(loudness_lufs)
+}
+
 pub fn parse_tempo_bpm(input: &str) -> Option<TempoBpm> {
     match input.parse() {
         Ok(bpm) => {
@@ -179,6 +262,25 @@ pub fn parse_tempo_bpm(input: &str) -> Option<TempoBpm> {
     }
 }
 
+pub fn format_tempo_bpm(tempo_bpm: TempoBpm) -> String {
+    tempo_bpm.0.to_string()
+}
+
+/// Format the tempo as an integer number, e.g. for the ID3v2 TBPM frame
+pub fn format_tempo_bpm_integer(tempo_bpm: TempoBpm) -> String {
+    format!("{:.0}", tempo_bpm.0)
+}
+
+/// Format the key signature in a notation that is widely supported
+///
+/// Returns `None` for an unknown key signature.
+pub fn format_key_signature(key_signature: KeySignature) -> Option<&'static str> {
+    if key_signature.is_unknown() {
+        return None;
+    }
+    Some(key_signature.code().as_serato_str())
+}
+
 pub fn parse_key_signature(input: &str) -> Option<KeySignature> {
     let input = input.trim();
     let key_code = KeyCode::from_lancelot_str(input);
@@ -362,6 +464,24 @@ pub fn parse_artwork_from_embedded_image(
     })
 }
 
+/// Select the first embedded image that is accepted as artwork
+pub fn select_artwork_image_from_embedded_images<'a>(
+    embedded_images: impl Iterator<Item = (&'a [u8], Option<ImageFormat>)>,
+) -> Option<ArtworkImage> {
+    // No digest is needed for selecting the image
+    let mut image_digest = MediaDigest::default();
+    embedded_images
+        .filter_map(|(image_data, image_format)| {
+            let artwork =
+                parse_artwork_from_embedded_image(image_data, image_format, &mut image_digest)?;
+            Some(ArtworkImage {
+                media_type: artwork.media_type.unwrap_or_else(|| IMAGE_STAR.to_string()),
+                data: image_data.to_vec(),
+            })
+        })
+        .next()
+}
+
 ///////////////////////////////////////////////////////////////////////
 // Tests
 ///////////////////////////////////////////////////////////////////////
@@ -15,6 +15,7 @@
 
 ///////////////////////////////////////////////////////////////////////
 use crate::Result;
+use anyhow::anyhow;
 use aoide_core::{
     audio::PositionMs,
     track::cue::{Cue, CueFlags, OutMode},
@@ -23,7 +24,18 @@ use aoide_core::{
         CanonicalizeInto as _,
     },
 };
-use triseratops::tag::{color::Color as SeratoColor, TagContainer};
+use std::convert::TryFrom as _;
+use triseratops::tag::{
+    color::Color as SeratoColor,
+    generic::{Cue as SeratoCue, Loop as SeratoLoop},
+    TagContainer, TagFormat,
+};
+
+/// The color that Serato assigns to new hot cues (red)
+const DEFAULT_HOTCUE_COLOR: u32 = 0xCC_00_00;
+
+/// The color that Serato assigns to new loops (blue)
+const DEFAULT_LOOP_COLOR: u32 = 0x27_AA_E1;
 
 /// Return a canonical vector of cues found in the tag container.
 pub fn read_cues(serato_tags: &TagContainer) -> Result<Vec<Cue>> {
@@ -76,3 +88,116 @@ pub fn read_track_color(serato_tags: &TagContainer) -> Option<Color> {
         .map(RgbColor)
         .map(Color::Rgb)
 }
+
+fn export_position_millis(position: PositionMs) -> u32 {
+    let PositionMs(millis) = position;
+    if millis <= 0.0 {
+        0
+    } else if millis >= f64::from(u32::MAX) {
+        u32::MAX
+    } else {
+        millis.round() as u32
+    }
+}
+
+fn export_slot_index(cue: &Cue) -> Option<u8> {
+    cue.slot_index
+        .and_then(|slot_index| u8::try_from(slot_index).ok())
+}
+
+/// Replace all hot cues and loops in the tag container.
+///
+/// Only cues that are numbered by a slot index are exported, i.e.
+/// hot cues in the first and loops in the second bank like on import.
+pub fn write_cues(serato_tags: &mut TagContainer, cues: &[Cue]) {
+    let mut serato_cues = vec![];
+    let mut serato_loops = vec![];
+    for cue in cues {
+        let index = if let Some(index) = export_slot_index(cue) {
+            index
+        } else {
+            continue;
+        };
+        match cue.bank_index {
+            0 => {
+                let in_position = if let Some(in_position) = cue.in_position {
+                    in_position
+                } else {
+                    continue;
+                };
+                let color = match cue.color {
+                    Some(Color::Rgb(RgbColor(rgb))) => rgb,
+                    _ => DEFAULT_HOTCUE_COLOR,
+                };
+                serato_cues.push(SeratoCue {
+                    index,
+                    position_millis: export_position_millis(in_position),
+                    color: SeratoColor::from_pro_hotcue_color(color.into()),
+                    label: cue.label.clone().unwrap_or_default(),
+                });
+            }
+            1 => {
+                let (in_position, out_position) =
+                    if let (Some(in_position), Some(out_position), Some(OutMode::Loop)) =
+                        (cue.in_position, cue.out_position, cue.out_mode)
+                    {
+                        (in_position, out_position)
+                    } else {
+                        continue;
+                    };
+                let color = match cue.color {
+                    Some(Color::Rgb(RgbColor(rgb))) => rgb,
+                    _ => DEFAULT_LOOP_COLOR,
+                };
+                serato_loops.push(SeratoLoop {
+                    index,
+                    start_position_millis: export_position_millis(in_position),
+                    end_position_millis: export_position_millis(out_position),
+                    color: color.into(),
+                    is_locked: cue.flags.contains(CueFlags::LOCKED),
+                    label: cue.label.clone().unwrap_or_default(),
+                });
+            }
+            _ => {}
+        }
+    }
+    serato_tags.set_cues(serato_cues);
+    serato_tags.set_loops(serato_loops);
+}
+
+/// Replace the track color in the tag container.
+///
+/// Indexed colors cannot be represented and leave the existing
+/// track color untouched.
+pub fn write_track_color(serato_tags: &mut TagContainer, color: Option<Color>) {
+    let displayed_color = match color {
+        Some(Color::Rgb(RgbColor(rgb))) => Some(SeratoColor::from(rgb)),
+        Some(Color::Index(index)) => {
+            log::debug!("Skipping export of indexed track color {}", index);
+            return;
+        }
+        None => None,
+    };
+    serato_tags.set_track_color(SeratoColor::from_displayed_track_color(displayed_color));
+}
+
+/// Serialize the Serato Markers_ data for the given tag format.
+pub fn dump_markers(serato_tags: &TagContainer, format: TagFormat) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    serato_tags
+        .dump_markers(&mut data, format)
+        .map_err(|err| anyhow!("Failed to serialize Serato Markers: {}", err))?;
+    Ok(data)
+}
+
+/// Serialize the Serato Markers2 data for the given tag format.
+pub fn dump_markers2(serato_tags: &TagContainer, format: TagFormat) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    serato_tags
+        .dump_markers2(&mut data, format)
+        .map_err(|err| anyhow!("Failed to serialize Serato Markers2: {}", err))?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,84 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::track::cue::SlotIndex;
+
+fn hot_cue(slot_index: SlotIndex, position_millis: f64, label: &str) -> Cue {
+    Cue {
+        bank_index: 0,
+        slot_index: Some(slot_index),
+        in_position: Some(PositionMs(position_millis)),
+        out_position: None,
+        out_mode: None,
+        label: Some(label.to_owned()),
+        color: None,
+        flags: CueFlags::empty(),
+    }
+}
+
+fn dump_and_parse_markers2(serato_tags: &TagContainer) -> TagContainer {
+    let data = dump_markers2(serato_tags, TagFormat::ID3).unwrap();
+    let mut parsed = TagContainer::new();
+    parsed.parse_markers2(&data, TagFormat::ID3).unwrap();
+    parsed
+}
+
+#[test]
+fn write_cues_preserves_track_color_in_markers2() {
+    let mut serato_tags = TagContainer::new();
+    write_track_color(&mut serato_tags, Some(Color::Rgb(RgbColor(0xFF_99_FF))));
+    write_cues(&mut serato_tags, &[hot_cue(0, 1000.0, "first")]);
+    let mut serato_tags = dump_and_parse_markers2(&serato_tags);
+    let track_color = read_track_color(&serato_tags);
+    assert!(track_color.is_some());
+
+    write_cues(&mut serato_tags, &[hot_cue(1, 2000.0, "second")]);
+    let serato_tags = dump_and_parse_markers2(&serato_tags);
+
+    assert_eq!(track_color, read_track_color(&serato_tags));
+    let cues = read_cues(&serato_tags).unwrap();
+    assert_eq!(1, cues.len());
+    assert_eq!(Some(1), cues[0].slot_index);
+    assert_eq!(Some(PositionMs(2000.0)), cues[0].in_position);
+    assert_eq!(Some("second"), cues[0].label.as_deref());
+}
+
+#[test]
+fn write_track_color_preserves_cues_in_markers2() {
+    let mut serato_tags = TagContainer::new();
+    write_cues(
+        &mut serato_tags,
+        &[hot_cue(0, 1000.0, "first"), hot_cue(2, 3000.0, "third")],
+    );
+    let mut serato_tags = dump_and_parse_markers2(&serato_tags);
+    let cues = read_cues(&serato_tags).unwrap();
+    assert_eq!(2, cues.len());
+
+    write_track_color(&mut serato_tags, Some(Color::Rgb(RgbColor(0x99_FF_99))));
+    let mut serato_tags = dump_and_parse_markers2(&serato_tags);
+    assert_eq!(cues, read_cues(&serato_tags).unwrap());
+    let track_color = read_track_color(&serato_tags);
+    assert!(track_color.is_some());
+
+    // Indexed colors leave the existing track color untouched
+    write_track_color(&mut serato_tags, Some(Color::Index(1)));
+    let serato_tags = dump_and_parse_markers2(&serato_tags);
+    assert_eq!(cues, read_cues(&serato_tags).unwrap());
+    assert_eq!(track_color, read_track_color(&serato_tags));
+}
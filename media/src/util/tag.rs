@@ -17,7 +17,7 @@
 
 use aoide_core::tag::{
     Facet as TagFacet, FacetValue, Label as TagLabel, LabelValue, PlainTag, Score as TagScore,
-    ScoreValue, TagsMap,
+    ScoreValue, Tags, TagsMap,
 };
 
 use semval::IsValid as _;
@@ -151,3 +151,45 @@ pub fn import_faceted_tags(
     }
     import_count
 }
+
+/// Find all plain tags of the given facet
+pub fn faceted_tags<'a>(tags: &'a Tags, facet: &TagFacet) -> &'a [PlainTag] {
+    tags.facets
+        .iter()
+        .find(|faceted_tags| faceted_tags.facet == *facet)
+        .map(|faceted_tags| faceted_tags.tags.as_slice())
+        .unwrap_or_default()
+}
+
+/// Export the labels of tags ordered by descending score
+///
+/// If a label separator is configured all labels are concatenated into
+/// a single value. This is the inverse of [`import_faceted_tags`].
+pub fn export_faceted_tags(
+    tag_mapping_config: Option<&TagMappingConfig>,
+    tags: &[PlainTag],
+) -> Vec<LabelValue> {
+    let mut tags: Vec<_> = tags.iter().collect();
+    tags.sort_by(|lhs, rhs| {
+        rhs.score
+            .value()
+            .partial_cmp(&lhs.score.value())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let labels = tags
+        .into_iter()
+        .filter_map(|tag| tag.label.as_ref())
+        .map(|label| label.value().to_owned());
+    if let Some(tag_mapping_config) = tag_mapping_config {
+        if !tag_mapping_config.label_separator.is_empty() {
+            let joined_labels = labels
+                .collect::<Vec<_>>()
+                .join(&tag_mapping_config.label_separator);
+            if joined_labels.is_empty() {
+                return vec![];
+            }
+            return vec![joined_labels];
+        }
+    }
+    labels.collect()
+}
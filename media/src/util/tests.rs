@@ -45,6 +45,29 @@ fn parse_replay_gain_invalid() {
     assert!(parse_replay_gain("+0.178062").is_none());
 }
 
+#[test]
+fn format_replay_gain_roundtrip() {
+    assert_eq!("-9.51 dB", format_replay_gain(LoudnessLufs(-8.49428)));
+    assert_eq!("0.18 dB", format_replay_gain(LoudnessLufs(-18.178062)));
+    let loudness = LoudnessLufs(-12.5);
+    assert_eq!(
+        loudness,
+        parse_replay_gain(&format_replay_gain(loudness)).unwrap()
+    );
+}
+
+#[test]
+fn format_key_signature_roundtrip() {
+    assert!(format_key_signature(KeySignature::unknown()).is_none());
+    for key_code in &[KeyCode::Cmaj, KeyCode::Amin, KeyCode::Gbmin, KeyCode::Bbmaj] {
+        let key_signature = KeySignature::new(*key_code);
+        assert_eq!(
+            Some(key_signature),
+            format_key_signature(key_signature).and_then(parse_key_signature)
+        );
+    }
+}
+
 #[test]
 fn parse_year_tag_valid() {
     assert_eq!(
@@ -83,3 +106,14 @@ fn parse_year_tag_valid() {
         parse_year_tag(" 2009-09-18T07:00:00 ").unwrap().to_string()
     );
 }
+
+#[test]
+fn parse_r128_gain_q78() {
+    assert_eq!(Some(LoudnessLufs(-23.0)), parse_r128_gain("0"));
+    // -6 dB
+    assert_eq!(Some(LoudnessLufs(-17.0)), parse_r128_gain("-1536"));
+    // +1.5 dB
+    assert_eq!(Some(LoudnessLufs(-24.5)), parse_r128_gain(" 384 "));
+    assert_eq!(None, parse_r128_gain("-6.0 dB"));
+    assert_eq!(None, parse_r128_gain("40000"));
+}
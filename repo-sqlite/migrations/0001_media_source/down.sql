@@ -13,4 +13,5 @@
 -- You should have received a copy of the GNU Affero General Public License
 -- along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+DROP TABLE IF EXISTS media_source_fingerprint;
 DROP TABLE IF EXISTS media_source;
@@ -36,7 +36,13 @@ CREATE TABLE IF NOT EXISTS media_source (
     audio_channel_count    INTEGER,          -- number of channels
     audio_samplerate_hz    REAL,             -- Hz
     audio_bitrate_bps      REAL,             -- bits per second (bps)
+    audio_bitrate_mode     TINYINT,          -- 1 = constant (CBR), 2 = variable (VBR)
     audio_loudness_lufs    REAL,             -- LUFS (dB)
+    audio_loudness_range_lu REAL,            -- LU (dB)
+    audio_true_peak_dbtp   REAL,             -- dBTP
+    audio_waveform         BINARY,           -- waveform overview, 5 bytes (peak, rms, low, mid, high) per bin
+    audio_range_start_ms   REAL,             -- milliseconds, only for virtual tracks
+    audio_range_end_ms     REAL,             -- milliseconds, only for virtual tracks
     audio_encoder          TEXT,             -- both name and settings, often referred to as encoded_by
     -- properties: artwork
     artwork_uri            TEXT,             -- RFC 3986, absolute or relative to the media_source URI
@@ -81,3 +87,13 @@ CREATE INDEX idx_media_source_content_digest ON media_source (
 CREATE INDEX idx_media_source_audio_duration_ms ON media_source (
     audio_duration_ms
 );
+
+CREATE TABLE IF NOT EXISTS media_source_fingerprint (
+    -- relations (immutable)
+    source_id              INTEGER NOT NULL,
+    -- properties
+    fingerprint            BINARY NOT NULL,  -- acoustic fingerprint, sub-fingerprints as 32-bit LE integers
+    --
+    FOREIGN KEY(source_id) REFERENCES media_source(row_id),
+    UNIQUE (source_id)
+);
@@ -37,3 +37,22 @@ CREATE TABLE IF NOT EXISTS track_cue (
     FOREIGN KEY(track_id) REFERENCES track(row_id),
     UNIQUE (track_id, bank_idx, slot_idx)
 );
+
+CREATE TABLE IF NOT EXISTS track_lyrics (
+    row_id                   INTEGER PRIMARY KEY,
+    -- relations (immutable)
+    track_id                 INTEGER NOT NULL,
+    -- properties
+    lang                     TEXT,     -- ISO 639-2 language code
+    -- position_ms:
+    -- NULL = unsynchronized plain text (at most one per language)
+    -- otherwise offset of a time-synchronized line from start of media source in milliseconds
+    position_ms              REAL,
+    text                     TEXT NOT NULL,
+    --
+    FOREIGN KEY(track_id) REFERENCES track(row_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_track_lyrics_track_id_lang ON track_lyrics (
+    track_id, lang
+);
@@ -20,8 +20,12 @@ use crate::prelude::*;
 use aoide_core::{
     audio::{
         channel::{ChannelCount, NumberOfChannels},
-        signal::{BitrateBps, BitsPerSecond, LoudnessLufs, SampleRateHz},
-        AudioContent, DurationMs,
+        signal::{
+            BitrateBps, BitrateMode, BitsPerSecond, LoudnessLufs, LoudnessRangeLu, SampleRateHz,
+            TruePeakDbtp,
+        },
+        waveform::WaveformOverview,
+        AudioContent, AudioRange, DurationMs, PositionMs,
     },
     media::{Artwork, Content, ContentMetadataFlags, ImageDimension, ImageSize, Source},
     util::clock::*,
@@ -29,6 +33,8 @@ use aoide_core::{
 
 use aoide_repo::collection::RecordId as CollectionId;
 
+use num_traits::FromPrimitive as _;
+
 use std::convert::TryInto as _;
 
 ///////////////////////////////////////////////////////////////////////
@@ -52,7 +58,13 @@ pub struct QueryableRecord {
     pub audio_channel_count: Option<i16>,
     pub audio_samplerate_hz: Option<f64>,
     pub audio_bitrate_bps: Option<f64>,
+    pub audio_bitrate_mode: Option<i16>,
     pub audio_loudness_lufs: Option<f64>,
+    pub audio_loudness_range_lu: Option<f64>,
+    pub audio_true_peak_dbtp: Option<f64>,
+    pub audio_waveform: Option<Vec<u8>>,
+    pub audio_range_start_ms: Option<f64>,
+    pub audio_range_end_ms: Option<f64>,
     pub audio_encoder: Option<String>,
     pub artwork_uri: Option<String>,
     pub artwork_type: Option<String>,
@@ -81,7 +93,13 @@ impl From<QueryableRecord> for (RecordHeader, Source) {
             audio_channel_count,
             audio_samplerate_hz,
             audio_bitrate_bps,
+            audio_bitrate_mode,
             audio_loudness_lufs,
+            audio_loudness_range_lu,
+            audio_true_peak_dbtp,
+            audio_waveform,
+            audio_range_start_ms,
+            audio_range_end_ms,
             audio_encoder,
             artwork_uri,
             artwork_type,
@@ -95,7 +113,25 @@ impl From<QueryableRecord> for (RecordHeader, Source) {
             channels: audio_channel_count.map(|val| ChannelCount(val as NumberOfChannels).into()),
             sample_rate: audio_samplerate_hz.map(SampleRateHz::from_inner),
             bitrate: audio_bitrate_bps.map(|val| BitrateBps::from_inner(val as BitsPerSecond)),
+            bitrate_mode: audio_bitrate_mode.and_then(|val| {
+                BitrateMode::from_i16(val).or_else(|| {
+                    log::error!("Invalid bitrate mode value: {}", val);
+                    None
+                })
+            }),
             loudness: audio_loudness_lufs.map(LoudnessLufs),
+            loudness_range: audio_loudness_range_lu.map(LoudnessRangeLu),
+            true_peak: audio_true_peak_dbtp.map(TruePeakDbtp),
+            waveform: audio_waveform.as_deref().and_then(|bytes| {
+                WaveformOverview::from_bytes(bytes).or_else(|| {
+                    log::error!("Invalid waveform overview with {} bytes", bytes.len());
+                    None
+                })
+            }),
+            range: audio_range_start_ms.map(|start_ms| AudioRange {
+                start: PositionMs(start_ms),
+                end: audio_range_end_ms.map(PositionMs),
+            }),
             encoder: audio_encoder,
         };
         debug_assert!(artwork_size_width.is_some() == artwork_size_height.is_some());
@@ -154,7 +190,13 @@ pub struct InsertableRecord<'a> {
     pub audio_channel_count: Option<i16>,
     pub audio_samplerate_hz: Option<f64>,
     pub audio_bitrate_bps: Option<f64>,
+    pub audio_bitrate_mode: Option<i16>,
     pub audio_loudness_lufs: Option<f64>,
+    pub audio_loudness_range_lu: Option<f64>,
+    pub audio_true_peak_dbtp: Option<f64>,
+    pub audio_waveform: Option<Vec<u8>>,
+    pub audio_range_start_ms: Option<f64>,
+    pub audio_range_end_ms: Option<f64>,
     pub audio_encoder: Option<&'a str>,
     pub artwork_uri: Option<&'a str>,
     pub artwork_type: Option<&'a str>,
@@ -217,9 +259,28 @@ impl<'a> InsertableRecord<'a> {
             audio_bitrate_bps: audio_content
                 .and_then(|audio| audio.bitrate)
                 .map(BitrateBps::to_inner),
+            audio_bitrate_mode: audio_content
+                .and_then(|audio| audio.bitrate_mode)
+                .map(|mode| mode as i16),
             audio_loudness_lufs: audio_content
                 .and_then(|audio| audio.loudness)
                 .map(|loudness| loudness.0),
+            audio_loudness_range_lu: audio_content
+                .and_then(|audio| audio.loudness_range)
+                .map(|loudness_range| loudness_range.0),
+            audio_true_peak_dbtp: audio_content
+                .and_then(|audio| audio.true_peak)
+                .map(|true_peak| true_peak.0),
+            audio_waveform: audio_content
+                .and_then(|audio| audio.waveform.as_ref())
+                .map(WaveformOverview::to_bytes),
+            audio_range_start_ms: audio_content
+                .and_then(|audio| audio.range)
+                .map(|range| range.start.0),
+            audio_range_end_ms: audio_content
+                .and_then(|audio| audio.range)
+                .and_then(|range| range.end)
+                .map(|end| end.0),
             audio_encoder: audio_content.and_then(|audio| audio.encoder.as_deref()),
             artwork_uri: artwork_uri.as_ref().map(String::as_str),
             artwork_type: artwork_type.as_ref().map(String::as_str),
@@ -248,7 +309,13 @@ pub struct UpdatableRecord<'a> {
     pub audio_channel_count: Option<i16>,
     pub audio_samplerate_hz: Option<f64>,
     pub audio_bitrate_bps: Option<f64>,
+    pub audio_bitrate_mode: Option<i16>,
     pub audio_loudness_lufs: Option<f64>,
+    pub audio_loudness_range_lu: Option<f64>,
+    pub audio_true_peak_dbtp: Option<f64>,
+    pub audio_waveform: Option<Vec<u8>>,
+    pub audio_range_start_ms: Option<f64>,
+    pub audio_range_end_ms: Option<f64>,
     pub audio_encoder: Option<&'a str>,
     pub artwork_uri: Option<&'a str>,
     pub artwork_type: Option<&'a str>,
@@ -304,9 +371,28 @@ impl<'a> UpdatableRecord<'a> {
             audio_bitrate_bps: audio_content
                 .and_then(|audio| audio.bitrate)
                 .map(BitrateBps::to_inner),
+            audio_bitrate_mode: audio_content
+                .and_then(|audio| audio.bitrate_mode)
+                .map(|mode| mode as i16),
             audio_loudness_lufs: audio_content
                 .and_then(|audio| audio.loudness)
                 .map(|loudness| loudness.0),
+            audio_loudness_range_lu: audio_content
+                .and_then(|audio| audio.loudness_range)
+                .map(|loudness_range| loudness_range.0),
+            audio_true_peak_dbtp: audio_content
+                .and_then(|audio| audio.true_peak)
+                .map(|true_peak| true_peak.0),
+            audio_waveform: audio_content
+                .and_then(|audio| audio.waveform.as_ref())
+                .map(WaveformOverview::to_bytes),
+            audio_range_start_ms: audio_content
+                .and_then(|audio| audio.range)
+                .map(|range| range.start.0),
+            audio_range_end_ms: audio_content
+                .and_then(|audio| audio.range)
+                .and_then(|range| range.end)
+                .map(|end| end.0),
             audio_encoder: audio_content.and_then(|audio| audio.encoder.as_deref()),
             artwork_uri: artwork_uri.as_ref().map(String::as_str),
             artwork_type: artwork_type.as_ref().map(String::as_str),
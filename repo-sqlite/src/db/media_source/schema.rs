@@ -35,7 +35,13 @@ table! {
         audio_channel_count -> Nullable<SmallInt>,
         audio_samplerate_hz -> Nullable<Double>,
         audio_bitrate_bps -> Nullable<Double>,
+        audio_bitrate_mode -> Nullable<SmallInt>,
         audio_loudness_lufs -> Nullable<Double>,
+        audio_loudness_range_lu -> Nullable<Double>,
+        audio_true_peak_dbtp -> Nullable<Double>,
+        audio_waveform -> Nullable<Binary>,
+        audio_range_start_ms -> Nullable<Double>,
+        audio_range_end_ms -> Nullable<Double>,
         audio_encoder -> Nullable<Text>,
         artwork_uri -> Nullable<Text>,
         artwork_type -> Nullable<Text>,
@@ -47,3 +53,12 @@ table! {
 }
 
 joinable!(media_source -> collection (collection_id));
+
+table! {
+    media_source_fingerprint (source_id) {
+        source_id -> BigInt,
+        fingerprint -> Binary,
+    }
+}
+
+joinable!(media_source_fingerprint -> media_source (source_id));
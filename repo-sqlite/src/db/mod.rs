@@ -21,6 +21,7 @@ pub mod playlist_entry;
 pub mod track;
 pub mod track_actor;
 pub mod track_cue;
+pub mod track_lyrics;
 pub mod track_tag;
 pub mod track_title;
 
@@ -33,6 +34,7 @@ mod join {
     allow_tables_to_appear_in_same_query!(
         collection,
         media_source,
+        media_source_fingerprint,
         track,
         playlist,
         playlist_entry,
@@ -19,7 +19,7 @@ pub mod schema;
 use aoide_core::{
     media::Source,
     tag::Tags,
-    track::{actor::Actor, cue::Cue, title::Title},
+    track::{actor::Actor, cue::Cue, lyrics::Lyrics, title::Title},
     util::Canonical,
 };
 
@@ -42,4 +42,5 @@ pub struct EntityPreload {
     pub album_actors: Canonical<Vec<Actor>>,
     pub tags: Canonical<Tags>,
     pub cues: Canonical<Vec<Cue>>,
+    pub lyrics: Canonical<Vec<Lyrics>>,
 }
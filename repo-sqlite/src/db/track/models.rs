@@ -105,6 +105,7 @@ pub fn load_repo_entity(
         album_actors,
         tags,
         cues,
+        lyrics,
     } = preload;
     let QueryableRecord {
         id,
@@ -231,6 +232,7 @@ pub fn load_repo_entity(
         color,
         metrics,
         cues,
+        lyrics,
         play_counter,
     };
     let entity = Entity::new(entity_hdr, track);
@@ -293,6 +295,7 @@ impl<'a> InsertableRecord<'a> {
                     times_played,
                 },
             cues: _,
+            lyrics: _,
             tags: _,
         } = &entity.body;
         let Release {
@@ -429,6 +432,7 @@ impl<'a> UpdatableRecord<'a> {
                     times_played,
                 },
             cues: _,
+            lyrics: _,
             tags: _,
         } = track;
         let Release {
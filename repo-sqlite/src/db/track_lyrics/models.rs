@@ -0,0 +1,76 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::{schema::*, *};
+
+use aoide_core::audio::PositionInMilliseconds;
+
+///////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Queryable, Identifiable)]
+#[table_name = "track_lyrics"]
+pub struct QueryableRecord {
+    pub id: RowId,
+    pub track_id: RowId,
+    pub lang: Option<String>,
+    pub position_ms: Option<PositionInMilliseconds>,
+    pub text: String,
+}
+
+impl From<QueryableRecord> for (RecordId, Record) {
+    fn from(from: QueryableRecord) -> Self {
+        let QueryableRecord {
+            id,
+            track_id,
+            lang,
+            position_ms,
+            text,
+        } = from;
+        let record = Record {
+            track_id: track_id.into(),
+            language: lang,
+            position: position_ms.map(PositionMs),
+            text,
+        };
+        (id.into(), record)
+    }
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "track_lyrics"]
+pub struct InsertableRecord<'a> {
+    pub track_id: RowId,
+    pub lang: Option<&'a str>,
+    pub position_ms: Option<PositionInMilliseconds>,
+    pub text: &'a str,
+}
+
+impl<'a> InsertableRecord<'a> {
+    pub fn bind(
+        track_id: RecordId,
+        language: Option<&'a str>,
+        position: Option<PositionMs>,
+        text: &'a str,
+    ) -> Self {
+        Self {
+            track_id: track_id.into(),
+            lang: language,
+            position_ms: position.map(|pos| pos.0),
+            text,
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use crate::db::track::schema::*;
+
+table! {
+    track_lyrics (row_id) {
+        row_id -> BigInt,
+        track_id -> BigInt,
+        lang -> Nullable<Text>,
+        position_ms -> Nullable<Double>,
+        text -> Text,
+    }
+}
+
+joinable!(track_lyrics -> track (track_id));
@@ -21,7 +21,13 @@ use crate::{
     prelude::*,
 };
 
-use aoide_core::{media::Source, util::clock::DateTime};
+use aoide_core::{
+    audio::fingerprint::Fingerprint,
+    media::{Digest, Source},
+    util::clock::DateTime,
+};
+
+use std::convert::TryInto as _;
 
 use aoide_repo::{collection::RecordId as CollectionId, media::source::*};
 
@@ -105,6 +111,14 @@ impl<'db> Repo for crate::prelude::Connection<'db> {
         // Reuse the tested subselect with reliable predicate filtering
         // even if it might be slightly less efficient! The query optimizer
         // should detect this.
+        diesel::delete(media_source_fingerprint::table.filter(
+            media_source_fingerprint::source_id.eq_any(subselect::filter_by_path_predicate(
+                collection_id,
+                path_predicate,
+            )),
+        ))
+        .execute(self.as_ref())
+        .map_err(repo_error)?;
         diesel::delete(media_source::table.filter(media_source::row_id.eq_any(
             subselect::filter_by_path_predicate(collection_id, path_predicate),
         )))
@@ -149,6 +163,7 @@ impl<'db> Repo for crate::prelude::Connection<'db> {
     }
 
     fn delete_media_source(&self, id: RecordId) -> RepoResult<()> {
+        self.update_media_source_fingerprint(id, None)?;
         let target = media_source::table.filter(media_source::row_id.eq(RowId::from(id)));
         let query = diesel::delete(target);
         let rows_affected: usize = query.execute(self.as_ref()).map_err(repo_error)?;
@@ -180,10 +195,75 @@ impl<'db> Repo for crate::prelude::Connection<'db> {
             .map(Into::into)
     }
 
+    fn update_media_source_fingerprint(
+        &self,
+        id: RecordId,
+        fingerprint: Option<&Fingerprint>,
+    ) -> RepoResult<()> {
+        let target = media_source_fingerprint::table
+            .filter(media_source_fingerprint::source_id.eq(RowId::from(id)));
+        diesel::delete(target)
+            .execute(self.as_ref())
+            .map_err(repo_error)?;
+        if let Some(fingerprint) = fingerprint {
+            let query = diesel::insert_into(media_source_fingerprint::table).values((
+                media_source_fingerprint::source_id.eq(RowId::from(id)),
+                media_source_fingerprint::fingerprint.eq(fingerprint.to_bytes()),
+            ));
+            let rows_affected: usize = query.execute(self.as_ref()).map_err(repo_error)?;
+            debug_assert_eq!(1, rows_affected);
+        }
+        Ok(())
+    }
+
+    fn load_media_source_fingerprint(&self, id: RecordId) -> RepoResult<Option<Fingerprint>> {
+        let bytes = media_source_fingerprint::table
+            .select(media_source_fingerprint::fingerprint)
+            .filter(media_source_fingerprint::source_id.eq(RowId::from(id)))
+            .first::<Vec<u8>>(self.as_ref())
+            .optional()
+            .map_err(repo_error)?;
+        bytes
+            .map(|bytes| {
+                Fingerprint::from_bytes(&bytes)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid fingerprint of media source").into())
+            })
+            .transpose()
+    }
+
+    fn load_all_artwork_digests(&self) -> RepoResult<Vec<Digest>> {
+        let digests = media_source::table
+            .select(media_source::artwork_digest)
+            .filter(media_source::artwork_digest.is_not_null())
+            .distinct()
+            .load::<Option<Vec<u8>>>(self.as_ref())
+            .map_err(repo_error)?;
+        Ok(digests
+            .into_iter()
+            .flatten()
+            .filter_map(|bytes| bytes.try_into().ok())
+            .collect())
+    }
+
     fn purge_orphaned_media_sources_from_collection(
         &self,
         collection_id: CollectionId,
     ) -> RepoResult<usize> {
+        diesel::delete(
+            media_source_fingerprint::table.filter(
+                media_source_fingerprint::source_id.eq_any(
+                    media_source::table
+                        .select(media_source::row_id)
+                        .filter(media_source::collection_id.eq(RowId::from(collection_id)))
+                        .filter(
+                            media_source::row_id
+                                .ne_all(track::table.select(track::media_source_id)),
+                        ),
+                ),
+            ),
+        )
+        .execute(self.as_ref())
+        .map_err(repo_error)?;
         let target = media_source::table
             .filter(media_source::collection_id.eq(RowId::from(collection_id)))
             .filter(media_source::row_id.ne_all(track::table.select(track::media_source_id)));
@@ -193,6 +273,11 @@ impl<'db> Repo for crate::prelude::Connection<'db> {
     }
 
     fn purge_orphaned_media_sources_from_all_collections(&self) -> RepoResult<usize> {
+        diesel::delete(media_source_fingerprint::table.filter(
+            media_source_fingerprint::source_id.ne_all(track::table.select(track::media_source_id)),
+        ))
+        .execute(self.as_ref())
+        .map_err(repo_error)?;
         let target = media_source::table
             .filter(media_source::row_id.ne_all(track::table.select(track::media_source_id)));
         let query = diesel::delete(target);
@@ -18,7 +18,10 @@ use super::*;
 use crate::prelude::tests::*;
 
 use aoide_core::{
-    audio::{AudioContent, DurationMs},
+    audio::{
+        waveform::{WaveformBin, WaveformOverview, WAVEFORM_OVERVIEW_BIN_COUNT},
+        AudioContent, DurationMs,
+    },
     collection::{Collection, Entity as CollectionEntity, MediaSourceConfig},
     entity::EntityHeader,
     media::{self, SourcePath, SourcePathKind},
@@ -76,6 +79,16 @@ fn insert_media_source() -> anyhow::Result<()> {
         content_metadata_flags: Default::default(),
         content: AudioContent {
             duration: Some(DurationMs::from_inner(543.0)),
+            waveform: Some(WaveformOverview::new(vec![
+                WaveformBin {
+                    peak: 255,
+                    rms: 180,
+                    low: 120,
+                    mid: 90,
+                    high: 30,
+                };
+                WAVEFORM_OVERVIEW_BIN_COUNT
+            ])),
             ..Default::default()
         }
         .into(),
@@ -307,3 +320,86 @@ fn relocate_by_path() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn update_and_delete_fingerprint() -> anyhow::Result<()> {
+    let fixture = Fixture::new()?;
+    let db = crate::Connection::new(&fixture.db);
+
+    let source = media::Source {
+        collected_at: DateTime::now_local(),
+        synchronized_at: Some(DateTime::now_utc()),
+        path: SourcePath::new("file:///home/file.mp3".to_owned()),
+        content_type: "audio/mpeg".to_owned(),
+        content_digest: None,
+        content_metadata_flags: Default::default(),
+        content: AudioContent {
+            duration: Some(DurationMs::from_inner(1.0)),
+            ..Default::default()
+        }
+        .into(),
+        artwork: Default::default(),
+    };
+    let header = db.insert_media_source(DateTime::now_utc(), fixture.collection_id, &source)?;
+    assert_eq!(None, db.load_media_source_fingerprint(header.id)?);
+
+    let fingerprint = Fingerprint::new(vec![0x0123_4567, 0x89ab_cdef, 0]);
+    db.update_media_source_fingerprint(header.id, Some(&fingerprint))?;
+    assert_eq!(
+        Some(&fingerprint),
+        db.load_media_source_fingerprint(header.id)?.as_ref()
+    );
+
+    let replaced_fingerprint = Fingerprint::new(vec![1, 2]);
+    db.update_media_source_fingerprint(header.id, Some(&replaced_fingerprint))?;
+    assert_eq!(
+        Some(replaced_fingerprint),
+        db.load_media_source_fingerprint(header.id)?
+    );
+
+    // The fingerprint is deleted together with the media source
+    db.delete_media_source(header.id)?;
+    assert_eq!(None, db.load_media_source_fingerprint(header.id)?);
+
+    Ok(())
+}
+
+#[test]
+fn load_all_artwork_digests() -> anyhow::Result<()> {
+    let fixture = Fixture::new()?;
+    let db = crate::Connection::new(&fixture.db);
+
+    let new_source = |path: &str, digest| media::Source {
+        collected_at: DateTime::now_local(),
+        synchronized_at: Some(DateTime::now_utc()),
+        path: SourcePath::new(path.to_owned()),
+        content_type: "audio/mpeg".to_owned(),
+        content_digest: None,
+        content_metadata_flags: Default::default(),
+        content: AudioContent::default().into(),
+        artwork: Artwork {
+            media_type: Some("image/jpeg".to_owned()),
+            digest,
+            ..Default::default()
+        },
+    };
+    for (path, digest) in &[
+        ("file:///home/file1.mp3", Some([1; 32])),
+        ("file:///home/file2.mp3", None),
+        ("file:///home/file3.mp3", Some([3; 32])),
+        // Shared artwork
+        ("file:///home/file4.mp3", Some([1; 32])),
+    ] {
+        db.insert_media_source(
+            DateTime::now_utc(),
+            fixture.collection_id,
+            &new_source(path, *digest),
+        )?;
+    }
+
+    let mut digests = db.load_all_artwork_digests()?;
+    digests.sort_unstable();
+    assert_eq!(vec![[1; 32], [3; 32]], digests);
+
+    Ok(())
+}
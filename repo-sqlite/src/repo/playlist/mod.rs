@@ -133,6 +133,15 @@ impl<'db> EntityRepo for crate::Connection<'db> {
         let (record_header, _, entity) = record.into();
         Ok((record_header, entity))
     }
+
+    fn load_playlist_collection_id(&self, id: RecordId) -> RepoResult<CollectionId> {
+        playlist::table
+            .select(playlist::collection_id)
+            .filter(playlist::row_id.eq(RowId::from(id)))
+            .first::<RowId>(self.as_ref())
+            .map_err(repo_error)
+            .map(Into::into)
+    }
 }
 
 fn min_playlist_entry_ordering(
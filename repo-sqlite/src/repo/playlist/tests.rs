@@ -89,6 +89,7 @@ impl Fixture {
                 album: Default::default(),
                 color: None,
                 cues: Default::default(),
+                lyrics: Default::default(),
                 indexes: Default::default(),
                 metrics: Default::default(),
                 play_counter: Default::default(),
@@ -473,3 +474,19 @@ fn copy_all_entries() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn load_playlist_collection_id() -> anyhow::Result<()> {
+    let fixture = Fixture::new()?;
+    let db = crate::Connection::new(&fixture.db);
+
+    let entity_with_entries = fixture.create_playlists_with_track_entries(0)?;
+    let playlist_id = db.resolve_playlist_id(&entity_with_entries.hdr.uid)?;
+
+    assert_eq!(
+        fixture.collection_id,
+        db.load_playlist_collection_id(playlist_id)?
+    );
+
+    Ok(())
+}
@@ -29,10 +29,17 @@ use crate::{
 };
 
 use aoide_core::{
+    audio::fingerprint::Fingerprint,
     entity::{EntityHeader, EntityRevision, EntityUid},
-    media::Source,
+    media::{Content, Source},
     tag::*,
-    track::{actor::Actor, cue::Cue, title::Title, *},
+    track::{
+        actor::Actor,
+        cue::Cue,
+        lyrics::{Lyrics, SyncedLine},
+        title::Title,
+        *,
+    },
     util::{clock::*, Canonical},
 };
 
@@ -286,6 +293,100 @@ fn update_track_cues(
     Ok(())
 }
 
+fn load_track_lyrics(
+    db: &crate::Connection<'_>,
+    track_id: RecordId,
+) -> RepoResult<Canonical<Vec<Lyrics>>> {
+    use crate::db::track_lyrics::{models::*, schema::*, *};
+    let records = track_lyrics::table
+        .filter(track_lyrics::track_id.eq(RowId::from(track_id)))
+        // Establish canonical ordering on load!
+        .then_order_by(track_lyrics::lang)
+        .then_order_by(track_lyrics::position_ms)
+        .then_order_by(track_lyrics::row_id)
+        .load::<QueryableRecord>(db.as_ref())
+        .map_err(repo_error)?;
+    let mut lyrics: Vec<Lyrics> = Vec::new();
+    for queryable in records {
+        let (_, record) = queryable.into();
+        let Record {
+            track_id: _,
+            language,
+            position,
+            text,
+        } = record;
+        if lyrics
+            .last()
+            .map(|last| last.language != language)
+            .unwrap_or(true)
+        {
+            lyrics.push(Lyrics {
+                language,
+                ..Default::default()
+            });
+        }
+        let last = lyrics.last_mut().expect("current lyrics");
+        if let Some(position) = position {
+            last.lines.push(SyncedLine { position, text });
+        } else {
+            debug_assert!(last.text.is_none());
+            last.text = Some(text);
+        }
+    }
+    Ok(Canonical::tie(lyrics))
+}
+
+fn delete_track_lyrics(db: &crate::Connection<'_>, track_id: RecordId) -> RepoResult<usize> {
+    use crate::db::track_lyrics::schema::*;
+    diesel::delete(track_lyrics::table.filter(track_lyrics::track_id.eq(RowId::from(track_id))))
+        .execute(db.as_ref())
+        .map_err(repo_error)
+}
+
+fn insert_track_lyrics(
+    db: &crate::Connection<'_>,
+    track_id: RecordId,
+    lyrics: Canonical<&[Lyrics]>,
+) -> RepoResult<()> {
+    use crate::db::track_lyrics::{models::*, schema::*};
+    for Lyrics {
+        language,
+        text,
+        lines,
+    } in lyrics.iter()
+    {
+        let language = language.as_ref().map(String::as_str);
+        let insertables = text
+            .iter()
+            .map(|text| InsertableRecord::bind(track_id, language, None, text))
+            .chain(lines.iter().map(|SyncedLine { position, text }| {
+                InsertableRecord::bind(track_id, language, Some(*position), text)
+            }));
+        for insertable in insertables {
+            diesel::insert_into(track_lyrics::table)
+                .values(&insertable)
+                .execute(db.as_ref())
+                .map_err(repo_error)?;
+        }
+    }
+    Ok(())
+}
+
+fn update_track_lyrics(
+    db: &crate::Connection<'_>,
+    track_id: RecordId,
+    new_lyrics: Canonical<&[Lyrics]>,
+) -> RepoResult<()> {
+    let old_lyrics = load_track_lyrics(db, track_id)?;
+    if old_lyrics.as_slice() == new_lyrics {
+        log::debug!("Keeping unmodified track lyrics");
+        return Ok(());
+    }
+    delete_track_lyrics(db, track_id)?;
+    insert_track_lyrics(db, track_id, new_lyrics)?;
+    Ok(())
+}
+
 fn load_track_tags(db: &crate::Connection<'_>, track_id: RecordId) -> RepoResult<Canonical<Tags>> {
     use crate::db::track_tag::{models::*, schema::*};
     track_tag::table
@@ -389,6 +490,7 @@ fn preload_entity(
         album_actors,
         album_titles,
         cues: load_track_cues(db, id)?,
+        lyrics: load_track_lyrics(db, id)?,
         tags: load_track_tags(db, id)?,
         track_actors,
         track_titles,
@@ -444,6 +546,7 @@ impl<'db> EntityRepo for crate::Connection<'db> {
             created_entity.body.album.actors.as_slice(),
         )?;
         insert_track_cues(self, id, created_entity.body.cues.as_slice())?;
+        insert_track_lyrics(self, id, created_entity.body.lyrics.as_slice())?;
         insert_track_tags(self, id, &created_entity.body.tags)?;
         Ok(id)
     }
@@ -481,6 +584,7 @@ impl<'db> EntityRepo for crate::Connection<'db> {
             updated_entity.body.album.actors.as_slice(),
         )?;
         update_track_cues(self, id, updated_entity.body.cues.as_slice())?;
+        update_track_lyrics(self, id, updated_entity.body.lyrics.as_slice())?;
         update_track_tags(self, id, &updated_entity.body.tags)?;
         Ok(())
     }
@@ -489,6 +593,7 @@ impl<'db> EntityRepo for crate::Connection<'db> {
         delete_track_and_album_titles(self, id)?;
         delete_track_and_album_actors(self, id)?;
         delete_track_cues(self, id)?;
+        delete_track_lyrics(self, id)?;
         delete_track_tags(self, id)?;
         let target = track::table.filter(track::row_id.eq(RowId::from(id)));
         let query = diesel::delete(target);
@@ -610,6 +715,13 @@ impl<'db> EntityRepo for crate::Connection<'db> {
                 }
                 track.media_source.collected_at = entity.body.media_source.collected_at;
             }
+            // The waveform is not exchanged with the track and must
+            // only be replaced by a newly generated waveform
+            let Content::Audio(audio_content) = &mut track.media_source.content;
+            if audio_content.waveform.is_none() {
+                let Content::Audio(loaded_audio_content) = &entity.body.media_source.content;
+                audio_content.waveform = loaded_audio_content.waveform.clone();
+            }
             if track == entity.body {
                 return Ok(ReplaceOutcome::Unchanged(media_source_id, id, entity));
             }
@@ -731,4 +843,33 @@ impl<'db> EntityRepo for crate::Connection<'db> {
                 count as u64
             })
     }
+
+    fn load_track_media_source_fingerprints(
+        &self,
+        ids: &[RecordId],
+    ) -> RepoResult<Vec<(RecordId, Fingerprint)>> {
+        let rows = track::table
+            .inner_join(
+                media_source_fingerprint::table
+                    .on(media_source_fingerprint::source_id.eq(track::media_source_id)),
+            )
+            .select((track::row_id, media_source_fingerprint::fingerprint))
+            .filter(track::row_id.eq_any(ids.iter().copied().map(RowId::from)))
+            .load::<(RowId, Vec<u8>)>(self.as_ref())
+            .map_err(repo_error)?;
+        rows.into_iter()
+            .map(|(row_id, bytes)| -> RepoResult<_> {
+                let fingerprint = Fingerprint::from_bytes(&bytes)
+                    .ok_or_else(|| anyhow::anyhow!("Invalid fingerprint of media source"))?;
+                Ok((row_id.into(), fingerprint))
+            })
+            .collect()
+    }
 }
+
+///////////////////////////////////////////////////////////////////////
+// Tests
+///////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests;
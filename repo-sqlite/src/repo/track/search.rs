@@ -16,7 +16,8 @@
 use crate::{
     db::{
         media_source::schema::*, media_tracker::schema::*, playlist::schema::*,
-        playlist_entry::schema::*, track::schema::*, track_cue::schema::*, track_tag::schema::*,
+        playlist_entry::schema::*, track::schema::*, track_cue::schema::*, track_lyrics::schema::*,
+        track_tag::schema::*,
     },
     prelude::*,
 };
@@ -779,6 +780,52 @@ fn build_condition_filter_expression(
     }
 }
 
+/// Filter a boxed select statement by comparing a text column
+/// with a string predicate
+///
+/// The qualified column name is needed for building the SQL
+/// expression of prefix comparisons literally.
+macro_rules! filter_by_string_predicate {
+    ($select:expr, $column:expr, $column_sql:literal, $predicate:expr) => {{
+        let select = $select;
+        let (cmp, val, dir) = $predicate.into();
+        let string_cmp_op = match cmp {
+            // Equal comparison without escape characters
+            StringCompare::Equals => StringCmpOp::Equal(val.to_owned()),
+            StringCompare::Prefix => StringCmpOp::Prefix(escape_single_quotes(val), val.len()),
+            // Like comparisons with escaped wildcard character
+            StringCompare::StartsWith => StringCmpOp::Like(escape_like_starts_with(val)),
+            StringCompare::EndsWith => StringCmpOp::Like(escape_like_ends_with(val)),
+            StringCompare::Contains => StringCmpOp::Like(escape_like_contains(val)),
+            StringCompare::Matches => StringCmpOp::Like(escape_like_matches(val)),
+        };
+        match string_cmp_op {
+            StringCmpOp::Equal(eq) => {
+                if dir {
+                    select.filter($column.eq(eq))
+                } else {
+                    select.filter($column.ne(eq))
+                }
+            }
+            StringCmpOp::Prefix(prefix, len) => {
+                let sql_prefix_filter = if dir {
+                    format!(concat!("substr(", $column_sql, ",1,{})='{}'"), len, prefix)
+                } else {
+                    format!(concat!("substr(", $column_sql, ",1,{})<>'{}'"), len, prefix)
+                };
+                select.filter(diesel::dsl::sql(&sql_prefix_filter))
+            }
+            StringCmpOp::Like(like) => {
+                if dir {
+                    select.filter($column.like(like).escape(LIKE_ESCAPE_CHARACTER))
+                } else {
+                    select.filter($column.not_like(like).escape(LIKE_ESCAPE_CHARACTER))
+                }
+            }
+        }
+    }};
+}
+
 fn select_track_ids_matching_tag_filter<'a, DB>(
     tag_filter: &'a TagFilter,
 ) -> (
@@ -808,45 +855,12 @@ where
 
     // Filter labels
     if let Some(ref label) = tag_filter.label {
-        let (cmp, val, dir) = label.borrow().into();
-        let string_cmp_op = match cmp {
-            // Equal comparison without escape characters
-            StringCompare::Equals => StringCmpOp::Equal(val.to_owned()),
-            StringCompare::Prefix => StringCmpOp::Prefix(escape_single_quotes(val), val.len()),
-            // Like comparisons with escaped wildcard character
-            StringCompare::StartsWith => StringCmpOp::Like(escape_like_starts_with(val)),
-            StringCompare::EndsWith => StringCmpOp::Like(escape_like_ends_with(val)),
-            StringCompare::Contains => StringCmpOp::Like(escape_like_contains(val)),
-            StringCompare::Matches => StringCmpOp::Like(escape_like_matches(val)),
-        };
-        select = match string_cmp_op {
-            StringCmpOp::Equal(eq) => {
-                if dir {
-                    select.filter(track_tag::label.eq(eq))
-                } else {
-                    select.filter(track_tag::label.ne(eq))
-                }
-            }
-            StringCmpOp::Prefix(prefix, len) => {
-                let sql_prefix_filter = if dir {
-                    format!("substr(track_tag.label,1,{})='{}'", len, prefix)
-                } else {
-                    format!("substr(track_tag.label,1,{})<>'{}'", len, prefix)
-                };
-                select.filter(diesel::dsl::sql(&sql_prefix_filter))
-            }
-            StringCmpOp::Like(like) => {
-                if dir {
-                    select.filter(track_tag::label.like(like).escape(LIKE_ESCAPE_CHARACTER))
-                } else {
-                    select.filter(
-                        track_tag::label
-                            .not_like(like)
-                            .escape(LIKE_ESCAPE_CHARACTER),
-                    )
-                }
-            }
-        };
+        select = filter_by_string_predicate!(
+            select,
+            track_tag::label,
+            "track_tag.label",
+            label.borrow()
+        );
     }
 
     // Filter tag score
@@ -912,50 +926,48 @@ where
 
     // Filter labels
     if let Some(label) = cue_label_filter.value {
-        let (cmp, val, dir) = label.into();
-        let string_cmp_op = match cmp {
-            // Equal comparison without escape characters
-            StringCompare::Equals => StringCmpOp::Equal(val.to_owned()),
-            StringCompare::Prefix => StringCmpOp::Prefix(escape_single_quotes(val), val.len()),
-            // Like comparisons with escaped wildcard character
-            StringCompare::StartsWith => StringCmpOp::Like(escape_like_starts_with(val)),
-            StringCompare::EndsWith => StringCmpOp::Like(escape_like_ends_with(val)),
-            StringCompare::Contains => StringCmpOp::Like(escape_like_contains(val)),
-            StringCompare::Matches => StringCmpOp::Like(escape_like_matches(val)),
-        };
-        select = match string_cmp_op {
-            StringCmpOp::Equal(eq) => {
-                if dir {
-                    select.filter(track_cue::label.eq(eq))
-                } else {
-                    select.filter(track_cue::label.ne(eq))
-                }
-            }
-            StringCmpOp::Prefix(prefix, len) => {
-                let sql_prefix_filter = if dir {
-                    format!("substr(track_cue.label,1,{})='{}'", len, prefix)
-                } else {
-                    format!("substr(track_cue.label,1,{})<>'{}'", len, prefix)
-                };
-                select.filter(diesel::dsl::sql(&sql_prefix_filter))
-            }
-            StringCmpOp::Like(like) => {
-                if dir {
-                    select.filter(track_cue::label.like(like).escape(LIKE_ESCAPE_CHARACTER))
-                } else {
-                    select.filter(
-                        track_cue::label
-                            .not_like(like)
-                            .escape(LIKE_ESCAPE_CHARACTER),
-                    )
-                }
-            }
-        };
+        select = filter_by_string_predicate!(select, track_cue::label, "track_cue.label", label);
     }
 
     (select, cue_label_filter.modifier)
 }
 
+fn build_lyrics_text_filter_expression(
+    filter: StringFilterBorrowed<'_>,
+) -> TrackSearchBoxedExpression<'_> {
+    let (subselect, filter_modifier) = select_track_ids_matching_lyrics_filter(filter);
+    match filter_modifier {
+        None => Box::new(track::row_id.eq_any(subselect)),
+        Some(FilterModifier::Complement) => Box::new(track::row_id.ne_all(subselect)),
+    }
+}
+
+fn select_track_ids_matching_lyrics_filter<'s, 'db, DB>(
+    lyrics_text_filter: StringFilterBorrowed<'s>,
+) -> (
+    diesel::query_builder::BoxedSelectStatement<
+        'db,
+        diesel::sql_types::BigInt,
+        track_lyrics::table,
+        DB,
+    >,
+    Option<FilterModifier>,
+)
+where
+    DB: diesel::backend::Backend + 'db,
+{
+    let mut select = track_lyrics::table
+        .select(track_lyrics::track_id)
+        .into_boxed();
+
+    // Filter text of both unsynchronized and synchronized lyrics
+    if let Some(text) = lyrics_text_filter.value {
+        select = filter_by_string_predicate!(select, track_lyrics::text, "track_lyrics.text", text);
+    }
+
+    (select, lyrics_text_filter.modifier)
+}
+
 fn build_playlist_uid_filter_expression(
     playlist_uid: &EntityUid,
 ) -> TrackSearchBoxedExpression<'_> {
@@ -990,6 +1002,7 @@ impl TrackSearchBoxedExpressionBuilder for SearchFilter {
             Condition(filter) => build_condition_filter_expression(*filter),
             Tag(filter) => build_tag_filter_expression(filter),
             CueLabel(filter) => build_cue_label_filter_expression(filter.borrow()),
+            LyricsText(filter) => build_lyrics_text_filter_expression(filter.borrow()),
             PlaylistUid(playlist_uid) => build_playlist_uid_filter_expression(playlist_uid),
             All(filters) => filters
                 .iter()
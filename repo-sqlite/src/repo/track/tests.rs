@@ -13,115 +13,273 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+///////////////////////////////////////////////////////////////////////
+
 use super::*;
 
-use aoide_core::entity::EntityHeader;
+use crate::prelude::tests::*;
 
-embed_migrations!("migrations");
+use aoide_core::{
+    audio::{
+        waveform::{WaveformBin, WaveformOverview},
+        AudioContent, DurationMs, PositionMs,
+    },
+    collection::{Collection, Entity as CollectionEntity, MediaSourceConfig},
+    media::{SourcePath, SourcePathKind},
+    util::color::Color,
+};
 
-fn establish_connection() -> SqliteConnection {
-    let connection =
-        SqliteConnection::establish(":memory:").expect("in-memory database connection");
-    embedded_migrations::run(&connection).expect("database schema migration");
-    connection
-}
+use aoide_repo::collection::EntityRepo as _;
 
-fn create_collection(repo: &dyn EntityRepo, collection: Collection) -> RepoResult<Entity> {
-    let entity = Entity::new(EntityHeader::initial_random(), collection);
-    repo.insert_collection_entity(DateTime::now(), &entity)
-        .and(Ok(entity))
+struct Fixture {
+    db: SqliteConnection,
+    collection_id: CollectionId,
 }
 
-#[test]
-fn insert_collection() {
-    let db_connection = establish_connection();
-    let connection = crate::Connection::from(&db_connection);
-    let entity = create_collection(
-        &connection,
-        Collection {
-            title: "Test Collection".into(),
-            notes: Some("Some personal notes".into()),
+impl Fixture {
+    fn new() -> TestResult<Self> {
+        let collection = Collection {
+            title: "Collection".into(),
+            notes: None,
             kind: None,
             color: None,
-        },
-    )
-    .unwrap();
-    println!("Created entity: {:?}", entity);
+            media_source_config: MediaSourceConfig {
+                path_kind: SourcePathKind::VirtualFilePath,
+                base_url: None,
+            },
+        };
+        let db = establish_connection()?;
+        let collection_entity = CollectionEntity::new(EntityHeader::initial_random(), collection);
+        let collection_id = crate::Connection::new(&db)
+            .insert_collection_entity(DateTime::now_utc(), &collection_entity)?;
+        Ok(Self { db, collection_id })
+    }
+
+    fn create_media_source_and_track(
+        &self,
+        path: &str,
+        track: Track,
+    ) -> RepoResult<(MediaSourceId, RecordId)> {
+        let db = crate::Connection::new(&self.db);
+        let created_at = DateTime::now_utc();
+        let media_source = Source {
+            path: SourcePath::new(path.to_owned()),
+            ..track.media_source.clone()
+        };
+        let media_source_id = db
+            .insert_media_source(created_at, self.collection_id, &media_source)?
+            .id;
+        let track_entity = Entity::new(
+            EntityHeader::initial_random(),
+            Track {
+                media_source,
+                ..track
+            },
+        );
+        let track_id = db.insert_track_entity(created_at, media_source_id, &track_entity)?;
+        Ok((media_source_id, track_id))
+    }
+}
+
+fn new_track() -> Track {
+    Track::new_from_media_source(Source {
+        collected_at: DateTime::now_local(),
+        synchronized_at: Some(DateTime::now_utc()),
+        path: SourcePath::new("file.mp3".to_owned()),
+        content_type: "audio/mpeg".to_owned(),
+        content_digest: None,
+        content_metadata_flags: Default::default(),
+        content: AudioContent {
+            duration: Some(DurationMs::from_inner(1.0)),
+            ..Default::default()
+        }
+        .into(),
+        artwork: Default::default(),
+    })
 }
 
 #[test]
-fn update_collection() -> RepoResult<()> {
-    let db_connection = establish_connection();
-    let connection = crate::Connection::from(&db_connection);
-
-    let mut entity = create_collection(
-        &connection,
-        Collection {
-            title: "Test Collection".into(),
-            notes: Some("Description".into()),
-            kind: None,
-            color: None,
-        },
+fn load_track_media_source_fingerprints() -> anyhow::Result<()> {
+    let fixture = Fixture::new()?;
+    let db = crate::Connection::new(&fixture.db);
+
+    let (media_source_id1, track_id1) =
+        fixture.create_media_source_and_track("file1.mp3", new_track())?;
+    let (_, track_id2) = fixture.create_media_source_and_track("file2.mp3", new_track())?;
+    let (media_source_id3, track_id3) =
+        fixture.create_media_source_and_track("file3.mp3", new_track())?;
+
+    let fingerprint1 = Fingerprint::new(vec![1, 2, 3]);
+    db.update_media_source_fingerprint(media_source_id1, Some(&fingerprint1))?;
+    let fingerprint3 = Fingerprint::new(vec![4, 5, 6]);
+    db.update_media_source_fingerprint(media_source_id3, Some(&fingerprint3))?;
+
+    // Tracks without a fingerprint are omitted
+    let mut fingerprints =
+        db.load_track_media_source_fingerprints(&[track_id1, track_id2, track_id3])?;
+    fingerprints.sort_by_key(|(track_id, _)| *track_id);
+    assert_eq!(
+        vec![(track_id1, fingerprint1), (track_id3, fingerprint3.clone())],
+        fingerprints
+    );
+
+    // Only the requested tracks are loaded
+    assert_eq!(
+        vec![(track_id3, fingerprint3)],
+        db.load_track_media_source_fingerprints(&[track_id2, track_id3])?
+    );
+    assert!(db.load_track_media_source_fingerprints(&[])?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn replace_collected_track_preserves_waveform() -> anyhow::Result<()> {
+    let fixture = Fixture::new()?;
+    let db = crate::Connection::new(&fixture.db);
+
+    let waveform = WaveformOverview::new(vec![WaveformBin::default(); 2]);
+    let mut track = new_track();
+    let Content::Audio(audio_content) = &mut track.media_source.content;
+    audio_content.waveform = Some(waveform.clone());
+    fixture.create_media_source_and_track("file.mp3", track)?;
+
+    // A track without a waveform, e.g. deserialized from JSON
+    let mut replacement = new_track();
+    replacement.color = Some(Color::Index(1));
+    let outcome = db.replace_collected_track_by_media_source_path(
+        fixture.collection_id,
+        true,
+        ReplaceMode::UpdateOnly,
+        replacement,
     )?;
-    let id = connection.resolve_collection_id(&entity.hdr.uid)?;
-
-    // Bump revision number for testing
-    let outdated_rev = entity.hdr.rev;
-    entity.hdr.rev = outdated_rev.next();
-    connection.update_collection_entity(id, DateTime::now(), &entity)?;
-    assert_eq!(entity, connection.load_collection_entity(id)?.1);
-
-    // Prepare update
-    let mut updated_entity = entity.clone();
-    updated_entity.hdr.rev = updated_entity.hdr.rev.next();
-    updated_entity.body.title = "Retitled Collection".into();
-    assert_ne!(entity, updated_entity);
-
-    // Outdated revision -> Conflict
-    assert!(matches!(
-        connection.update_collection_entity_revision(
-            DateTime::now(),
-            &outdated_rev,
-            &updated_entity,
-        ),
-        Err(RepoError::Conflict),
-    ));
-    // Unchanged
-    assert_eq!(entity, connection.load_collection_entity(id)?.1);
-
-    // Current revision -> Success
-    let current_rev = connection.load_collection_entity(id)?.1.hdr.rev;
-    connection.update_collection_entity_revision(
-        DateTime::now(),
-        &current_rev,
+    let entity = match outcome {
+        ReplaceOutcome::Updated(_, _, entity) => entity,
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    };
+    let Content::Audio(audio_content) = &entity.body.media_source.content;
+    assert_eq!(Some(&waveform), audio_content.waveform.as_ref());
+
+    let (_, _, loaded) =
+        db.load_track_entity_by_media_source_path(fixture.collection_id, "file.mp3")?;
+    let Content::Audio(audio_content) = &loaded.body.media_source.content;
+    assert_eq!(Some(&waveform), audio_content.waveform.as_ref());
+
+    Ok(())
+}
+
+fn new_lyrics() -> Vec<Lyrics> {
+    vec![
+        Lyrics {
+            language: None,
+            text: Some("Plain text".to_owned()),
+            lines: vec![],
+        },
+        Lyrics {
+            language: Some("eng".to_owned()),
+            text: Some("First line\nChorus".to_owned()),
+            lines: vec![
+                SyncedLine {
+                    position: PositionMs(1_000.0),
+                    text: "First line".to_owned(),
+                },
+                SyncedLine {
+                    position: PositionMs(2_500.0),
+                    text: "Chorus".to_owned(),
+                },
+            ],
+        },
+    ]
+}
+
+#[test]
+fn insert_update_and_load_track_lyrics() -> anyhow::Result<()> {
+    let fixture = Fixture::new()?;
+    let db = crate::Connection::new(&fixture.db);
+
+    let lyrics = new_lyrics();
+    let mut track = new_track();
+    track.lyrics = Canonical::tie(lyrics.clone());
+    let (media_source_id, track_id) = fixture.create_media_source_and_track("file.mp3", track)?;
+
+    let (_, entity) = db.load_track_entity(track_id)?;
+    assert_eq!(lyrics, *entity.body.lyrics);
+
+    // Replace all lyrics
+    let updated_lyrics = vec![Lyrics {
+        language: Some("deu".to_owned()),
+        text: None,
+        lines: vec![SyncedLine {
+            position: PositionMs(500.0),
+            text: "Erste Zeile".to_owned(),
+        }],
+    }];
+    let mut updated_entity = entity;
+    updated_entity.body.lyrics = Canonical::tie(updated_lyrics.clone());
+    db.update_track_entity(
+        track_id,
+        DateTime::now_utc(),
+        media_source_id,
         &updated_entity,
     )?;
-    assert_eq!(updated_entity, connection.load_collection_entity(id)?.1);
-
-    // Revert update
-    connection.update_collection_entity(id, DateTime::now(), &entity)?;
-    assert_eq!(entity, connection.load_collection_entity(id)?.1);
+    let (_, entity) = db.load_track_entity(track_id)?;
+    assert_eq!(updated_lyrics, *entity.body.lyrics);
 
     Ok(())
 }
 
 #[test]
-fn delete_collection() -> RepoResult<()> {
-    let db_connection = establish_connection();
-    let connection = crate::Connection::from(&db_connection);
-    let entity = create_collection(
-        &connection,
-        Collection {
-            title: "Test Collection".into(),
-            notes: None,
-            kind: None,
-            color: None,
-        },
-    )
-    .unwrap();
-    println!("Created entity: {:?}", entity);
-    let id = connection.resolve_collection_id(&entity.hdr.uid)?;
-    connection.delete_collection_entity(id)?;
-    println!("Removed entity: {}", entity.hdr.uid);
+fn search_tracks_by_lyrics_text() -> anyhow::Result<()> {
+    let fixture = Fixture::new()?;
+    let db = crate::Connection::new(&fixture.db);
+
+    let mut track = new_track();
+    track.lyrics = Canonical::tie(new_lyrics());
+    let (_, track_id1) = fixture.create_media_source_and_track("file1.mp3", track)?;
+    let (_, track_id2) = fixture.create_media_source_and_track("file2.mp3", new_track())?;
+
+    let search_track_ids = |filter: SearchFilter| -> RepoResult<Vec<RecordId>> {
+        let mut collected = Vec::new();
+        db.search_collected_tracks(
+            fixture.collection_id,
+            &Default::default(),
+            Some(filter),
+            vec![],
+            &mut collected,
+        )?;
+        Ok(collected
+            .into_iter()
+            .map(|(record_header, _): (RecordHeader, Entity)| record_header.id)
+            .collect())
+    };
+
+    // Both plain text and synchronized lines are matched
+    assert_eq!(
+        vec![track_id1],
+        search_track_ids(SearchFilter::LyricsText(StringFilter {
+            modifier: None,
+            value: Some(StringPredicate::Contains("plain".to_owned())),
+        }))?
+    );
+    assert_eq!(
+        vec![track_id1],
+        search_track_ids(SearchFilter::LyricsText(StringFilter {
+            modifier: None,
+            value: Some(StringPredicate::Equals("Chorus".to_owned())),
+        }))?
+    );
+    assert_eq!(
+        vec![track_id2],
+        search_track_ids(SearchFilter::LyricsText(StringFilter {
+            modifier: Some(FilterModifier::Complement),
+            value: Some(StringPredicate::Prefix("First".to_owned())),
+        }))?
+    );
+    assert!(search_track_ids(SearchFilter::LyricsText(StringFilter {
+        modifier: None,
+        value: Some(StringPredicate::StartsWith("Verse".to_owned())),
+    }))?
+    .is_empty());
+
     Ok(())
 }
@@ -19,7 +19,11 @@ pub type RecordHeader = crate::RecordHeader<RecordId>;
 
 use crate::{collection::RecordId as CollectionId, prelude::*};
 
-use aoide_core::{media::Source, util::clock::DateTime};
+use aoide_core::{
+    audio::fingerprint::Fingerprint,
+    media::{Digest, Source},
+    util::clock::DateTime,
+};
 
 pub trait Repo {
     fn resolve_media_source_id_synchronized_at_by_path(
@@ -54,6 +58,16 @@ pub trait Repo {
         path: &str,
     ) -> RepoResult<(RecordHeader, Source)>;
 
+    fn update_media_source_fingerprint(
+        &self,
+        id: RecordId,
+        fingerprint: Option<&Fingerprint>,
+    ) -> RepoResult<()>;
+    fn load_media_source_fingerprint(&self, id: RecordId) -> RepoResult<Option<Fingerprint>>;
+
+    /// Load the distinct digests of the artwork of all media sources
+    fn load_all_artwork_digests(&self) -> RepoResult<Vec<Digest>>;
+
     fn relocate_media_sources_by_path_prefix(
         &self,
         updated_at: DateTime,
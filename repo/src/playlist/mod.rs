@@ -58,6 +58,8 @@ pub trait EntityRepo {
         created_at: DateTime,
         created_entity: &Entity,
     ) -> RepoResult<RecordId>;
+
+    fn load_playlist_collection_id(&self, id: RecordId) -> RepoResult<CollectionId>;
 }
 
 pub fn prepend_playlist_entries_default<R: EntryRepo + ?Sized>(
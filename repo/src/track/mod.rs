@@ -25,7 +25,7 @@ record_id_newtype!(RecordId);
 pub type RecordHeader = crate::RecordHeader<RecordId>;
 
 use aoide_core::{
-    audio::DurationMs,
+    audio::{fingerprint::Fingerprint, DurationMs},
     entity::{EntityHeader, EntityRevision, EntityUid},
     track::{release::DateOrDateTime, Entity, Track},
     util::clock::DateTime,
@@ -145,6 +145,7 @@ pub enum SearchFilter {
     Condition(ConditionFilter),
     Tag(tag::Filter),
     CueLabel(StringFilter),
+    LyricsText(StringFilter),
     PlaylistUid(EntityUid),
     All(Vec<SearchFilter>),
     Any(Vec<SearchFilter>),
@@ -293,4 +294,12 @@ pub trait EntityRepo: MediaSourceRepo {
     ) -> RepoResult<usize>;
 
     fn count_collected_tracks(&self, collection_id: CollectionId) -> RepoResult<u64>;
+
+    /// Load the acoustic fingerprints of the media sources of multiple tracks
+    ///
+    /// Tracks without a fingerprint are omitted from the results.
+    fn load_track_media_source_fingerprints(
+        &self,
+        ids: &[RecordId],
+    ) -> RepoResult<Vec<(RecordId, Fingerprint)>>;
 }
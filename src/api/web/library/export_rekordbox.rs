@@ -0,0 +1,60 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+mod uc {
+    pub use crate::usecases::library::rekordbox::*;
+}
+
+mod _core {
+    pub use aoide_core::entity::EntityUid;
+}
+
+use aoide_core_serde::entity::EntityUid;
+
+///////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Params {
+    /// Export only the given playlists and their tracks instead
+    /// of the whole collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub playlist_uids: Option<Vec<EntityUid>>,
+}
+
+pub type RequestBody = Params;
+
+/// The contents of the `rekordbox.xml` file
+pub type ResponseBody = String;
+
+pub fn handle_request(
+    pooled_connection: SqlitePooledConnection,
+    collection_uid: &_core::EntityUid,
+    request_body: RequestBody,
+) -> Result<ResponseBody> {
+    let RequestBody { playlist_uids } = request_body;
+    let playlist_uids: Option<Vec<_core::EntityUid>> =
+        playlist_uids.map(|uids| uids.into_iter().map(Into::into).collect());
+    Ok(uc::export_xml(
+        &pooled_connection,
+        collection_uid,
+        &faceted_tag_mapping_config(),
+        playlist_uids.as_deref(),
+    )?)
+}
@@ -0,0 +1,62 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+mod uc {
+    pub use crate::usecases::library::traktor::*;
+}
+
+use aoide_core::entity::EntityUid;
+
+use url::Url;
+
+///////////////////////////////////////////////////////////////////////
+
+pub type QueryParams = ImportQueryParams;
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Params {
+    /// The file URL of the `collection.nml`
+    pub url: Url,
+}
+
+pub type RequestBody = Params;
+pub type ResponseBody = ImportSummary;
+
+pub fn handle_request(
+    pooled_connection: SqlitePooledConnection,
+    collection_uid: &EntityUid,
+    query_params: QueryParams,
+    request_body: RequestBody,
+) -> Result<ResponseBody> {
+    let QueryParams { replace_mode } = query_params;
+    let replace_mode = replace_mode.unwrap_or(ReplaceMode::UpdateOrCreate);
+    let RequestBody { url } = request_body;
+    let nml_file_path = url
+        .to_file_path()
+        .map_err(|()| anyhow::anyhow!("Invalid file URL: {}", url))?;
+    Ok(uc::import_nml_file(
+        &pooled_connection,
+        collection_uid,
+        replace_mode.into(),
+        &faceted_tag_mapping_config(),
+        &nml_file_path,
+    )
+    .map(Into::into)?)
+}
@@ -0,0 +1,83 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::api::web::tracks::{import_and_replace::Summary as TracksSummary, replace::ReplaceMode};
+
+mod _uc {
+    pub use aoide_usecases::library::Summary;
+}
+
+use aoide_core::track::tag::{FACET_GENRE, FACET_MOOD};
+use aoide_core_serde::playlist::Entity as PlaylistEntity;
+use aoide_media::util::tag::{
+    FacetedTagMappingConfig, FacetedTagMappingConfigInner, TagMappingConfig,
+};
+
+///////////////////////////////////////////////////////////////////////
+
+pub mod export_rekordbox;
+pub mod export_traktor;
+pub mod import_itunes;
+pub mod import_mixxx;
+pub mod import_rekordbox;
+pub mod import_traktor;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub tracks: TracksSummary,
+    pub playlists: Vec<PlaylistEntity>,
+}
+
+impl From<_uc::Summary> for ImportSummary {
+    fn from(from: _uc::Summary) -> Self {
+        let _uc::Summary { tracks, playlists } = from;
+        Self {
+            tracks: tracks.into(),
+            playlists: playlists.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct ImportQueryParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replace_mode: Option<ReplaceMode>,
+}
+
+fn faceted_tag_mapping_config() -> FacetedTagMappingConfig {
+    // FIXME: Replace hard-coded tag mapping config
+    let mut faceted_tag_mapping_config = FacetedTagMappingConfigInner::default();
+    faceted_tag_mapping_config.insert(
+        FACET_GENRE.to_owned().into(),
+        TagMappingConfig {
+            label_separator: ";".into(),
+            split_score_attenuation: 0.75,
+        },
+    );
+    faceted_tag_mapping_config.insert(
+        FACET_MOOD.to_owned().into(),
+        TagMappingConfig {
+            label_separator: ";".into(),
+            split_score_attenuation: 0.75,
+        },
+    );
+    faceted_tag_mapping_config.into()
+}
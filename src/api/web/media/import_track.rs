@@ -43,7 +43,10 @@ pub struct QueryParams {
 
 pub type ResponseBody = Option<Track>;
 
-pub fn handle_request(query_params: QueryParams) -> Result<ResponseBody> {
+pub fn handle_request(
+    sidecar_artwork_file_names: &[String],
+    query_params: QueryParams,
+) -> Result<ResponseBody> {
     let QueryParams { url } = query_params;
     // FIXME: Replace hard-coded tag mapping config
     let mut faceted_tag_mapping_config = FacetedTagMappingConfigInner::default();
@@ -63,6 +66,7 @@ pub fn handle_request(query_params: QueryParams) -> Result<ResponseBody> {
     );
     let config = ImportTrackConfig {
         faceted_tag_mapping: faceted_tag_mapping_config.into(),
+        sidecar_artwork_file_names: sidecar_artwork_file_names.to_vec(),
     };
     let source_path = match VirtualFilePathResolver::new().resolve_path_from_url(&url) {
         Ok(path) => path,
@@ -86,10 +90,17 @@ pub fn handle_request(query_params: QueryParams) -> Result<ResponseBody> {
         source_path,
         uc::SynchronizedImportMode::Always,
         &config,
-        ImportTrackFlags::all(),
+        // Only read the metadata without decoding and analyzing the audio stream
+        ImportTrackFlags::all()
+            - ImportTrackFlags::ANALYZE_LOUDNESS
+            - ImportTrackFlags::ANALYZE_TEMPO
+            - ImportTrackFlags::ANALYZE_KEY_SIGNATURE
+            - ImportTrackFlags::ANALYZE_FINGERPRINT
+            - ImportTrackFlags::ANALYZE_WAVEFORM,
         DateTime::now_local(),
+        &mut uc::SidecarArtworkFileCache::new(),
     )? {
-        uc::ImportTrackFromFileOutcome::Imported(track) => Some(track),
+        uc::ImportTrackFromFileOutcome::Imported(track, _) => Some(track),
         uc::ImportTrackFromFileOutcome::SkippedSynchronized(_) => unreachable!(),
         uc::ImportTrackFromFileOutcome::SkippedDirectory => None,
     };
@@ -14,6 +14,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use aoide_core::media::resolver::VirtualFilePathResolver;
+use aoide_media::io::import::ImportTrackFlags;
 
 use super::*;
 
@@ -44,3 +45,52 @@ impl From<ImportMode> for uc::ImportMode {
         }
     }
 }
+
+/// Selects the analyses that are performed on the decoded audio stream
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct AnalyzeAudioParams {
+    #[serde(default)]
+    pub loudness: bool,
+
+    #[serde(default)]
+    pub tempo: bool,
+
+    #[serde(default)]
+    pub key: bool,
+
+    #[serde(default)]
+    pub fingerprint: bool,
+
+    #[serde(default)]
+    pub waveform: bool,
+}
+
+impl AnalyzeAudioParams {
+    pub fn import_flags(self) -> ImportTrackFlags {
+        let Self {
+            loudness,
+            tempo,
+            key,
+            fingerprint,
+            waveform,
+        } = self;
+        let mut flags = ImportTrackFlags::empty();
+        if loudness {
+            flags |= ImportTrackFlags::ANALYZE_LOUDNESS;
+        }
+        if tempo {
+            flags |= ImportTrackFlags::ANALYZE_TEMPO;
+        }
+        if key {
+            flags |= ImportTrackFlags::ANALYZE_KEY_SIGNATURE;
+        }
+        if fingerprint {
+            flags |= ImportTrackFlags::ANALYZE_FINGERPRINT;
+        }
+        if waveform {
+            flags |= ImportTrackFlags::ANALYZE_WAVEFORM;
+        }
+        flags
+    }
+}
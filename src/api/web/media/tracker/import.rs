@@ -51,6 +51,9 @@ pub struct Params {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub import_mode: Option<ImportMode>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analyze_audio: Option<AnalyzeAudioParams>,
 }
 
 #[derive(Clone, Debug, Default, Serialize)]
@@ -168,6 +171,7 @@ pub type ResponseBody = Outcome;
 
 pub fn handle_request(
     pooled_connection: SqlitePooledConnection,
+    sidecar_artwork_file_names: &[String],
     collection_uid: &_core::EntityUid,
     request_body: RequestBody,
     progress_summary_tx: Option<&watch::Sender<uc::Summary>>,
@@ -176,6 +180,7 @@ pub fn handle_request(
     let RequestBody {
         root_url,
         import_mode,
+        analyze_audio,
     } = request_body;
     let import_mode = import_mode.unwrap_or(ImportMode::Modified);
     // FIXME: Replace hard-coded tag mapping config
@@ -196,12 +201,14 @@ pub fn handle_request(
     );
     let import_config = ImportTrackConfig {
         faceted_tag_mapping: faceted_tag_mapping_config.into(),
+        sidecar_artwork_file_names: sidecar_artwork_file_names.to_vec(),
     };
     // FIXME: Replace hard-coded import flags
     let import_flags = ImportTrackFlags::ARTWORK_DIGEST
         | ImportTrackFlags::ITUNES_ID3V2_GROUPING_MOVEMENT_WORK
         | ImportTrackFlags::MIXXX_CUSTOM_TAGS
-        | ImportTrackFlags::SERATO_TAGS;
+        | ImportTrackFlags::SERATO_TAGS
+        | analyze_audio.unwrap_or_default().import_flags();
     Ok(uc::import(
         &pooled_connection,
         collection_uid,
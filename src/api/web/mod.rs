@@ -44,6 +44,7 @@ use thiserror::Error;
 ///////////////////////////////////////////////////////////////////////
 
 pub mod collections;
+pub mod library;
 pub mod media;
 pub mod playlists;
 pub mod tracks;
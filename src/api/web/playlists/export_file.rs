@@ -0,0 +1,111 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+mod uc {
+    pub use crate::usecases::playlists::{file::*, xspf::export_xspf};
+
+    pub use aoide_media::playlist::{xspf::CONTENT_TYPE as XSPF_CONTENT_TYPE, FileFormat};
+}
+
+use aoide_core_serde::entity::EntityUid as SerdeEntityUid;
+
+///////////////////////////////////////////////////////////////////////
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    M3u,
+    Pls,
+    Xspf,
+}
+
+#[derive(Copy, Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PathMode {
+    Absolute,
+    Relative,
+}
+
+impl From<PathMode> for uc::PathMode {
+    fn from(from: PathMode) -> Self {
+        match from {
+            PathMode::Absolute => Self::Absolute,
+            PathMode::Relative => Self::Relative,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Params {
+    pub playlist_uid: SerdeEntityUid,
+
+    pub format: FileFormat,
+
+    /// Write absolute file paths (default) or paths relative
+    /// to the root directory of the collection
+    #[serde(default)]
+    pub path_mode: Option<PathMode>,
+}
+
+pub type RequestBody = Params;
+
+#[derive(Clone, Debug)]
+pub struct ResponseBody {
+    pub content_type: &'static str,
+
+    pub content: String,
+}
+
+pub fn handle_request(
+    pooled_connection: SqlitePooledConnection,
+    collection_uid: &EntityUid,
+    request_body: RequestBody,
+) -> Result<ResponseBody> {
+    let RequestBody {
+        playlist_uid,
+        format,
+        path_mode,
+    } = request_body;
+    let playlist_uid = playlist_uid.into();
+    let path_mode = path_mode.map(Into::into).unwrap_or(uc::PathMode::Absolute);
+    let file_format = match format {
+        FileFormat::M3u => uc::FileFormat::M3u,
+        FileFormat::Pls => uc::FileFormat::Pls,
+        FileFormat::Xspf => {
+            let content =
+                uc::export_xspf(&pooled_connection, collection_uid, &playlist_uid, path_mode)?;
+            return Ok(ResponseBody {
+                content_type: uc::XSPF_CONTENT_TYPE,
+                content,
+            });
+        }
+    };
+    let content = uc::export_file(
+        &pooled_connection,
+        collection_uid,
+        &playlist_uid,
+        file_format,
+        path_mode,
+    )?;
+    Ok(ResponseBody {
+        content_type: file_format.content_type(),
+        content,
+    })
+}
@@ -0,0 +1,101 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+mod uc {
+    pub use crate::usecases::playlists::{file::*, xspf::import_xspf_file};
+}
+
+use std::path::Path;
+use url::Url;
+
+///////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Params {
+    /// The file URL of an M3U, M3U8, PLS, or XSPF file
+    pub url: Url,
+
+    /// The properties of the new playlist
+    pub playlist: Playlist,
+
+    /// Assign the titles from the playlist file, e.g. of `#EXTINF`
+    /// directives, to the imported entries. Entry titles of XSPF
+    /// files are always imported.
+    #[serde(default)]
+    pub map_entry_titles: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub playlist: EntityWithEntriesSummary,
+
+    /// Locations that could not be resolved into tracks of the collection
+    pub unresolved: Vec<String>,
+}
+
+fn is_xspf_file(file_path: &Path) -> bool {
+    file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("xspf"))
+}
+
+pub type RequestBody = Params;
+pub type ResponseBody = ImportSummary;
+
+pub fn handle_request(
+    pooled_connection: SqlitePooledConnection,
+    collection_uid: &EntityUid,
+    request_body: RequestBody,
+) -> Result<ResponseBody> {
+    let RequestBody {
+        url,
+        playlist,
+        map_entry_titles,
+    } = request_body;
+    let file_path = url
+        .to_file_path()
+        .map_err(|()| anyhow::anyhow!("Invalid file URL: {}", url))?;
+    let uc::ImportSummary {
+        entity,
+        entries_summary,
+        unresolved,
+    } = if is_xspf_file(&file_path) {
+        uc::import_xspf_file(
+            &pooled_connection,
+            collection_uid,
+            &file_path,
+            playlist.into(),
+        )?
+    } else {
+        uc::import_file(
+            &pooled_connection,
+            collection_uid,
+            &file_path,
+            playlist.into(),
+            map_entry_titles,
+        )?
+    };
+    Ok(ImportSummary {
+        playlist: (entity, entries_summary).into(),
+        unresolved,
+    })
+}
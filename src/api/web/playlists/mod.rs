@@ -41,6 +41,8 @@ use aoide_core_serde::{
 
 pub mod create_collected;
 pub mod delete;
+pub mod export_file;
+pub mod import_file;
 pub mod list_collected;
 pub mod patch_entries;
 pub mod update;
@@ -0,0 +1,105 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use crate::api::web::tracks::search::RequestBody as SearchRequestBody;
+
+mod uc {
+    pub use crate::usecases::tracks::export_metadata::*;
+    pub use aoide_usecases::tracks::export_metadata::{Completion, Outcome, Summary};
+}
+
+use std::sync::atomic::AtomicBool;
+
+///////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Summary {
+    pub exported: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+impl From<uc::Summary> for Summary {
+    fn from(from: uc::Summary) -> Self {
+        let uc::Summary { exported, failed } = from;
+        Self {
+            exported: exported.into_iter().map(Into::into).collect(),
+            failed: failed.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Completion {
+    Finished,
+    Aborted,
+}
+
+impl From<uc::Completion> for Completion {
+    fn from(from: uc::Completion) -> Self {
+        use uc::Completion::*;
+        match from {
+            Finished => Self::Finished,
+            Aborted => Self::Aborted,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Outcome {
+    pub completion: Completion,
+    pub summary: Summary,
+}
+
+impl From<uc::Outcome> for Outcome {
+    fn from(from: uc::Outcome) -> Self {
+        let uc::Outcome {
+            completion,
+            summary,
+        } = from;
+        Self {
+            completion: completion.into(),
+            summary: summary.into(),
+        }
+    }
+}
+
+pub type RequestBody = SearchRequestBody;
+
+pub type ResponseBody = Outcome;
+
+pub fn handle_request(
+    pooled_connection: SqlitePooledConnection,
+    collection_uid: &_core::EntityUid,
+    request_body: RequestBody,
+    abort_flag: &AtomicBool,
+) -> Result<ResponseBody> {
+    let (config, flags) = export_track_config_and_flags();
+    let SearchRequestBody { filter, ordering } = request_body;
+    Ok(uc::export_metadata_into_files_of_search_results(
+        &pooled_connection,
+        collection_uid,
+        filter.map(Into::into),
+        ordering.into_iter().map(Into::into).collect(),
+        &config,
+        flags,
+        abort_flag,
+    )
+    .map(Into::into)?)
+}
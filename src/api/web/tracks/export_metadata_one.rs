@@ -0,0 +1,39 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+mod uc {
+    pub use crate::usecases::tracks::export_metadata::*;
+}
+
+///////////////////////////////////////////////////////////////////////
+
+pub type ResponseBody = ();
+
+pub fn handle_request(
+    pooled_connection: SqlitePooledConnection,
+    collection_uid: &_core::EntityUid,
+    track_uid: &_core::EntityUid,
+) -> Result<ResponseBody> {
+    let (config, flags) = export_track_config_and_flags();
+    Ok(uc::export_metadata_into_file(
+        &pooled_connection,
+        collection_uid,
+        track_uid,
+        &config,
+        flags,
+    )?)
+}
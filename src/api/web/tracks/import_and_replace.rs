@@ -126,6 +126,7 @@ pub type ResponseBody = Outcome;
 
 pub fn handle_request(
     pooled_connection: SqlitePooledConnection,
+    sidecar_artwork_file_names: &[String],
     collection_uid: &_core::EntityUid,
     query_params: QueryParams,
     request_body: RequestBody,
@@ -155,6 +156,7 @@ pub fn handle_request(
     );
     let import_config = ImportTrackConfig {
         faceted_tag_mapping: faceted_tag_mapping_config.into(),
+        sidecar_artwork_file_names: sidecar_artwork_file_names.to_vec(),
     };
     // FIXME: Replace hard-coded import flags
     let import_flags = ImportTrackFlags::ARTWORK_DIGEST
@@ -0,0 +1,144 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+mod uc {
+    pub use crate::usecases::tracks::artwork::*;
+}
+
+use aoide_core::media::{Digest, ImageDimension};
+
+use aoide_core_serde::media::Base64;
+
+use aoide_media::fs::artwork_cache::ArtworkCache;
+
+use warp::http::{header, Response};
+
+///////////////////////////////////////////////////////////////////////
+
+/// The value of the `ETag` header, i.e. the quoted and
+/// base64-encoded digest of the artwork image
+pub fn entity_tag(digest: &Digest) -> String {
+    format!("\"{}\"", Base64::encode(digest).as_ref())
+}
+
+/// Check if any of the entity tags in the value of an
+/// `If-None-Match` header matches the digest
+///
+/// Uses the weak comparison function as required for this header.
+/// The wildcard matches any existing artwork.
+pub fn if_none_match(header_value: &str, digest: &Digest) -> bool {
+    let expected_tag = entity_tag(digest);
+    header_value.split(',').any(|entity_tag| {
+        let entity_tag = entity_tag.trim();
+        entity_tag == "*" || entity_tag.strip_prefix("W/").unwrap_or(entity_tag) == expected_tag
+    })
+}
+
+/// The edge lengths of the squares that resized images fit into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtworkSizes {
+    pub small: ImageDimension,
+    pub medium: ImageDimension,
+    pub large: ImageDimension,
+}
+
+impl Default for ArtworkSizes {
+    fn default() -> Self {
+        Self {
+            small: 64,
+            medium: 256,
+            large: 640,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Disabled if `None`
+    pub artwork_cache: Option<ArtworkCache>,
+
+    pub sizes: ArtworkSizes,
+
+    /// File names of sidecar artwork images that might be loaded
+    /// from the directory of a track
+    pub sidecar_artwork_file_names: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtworkSize {
+    Small,
+    Medium,
+    Large,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryParams {
+    /// The full-resolution image is loaded if `None`
+    pub size: Option<ArtworkSize>,
+}
+
+pub type ResponseBody = Response<Vec<u8>>;
+
+pub fn handle_request(
+    pooled_connection: SqlitePooledConnection,
+    config: &Config,
+    collection_uid: &_core::EntityUid,
+    track_uid: &_core::EntityUid,
+    query_params: QueryParams,
+    if_none_match_header: Option<&str>,
+) -> Result<ResponseBody> {
+    let QueryParams { size } = query_params;
+    let max_dimension = size.map(|size| match size {
+        ArtworkSize::Small => config.sizes.small,
+        ArtworkSize::Medium => config.sizes.medium,
+        ArtworkSize::Large => config.sizes.large,
+    });
+    let outcome = uc::load_artwork_image(
+        &pooled_connection,
+        config.artwork_cache.as_ref(),
+        &config.sidecar_artwork_file_names,
+        collection_uid,
+        track_uid,
+        max_dimension,
+        |digest| {
+            if_none_match_header
+                .map(|header_value| if_none_match(header_value, digest))
+                .unwrap_or(false)
+        },
+    )?;
+    let response = match outcome {
+        uc::Outcome::Unmodified(digest) => Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header(header::ETAG, entity_tag(&digest))
+            .body(Vec::new()),
+        uc::Outcome::Loaded { digest, image } => {
+            let mut builder = Response::builder().header(header::CONTENT_TYPE, image.media_type);
+            if let Some(digest) = digest {
+                builder = builder.header(header::ETAG, entity_tag(&digest));
+            }
+            builder.body(image.data)
+        }
+    };
+    Ok(response.map_err(anyhow::Error::from)?)
+}
+
+#[cfg(test)]
+mod tests;
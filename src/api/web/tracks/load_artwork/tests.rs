@@ -0,0 +1,73 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+const DIGEST: Digest = [1; 32];
+
+const OTHER_DIGEST: Digest = [2; 32];
+
+#[test]
+fn entity_tag_is_quoted_base64url() {
+    assert_eq!(
+        "\"AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE\"",
+        entity_tag(&DIGEST)
+    );
+}
+
+#[test]
+fn if_none_match_single_tag() {
+    assert!(if_none_match(&entity_tag(&DIGEST), &DIGEST));
+    assert!(!if_none_match(&entity_tag(&OTHER_DIGEST), &DIGEST));
+    // Unquoted tags never match
+    assert!(!if_none_match(
+        "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE",
+        &DIGEST
+    ));
+}
+
+#[test]
+fn if_none_match_weak_tag() {
+    let weak_tag = format!("W/{}", entity_tag(&DIGEST));
+    assert!(if_none_match(&weak_tag, &DIGEST));
+    let weak_tag = format!("W/{}", entity_tag(&OTHER_DIGEST));
+    assert!(!if_none_match(&weak_tag, &DIGEST));
+}
+
+#[test]
+fn if_none_match_list_of_tags() {
+    let header_value = format!(
+        "{}, W/{},{}",
+        entity_tag(&OTHER_DIGEST),
+        entity_tag(&DIGEST),
+        entity_tag(&OTHER_DIGEST)
+    );
+    assert!(if_none_match(&header_value, &DIGEST));
+    let header_value = format!(
+        "{} , W/{}",
+        entity_tag(&OTHER_DIGEST),
+        entity_tag(&OTHER_DIGEST)
+    );
+    assert!(!if_none_match(&header_value, &DIGEST));
+}
+
+#[test]
+fn if_none_match_wildcard() {
+    assert!(if_none_match("*", &DIGEST));
+    assert!(if_none_match(" * ", &DIGEST));
+    assert!(!if_none_match("", &DIGEST));
+}
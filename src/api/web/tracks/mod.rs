@@ -19,6 +19,12 @@ mod _core {
     pub use aoide_core::{entity::EntityUid, track::Entity};
 }
 
+use aoide_core::track::tag::{FACET_GENRE, FACET_MOOD};
+use aoide_media::{
+    io::export::{ExportTrackConfig, ExportTrackFlags},
+    util::tag::{FacetedTagMappingConfigInner, TagMappingConfig},
+};
+
 use aoide_repo::{
     prelude::{RecordCollector, ReservableRecordCollector},
     track::RecordHeader,
@@ -28,9 +34,14 @@ use aoide_core_serde::track::Entity;
 
 ///////////////////////////////////////////////////////////////////////
 
+pub mod analyze_audio;
+pub mod export_metadata_many;
+pub mod export_metadata_one;
 pub mod import_and_replace;
+pub mod load_artwork;
 pub mod load_many;
 pub mod load_one;
+pub mod load_waveform;
 pub mod purge;
 pub mod replace;
 pub mod resolve;
@@ -73,3 +84,29 @@ impl ReservableRecordCollector for EntityCollector {
         inner.reserve(additional);
     }
 }
+
+// FIXME: Replace hard-coded tag mapping config and export flags
+fn export_track_config_and_flags() -> (ExportTrackConfig, ExportTrackFlags) {
+    let mut faceted_tag_mapping_config = FacetedTagMappingConfigInner::default();
+    faceted_tag_mapping_config.insert(
+        FACET_GENRE.to_owned().into(),
+        TagMappingConfig {
+            label_separator: ";".into(),
+            split_score_attenuation: 0.75,
+        },
+    );
+    faceted_tag_mapping_config.insert(
+        FACET_MOOD.to_owned().into(),
+        TagMappingConfig {
+            label_separator: ";".into(),
+            split_score_attenuation: 0.75,
+        },
+    );
+    let config = ExportTrackConfig {
+        faceted_tag_mapping: faceted_tag_mapping_config.into(),
+    };
+    let flags = ExportTrackFlags::ITUNES_ID3V2_GROUPING_MOVEMENT_WORK
+        | ExportTrackFlags::MIXXX_CUSTOM_TAGS
+        | ExportTrackFlags::SERATO_TAGS;
+    (config, flags)
+}
@@ -394,6 +394,7 @@ pub enum SearchFilter {
     Condition(ConditionFilter),
     Tag(TagFilter),
     CueLabel(StringFilter),
+    LyricsText(StringFilter),
     PlaylistUid(EntityUid),
     All(Vec<SearchFilter>),
     Any(Vec<SearchFilter>),
@@ -410,6 +411,7 @@ impl From<SearchFilter> for _repo::SearchFilter {
             Condition(from) => Self::Condition(from.into()),
             Tag(from) => Self::Tag(from.into()),
             CueLabel(from) => Self::CueLabel(from.into()),
+            LyricsText(from) => Self::LyricsText(from.into()),
             PlaylistUid(from) => Self::PlaylistUid(from.into()),
             All(from) => Self::All(from.into_iter().map(Into::into).collect()),
             Any(from) => Self::Any(from.into_iter().map(Into::into).collect()),
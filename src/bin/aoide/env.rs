@@ -13,12 +13,19 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use aoide::api::web::tracks::load_artwork::ArtworkSizes;
+
+use aoide_core::media::ImageDimension;
+
+use aoide_media::io::import::DEFAULT_SIDECAR_ARTWORK_FILE_NAMES;
+
 use anyhow::Error;
 use dotenv::dotenv;
 use log::LevelFilter as LogLevelFilter;
 use std::{
     env,
     net::{IpAddr, Ipv6Addr, SocketAddr},
+    path::PathBuf,
 };
 
 pub fn init_environment() {
@@ -155,3 +162,99 @@ pub fn parse_database_connection_pool_size() -> u32 {
         })
         .unwrap_or(DEFAULT_DATABASE_CONNECTION_POOL_SIZE)
 }
+
+const ARTWORK_CACHE_DIR_ENV: &str = "ARTWORK_CACHE_DIR";
+
+pub fn parse_artwork_cache_dir() -> Option<PathBuf> {
+    env::var(ARTWORK_CACHE_DIR_ENV)
+        .map_err(Error::from)
+        .ok()
+        .and_then(|var| {
+            log::debug!("{} = {}", ARTWORK_CACHE_DIR_ENV, var);
+            if var.trim().is_empty() {
+                None
+            } else {
+                Some(PathBuf::from(var))
+            }
+        })
+}
+
+const ARTWORK_CACHE_MAX_SIZE_MB_ENV: &str = "ARTWORK_CACHE_MAX_SIZE_MB";
+const DEFAULT_ARTWORK_CACHE_MAX_SIZE_MB: u64 = 256;
+
+pub fn parse_artwork_cache_max_size_mb() -> u64 {
+    env::var(ARTWORK_CACHE_MAX_SIZE_MB_ENV)
+        .map_err(Into::into)
+        .and_then(|var| {
+            log::debug!("{} = {}", ARTWORK_CACHE_MAX_SIZE_MB_ENV, var);
+            if var.trim().is_empty() {
+                Ok(DEFAULT_ARTWORK_CACHE_MAX_SIZE_MB)
+            } else {
+                var.parse().map_err(|err| {
+                    log::warn!("Failed to parse {}: {}", ARTWORK_CACHE_MAX_SIZE_MB_ENV, err);
+                    Error::from(err)
+                })
+            }
+        })
+        .unwrap_or(DEFAULT_ARTWORK_CACHE_MAX_SIZE_MB)
+}
+
+const ARTWORK_SIZE_SMALL_ENV: &str = "ARTWORK_SIZE_SMALL";
+const ARTWORK_SIZE_MEDIUM_ENV: &str = "ARTWORK_SIZE_MEDIUM";
+const ARTWORK_SIZE_LARGE_ENV: &str = "ARTWORK_SIZE_LARGE";
+
+fn parse_artwork_size(key: &str, default_size: ImageDimension) -> ImageDimension {
+    env::var(key)
+        .map_err(Into::into)
+        .and_then(|var| {
+            log::debug!("{} = {}", key, var);
+            if var.trim().is_empty() {
+                Ok(default_size)
+            } else {
+                var.parse().map_err(|err| {
+                    log::warn!("Failed to parse {}: {}", key, err);
+                    Error::from(err)
+                })
+            }
+        })
+        .map(|size| {
+            if size > 0 {
+                size
+            } else {
+                log::warn!("Invalid {} = {}", key, size);
+                default_size
+            }
+        })
+        .unwrap_or(default_size)
+}
+
+pub fn parse_artwork_sizes(default_sizes: ArtworkSizes) -> ArtworkSizes {
+    ArtworkSizes {
+        small: parse_artwork_size(ARTWORK_SIZE_SMALL_ENV, default_sizes.small),
+        medium: parse_artwork_size(ARTWORK_SIZE_MEDIUM_ENV, default_sizes.medium),
+        large: parse_artwork_size(ARTWORK_SIZE_LARGE_ENV, default_sizes.large),
+    }
+}
+
+const SIDECAR_ARTWORK_FILE_NAMES_ENV: &str = "SIDECAR_ARTWORK_FILE_NAMES";
+
+pub fn parse_sidecar_artwork_file_names() -> Vec<String> {
+    env::var(SIDECAR_ARTWORK_FILE_NAMES_ENV)
+        .map_err(Error::from)
+        .ok()
+        .map(|var| {
+            log::debug!("{} = {}", SIDECAR_ARTWORK_FILE_NAMES_ENV, var);
+            var.split(',')
+                .map(str::trim)
+                .filter(|file_name| !file_name.is_empty())
+                .map(ToOwned::to_owned)
+                .collect()
+        })
+        .unwrap_or_else(|| {
+            DEFAULT_SIDECAR_ARTWORK_FILE_NAMES
+                .iter()
+                .copied()
+                .map(ToOwned::to_owned)
+                .collect()
+        })
+}
@@ -19,7 +19,7 @@ mod env;
 
 use aoide::{
     api::web::{
-        collections, handle_rejection,
+        collections, handle_rejection, library,
         media::{self, tracker::Progress as MediaTrackerProgress},
         playlists, reject_on_error, tracks, Error,
     },
@@ -28,6 +28,8 @@ use aoide::{
 
 use aoide_core::entity::EntityUid;
 
+use aoide_media::fs::artwork_cache::{ArtworkCache, ArtworkCacheConfig};
+
 use aoide_media::fs::digest::ProgressEvent as HashingProgressEvent;
 use futures::future::{join, FutureExt};
 use std::{
@@ -160,6 +162,22 @@ pub async fn main() -> Result<(), Error> {
     let guarded_connection_pool = Arc::new(RwLock::new(connection_pool));
     let guarded_connection_pool = warp::any().map(move || guarded_connection_pool.clone());
 
+    let artwork_cache = env::parse_artwork_cache_dir().map(|root_dir| {
+        log::info!("Artwork cache directory: {}", root_dir.display());
+        ArtworkCache::new(ArtworkCacheConfig {
+            root_dir,
+            max_total_size: env::parse_artwork_cache_max_size_mb() * 1024 * 1024,
+        })
+    });
+    let sidecar_artwork_file_names = Arc::new(env::parse_sidecar_artwork_file_names());
+    let artwork_config = Arc::new(tracks::load_artwork::Config {
+        artwork_cache,
+        sizes: env::parse_artwork_sizes(Default::default()),
+        sidecar_artwork_file_names: sidecar_artwork_file_names.to_vec(),
+    });
+    let artwork_config = warp::any().map(move || artwork_config.clone());
+    let sidecar_artwork_file_names = warp::any().map(move || sidecar_artwork_file_names.clone());
+
     let media_tracker_progress = Arc::new(Mutex::new(MediaTrackerProgress::Idle));
     let media_tracker_progress = warp::any().map(move || media_tracker_progress.clone());
 
@@ -221,8 +239,12 @@ pub async fn main() -> Result<(), Error> {
     let tracks_path = warp::path("t");
     let playlists_path = warp::path("p");
     let media_path = warp::path("m");
+    let itunes_path = warp::path("itunes");
     let media_tracker_path = warp::path("media-tracker");
+    let mixxx_path = warp::path("mixxx");
+    let rekordbox_path = warp::path("rekordbox");
     let storage_path = warp::path("storage");
+    let traktor_path = warp::path("traktor");
 
     // Collections
     let collections_create = warp::post()
@@ -466,11 +488,13 @@ pub async fn main() -> Result<(), Error> {
         .and(warp::path("import"))
         .and(warp::path::end())
         .and(warp::body::json())
+        .and(sidecar_artwork_file_names.clone())
         .and(guarded_connection_pool.clone())
         .and(media_tracker_progress.clone())
         .and_then(
             |uid,
              request_body,
+             sidecar_artwork_file_names: Arc<Vec<String>>,
              guarded_connection_pool: GuardedConnectionPool,
              media_tracker_progress: Arc<Mutex<MediaTrackerProgress>>| async move {
                 let (progress_summary_tx, mut progress_summary_rx) =
@@ -493,6 +517,7 @@ pub async fn main() -> Result<(), Error> {
                     move |pooled_connection| {
                         media::tracker::import::handle_request(
                             pooled_connection,
+                            &sidecar_artwork_file_names,
                             &uid,
                             request_body,
                             Some(&progress_summary_tx),
@@ -644,11 +669,13 @@ pub async fn main() -> Result<(), Error> {
             .and(warp::path::end())
             .and(warp::query())
             .and(warp::body::json())
+            .and(sidecar_artwork_file_names.clone())
             .and(guarded_connection_pool.clone())
             .and_then(
                 |uid,
                  query_params,
                  request_body,
+                 sidecar_artwork_file_names: Arc<Vec<String>>,
                  guarded_connection_pool: GuardedConnectionPool| async move {
                     let abort_flag = AtomicBool::new(false);
                     spawn_blocking_database_write_task(
@@ -656,6 +683,7 @@ pub async fn main() -> Result<(), Error> {
                         move |pooled_connection| {
                             tracks::import_and_replace::handle_request(
                                 pooled_connection,
+                                &sidecar_artwork_file_names,
                                 &uid,
                                 query_params,
                                 request_body,
@@ -689,11 +717,134 @@ pub async fn main() -> Result<(), Error> {
                 .map(|response_body| warp::reply::json(&response_body))
             },
         );
+    let collected_tracks_export_metadata_many = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(tracks_path)
+        .and(warp::path("export-metadata"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |uid, request_body, guarded_connection_pool: GuardedConnectionPool| async move {
+                let abort_flag = AtomicBool::new(false);
+                spawn_blocking_database_read_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        tracks::export_metadata_many::handle_request(
+                            pooled_connection,
+                            &uid,
+                            request_body,
+                            &abort_flag,
+                        )
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| warp::reply::json(&response_body))
+            },
+        );
+    let collected_tracks_export_metadata_one = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(tracks_path)
+        .and(path_param_uid)
+        .and(warp::path("export-metadata"))
+        .and(warp::path::end())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |collection_uid, track_uid, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_read_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        tracks::export_metadata_one::handle_request(
+                            pooled_connection,
+                            &collection_uid,
+                            &track_uid,
+                        )
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|()| StatusCode::NO_CONTENT)
+            },
+        );
+    let collected_tracks_load_artwork = warp::get()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(tracks_path)
+        .and(path_param_uid)
+        .and(warp::path("artwork"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::header::optional::<String>("if-none-match"))
+        .and(artwork_config.clone())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |collection_uid,
+             track_uid,
+             query_params,
+             if_none_match: Option<String>,
+             artwork_config: Arc<tracks::load_artwork::Config>,
+             guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_read_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        tracks::load_artwork::handle_request(
+                            pooled_connection,
+                            &artwork_config,
+                            &collection_uid,
+                            &track_uid,
+                            query_params,
+                            if_none_match.as_deref(),
+                        )
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+            },
+        );
+    let collected_tracks_analyze_audio =
+        warp::post()
+            .and(collections_path)
+            .and(path_param_uid)
+            .and(tracks_path)
+            .and(warp::path("analyze-audio"))
+            .and(warp::path::end())
+            .and(warp::query())
+            .and(warp::body::json())
+            .and(guarded_connection_pool.clone())
+            .and_then(
+                |uid,
+                 query_params,
+                 request_body,
+                 guarded_connection_pool: GuardedConnectionPool| async move {
+                    spawn_blocking_database_write_task(
+                        guarded_connection_pool,
+                        move |pooled_connection| {
+                            tracks::analyze_audio::handle_request(
+                                pooled_connection,
+                                &uid,
+                                query_params,
+                                request_body,
+                                &MEDIA_TRACKER_ABORT_FLAG,
+                            )
+                        },
+                    )
+                    .await
+                    .map_err(reject_on_error)
+                    .map(|response_body| warp::reply::json(&response_body))
+                },
+            );
     let collected_tracks_filters = collected_tracks_resolve
         .or(collected_tracks_search)
         .or(collected_tracks_replace)
         .or(collected_tracks_import_and_replace)
-        .or(collected_tracks_purge);
+        .or(collected_tracks_purge)
+        .or(collected_tracks_export_metadata_many)
+        .or(collected_tracks_export_metadata_one)
+        .or(collected_tracks_load_artwork)
+        .or(collected_tracks_analyze_audio);
 
     // Tracks
     let tracks_load_one = warp::get()
@@ -733,7 +884,28 @@ pub async fn main() -> Result<(), Error> {
                 .map(|response_body| warp::reply::json(&response_body))
             },
         );
-    let tracks_filters = tracks_load_many.or(tracks_load_one);
+    let tracks_load_waveform = warp::get()
+        .and(tracks_path)
+        .and(path_param_uid)
+        .and(warp::path("waveform"))
+        .and(warp::path::end())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |uid, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_read_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        tracks::load_waveform::handle_request(pooled_connection, &uid)
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| warp::reply::json(&response_body))
+            },
+        );
+    let tracks_filters = tracks_load_many
+        .or(tracks_load_one)
+        .or(tracks_load_waveform);
 
     let collected_playlists_create = warp::post()
         .and(collections_path)
@@ -778,7 +950,241 @@ pub async fn main() -> Result<(), Error> {
                 .map(|response_body| warp::reply::json(&response_body))
             },
         );
-    let collected_playlists_filters = collected_playlists_list.or(collected_playlists_create);
+    let collected_playlists_import_file = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(playlists_path)
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |collection_uid, request_body, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_write_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        playlists::import_file::handle_request(pooled_connection, &collection_uid, request_body)
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| {
+                    warp::reply::with_status(warp::reply::json(&response_body), StatusCode::CREATED)
+                })
+            },
+        );
+    let collected_playlists_export_file = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(playlists_path)
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |collection_uid, request_body, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_read_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        playlists::export_file::handle_request(pooled_connection, &collection_uid, request_body)
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| {
+                    let playlists::export_file::ResponseBody {
+                        content_type,
+                        content,
+                    } = response_body;
+                    warp::reply::with_header(
+                        content,
+                        "Content-Type",
+                        format!("{};charset=utf-8", content_type),
+                    )
+                })
+            },
+        );
+    let collected_playlists_filters = collected_playlists_list
+        .or(collected_playlists_create)
+        .or(collected_playlists_import_file)
+        .or(collected_playlists_export_file);
+
+    let collected_itunes_import = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(itunes_path)
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::body::json())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |uid, query_params, request_body, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_write_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        library::import_itunes::handle_request(
+                            pooled_connection,
+                            &uid,
+                            query_params,
+                            request_body,
+                        )
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| warp::reply::json(&response_body))
+            },
+        );
+    let collected_mixxx_import = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(mixxx_path)
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::body::json())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |uid, query_params, request_body, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_write_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        library::import_mixxx::handle_request(
+                            pooled_connection,
+                            &uid,
+                            query_params,
+                            request_body,
+                        )
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| warp::reply::json(&response_body))
+            },
+        );
+    let collected_rekordbox_import = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(rekordbox_path)
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::body::json())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |uid, query_params, request_body, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_write_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        library::import_rekordbox::handle_request(
+                            pooled_connection,
+                            &uid,
+                            query_params,
+                            request_body,
+                        )
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| warp::reply::json(&response_body))
+            },
+        );
+    let collected_rekordbox_export = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(rekordbox_path)
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |uid, request_body, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_read_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        library::export_rekordbox::handle_request(
+                            pooled_connection,
+                            &uid,
+                            request_body,
+                        )
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| {
+                    warp::reply::with_header(
+                        response_body,
+                        "Content-Type",
+                        "application/xml;charset=utf-8",
+                    )
+                })
+            },
+        );
+    let collected_traktor_import = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(traktor_path)
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::body::json())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |uid, query_params, request_body, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_write_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        library::import_traktor::handle_request(
+                            pooled_connection,
+                            &uid,
+                            query_params,
+                            request_body,
+                        )
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| warp::reply::json(&response_body))
+            },
+        );
+    let collected_traktor_export = warp::post()
+        .and(collections_path)
+        .and(path_param_uid)
+        .and(traktor_path)
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(warp::body::json())
+        .and(guarded_connection_pool.clone())
+        .and_then(
+            |uid, request_body, guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_read_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        library::export_traktor::handle_request(
+                            pooled_connection,
+                            &uid,
+                            request_body,
+                        )
+                    },
+                )
+                .await
+                .map_err(reject_on_error)
+                .map(|response_body| {
+                    warp::reply::with_header(
+                        response_body,
+                        "Content-Type",
+                        "application/xml;charset=utf-8",
+                    )
+                })
+            },
+        );
+    let collected_library_filters = collected_itunes_import
+        .or(collected_mixxx_import)
+        .or(collected_rekordbox_import)
+        .or(collected_rekordbox_export)
+        .or(collected_traktor_import)
+        .or(collected_traktor_export);
 
     let playlists_update =
         warp::put()
@@ -866,25 +1272,42 @@ pub async fn main() -> Result<(), Error> {
         .and(warp::path("import-track"))
         .and(warp::path::end())
         .and(warp::query())
-        .and_then(|query_params| async move {
-            tokio::task::spawn_blocking(move || media::import_track::handle_request(query_params))
+        .and(sidecar_artwork_file_names.clone())
+        .and_then(
+            |query_params, sidecar_artwork_file_names: Arc<Vec<String>>| async move {
+                tokio::task::spawn_blocking(move || {
+                    media::import_track::handle_request(&sidecar_artwork_file_names, query_params)
+                })
                 .await
                 .map_err(reject_on_error)? // JoinError
                 .map_err(reject_on_error)
                 .map(|response_body| warp::reply::json(&response_body))
-        });
+            },
+        );
 
     // Storage
     let storage_cleanse = warp::post()
         .and(storage_path)
         .and(warp::path("cleanse"))
         .and(warp::path::end())
+        .and(artwork_config.clone())
         .and(guarded_connection_pool.clone())
         .and_then(
-            |guarded_connection_pool: GuardedConnectionPool| async move {
-                spawn_blocking_database_write_task(guarded_connection_pool, |pooled_connection| {
-                    Ok(uc::database::cleanse(&pooled_connection)?)
-                })
+            |artwork_config: Arc<tracks::load_artwork::Config>,
+             guarded_connection_pool: GuardedConnectionPool| async move {
+                spawn_blocking_database_write_task(
+                    guarded_connection_pool,
+                    move |pooled_connection| {
+                        uc::database::cleanse(&pooled_connection)?;
+                        if let Some(artwork_cache) = &artwork_config.artwork_cache {
+                            uc::tracks::artwork::cleanse_artwork_cache(
+                                &pooled_connection,
+                                artwork_cache,
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
                 .await
                 .map_err(reject_on_error)
                 .map(|()| StatusCode::NO_CONTENT)
@@ -923,6 +1346,7 @@ pub async fn main() -> Result<(), Error> {
     let server = warp::serve(
         collected_tracks_filters
             .or(collected_playlists_filters)
+            .or(collected_library_filters)
             .or(collections_filters)
             .or(tracks_filters)
             .or(playlists_filters)
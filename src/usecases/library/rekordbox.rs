@@ -0,0 +1,82 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_media::util::tag::FacetedTagMappingConfig;
+use aoide_repo::track::ReplaceMode;
+
+use std::{fs, path::Path};
+
+mod uc {
+    pub use aoide_usecases::{
+        collection::resolve_collection_id_for_virtual_file_path,
+        library::{rekordbox::*, Summary},
+        Error,
+    };
+}
+
+pub fn import_xml_file(
+    connection: &SqliteConnection,
+    collection_uid: &EntityUid,
+    replace_mode: ReplaceMode,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    xml_file_path: &Path,
+) -> Result<uc::Summary> {
+    let xml = fs::read_to_string(xml_file_path)?;
+    let db = RepoConnection::new(connection);
+    Ok(
+        db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (collection_id, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            uc::import_xml(
+                &db,
+                collection_id,
+                &source_path_resolver,
+                faceted_tag_mapping,
+                replace_mode,
+                &xml,
+            )
+            .map_err(DieselTransactionError::new)
+        })?,
+    )
+}
+
+pub fn export_xml(
+    connection: &SqliteConnection,
+    collection_uid: &EntityUid,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    playlist_uids: Option<&[EntityUid]>,
+) -> Result<String> {
+    let db = RepoConnection::new(connection);
+    Ok(
+        db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (collection_id, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            uc::export_xml(
+                &db,
+                collection_id,
+                &source_path_resolver,
+                faceted_tag_mapping,
+                playlist_uids,
+            )
+            .map_err(DieselTransactionError::new)
+        })?,
+    )
+}
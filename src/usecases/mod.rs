@@ -26,6 +26,7 @@ use thiserror::Error;
 
 pub mod collections;
 pub mod database;
+pub mod library;
 pub mod media;
 pub mod playlists;
 pub mod tracks;
@@ -0,0 +1,88 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_media::playlist::FileFormat;
+
+use std::{fs, path::Path};
+
+mod uc {
+    pub use aoide_usecases::{
+        collection::resolve_collection_id_for_virtual_file_path, playlists::file::*, Error,
+    };
+}
+
+pub use uc::{ImportSummary, PathMode};
+
+pub fn import_file(
+    connection: &SqliteConnection,
+    collection_uid: &EntityUid,
+    file_path: &Path,
+    new_playlist: Playlist,
+    map_entry_titles: bool,
+) -> Result<ImportSummary> {
+    let file_format = FileFormat::from_path(file_path)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported playlist file: {}", file_path.display()))?;
+    // Legacy M3U files are not necessarily encoded as UTF-8
+    let input = String::from_utf8_lossy(&fs::read(file_path)?).into_owned();
+    let db = RepoConnection::new(connection);
+    Ok(
+        db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (collection_id, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            uc::import_file(
+                &db,
+                collection_id,
+                &source_path_resolver,
+                file_format,
+                &input,
+                file_path.parent(),
+                new_playlist,
+                map_entry_titles,
+            )
+            .map_err(DieselTransactionError::new)
+        })?,
+    )
+}
+
+pub fn export_file(
+    connection: &SqliteConnection,
+    collection_uid: &EntityUid,
+    playlist_uid: &EntityUid,
+    file_format: FileFormat,
+    path_mode: PathMode,
+) -> Result<String> {
+    let db = RepoConnection::new(connection);
+    Ok(
+        db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (collection_id, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            uc::export_file(
+                &db,
+                collection_id,
+                &source_path_resolver,
+                playlist_uid,
+                file_format,
+                path_mode,
+            )
+            .map_err(DieselTransactionError::new)
+        })?,
+    )
+}
@@ -30,5 +30,7 @@ use aoide_repo::{
 pub mod create;
 pub mod delete;
 pub mod entries;
+pub mod file;
 pub mod load;
 pub mod update;
+pub mod xspf;
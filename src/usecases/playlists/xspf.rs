@@ -0,0 +1,76 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use std::{fs, path::Path};
+use url::Url;
+
+mod uc {
+    pub use aoide_usecases::{
+        collection::resolve_collection_id_for_virtual_file_path,
+        playlists::{file::*, xspf::*},
+        Error,
+    };
+}
+
+pub use uc::{ImportSummary, PathMode};
+
+pub fn import_xspf_file(
+    connection: &SqliteConnection,
+    collection_uid: &EntityUid,
+    file_path: &Path,
+    new_playlist: Playlist,
+) -> Result<ImportSummary> {
+    let input = fs::read_to_string(file_path)?;
+    let base_url = Url::from_file_path(file_path).ok();
+    let db = RepoConnection::new(connection);
+    Ok(
+        db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (collection_id, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            uc::import_xspf(
+                &db,
+                collection_id,
+                &source_path_resolver,
+                &input,
+                base_url.as_ref(),
+                new_playlist,
+            )
+            .map_err(DieselTransactionError::new)
+        })?,
+    )
+}
+
+pub fn export_xspf(
+    connection: &SqliteConnection,
+    collection_uid: &EntityUid,
+    playlist_uid: &EntityUid,
+    path_mode: PathMode,
+) -> Result<String> {
+    let db = RepoConnection::new(connection);
+    Ok(
+        db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (_, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            uc::export_xspf(&db, &source_path_resolver, playlist_uid, path_mode)
+                .map_err(DieselTransactionError::new)
+        })?,
+    )
+}
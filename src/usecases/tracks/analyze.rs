@@ -0,0 +1,64 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_media::io::import::ImportTrackFlags;
+use aoide_repo::track::{SearchFilter, SortOrder};
+
+use std::sync::atomic::AtomicBool;
+
+mod uc {
+    pub use aoide_usecases::{
+        collection::resolve_collection_id_for_virtual_file_path, tracks::analyze::*, Error,
+    };
+}
+
+pub fn analyze_audio_of_search_results(
+    connection: &SqliteConnection,
+    collection_uid: &EntityUid,
+    filter: Option<SearchFilter>,
+    ordering: Vec<SortOrder>,
+    flags: ImportTrackFlags,
+    abort_flag: &AtomicBool,
+) -> Result<uc::Outcome> {
+    let db = RepoConnection::new(connection);
+    let (collection_id, source_path_resolver, tracks) = db
+        .transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (collection_id, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            let tracks = uc::load_tracks_to_analyze(&db, collection_id, filter, ordering)
+                .map_err(DieselTransactionError::new)?;
+            Ok((collection_id, source_path_resolver, tracks))
+        })?;
+    // The audio streams are decoded outside of any transaction and
+    // the results are committed separately for each track
+    Ok(uc::analyze_audio_of_tracks(
+        &source_path_resolver,
+        tracks,
+        flags,
+        &mut |entity_header, track, fingerprint| {
+            db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+                uc::store_analyzed_track(&db, collection_id, entity_header, track, fingerprint)
+                    .map_err(DieselTransactionError::new)
+            })
+            .map_err(DieselTransactionError::into_inner)
+        },
+        abort_flag,
+    )?)
+}
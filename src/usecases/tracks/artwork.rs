@@ -0,0 +1,80 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::media::{Digest, ImageDimension};
+
+use aoide_media::fs::artwork_cache::ArtworkCache;
+
+mod uc {
+    pub use aoide_usecases::{
+        collection::resolve_collection_id_for_virtual_file_path, tracks::artwork::*, Error,
+    };
+}
+
+pub use uc::Outcome;
+
+pub fn load_artwork_image(
+    connection: &SqliteConnection,
+    artwork_cache: Option<&ArtworkCache>,
+    sidecar_artwork_file_names: &[String],
+    collection_uid: &EntityUid,
+    track_uid: &EntityUid,
+    max_dimension: Option<ImageDimension>,
+    is_unmodified: impl FnOnce(&Digest) -> bool,
+) -> Result<Outcome> {
+    let db = RepoConnection::new(connection);
+    Ok(
+        db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (_collection_id, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            let track_id = db.resolve_track_id(track_uid)?;
+            uc::load_artwork_image(
+                &db,
+                &source_path_resolver,
+                artwork_cache,
+                sidecar_artwork_file_names,
+                track_id,
+                max_dimension,
+                is_unmodified,
+            )
+            .map_err(DieselTransactionError::new)
+        })?,
+    )
+}
+
+pub fn cleanse_artwork_cache(
+    connection: &SqliteConnection,
+    artwork_cache: &ArtworkCache,
+) -> Result<()> {
+    log::info!("Cleansing artwork cache");
+    let db = RepoConnection::new(connection);
+    let (purged_entries, eviction_outcome) = db
+        .transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            uc::cleanse_artwork_cache(&db, artwork_cache).map_err(DieselTransactionError::new)
+        })?;
+    log::info!(
+        "Purged {} unreferenced entries and evicted {} files from artwork cache: {} -> {} bytes",
+        purged_entries,
+        eviction_outcome.evicted_files,
+        eviction_outcome.total_size_before,
+        eviction_outcome.total_size_after,
+    );
+    Ok(())
+}
@@ -0,0 +1,84 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::*;
+
+use aoide_media::io::export::{ExportTrackConfig, ExportTrackFlags};
+use aoide_repo::track::{SearchFilter, SortOrder};
+
+use std::sync::atomic::AtomicBool;
+
+mod uc {
+    pub use aoide_usecases::{
+        collection::resolve_collection_id_for_virtual_file_path, tracks::export_metadata::*, Error,
+    };
+}
+
+pub fn export_metadata_into_file(
+    connection: &SqliteConnection,
+    collection_uid: &EntityUid,
+    track_uid: &EntityUid,
+    config: &ExportTrackConfig,
+    flags: ExportTrackFlags,
+) -> Result<()> {
+    let db = RepoConnection::new(connection);
+    Ok(
+        db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (collection_id, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            let track_id = db.resolve_track_id(track_uid)?;
+            uc::export_metadata_into_file(
+                &db,
+                collection_id,
+                &source_path_resolver,
+                track_id,
+                config,
+                flags,
+            )
+            .map_err(DieselTransactionError::new)
+        })?,
+    )
+}
+
+pub fn export_metadata_into_files_of_search_results(
+    connection: &SqliteConnection,
+    collection_uid: &EntityUid,
+    filter: Option<SearchFilter>,
+    ordering: Vec<SortOrder>,
+    config: &ExportTrackConfig,
+    flags: ExportTrackFlags,
+    abort_flag: &AtomicBool,
+) -> Result<uc::Outcome> {
+    let db = RepoConnection::new(connection);
+    Ok(
+        db.transaction::<_, DieselTransactionError<uc::Error>, _>(|| {
+            let (collection_id, source_path_resolver) =
+                uc::resolve_collection_id_for_virtual_file_path(&db, collection_uid, None)
+                    .map_err(DieselTransactionError::new)?;
+            uc::export_metadata_into_files_of_search_results(
+                &db,
+                collection_id,
+                &source_path_resolver,
+                filter,
+                ordering,
+                config,
+                flags,
+                abort_flag,
+            )
+            .map_err(DieselTransactionError::new)
+        })?,
+    )
+}
@@ -27,6 +27,9 @@ use aoide_repo::{
 
 ///////////////////////////////////////////////////////////////////////
 
+pub mod analyze;
+pub mod artwork;
+pub mod export_metadata;
 pub mod load;
 pub mod purge;
 pub mod replace;
@@ -23,7 +23,9 @@ use std::result::Result as StdResult;
 use thiserror::Error;
 
 pub mod collection;
+pub mod library;
 pub mod media;
+pub mod playlists;
 pub mod tracks;
 
 #[derive(Error, Debug)]
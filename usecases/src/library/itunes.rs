@@ -0,0 +1,50 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::media::resolver::VirtualFilePathResolver;
+
+use aoide_media::{library::itunes, util::tag::FacetedTagMappingConfig};
+
+/// The kind of playlists that have been imported from iTunes.
+pub const PLAYLIST_KIND: &str = "itunes";
+
+/// Import tracks and user playlists from an iTunes Music Library XML file.
+pub fn import_xml<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    source_path_resolver: &VirtualFilePathResolver,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    replace_mode: ReplaceMode,
+    xml: &str,
+) -> Result<Summary>
+where
+    Repo: TrackEntityRepo + PlaylistRepo,
+{
+    let imported_at = DateTime::now_local();
+    let library =
+        itunes::import_library(xml, faceted_tag_mapping, source_path_resolver, imported_at)?;
+    import_library(
+        repo,
+        collection_id,
+        replace_mode,
+        PLAYLIST_KIND,
+        imported_at,
+        library,
+    )
+}
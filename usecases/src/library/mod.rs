@@ -0,0 +1,282 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::tracks::replace::{
+    replace_collected_track_by_media_source_path, Summary as TracksSummary,
+};
+
+use aoide_core::{
+    entity::{EntityHeader, EntityUid},
+    playlist::{
+        track::Item as TrackItem, Entity as PlaylistEntity, Entry as PlaylistEntry, Item, Playlist,
+    },
+    track::Entity as TrackEntity,
+    util::clock::DateTime,
+};
+
+use aoide_media::library::{CollectionTrack, Library, Playlist as LibraryPlaylist};
+
+use aoide_repo::{
+    collection::RecordId as CollectionId,
+    playlist::{RecordId as PlaylistId, Repo as PlaylistRepo},
+    track::{EntityRepo as TrackEntityRepo, ReplaceMode},
+};
+
+use std::collections::HashMap;
+
+pub mod itunes;
+pub mod mixxx;
+pub mod rekordbox;
+pub mod traktor;
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub tracks: TracksSummary,
+
+    /// Playlists that have been created or updated, including all
+    /// entries that refer to imported tracks.
+    pub playlists: Vec<PlaylistEntity>,
+}
+
+/// Import the tracks and playlists of a DJ library into a collection.
+///
+/// Tracks are replaced by their media source path. The metadata of
+/// tracks that have already been collected is merged with the metadata
+/// from the library, preserving the media source and all properties
+/// that are not provided by the library.
+///
+/// Playlist entries that refer to tracks which have not been imported
+/// are dropped. The created playlists are tagged with the given kind.
+/// Existing playlists of this kind with the same title are updated by
+/// replacing all of their entries.
+pub fn import_library<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    replace_mode: ReplaceMode,
+    playlist_kind: &str,
+    imported_at: DateTime,
+    library: Library,
+) -> Result<Summary>
+where
+    Repo: TrackEntityRepo + PlaylistRepo,
+{
+    let Library {
+        tracks,
+        playlists,
+        not_imported,
+    } = library;
+    let mut summary = Summary::default();
+    summary.tracks.not_imported = not_imported.into_iter().map(Into::into).collect();
+    let mut track_uids: HashMap<String, EntityUid> = HashMap::with_capacity(tracks.len());
+    for CollectionTrack { track_id, track } in tracks {
+        let media_source_path = track.media_source.path.clone();
+        let track = if let Some((_, _, entity)) = repo
+            .load_track_entity_by_media_source_path(collection_id, &media_source_path)
+            .optional()?
+        {
+            let mut collected_track = entity.body;
+            // The library only refers to the media source and doesn't
+            // provide its artwork, synchronization time, or analyzed
+            // audio properties
+            let media_source = collected_track.media_source.clone();
+            collected_track.merge_newer_from_synchronized_media_source(track);
+            collected_track.media_source = media_source;
+            collected_track
+        } else {
+            track
+        };
+        if replace_collected_track_by_media_source_path(
+            &mut summary.tracks,
+            repo,
+            collection_id,
+            replace_mode,
+            true,
+            track,
+        )?
+        .is_none()
+        {
+            continue;
+        }
+        let (_, _, entity_header) = repo
+            .resolve_track_entity_header_by_media_source_path(collection_id, &media_source_path)?;
+        track_uids.insert(track_id, entity_header.uid);
+    }
+    let mut playlist_records = Vec::new();
+    repo.load_collected_playlist_entities_with_entries_summary(
+        collection_id,
+        Some(playlist_kind),
+        None,
+        &mut playlist_records,
+    )?;
+    let mut existing_playlists: HashMap<String, (PlaylistId, PlaylistEntity)> = playlist_records
+        .into_iter()
+        .map(|(record_header, (entity, _))| (entity.body.title.clone(), (record_header.id, entity)))
+        .collect();
+    for playlist in playlists {
+        let entries: Vec<_> = playlist
+            .track_ids
+            .iter()
+            .filter_map(|track_id| track_uids.get(track_id))
+            .map(|uid| PlaylistEntry {
+                added_at: imported_at,
+                title: None,
+                notes: None,
+                item: Item::Track(TrackItem { uid: uid.clone() }),
+            })
+            .collect();
+        let title = playlist.flattened_title();
+        let (playlist_id, entity) =
+            if let Some((playlist_id, entity)) = existing_playlists.remove(&title) {
+                repo.remove_all_playlist_entries(playlist_id)?;
+                (playlist_id, entity)
+            } else {
+                let entity = PlaylistEntity::new(
+                    EntityHeader::initial_random(),
+                    Playlist {
+                        collected_at: imported_at,
+                        title,
+                        kind: Some(playlist_kind.to_owned()),
+                        notes: None,
+                        color: None,
+                        flags: Default::default(),
+                    },
+                );
+                let playlist_id =
+                    repo.insert_collected_playlist_entity(collection_id, imported_at, &entity)?;
+                (playlist_id, entity)
+            };
+        repo.append_playlist_entries(playlist_id, &entries)?;
+        summary.playlists.push(entity);
+    }
+    Ok(summary)
+}
+
+fn push_collection_track(
+    tracks: &mut Vec<CollectionTrack>,
+    track_ids: &mut HashMap<EntityUid, String>,
+    entity: TrackEntity,
+) -> String {
+    // Track IDs are assigned sequentially, starting at 1
+    let track_id = (tracks.len() + 1).to_string();
+    track_ids.insert(entity.hdr.uid, track_id.clone());
+    tracks.push(CollectionTrack {
+        track_id: track_id.clone(),
+        track: entity.body,
+    });
+    track_id
+}
+
+/// Restore the folders of playlists that have been imported
+/// from the same kind of library.
+fn export_playlist(
+    playlist: &Playlist,
+    playlist_kind: &str,
+    track_ids: Vec<String>,
+) -> LibraryPlaylist {
+    if playlist.kind.as_deref() == Some(playlist_kind) {
+        LibraryPlaylist::from_flattened_title(&playlist.title, track_ids)
+    } else {
+        LibraryPlaylist {
+            folders: vec![],
+            name: playlist.title.clone(),
+            track_ids,
+        }
+    }
+}
+
+/// Collect the tracks and playlists of a collection for exporting
+/// them into a DJ library.
+///
+/// Without any playlist UIDs all tracks and playlists of the collection
+/// are exported. Otherwise only the given playlists and the tracks they
+/// refer to are exported.
+pub fn export_library<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    playlist_kind: &str,
+    playlist_uids: Option<&[EntityUid]>,
+) -> Result<Library>
+where
+    Repo: TrackEntityRepo + PlaylistRepo,
+{
+    let mut tracks = Vec::new();
+    let mut track_ids = HashMap::new();
+    let playlist_ids = if let Some(playlist_uids) = playlist_uids {
+        playlist_uids
+            .iter()
+            .map(|uid| repo.resolve_playlist_id(uid))
+            .collect::<RepoResult<Vec<_>>>()?
+    } else {
+        let mut track_entities = Vec::new();
+        repo.search_collected_tracks(
+            collection_id,
+            &Default::default(),
+            None,
+            vec![],
+            &mut track_entities,
+        )?;
+        for (_, entity) in track_entities {
+            push_collection_track(&mut tracks, &mut track_ids, entity);
+        }
+        let mut playlist_records = Vec::new();
+        repo.load_collected_playlist_entities_with_entries_summary(
+            collection_id,
+            None,
+            None,
+            &mut playlist_records,
+        )?;
+        playlist_records
+            .into_iter()
+            .map(|(record_header, _)| record_header.id)
+            .collect()
+    };
+    let mut playlists = Vec::with_capacity(playlist_ids.len());
+    for playlist_id in playlist_ids {
+        let (_, entity) = repo.load_playlist_entity(playlist_id)?;
+        let entries = repo.load_all_playlist_entries(playlist_id)?;
+        let mut entry_track_ids = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let uid = match entry.item {
+                Item::Track(TrackItem { uid }) => uid,
+                Item::Separator => continue,
+            };
+            let track_id = if let Some(track_id) = track_ids.get(&uid) {
+                track_id.clone()
+            } else if playlist_uids.is_some() {
+                let (_, track_entity) = repo.load_track_entity_by_uid(&uid)?;
+                push_collection_track(&mut tracks, &mut track_ids, track_entity)
+            } else {
+                // All tracks of the collection have already been loaded
+                log::warn!("Skipping entry of unknown track {}", uid);
+                continue;
+            };
+            entry_track_ids.push(track_id);
+        }
+        playlists.push(export_playlist(
+            &entity.body,
+            playlist_kind,
+            entry_track_ids,
+        ));
+    }
+    Ok(Library {
+        tracks,
+        playlists,
+        not_imported: vec![],
+    })
+}
@@ -0,0 +1,72 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::media::resolver::VirtualFilePathResolver;
+
+use aoide_media::{library::rekordbox, util::tag::FacetedTagMappingConfig};
+
+/// The kind of playlists that have been imported from Rekordbox.
+pub const PLAYLIST_KIND: &str = "rekordbox";
+
+/// Import tracks and playlists from a Rekordbox XML collection.
+pub fn import_xml<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    source_path_resolver: &VirtualFilePathResolver,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    replace_mode: ReplaceMode,
+    xml: &str,
+) -> Result<Summary>
+where
+    Repo: TrackEntityRepo + PlaylistRepo,
+{
+    let imported_at = DateTime::now_local();
+    let library =
+        rekordbox::import_library(xml, faceted_tag_mapping, source_path_resolver, imported_at)?;
+    import_library(
+        repo,
+        collection_id,
+        replace_mode,
+        PLAYLIST_KIND,
+        imported_at,
+        library,
+    )
+}
+
+/// Export tracks and playlists of a collection as Rekordbox XML.
+pub fn export_xml<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    source_path_resolver: &VirtualFilePathResolver,
+    faceted_tag_mapping: &FacetedTagMappingConfig,
+    playlist_uids: Option<&[EntityUid]>,
+) -> Result<String>
+where
+    Repo: TrackEntityRepo + PlaylistRepo,
+{
+    let Library {
+        tracks, playlists, ..
+    } = export_library(repo, collection_id, PLAYLIST_KIND, playlist_uids)?;
+    Ok(rekordbox::export_library(
+        &tracks,
+        &playlists,
+        faceted_tag_mapping,
+        source_path_resolver,
+    )?)
+}
@@ -16,19 +16,29 @@
 use super::*;
 
 use aoide_core::{
-    media::{resolver::VirtualFilePathResolver, SourcePath},
-    track::Track,
+    audio::fingerprint::Fingerprint,
+    media::{resolver::VirtualFilePathResolver, Artwork, Source, SourcePath},
+    track::{lyrics::Lyrics, Track},
     util::clock::DateTime,
 };
 
 use aoide_media::{
-    fmt::{flac, mp3, mp4, ogg},
+    analyze::analyze_audio_file,
+    fmt::{aiff, ape, flac, mp3, mp4, mpc, ogg, opus, wav, wavpack},
     fs::open_local_file_for_reading,
-    io::import::*,
-    util::guess_mime_from_path,
+    io::{
+        export::{ExportTrack as _, ExportTrackConfig, ExportTrackFlags},
+        import::*,
+    },
+    lyrics::{collect_lyrics, parse_lrc, LRC_FILE_EXTENSION},
+    util::{digest::MediaDigest, guess_mime_from_path, parse_artwork_from_embedded_image},
 };
 
-use std::{io::BufReader, path::PathBuf};
+use std::{
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
 use url::Url;
 
 ///////////////////////////////////////////////////////////////////////
@@ -49,18 +59,27 @@ pub enum SynchronizedImportMode {
     },
     Modified {
         last_synchronized_at: Option<DateTime>,
+
+        /// The relative URI of the sidecar artwork file that has
+        /// been imported during the last synchronization (if any)
+        last_sidecar_artwork_uri: Option<String>,
     },
     Always,
 }
 
 impl SynchronizedImportMode {
-    pub const fn new(import_mode: ImportMode, last_synchronized_at: Option<DateTime>) -> Self {
+    pub fn new(
+        import_mode: ImportMode,
+        last_synchronized_at: Option<DateTime>,
+        last_sidecar_artwork_uri: Option<String>,
+    ) -> Self {
         match import_mode {
             ImportMode::Once => Self::Once {
                 synchronized_before: last_synchronized_at.is_some(),
             },
             ImportMode::Modified => Self::Modified {
                 last_synchronized_at,
+                last_sidecar_artwork_uri,
             },
             ImportMode::Always => Self::Always,
         }
@@ -69,7 +88,9 @@ impl SynchronizedImportMode {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ImportTrackFromFileOutcome {
-    Imported(Track),
+    /// The imported track and the acoustic fingerprint of its
+    /// media source if requested
+    Imported(Track, Option<Fingerprint>),
     SkippedSynchronized(DateTime),
     SkippedDirectory,
 }
@@ -81,6 +102,7 @@ pub fn import_track_from_local_file_path(
     config: &ImportTrackConfig,
     flags: ImportTrackFlags,
     collected_at: DateTime,
+    sidecar_artwork_file_cache: &mut SidecarArtworkFileCache,
 ) -> Result<ImportTrackFromFileOutcome> {
     let file_path = source_path_resolver.build_file_path(&source_path);
     let (canonical_path, file) =
@@ -92,13 +114,36 @@ pub fn import_track_from_local_file_path(
         };
     let file_metadata = file.metadata().map_err(MediaError::from)?;
     let mime = guess_mime_from_path(&canonical_path)?;
-    let last_modified_at = file_metadata
+    let file_last_modified_at = file_metadata
         .modified()
         .map(DateTime::from)
         .unwrap_or_else(|_| {
             log::error!("Using current time instead of inaccessible last modification time");
             DateTime::now_utc()
         });
+    let sidecar_artwork_file = if flags.contains(ImportTrackFlags::ARTWORK) {
+        sidecar_artwork_file_cache
+            .find_sidecar_artwork_file(&canonical_path, &config.sidecar_artwork_file_names)
+    } else {
+        None
+    };
+    let sidecar_lyrics_file = if flags.contains(ImportTrackFlags::METADATA) {
+        find_sidecar_lyrics_file(&canonical_path)
+    } else {
+        None
+    };
+    // Modifications of the sidecar artwork and lyrics files are
+    // considered as modifications of the track
+    let last_modified_at = sidecar_artwork_file
+        .as_ref()
+        .map(|sidecar| sidecar.last_modified_at)
+        .into_iter()
+        .chain(
+            sidecar_lyrics_file
+                .as_ref()
+                .map(|sidecar| sidecar.last_modified_at),
+        )
+        .fold(file_last_modified_at, DateTime::max);
     match mode {
         SynchronizedImportMode::Once {
             synchronized_before,
@@ -116,9 +161,18 @@ pub fn import_track_from_local_file_path(
         }
         SynchronizedImportMode::Modified {
             last_synchronized_at,
+            last_sidecar_artwork_uri,
         } => {
             if let Some(last_synchronized_at) = last_synchronized_at {
-                if last_modified_at <= last_synchronized_at {
+                // The previously imported sidecar artwork file might
+                // have been deleted, renamed, or replaced by another
+                // file with a higher precedence
+                let sidecar_artwork_unchanged = last_sidecar_artwork_uri.is_none()
+                    || last_sidecar_artwork_uri.as_deref()
+                        == sidecar_artwork_file
+                            .as_ref()
+                            .map(|sidecar| sidecar.uri.as_str());
+                if sidecar_artwork_unchanged && last_modified_at <= last_synchronized_at {
                     log::debug!(
                         "Skipping reimport of synchronized file {} modified at {} <= {}",
                         canonical_path.display(),
@@ -147,14 +201,389 @@ pub fn import_track_from_local_file_path(
     };
     let mut reader: Box<dyn Reader> = Box::new(BufReader::new(file));
     let new_track = input.into_new_track(source_path, &mime);
-    let track = match mime.as_ref() {
+    let mut track = match mime.as_ref() {
         "audio/flac" => flac::ImportTrack.import_track(config, flags, new_track, &mut reader),
         "audio/mpeg" => mp3::ImportTrack.import_track(config, flags, new_track, &mut reader),
         "audio/m4a" | "video/mp4" => {
             mp4::ImportTrack.import_track(config, flags, new_track, &mut reader)
         }
         "audio/ogg" => ogg::ImportTrack.import_track(config, flags, new_track, &mut reader),
+        "audio/opus" => opus::ImportTrack.import_track(config, flags, new_track, &mut reader),
+        "audio/wav" | "audio/x-wav" | "audio/vnd.wave" => {
+            wav::ImportTrack.import_track(config, flags, new_track, &mut reader)
+        }
+        "audio/x-ape" => ape::ImportTrack.import_track(config, flags, new_track, &mut reader),
+        "audio/x-musepack" => mpc::ImportTrack.import_track(config, flags, new_track, &mut reader),
+        "audio/x-wavpack" => {
+            wavpack::ImportTrack.import_track(config, flags, new_track, &mut reader)
+        }
+        "audio/aiff" | "audio/x-aiff" => {
+            aiff::ImportTrack.import_track(config, flags, new_track, &mut reader)
+        }
         _ => Err(MediaError::UnsupportedContentType(mime)),
     }?;
-    Ok(ImportTrackFromFileOutcome::Imported(track))
+    if track.media_source.artwork.is_empty() {
+        if let Some(sidecar_artwork_file) = sidecar_artwork_file {
+            if let Some(artwork) = import_sidecar_artwork(flags, sidecar_artwork_file) {
+                track.media_source.artwork = artwork;
+            }
+        }
+    }
+    if let Some(sidecar_lyrics_file) = sidecar_lyrics_file {
+        if let Some(sidecar_lyrics) = import_sidecar_lyrics(sidecar_lyrics_file) {
+            // Embedded lyrics take precedence
+            track.lyrics = collect_lyrics(
+                std::mem::take(&mut track.lyrics)
+                    .untie()
+                    .into_iter()
+                    .chain(std::iter::once(sidecar_lyrics)),
+            );
+        }
+    }
+    let fingerprint = match analyze_audio_file(&canonical_path, flags, &mut track) {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => {
+            // The metadata is imported even if the audio analysis failed
+            log::warn!(
+                "Failed to analyze audio of file {}: {}",
+                canonical_path.display(),
+                err
+            );
+            None
+        }
+    };
+    Ok(ImportTrackFromFileOutcome::Imported(track, fingerprint))
+}
+
+#[derive(Debug, Clone)]
+struct SidecarArtworkFile {
+    path: PathBuf,
+
+    /// The percent-encoded file name, i.e. relative to the
+    /// directory of the track
+    uri: String,
+
+    last_modified_at: DateTime,
+}
+
+/// Find the sidecar artwork file with the highest precedence in
+/// the given directory
+fn find_sidecar_artwork_file(
+    dir_path: &Path,
+    sidecar_file_names: &[String],
+) -> Option<SidecarArtworkFile> {
+    if sidecar_file_names.is_empty() {
+        return None;
+    }
+    let mut dir_entries = match fs::read_dir(dir_path) {
+        Ok(read_dir) => read_dir
+            .filter_map(|dir_entry| dir_entry.ok())
+            .filter(|dir_entry| {
+                dir_entry
+                    .file_type()
+                    .map(|file_type| file_type.is_file())
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            log::warn!("Failed to read directory {}: {}", dir_path.display(), err);
+            return None;
+        }
+    };
+    // Deterministic precedence if multiple file names only
+    // differ by case
+    dir_entries.sort_unstable_by_key(|dir_entry| dir_entry.file_name());
+    let dir_entry = sidecar_file_names.iter().find_map(|sidecar_file_name| {
+        dir_entries.iter().find(|dir_entry| {
+            dir_entry
+                .file_name()
+                .to_str()
+                .map(|file_name| file_name.eq_ignore_ascii_case(sidecar_file_name))
+                .unwrap_or(false)
+        })
+    })?;
+    let path = dir_entry.path();
+    let uri = Url::from_file_path(&path)
+        .ok()?
+        .path_segments()?
+        .last()?
+        .to_owned();
+    let last_modified_at = dir_entry
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::from)
+        .map_err(|err| {
+            log::warn!(
+                "Failed to read last modification time of {}: {}",
+                path.display(),
+                err
+            );
+        })
+        .ok()?;
+    Some(SidecarArtworkFile {
+        path,
+        uri,
+        last_modified_at,
+    })
+}
+
+/// Remembers the sidecar artwork file of the most recently
+/// visited directory
+///
+/// Files are usually imported directory by directory. The directory
+/// only needs to be read once for all the files it contains. A cache
+/// must only be used with the same sidecar artwork file names.
+#[derive(Debug, Default)]
+pub struct SidecarArtworkFileCache {
+    dir_path: Option<PathBuf>,
+    sidecar_artwork_file: Option<SidecarArtworkFile>,
+}
+
+impl SidecarArtworkFileCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Find the sidecar artwork file with the highest precedence in
+    /// the directory of the given file
+    fn find_sidecar_artwork_file(
+        &mut self,
+        file_path: &Path,
+        sidecar_file_names: &[String],
+    ) -> Option<SidecarArtworkFile> {
+        let dir_path = file_path.parent()?;
+        if self.dir_path.as_deref() != Some(dir_path) {
+            self.sidecar_artwork_file = find_sidecar_artwork_file(dir_path, sidecar_file_names);
+            self.dir_path = Some(dir_path.to_owned());
+        }
+        self.sidecar_artwork_file.clone()
+    }
+}
+
+fn import_sidecar_artwork(
+    flags: ImportTrackFlags,
+    sidecar_artwork_file: SidecarArtworkFile,
+) -> Option<Artwork> {
+    let SidecarArtworkFile { path, uri, .. } = sidecar_artwork_file;
+    let image_data = fs::read(&path)
+        .map_err(|err| {
+            log::warn!("Failed to read artwork image {}: {}", path.display(), err);
+        })
+        .ok()?;
+    let mut image_digest = if flags.contains(ImportTrackFlags::ARTWORK_DIGEST) {
+        if flags.contains(ImportTrackFlags::ARTWORK_DIGEST_SHA256) {
+            // Compatibility
+            MediaDigest::sha256()
+        } else {
+            // Default
+            MediaDigest::new()
+        }
+    } else {
+        Default::default()
+    };
+    let artwork = parse_artwork_from_embedded_image(&image_data, None, &mut image_digest)?;
+    Some(Artwork {
+        uri: Some(uri),
+        ..artwork
+    })
+}
+
+#[derive(Debug)]
+struct SidecarLyricsFile {
+    path: PathBuf,
+
+    last_modified_at: DateTime,
+}
+
+/// Find the sidecar LRC file with the same file name as the given
+/// file, i.e. only with a different extension
+fn find_sidecar_lyrics_file(file_path: &Path) -> Option<SidecarLyricsFile> {
+    let path = file_path.with_extension(LRC_FILE_EXTENSION);
+    let metadata = fs::metadata(&path)
+        .ok()
+        .filter(|metadata| metadata.is_file())?;
+    let last_modified_at = metadata
+        .modified()
+        .map(DateTime::from)
+        .map_err(|err| {
+            log::warn!(
+                "Failed to read last modification time of {}: {}",
+                path.display(),
+                err
+            );
+        })
+        .ok()?;
+    Some(SidecarLyricsFile {
+        path,
+        last_modified_at,
+    })
+}
+
+fn import_sidecar_lyrics(sidecar_lyrics_file: SidecarLyricsFile) -> Option<Lyrics> {
+    let SidecarLyricsFile { path, .. } = sidecar_lyrics_file;
+    let input = fs::read_to_string(&path)
+        .map_err(|err| {
+            log::warn!("Failed to read lyrics {}: {}", path.display(), err);
+        })
+        .ok()?;
+    let lyrics = parse_lrc(&input);
+    if lyrics.is_none() {
+        log::warn!("No time-synchronized lyrics found in {}", path.display());
+    }
+    lyrics
+}
+
+/// Resolve the file path of a sidecar artwork file from its
+/// URI relative to the file path of the track
+///
+/// Only the plain file names of sidecar artwork files in the
+/// directory of the track are accepted.
+fn resolve_sidecar_artwork_file_path(
+    file_path: &Path,
+    uri: &str,
+    sidecar_file_names: &[String],
+) -> Option<PathBuf> {
+    if uri.is_empty() || uri.contains(|c| matches!(c, '/' | '\\' | '?' | '#')) {
+        return None;
+    }
+    let dir_path = file_path.parent()?;
+    let path = Url::from_directory_path(dir_path)
+        .ok()?
+        .join(uri)
+        .ok()?
+        .to_file_path()
+        .ok()?;
+    if path.parent() != Some(dir_path) {
+        // The percent-decoded file name is not a single segment
+        return None;
+    }
+    let file_name = path.file_name()?.to_str()?;
+    if !sidecar_file_names
+        .iter()
+        .any(|sidecar_file_name| file_name.eq_ignore_ascii_case(sidecar_file_name))
+    {
+        return None;
+    }
+    Some(path)
+}
+
+/// Load the full-resolution image of the embedded or sidecar artwork
+///
+/// The file is not imported and the track is not modified.
+pub fn load_artwork_image_from_local_file_path(
+    source_path_resolver: &VirtualFilePathResolver,
+    media_source: &Source,
+    sidecar_artwork_file_names: &[String],
+) -> Result<Option<ArtworkImage>> {
+    let file_path = source_path_resolver.build_file_path(&media_source.path);
+    let (canonical_path, file) =
+        if let Some((canonical_path, file)) = open_local_file_for_reading(&file_path)? {
+            (canonical_path, file)
+        } else {
+            log::debug!("{} is a directory", file_path.display());
+            return Ok(None);
+        };
+    if let Some(uri) = &media_source.artwork.uri {
+        let sidecar_path = if let Some(path) =
+            resolve_sidecar_artwork_file_path(&canonical_path, uri, sidecar_artwork_file_names)
+        {
+            path
+        } else {
+            log::warn!("Invalid artwork URI: {}", uri);
+            return Ok(None);
+        };
+        let sidecar_path = match sidecar_path.canonicalize() {
+            Ok(path) => path,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                log::debug!("Artwork image {} not found", sidecar_path.display());
+                return Ok(None);
+            }
+            Err(err) => return Err(MediaError::from(err).into()),
+        };
+        if sidecar_path.parent() != canonical_path.parent() {
+            // Symbolic links must not escape from the directory of the track
+            log::warn!(
+                "Artwork image {} is not located in the directory of the track",
+                sidecar_path.display()
+            );
+            return Ok(None);
+        }
+        let data = match fs::read(&sidecar_path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                log::debug!("Artwork image {} not found", sidecar_path.display());
+                return Ok(None);
+            }
+            Err(err) => return Err(MediaError::from(err).into()),
+        };
+        let media_type = media_source
+            .artwork
+            .media_type
+            .clone()
+            .unwrap_or_else(|| "image/*".to_owned());
+        return Ok(Some(ArtworkImage { media_type, data }));
+    }
+    let mut reader: Box<dyn Reader> = Box::new(BufReader::new(file));
+    let artwork_image = match media_source.content_type.as_str() {
+        "audio/flac" => flac::ImportTrack.load_artwork_image(&mut reader),
+        "audio/mpeg" => mp3::ImportTrack.load_artwork_image(&mut reader),
+        "audio/m4a" | "video/mp4" => mp4::ImportTrack.load_artwork_image(&mut reader),
+        "audio/ogg" => ogg::ImportTrack.load_artwork_image(&mut reader),
+        "audio/opus" => opus::ImportTrack.load_artwork_image(&mut reader),
+        "audio/wav" | "audio/x-wav" | "audio/vnd.wave" => {
+            wav::ImportTrack.load_artwork_image(&mut reader)
+        }
+        "audio/x-ape" => ape::ImportTrack.load_artwork_image(&mut reader),
+        "audio/x-musepack" => mpc::ImportTrack.load_artwork_image(&mut reader),
+        "audio/x-wavpack" => wavpack::ImportTrack.load_artwork_image(&mut reader),
+        "audio/aiff" | "audio/x-aiff" => aiff::ImportTrack.load_artwork_image(&mut reader),
+        content_type => Err(content_type
+            .parse()
+            .map(MediaError::UnsupportedContentType)
+            .unwrap_or(MediaError::UnknownContentType)),
+    }?;
+    Ok(artwork_image)
+}
+
+/// Export the metadata of a track into its file
+///
+/// Returns the last modification time of the file after the export
+/// that should be stored as the new synchronization time of the
+/// media source. Otherwise the file would be considered as modified
+/// and reimported.
+pub fn export_track_to_local_file_path(
+    source_path_resolver: &VirtualFilePathResolver,
+    track: &Track,
+    config: &ExportTrackConfig,
+    flags: ExportTrackFlags,
+) -> Result<DateTime> {
+    if SourcePath::is_virtual_track(&track.media_source.path) {
+        // The metadata of the file is shared by all virtual tracks
+        return Err(anyhow::anyhow!(
+            "Cannot export metadata of virtual track {}",
+            track.media_source.path
+        )
+        .into());
+    }
+    let file_path = source_path_resolver.build_file_path(&track.media_source.path);
+    match track.media_source.content_type.as_str() {
+        "audio/flac" => flac::ExportTrack.export_track_to_path(config, flags, track, &file_path),
+        "audio/mpeg" => mp3::ExportTrack.export_track_to_path(config, flags, track, &file_path),
+        "audio/m4a" | "video/mp4" => {
+            mp4::ExportTrack.export_track_to_path(config, flags, track, &file_path)
+        }
+        "audio/ogg" => ogg::ExportTrack.export_track_to_path(config, flags, track, &file_path),
+        content_type => Err(content_type
+            .parse()
+            .map(MediaError::UnsupportedContentType)
+            .unwrap_or(MediaError::UnknownContentType)),
+    }?;
+    let last_modified_at = fs::metadata(&file_path)
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::from)
+        .map_err(MediaError::from)?;
+    Ok(last_modified_at)
 }
+
+#[cfg(test)]
+mod tests;
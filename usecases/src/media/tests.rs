@@ -0,0 +1,177 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::audio::AudioContent;
+
+use aoide_media::io::import::DEFAULT_SIDECAR_ARTWORK_FILE_NAMES;
+
+fn sidecar_file_names() -> Vec<String> {
+    DEFAULT_SIDECAR_ARTWORK_FILE_NAMES
+        .iter()
+        .copied()
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+fn create_temp_dir(name: &str) -> PathBuf {
+    let dir_path = std::env::temp_dir().join(format!(
+        "aoide-usecases-media-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir_path);
+    fs::create_dir_all(&dir_path).unwrap();
+    dir_path
+}
+
+#[test]
+fn resolve_sidecar_artwork_file_path_in_directory_of_track() {
+    let file_path = Path::new("/music/album/track.mp3");
+    let sidecar_file_names = sidecar_file_names();
+    assert_eq!(
+        Some(PathBuf::from("/music/album/cover.jpg")),
+        resolve_sidecar_artwork_file_path(file_path, "cover.jpg", &sidecar_file_names)
+    );
+    // Case-insensitive
+    assert_eq!(
+        Some(PathBuf::from("/music/album/Folder.PNG")),
+        resolve_sidecar_artwork_file_path(file_path, "Folder.PNG", &sidecar_file_names)
+    );
+    // Percent-encoded
+    assert_eq!(
+        Some(PathBuf::from("/music/album/my cover.jpg")),
+        resolve_sidecar_artwork_file_path(
+            file_path,
+            "my%20cover.jpg",
+            &["my cover.jpg".to_owned()]
+        )
+    );
+}
+
+#[test]
+fn resolve_sidecar_artwork_file_path_rejects_other_files() {
+    let file_path = Path::new("/music/album/track.mp3");
+    let sidecar_file_names = sidecar_file_names();
+    for uri in &[
+        "",
+        ".",
+        "..",
+        "%2E%2E",
+        "track.mp3",
+        "../cover.jpg",
+        "..%2Fcover.jpg",
+        "sub/cover.jpg",
+        "sub%2Fcover.jpg",
+        "sub\\cover.jpg",
+        "/music/album/cover.jpg",
+        "file:///music/album/cover.jpg",
+        "cover.jpg?query",
+        "cover.jpg#fragment",
+    ] {
+        assert_eq!(
+            None,
+            resolve_sidecar_artwork_file_path(file_path, uri, &sidecar_file_names),
+            "{}",
+            uri
+        );
+    }
+}
+
+#[test]
+fn find_sidecar_artwork_file_with_highest_precedence() {
+    let dir_path = create_temp_dir("find-sidecar-artwork");
+    fs::write(dir_path.join("Folder.jpg"), b"folder").unwrap();
+    fs::write(dir_path.join("cover.png"), b"cover").unwrap();
+    fs::create_dir(dir_path.join("cover.jpg")).unwrap();
+
+    let sidecar_artwork_file = find_sidecar_artwork_file(&dir_path, &sidecar_file_names());
+    let no_sidecar_artwork_file = find_sidecar_artwork_file(&dir_path, &[]);
+    let _ = fs::remove_dir_all(&dir_path);
+
+    // Directories are ignored
+    let sidecar_artwork_file = sidecar_artwork_file.unwrap();
+    assert_eq!("cover.png", sidecar_artwork_file.uri);
+    assert_eq!(dir_path.join("cover.png"), sidecar_artwork_file.path);
+    assert!(no_sidecar_artwork_file.is_none());
+}
+
+#[test]
+fn sidecar_artwork_file_cache_reads_directory_once() {
+    let dir_path = create_temp_dir("sidecar-artwork-file-cache");
+    let file_path = dir_path.join("track.mp3");
+    let sidecar_file_names = sidecar_file_names();
+    let mut cache = SidecarArtworkFileCache::new();
+
+    let first = cache.find_sidecar_artwork_file(&file_path, &sidecar_file_names);
+    fs::write(dir_path.join("cover.jpg"), b"cover").unwrap();
+    let cached = cache.find_sidecar_artwork_file(&dir_path.join("other.mp3"), &sidecar_file_names);
+    let uncached =
+        SidecarArtworkFileCache::new().find_sidecar_artwork_file(&file_path, &sidecar_file_names);
+    let _ = fs::remove_dir_all(&dir_path);
+
+    assert!(first.is_none());
+    assert!(cached.is_none());
+    assert_eq!("cover.jpg", uncached.unwrap().uri);
+}
+
+#[cfg(unix)]
+#[test]
+fn load_artwork_image_rejects_symbolic_links_outside_of_track_directory() {
+    let dir_path = create_temp_dir("load-sidecar-artwork");
+    let track_dir_path = dir_path.join("album");
+    fs::create_dir(&track_dir_path).unwrap();
+    let file_path = track_dir_path.join("track.mp3");
+    fs::write(&file_path, b"").unwrap();
+    fs::write(track_dir_path.join("cover.jpg"), b"cover").unwrap();
+    let secret_file_path = dir_path.join("secret.txt");
+    fs::write(&secret_file_path, b"secret").unwrap();
+    std::os::unix::fs::symlink(&secret_file_path, track_dir_path.join("folder.jpg")).unwrap();
+
+    let source_path_resolver = VirtualFilePathResolver::new();
+    let sidecar_file_names = sidecar_file_names();
+    let load_sidecar_artwork_image = |uri: &str| {
+        let media_source = Source {
+            collected_at: DateTime::now_utc(),
+            synchronized_at: None,
+            path: SourcePath::new(file_path.to_str().unwrap().to_owned()),
+            content_type: "audio/mpeg".to_owned(),
+            content_digest: None,
+            content_metadata_flags: Default::default(),
+            content: AudioContent::default().into(),
+            artwork: Artwork {
+                uri: Some(uri.to_owned()),
+                media_type: Some("image/jpeg".to_owned()),
+                ..Default::default()
+            },
+        };
+        load_artwork_image_from_local_file_path(
+            &source_path_resolver,
+            &media_source,
+            &sidecar_file_names,
+        )
+    };
+    let cover = load_sidecar_artwork_image("cover.jpg");
+    let folder = load_sidecar_artwork_image("folder.jpg");
+    let secret = load_sidecar_artwork_image("..%2Fsecret.txt");
+    let _ = fs::remove_dir_all(&dir_path);
+
+    assert_eq!(b"cover".to_vec(), cover.unwrap().unwrap().data);
+    assert!(folder.unwrap().is_none());
+    assert!(secret.unwrap().is_none());
+}
@@ -44,6 +44,7 @@ pub type FindCandidateParams = find_duplicate::Params;
 ///
 /// The media tracker is also updated, i.e. it will reference the updated
 /// old media source instead of the new media source that is removed.
+/// The acoustic fingerprint of the new media source is preserved.
 fn relink_moved_track_by_media_source_path<Repo>(
     repo: &Repo,
     collection_id: CollectionId,
@@ -65,6 +66,7 @@ where
         },
         ..new_entity.body
     };
+    let new_fingerprint = repo.load_media_source_fingerprint(new_source_id)?;
     // Relink the sources in the media tracker
     repo.media_tracker_relink_source(old_source_id, new_source_id)?;
     // Delete the soon obsolete track and source records to prevent
@@ -72,6 +74,9 @@ where
     // long as the track is not referenced elsewhere, e.g. playlists!
     repo.delete_track_entity(new_header.id)?;
     repo.delete_media_source(new_source_id)?;
+    if let Some(new_fingerprint) = &new_fingerprint {
+        repo.update_media_source_fingerprint(old_source_id, Some(new_fingerprint))?;
+    }
     // Finish with updating the old track
     if updated_track != old_entity.body {
         let updated_at = DateTime::now_utc();
@@ -163,8 +168,10 @@ where
         ordering,
         &mut lost_tracks,
     )?;
-    // Only consider tracks with a tracked media source
-    find_candidate_params.search_flags |= find_duplicate::SearchFlags::SOURCE_TRACKED;
+    // Only consider tracks with a tracked media source and prefer
+    // matching acoustic fingerprints over the metadata
+    find_candidate_params.search_flags |=
+        find_duplicate::SearchFlags::SOURCE_TRACKED | find_duplicate::SearchFlags::FINGERPRINT;
     let mut progress = Progress::new(lost_tracks.len());
     let mut relinked_media_sources = Vec::with_capacity(lost_tracks.len());
     for (old_header, old_entity) in lost_tracks {
@@ -177,7 +184,7 @@ where
         let candidates = find_duplicate(
             repo,
             collection_id,
-            old_header.id,
+            Some(old_header.id),
             old_entity.body,
             &find_candidate_params,
         )?;
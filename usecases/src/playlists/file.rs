@@ -0,0 +1,206 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::tracks::resolve::resolve_by_media_source_paths;
+
+use aoide_core::{
+    entity::{EntityHeader, EntityUid},
+    media::{
+        resolver::{SourcePathResolver as _, VirtualFilePathResolver},
+        Content,
+    },
+    playlist::{
+        track::Item as TrackItem, Entity as PlaylistEntity, EntriesSummary, Entry, Item, Playlist,
+    },
+    track::Track,
+    util::clock::DateTime,
+};
+
+use aoide_media::playlist::{resolve_location_url, FileFormat, Item as FileItem};
+
+use aoide_repo::{
+    collection::RecordId as CollectionId,
+    playlist::{EntityRepo as _, EntryRepo as _, Repo as PlaylistRepo},
+    track::EntityRepo as TrackEntityRepo,
+};
+
+use std::{collections::HashMap, path::Path};
+
+#[derive(Debug)]
+pub struct ImportSummary {
+    pub entity: PlaylistEntity,
+
+    pub entries_summary: EntriesSummary,
+
+    /// Locations of tracks that could not be resolved, i.e. that
+    /// are either outside of the collection or have not been
+    /// imported into the collection yet.
+    pub unresolved: Vec<String>,
+}
+
+/// How the paths of media sources are written into playlist files.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PathMode {
+    /// Absolute file paths
+    Absolute,
+
+    /// Paths relative to the root directory of the collection
+    Relative,
+}
+
+/// Create a new playlist from the tracks of a playlist file.
+///
+/// All locations are resolved into existing tracks of the collection.
+/// Relative file paths are resolved against the given base directory,
+/// i.e. the directory of the playlist file. Titles of the playlist file
+/// are only assigned to the created entries if requested.
+#[allow(clippy::too_many_arguments)]
+pub fn import_file<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    source_path_resolver: &VirtualFilePathResolver,
+    file_format: FileFormat,
+    input: &str,
+    base_dir: Option<&Path>,
+    new_playlist: Playlist,
+    map_entry_titles: bool,
+) -> Result<ImportSummary>
+where
+    Repo: TrackEntityRepo + PlaylistRepo,
+{
+    let items: Vec<_> = file_format
+        .import_items(input)?
+        .into_iter()
+        .filter_map(|item| match item {
+            FileItem::Track {
+                location, title, ..
+            } => {
+                let media_source_path = resolve_location_url(&location, base_dir)
+                    .and_then(|url| source_path_resolver.resolve_path_from_url(&url).ok())
+                    .map(String::from);
+                Some((location, title, media_source_path))
+            }
+            FileItem::Separator { .. } => None,
+        })
+        .collect();
+    let media_source_paths = items
+        .iter()
+        .filter_map(|(_, _, media_source_path)| media_source_path.clone())
+        .collect();
+    let track_uids: HashMap<_, _> =
+        resolve_by_media_source_paths(repo, collection_id, media_source_paths)?
+            .into_iter()
+            .map(|(media_source_path, entity_header)| (media_source_path, entity_header.uid))
+            .collect();
+    let added_at = DateTime::now_utc();
+    let mut entries = Vec::with_capacity(items.len());
+    let mut unresolved = Vec::new();
+    for (location, title, media_source_path) in items {
+        let uid = if let Some(uid) = media_source_path
+            .as_ref()
+            .and_then(|media_source_path| track_uids.get(media_source_path))
+        {
+            uid
+        } else {
+            log::info!("Unresolved playlist entry: {}", location);
+            unresolved.push(location);
+            continue;
+        };
+        entries.push(Entry {
+            added_at,
+            title: title.filter(|_| map_entry_titles),
+            notes: None,
+            item: Item::Track(TrackItem { uid: uid.clone() }),
+        });
+    }
+    let entity = PlaylistEntity::new(EntityHeader::initial_random(), new_playlist);
+    let playlist_id = repo.insert_collected_playlist_entity(collection_id, added_at, &entity)?;
+    repo.append_playlist_entries(playlist_id, &entries)?;
+    let (_, entity, entries_summary) =
+        repo.load_playlist_entity_with_entries_summary(playlist_id)?;
+    Ok(ImportSummary {
+        entity,
+        entries_summary,
+        unresolved,
+    })
+}
+
+/// Use `<Artist> - <Title>` if the entry has no title.
+fn default_entry_title(track: &Track) -> Option<String> {
+    match (track.track_artist(), track.track_title()) {
+        (Some(artist), Some(title)) => Some(format!("{} - {}", artist, title)),
+        (None, Some(title)) => Some(title.to_owned()),
+        (_, None) => None,
+    }
+}
+
+/// Export all entries of a playlist into a playlist file.
+///
+/// Separators are written as comments. Entries that refer to
+/// tracks that no longer exist in the collection are skipped.
+pub fn export_file<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    source_path_resolver: &VirtualFilePathResolver,
+    playlist_uid: &EntityUid,
+    file_format: FileFormat,
+    path_mode: PathMode,
+) -> Result<String>
+where
+    Repo: TrackEntityRepo + PlaylistRepo,
+{
+    let playlist_id = repo.resolve_playlist_id(playlist_uid)?;
+    if repo.load_playlist_collection_id(playlist_id)? != collection_id {
+        return Err(RepoError::NotFound.into());
+    }
+    let entries = repo.load_all_playlist_entries(playlist_id)?;
+    let mut items = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let Entry { title, item, .. } = entry;
+        let uid = match item {
+            Item::Track(TrackItem { uid }) => uid,
+            Item::Separator => {
+                items.push(FileItem::Separator { title });
+                continue;
+            }
+        };
+        let track = match repo.load_track_entity_by_uid(&uid) {
+            Ok((_, entity)) => entity.body,
+            Err(RepoError::NotFound) => {
+                log::warn!("Skipping playlist entry of deleted track {}", uid);
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let location = match path_mode {
+            PathMode::Absolute => source_path_resolver
+                .build_file_path(&track.media_source.path)
+                .display()
+                .to_string(),
+            PathMode::Relative => track.media_source.path.to_string(),
+        };
+        let Content::Audio(audio_content) = &track.media_source.content;
+        items.push(FileItem::Track {
+            location,
+            title: title.or_else(|| default_entry_title(&track)),
+            duration: audio_content.duration,
+        });
+    }
+    Ok(file_format.export_items(&items))
+}
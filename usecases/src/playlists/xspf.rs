@@ -0,0 +1,338 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::{
+    playlists::file::{ImportSummary, PathMode},
+    tracks::{
+        find_duplicate::{self, find_duplicate},
+        resolve::resolve_by_media_source_paths,
+    },
+};
+
+use aoide_core::{
+    audio::AudioContent,
+    entity::{EntityHeader, EntityUid},
+    media::{
+        resolver::{SourcePathResolver as _, VirtualFilePathResolver},
+        Content, ContentMetadataFlags, Source,
+    },
+    playlist::{
+        track::Item as TrackItem, Entity as PlaylistEntity, Entry, Item, Playlist,
+        PlaylistWithEntries,
+    },
+    track::Track,
+    util::clock::DateTime,
+};
+
+use aoide_media::{
+    playlist::xspf::{
+        export_playlist, import_playlist, Entry as XspfEntry, Item as XspfItem,
+        Playlist as XspfPlaylist, Track as XspfTrack,
+    },
+    util::guess_mime_from_path,
+};
+
+use aoide_repo::{
+    collection::RecordId as CollectionId,
+    playlist::{EntityRepo as _, EntryRepo as _, Repo as PlaylistRepo},
+    track::EntityRepo as TrackEntityRepo,
+};
+
+use anyhow::anyhow;
+use std::collections::HashMap;
+use url::Url;
+
+///////////////////////////////////////////////////////////////////////
+
+fn resolve_location_url(location: &str, base_url: Option<&Url>) -> Option<Url> {
+    if let Some(base_url) = base_url {
+        base_url.join(location).ok()
+    } else {
+        Url::parse(location).ok()
+    }
+}
+
+/// Build a template for finding the track by its metadata
+fn template_track(xspf_track: &XspfTrack, collected_at: DateTime) -> Option<Track> {
+    let XspfTrack {
+        location,
+        creator,
+        title,
+        duration,
+    } = xspf_track;
+    // The title is mandatory for identifying the track
+    let title = title.as_ref()?;
+    let location = location.as_deref().unwrap_or_default();
+    let content_type = guess_mime_from_path(location)
+        .map(|mime| mime.to_string())
+        .unwrap_or_default();
+    let audio_content = AudioContent {
+        duration: *duration,
+        ..Default::default()
+    };
+    let media_source = Source {
+        collected_at,
+        synchronized_at: None,
+        path: location.to_owned().into(),
+        content_type,
+        content_digest: None,
+        content_metadata_flags: ContentMetadataFlags::UNRELIABLE,
+        content: Content::Audio(audio_content),
+        artwork: Default::default(),
+    };
+    let mut track = Track::new_from_media_source(media_source);
+    track.set_track_title(title.as_str());
+    if let Some(creator) = creator {
+        track.set_track_artist(creator.as_str());
+    }
+    Some(track)
+}
+
+/// Only compare the audio duration if it is known, otherwise
+/// tracks would only match other tracks without a duration
+fn find_duplicate_params(template_track: &Track) -> find_duplicate::Params {
+    let mut search_flags =
+        find_duplicate::SearchFlags::TRACK_ARTIST | find_duplicate::SearchFlags::TRACK_TITLE;
+    let Content::Audio(audio_content) = &template_track.media_source.content;
+    if audio_content.duration.is_some() {
+        search_flags |= find_duplicate::SearchFlags::AUDIO_DURATION;
+    }
+    find_duplicate::Params {
+        search_flags,
+        ..Default::default()
+    }
+}
+
+fn find_unambiguous_track_uid<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    xspf_track: &XspfTrack,
+    collected_at: DateTime,
+) -> Result<Option<EntityUid>>
+where
+    Repo: TrackEntityRepo,
+{
+    let track = if let Some(track) = template_track(xspf_track, collected_at) {
+        track
+    } else {
+        return Ok(None);
+    };
+    let params = find_duplicate_params(&track);
+    let mut candidates = find_duplicate(repo, collection_id, None, track, &params)?;
+    if candidates.len() != 1 {
+        return Ok(None);
+    }
+    Ok(candidates.pop().map(|(_, entity)| entity.hdr.uid))
+}
+
+/// Create a new playlist from an XSPF file.
+///
+/// Locations are resolved into existing tracks of the collection.
+/// Relative locations are resolved against the given base URL, i.e.
+/// the URL of the XSPF file. Tracks with a missing or unresolvable
+/// location are matched by their creator, title, and duration if
+/// this is unambiguous.
+///
+/// The title and notes of the XSPF playlist replace those of the
+/// new playlist if present.
+pub fn import_xspf<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    source_path_resolver: &VirtualFilePathResolver,
+    input: &str,
+    base_url: Option<&Url>,
+    mut new_playlist: Playlist,
+) -> Result<ImportSummary>
+where
+    Repo: TrackEntityRepo + PlaylistRepo,
+{
+    let XspfPlaylist {
+        title,
+        notes,
+        entries: xspf_entries,
+    } = import_playlist(input)?;
+    if let Some(title) = title {
+        new_playlist.title = title;
+    }
+    if notes.is_some() {
+        new_playlist.notes = notes;
+    }
+    let media_source_paths = xspf_entries
+        .iter()
+        .filter_map(|entry| match &entry.item {
+            XspfItem::Track(XspfTrack {
+                location: Some(location),
+                ..
+            }) => resolve_location_url(location, base_url),
+            _ => None,
+        })
+        .filter_map(|url| source_path_resolver.resolve_path_from_url(&url).ok())
+        .map(String::from)
+        .collect();
+    let track_uids: HashMap<_, _> =
+        resolve_by_media_source_paths(repo, collection_id, media_source_paths)?
+            .into_iter()
+            .map(|(media_source_path, entity_header)| (media_source_path, entity_header.uid))
+            .collect();
+    let added_at = DateTime::now_utc();
+    let mut entries = Vec::with_capacity(xspf_entries.len());
+    let mut unresolved = Vec::new();
+    for xspf_entry in xspf_entries {
+        let XspfEntry { title, notes, item } = xspf_entry;
+        let item = match item {
+            XspfItem::Track(xspf_track) => {
+                let uid = xspf_track
+                    .location
+                    .as_deref()
+                    .and_then(|location| resolve_location_url(location, base_url))
+                    .and_then(|url| source_path_resolver.resolve_path_from_url(&url).ok())
+                    .map(String::from)
+                    .and_then(|media_source_path| track_uids.get(&media_source_path).cloned());
+                let uid = if let Some(uid) = uid {
+                    uid
+                } else if let Some(uid) =
+                    find_unambiguous_track_uid(repo, collection_id, &xspf_track, added_at)?
+                {
+                    uid
+                } else {
+                    let XspfTrack {
+                        location,
+                        creator,
+                        title,
+                        ..
+                    } = xspf_track;
+                    let unresolved_track = location.unwrap_or_else(|| {
+                        format!(
+                            "{} - {}",
+                            creator.unwrap_or_default(),
+                            title.unwrap_or_default()
+                        )
+                    });
+                    log::info!("Unresolved playlist entry: {}", unresolved_track);
+                    unresolved.push(unresolved_track);
+                    continue;
+                };
+                Item::Track(TrackItem { uid })
+            }
+            XspfItem::Separator => Item::Separator,
+        };
+        entries.push(Entry {
+            added_at,
+            title,
+            notes,
+            item,
+        });
+    }
+    let entity = PlaylistEntity::new(EntityHeader::initial_random(), new_playlist);
+    let playlist_id = repo.insert_collected_playlist_entity(collection_id, added_at, &entity)?;
+    repo.append_playlist_entries(playlist_id, &entries)?;
+    let (_, entity, entries_summary) =
+        repo.load_playlist_entity_with_entries_summary(playlist_id)?;
+    Ok(ImportSummary {
+        entity,
+        entries_summary,
+        unresolved,
+    })
+}
+
+fn export_location(
+    source_path_resolver: &VirtualFilePathResolver,
+    media_source_path: &str,
+    path_mode: PathMode,
+) -> Result<String> {
+    let url = source_path_resolver
+        .resolve_url_from_path(media_source_path)
+        .map_err(|err| {
+            anyhow!(
+                "Failed to resolve location of {}: {:?}",
+                media_source_path,
+                err
+            )
+        })?;
+    let location = match path_mode {
+        PathMode::Absolute => None,
+        PathMode::Relative => source_path_resolver
+            .resolve_url_from_path("")
+            .ok()
+            .and_then(|root_url| root_url.make_relative(&url)),
+    };
+    Ok(location.unwrap_or_else(|| url.into()))
+}
+
+/// Export a playlist with all its entries as an XSPF file.
+///
+/// Tracks are referenced by their location and identified by their
+/// creator, title, and duration.
+pub fn export_playlist_with_entries<Repo>(
+    repo: &Repo,
+    source_path_resolver: &VirtualFilePathResolver,
+    playlist_with_entries: PlaylistWithEntries,
+    path_mode: PathMode,
+) -> Result<String>
+where
+    Repo: TrackEntityRepo,
+{
+    let PlaylistWithEntries { playlist, entries } = playlist_with_entries;
+    let mut xspf_entries = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let Entry {
+            title, notes, item, ..
+        } = entry;
+        let item = match item {
+            Item::Track(TrackItem { uid }) => {
+                let (_, entity) = repo.load_track_entity_by_uid(&uid)?;
+                let track = entity.body;
+                let location =
+                    export_location(source_path_resolver, &track.media_source.path, path_mode)?;
+                let Content::Audio(audio_content) = &track.media_source.content;
+                XspfItem::Track(XspfTrack {
+                    location: Some(location),
+                    creator: track.track_artist().map(ToOwned::to_owned),
+                    title: track.track_title().map(ToOwned::to_owned),
+                    duration: audio_content.duration,
+                })
+            }
+            Item::Separator => XspfItem::Separator,
+        };
+        xspf_entries.push(XspfEntry { title, notes, item });
+    }
+    let xspf_playlist = XspfPlaylist {
+        title: Some(playlist.title),
+        notes: playlist.notes,
+        entries: xspf_entries,
+    };
+    Ok(export_playlist(&xspf_playlist))
+}
+
+pub fn export_xspf<Repo>(
+    repo: &Repo,
+    source_path_resolver: &VirtualFilePathResolver,
+    playlist_uid: &EntityUid,
+    path_mode: PathMode,
+) -> Result<String>
+where
+    Repo: TrackEntityRepo + PlaylistRepo,
+{
+    let playlist_id = repo.resolve_playlist_id(playlist_uid)?;
+    let entity = repo.load_playlist_entity_with_entries(playlist_id)?;
+    export_playlist_with_entries(repo, source_path_resolver, entity.body, path_mode)
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,74 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::audio::DurationMs;
+
+#[test]
+fn template_track_without_location_and_duration() {
+    let xspf_track = XspfTrack {
+        location: None,
+        creator: Some("Artist".to_owned()),
+        title: Some("Title".to_owned()),
+        duration: None,
+    };
+    let track = template_track(&xspf_track, DateTime::now_utc()).unwrap();
+    assert_eq!(Some("Artist"), track.track_artist());
+    assert_eq!(Some("Title"), track.track_title());
+    assert!(track.media_source.path.is_empty());
+    assert!(track.media_source.content_type.is_empty());
+    let Content::Audio(audio_content) = &track.media_source.content;
+    assert!(audio_content.duration.is_none());
+
+    let params = find_duplicate_params(&track);
+    assert!(params
+        .search_flags
+        .contains(find_duplicate::SearchFlags::TRACK_ARTIST));
+    assert!(params
+        .search_flags
+        .contains(find_duplicate::SearchFlags::TRACK_TITLE));
+    assert!(!params
+        .search_flags
+        .contains(find_duplicate::SearchFlags::AUDIO_DURATION));
+}
+
+#[test]
+fn template_track_with_duration() {
+    let xspf_track = XspfTrack {
+        location: Some("file:///home/test/track.mp3".to_owned()),
+        creator: None,
+        title: Some("Title".to_owned()),
+        duration: Some(DurationMs::from_inner(180_000.0)),
+    };
+    let track = template_track(&xspf_track, DateTime::now_utc()).unwrap();
+    let params = find_duplicate_params(&track);
+    assert!(params
+        .search_flags
+        .contains(find_duplicate::SearchFlags::AUDIO_DURATION));
+}
+
+#[test]
+fn template_track_without_title() {
+    let xspf_track = XspfTrack {
+        location: Some("file:///home/test/track.mp3".to_owned()),
+        creator: Some("Artist".to_owned()),
+        title: None,
+        duration: None,
+    };
+    assert!(template_track(&xspf_track, DateTime::now_utc()).is_none());
+}
@@ -0,0 +1,176 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use aoide_core::{audio::fingerprint::Fingerprint, entity::EntityHeader, media::SourcePath};
+
+use aoide_media::{analyze::analyze_audio_file, io::import::ImportTrackFlags};
+
+use aoide_repo::{
+    collection::RecordId as CollectionId,
+    track::{EntityRepo, ReplaceMode, ReplaceOutcome, SearchFilter, SortOrder},
+};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Completion {
+    Finished,
+    Aborted,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub analyzed: Vec<SourcePath>,
+    pub failed: Vec<SourcePath>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub completion: Completion,
+    pub summary: Summary,
+}
+
+/// Load all tracks in a collection that match the given filter
+/// for analyzing their audio streams.
+pub fn load_tracks_to_analyze<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    filter: Option<SearchFilter>,
+    ordering: Vec<SortOrder>,
+) -> Result<Vec<Entity>>
+where
+    Repo: EntityRepo,
+{
+    let mut tracks = Vec::new();
+    repo.search_collected_tracks(
+        collection_id,
+        &Default::default(),
+        filter,
+        ordering,
+        &mut tracks,
+    )?;
+    Ok(tracks.into_iter().map(|(_, entity)| entity).collect())
+}
+
+/// Store the results of analyzing the audio stream of a track
+///
+/// Returns `false` if the track has been modified concurrently
+/// after it has been loaded. The results are discarded in this
+/// case.
+pub fn store_analyzed_track<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    loaded_header: &EntityHeader,
+    track: Track,
+    fingerprint: Option<&Fingerprint>,
+) -> Result<bool>
+where
+    Repo: EntityRepo,
+{
+    let (_, _, entity_header) = repo.resolve_track_entity_header_by_media_source_path(
+        collection_id,
+        &track.media_source.path,
+    )?;
+    if entity_header != *loaded_header {
+        return Ok(false);
+    }
+    let outcome = repo.replace_collected_track_by_media_source_path(
+        collection_id,
+        true,
+        ReplaceMode::UpdateOnly,
+        track,
+    )?;
+    let media_source_id = match outcome {
+        ReplaceOutcome::Updated(media_source_id, _, _)
+        | ReplaceOutcome::Unchanged(media_source_id, _, _) => media_source_id,
+        _ => return Ok(false),
+    };
+    if let Some(fingerprint) = fingerprint {
+        repo.update_media_source_fingerprint(media_source_id, Some(fingerprint))?;
+    }
+    Ok(true)
+}
+
+/// Analyze the decoded audio streams of the given tracks
+///
+/// Only the analysis flags are considered. The audio streams are
+/// decoded independent of any repository. The results are handed
+/// over to `store_analyzed_track` one track at a time, e.g. for
+/// committing them in a separate transaction.
+///
+/// Failures of individual tracks are logged and reported in the
+/// summary. Virtual tracks within a single file are reported as
+/// failed.
+pub fn analyze_audio_of_tracks(
+    source_path_resolver: &VirtualFilePathResolver,
+    tracks: Vec<Entity>,
+    flags: ImportTrackFlags,
+    store_analyzed_track: &mut impl FnMut(&EntityHeader, Track, Option<&Fingerprint>) -> Result<bool>,
+    abort_flag: &AtomicBool,
+) -> Result<Outcome> {
+    let mut summary = Summary::default();
+    for entity in tracks {
+        if abort_flag.load(Ordering::Relaxed) {
+            log::info!("Aborting audio analysis: {:?}", summary);
+            return Ok(Outcome {
+                completion: Completion::Aborted,
+                summary,
+            });
+        }
+        let entity_header = entity.hdr;
+        let mut track = entity.body;
+        if SourcePath::is_virtual_track(&track.media_source.path) {
+            // Only the whole file could be analyzed
+            log::warn!(
+                "Analyzing the audio of virtual track {} is not supported",
+                track.media_source.path
+            );
+            summary.failed.push(track.media_source.path);
+            continue;
+        }
+        let file_path = source_path_resolver.build_file_path(&track.media_source.path);
+        let fingerprint = match analyze_audio_file(&file_path, flags, &mut track) {
+            Ok(fingerprint) => fingerprint,
+            Err(err) => {
+                log::warn!(
+                    "Failed to analyze audio of file {}: {}",
+                    file_path.display(),
+                    err
+                );
+                summary.failed.push(track.media_source.path);
+                continue;
+            }
+        };
+        let source_path = track.media_source.path.clone();
+        if !store_analyzed_track(&entity_header, track, fingerprint.as_ref())? {
+            log::warn!(
+                "Discarding audio analysis of file {} that has been modified concurrently",
+                file_path.display()
+            );
+            summary.failed.push(source_path);
+            continue;
+        }
+        log::debug!("Analyzed audio of file {}", file_path.display());
+        summary.analyzed.push(source_path);
+    }
+    Ok(Outcome {
+        completion: Completion::Finished,
+        summary,
+    })
+}
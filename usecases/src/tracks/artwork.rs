@@ -0,0 +1,134 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::media::load_artwork_image_from_local_file_path;
+
+use aoide_core::media::{Digest, ImageDimension};
+
+use aoide_media::{
+    fs::artwork_cache::{resize_artwork_image, ArtworkCache, ArtworkVariant, EvictionOutcome},
+    io::import::ArtworkImage,
+};
+
+use aoide_repo::{
+    media::source::Repo as MediaSourceRepo,
+    track::{EntityRepo, RecordId as TrackId},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The digest of the artwork matches the expected digest
+    /// and the image has not been loaded
+    Unmodified(Digest),
+
+    /// The image that has been loaded from the cache or the file
+    Loaded {
+        digest: Option<Digest>,
+        image: ArtworkImage,
+    },
+}
+
+/// Load the image of the embedded or sidecar artwork of a track
+///
+/// The full-resolution image is loaded if no maximum dimension is
+/// requested. Otherwise the image is scaled down to fit into a
+/// square with the given edge length.
+///
+/// Images are stored in and loaded from the artwork cache (if
+/// available) if the digest of the artwork is known. Loading the
+/// image is skipped if the digest of the artwork is accepted as
+/// unmodified, e.g. by a client cache.
+///
+/// Fails with `RepoError::NotFound` if the track has no artwork
+/// or if it could not be loaded from the file.
+pub fn load_artwork_image<Repo>(
+    repo: &Repo,
+    source_path_resolver: &VirtualFilePathResolver,
+    artwork_cache: Option<&ArtworkCache>,
+    sidecar_artwork_file_names: &[String],
+    track_id: TrackId,
+    max_dimension: Option<ImageDimension>,
+    is_unmodified: impl FnOnce(&Digest) -> bool,
+) -> Result<Outcome>
+where
+    Repo: EntityRepo,
+{
+    let (_, entity) = repo.load_track_entity(track_id)?;
+    let media_source = &entity.body.media_source;
+    let artwork = &media_source.artwork;
+    if artwork.is_empty() {
+        return Err(RepoError::NotFound.into());
+    }
+    let digest = artwork.digest;
+    if let Some(digest) = digest {
+        if is_unmodified(&digest) {
+            return Ok(Outcome::Unmodified(digest));
+        }
+    }
+    let artwork_cache = artwork_cache.zip(digest);
+    if let Some((artwork_cache, digest)) = artwork_cache {
+        let cached_image = if let Some(max_dimension) = max_dimension {
+            artwork_cache.load_or_resize(&digest, max_dimension)?
+        } else {
+            artwork_cache.load(&digest, ArtworkVariant::Original)?
+        };
+        if let Some(image) = cached_image {
+            return Ok(Outcome::Loaded {
+                digest: Some(digest),
+                image,
+            });
+        }
+    }
+    let original = load_artwork_image_from_local_file_path(
+        source_path_resolver,
+        media_source,
+        sidecar_artwork_file_names,
+    )?
+    .ok_or(RepoError::NotFound)?;
+    let image = if let Some((artwork_cache, digest)) = artwork_cache {
+        let cached = artwork_cache.store_original(&digest, &original)?;
+        match max_dimension {
+            Some(max_dimension) if cached => artwork_cache
+                .load_or_resize(&digest, max_dimension)?
+                .ok_or(RepoError::NotFound)?,
+            Some(max_dimension) => resize_artwork_image(&original, max_dimension)?,
+            None => original,
+        }
+    } else if let Some(max_dimension) = max_dimension {
+        resize_artwork_image(&original, max_dimension)?
+    } else {
+        original
+    };
+    Ok(Outcome::Loaded { digest, image })
+}
+
+/// Delete unreferenced entries from the artwork cache and evict
+/// the least recently used files afterwards
+pub fn cleanse_artwork_cache<Repo>(
+    repo: &Repo,
+    artwork_cache: &ArtworkCache,
+) -> Result<(usize, EvictionOutcome)>
+where
+    Repo: MediaSourceRepo,
+{
+    let referenced_digests = repo.load_all_artwork_digests()?.into_iter().collect();
+    let purged_entries = artwork_cache.purge_unreferenced(&referenced_digests)?;
+    let eviction_outcome = artwork_cache.evict()?;
+    Ok((purged_entries, eviction_outcome))
+}
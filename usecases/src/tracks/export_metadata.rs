@@ -0,0 +1,159 @@
+// aoide.org - Copyright (C) 2018-2021 Uwe Klotz <uwedotklotzatgmaildotcom> et al.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+///////////////////////////////////////////////////////////////////////
+
+use super::*;
+
+use crate::media::export_track_to_local_file_path;
+
+use aoide_core::{media::SourcePath, util::clock::DateTime};
+
+use aoide_media::io::export::{ExportTrackConfig, ExportTrackFlags};
+
+use aoide_repo::{
+    collection::RecordId as CollectionId,
+    media::source::Repo as MediaSourceRepo,
+    track::{EntityRepo, RecordId as TrackId, SearchFilter, SortOrder},
+};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Completion {
+    Finished,
+    Aborted,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub exported: Vec<SourcePath>,
+    pub failed: Vec<SourcePath>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub completion: Completion,
+    pub summary: Summary,
+}
+
+/// Store the last modification time of an exported file as the
+/// synchronization time of its media source
+///
+/// Prevents that files are considered as modified and reimported
+/// after exporting their metadata.
+fn update_media_source_synchronized_at<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    path: &SourcePath,
+    synchronized_at: DateTime,
+) -> Result<()>
+where
+    Repo: MediaSourceRepo,
+{
+    let (header, mut media_source) = repo.load_media_source_by_path(collection_id, path)?;
+    media_source.synchronized_at = Some(synchronized_at);
+    repo.update_media_source(header.id, DateTime::now_utc(), &media_source)?;
+    Ok(())
+}
+
+pub fn export_metadata_into_file<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    source_path_resolver: &VirtualFilePathResolver,
+    track_id: TrackId,
+    config: &ExportTrackConfig,
+    flags: ExportTrackFlags,
+) -> Result<()>
+where
+    Repo: EntityRepo + MediaSourceRepo,
+{
+    let (_, entity) = repo.load_track_entity(track_id)?;
+    let synchronized_at =
+        export_track_to_local_file_path(source_path_resolver, &entity.body, config, flags)?;
+    update_media_source_synchronized_at(
+        repo,
+        collection_id,
+        &entity.body.media_source.path,
+        synchronized_at,
+    )
+}
+
+/// Export the metadata of all tracks in a collection that match
+/// the given filter into their corresponding files.
+///
+/// Failures of individual tracks are logged and reported in the summary.
+// TODO: Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
+pub fn export_metadata_into_files_of_search_results<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    source_path_resolver: &VirtualFilePathResolver,
+    filter: Option<SearchFilter>,
+    ordering: Vec<SortOrder>,
+    config: &ExportTrackConfig,
+    flags: ExportTrackFlags,
+    abort_flag: &AtomicBool,
+) -> Result<Outcome>
+where
+    Repo: EntityRepo + MediaSourceRepo,
+{
+    let mut tracks = Vec::new();
+    repo.search_collected_tracks(
+        collection_id,
+        &Default::default(),
+        filter,
+        ordering,
+        &mut tracks,
+    )?;
+    let mut summary = Summary::default();
+    for (_, entity) in tracks {
+        if abort_flag.load(Ordering::Relaxed) {
+            log::info!("Aborting export of track metadata: {:?}", summary);
+            return Ok(Outcome {
+                completion: Completion::Aborted,
+                summary,
+            });
+        }
+        let track = entity.body;
+        match export_track_to_local_file_path(source_path_resolver, &track, config, flags).and_then(
+            |synchronized_at| {
+                update_media_source_synchronized_at(
+                    repo,
+                    collection_id,
+                    &track.media_source.path,
+                    synchronized_at,
+                )
+            },
+        ) {
+            Ok(()) => {
+                log::debug!("Exported metadata into file {}", track.media_source.path);
+                summary.exported.push(track.media_source.path);
+            }
+            Err(err) => {
+                log::warn!(
+                    "Failed to export metadata into file {}: {}",
+                    track.media_source.path,
+                    err
+                );
+                summary.failed.push(track.media_source.path);
+            }
+        }
+    }
+    Ok(Outcome {
+        completion: Completion::Finished,
+        summary,
+    })
+}
@@ -16,7 +16,7 @@
 use super::*;
 
 use aoide_core::{
-    audio::DurationMs,
+    audio::{fingerprint::Fingerprint, DurationMs},
     media::Content,
     track::{Entity as TrackEntity, Track},
 };
@@ -31,7 +31,7 @@ use aoide_repo::{
 };
 
 use bitflags::bitflags;
-use std::num::NonZeroUsize;
+use std::{collections::BTreeMap, num::NonZeroUsize};
 
 bitflags! {
     /// A bitmask for controlling how and if content metadata is
@@ -44,7 +44,11 @@ bitflags! {
         const TRACK_ARTIST   = 0b00001000;
         const TRACK_TITLE    = 0b00010000;
         const RELEASED_AT    = 0b00100000;
-        const ALL            = 0b00111111; // most restrictive
+        const AUDIO_DURATION = 0b10000000;
+        const ALL            = 0b10111111; // most restrictive
+        // Compare the acoustic fingerprints instead of the metadata
+        // if available. Candidates without a fingerprint are rejected.
+        const FINGERPRINT    = 0b01000000;
     }
 }
 
@@ -53,6 +57,7 @@ pub struct Params {
     pub audio_duration_tolerance: DurationMs,
     pub max_results: NonZeroUsize,
     pub search_flags: SearchFlags,
+    pub min_fingerprint_similarity: f64,
 }
 
 impl Params {
@@ -63,6 +68,7 @@ impl Params {
             audio_duration_tolerance: DurationMs::from_inner(500.0), // +/- 500 ms
             max_results,
             search_flags: SearchFlags::ALL,
+            min_fingerprint_similarity: 0.8,
         }
     }
 
@@ -80,10 +86,15 @@ impl Default for Params {
     }
 }
 
+/// Find duplicates of a track
+///
+/// The track itself is excluded from the results if its id is provided.
+/// Tracks that have not been stored yet, e.g. templates that only
+/// contain some metadata, don't have an id.
 pub fn find_duplicate<Repo>(
     repo: &Repo,
     collection_id: CollectionId,
-    track_id: TrackId,
+    track_id: Option<TrackId>,
     track: Track,
     params: &Params,
 ) -> RepoResult<Vec<(TrackId, TrackEntity)>>
@@ -94,7 +105,20 @@ where
         audio_duration_tolerance,
         search_flags,
         max_results,
+        min_fingerprint_similarity,
     } = params;
+    let fingerprint = if search_flags.contains(SearchFlags::FINGERPRINT) {
+        load_fingerprint(repo, collection_id, &track.media_source.path)?
+    } else {
+        None
+    };
+    let search_flags = if fingerprint.is_some() {
+        // The metadata of re-encoded or re-tagged files is unreliable
+        *search_flags
+            & (SearchFlags::SOURCE_TRACKED | SearchFlags::AUDIO_DURATION | SearchFlags::FINGERPRINT)
+    } else {
+        *search_flags
+    };
     let mut all_filters = Vec::with_capacity(10);
     if search_flags.contains(SearchFlags::TRACK_ARTIST) {
         if let Some(track_artist) = track.track_artist() {
@@ -155,23 +179,28 @@ where
             aoide_repo::track::ConditionFilter::SourceTracked,
         ));
     }
-    // Only sources with similar audio duration
-    let audio_duration_ms = match track.media_source.content {
-        Content::Audio(content) => content.duration,
-    };
-    all_filters.push(if let Some(audio_duration_ms) = audio_duration_ms {
-        SearchFilter::audio_duration_around(audio_duration_ms, *audio_duration_tolerance)
-    } else {
-        SearchFilter::Numeric(NumericFieldFilter {
-            field: NumericField::AudioDurationMs,
-            predicate: NumericPredicate::Equal(None),
-        })
-    });
-    // Only sources with equal content/file type
-    all_filters.push(SearchFilter::Phrase(PhraseFieldFilter {
-        fields: vec![StringField::SourceType],
-        terms: vec![track.media_source.content_type],
-    }));
+    if search_flags.contains(SearchFlags::AUDIO_DURATION) {
+        // Only sources with similar audio duration
+        let audio_duration_ms = match track.media_source.content {
+            Content::Audio(content) => content.duration,
+        };
+        all_filters.push(if let Some(audio_duration_ms) = audio_duration_ms {
+            SearchFilter::audio_duration_around(audio_duration_ms, *audio_duration_tolerance)
+        } else {
+            SearchFilter::Numeric(NumericFieldFilter {
+                field: NumericField::AudioDurationMs,
+                predicate: NumericPredicate::Equal(None),
+            })
+        });
+    }
+    // Only sources with equal content/file type if known. Re-encoded
+    // files are identified by their fingerprint regardless of the type.
+    if fingerprint.is_none() && !track.media_source.content_type.is_empty() {
+        all_filters.push(SearchFilter::Phrase(PhraseFieldFilter {
+            fields: vec![StringField::SourceType],
+            terms: vec![track.media_source.content_type],
+        }));
+    }
     let filter = SearchFilter::All(all_filters);
     // Prefer recently added sources, e.g. after scanning the file system
     let ordering = vec![SortOrder {
@@ -186,20 +215,44 @@ where
         ordering,
         &mut candidates,
     )?;
+    // Exclude the track if contained in the search results
+    candidates.retain(|(record_header, _)| Some(record_header.id) != track_id);
+    if let Some(fingerprint) = &fingerprint {
+        let candidate_ids: Vec<_> = candidates
+            .iter()
+            .map(|(record_header, _)| record_header.id)
+            .collect();
+        let candidate_fingerprints: BTreeMap<_, _> = repo
+            .load_track_media_source_fingerprints(&candidate_ids)?
+            .into_iter()
+            .collect();
+        candidates.retain(|(record_header, _)| {
+            let similarity = candidate_fingerprints
+                .get(&record_header.id)
+                .and_then(|candidate| fingerprint.similarity(candidate));
+            similarity.unwrap_or_default() >= *min_fingerprint_similarity
+        });
+    }
     Ok(candidates
         .into_iter()
-        .filter_map(|(record_header, entity)| {
-            if record_header.id == track_id {
-                // Exclude the track if contained in the search results
-                None
-            } else {
-                Some((record_header.id, entity))
-            }
-        })
         .take(max_results.get())
+        .map(|(record_header, entity)| (record_header.id, entity))
         .collect())
 }
 
+fn load_fingerprint<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    media_source_path: &str,
+) -> RepoResult<Option<Fingerprint>>
+where
+    Repo: TrackRepo,
+{
+    let (media_source_id, _) =
+        repo.resolve_media_source_id_synchronized_at_by_path(collection_id, media_source_path)?;
+    repo.load_media_source_fingerprint(media_source_id)
+}
+
 pub fn find_duplicate_by_media_source_path<Repo>(
     repo: &Repo,
     collection_id: CollectionId,
@@ -211,5 +264,5 @@ where
 {
     let (_media_source_id, RecordHeader { id: track_id, .. }, entity) =
         repo.load_track_entity_by_media_source_path(collection_id, media_source_path)?;
-    find_duplicate(repo, collection_id, track_id, entity.body, params)
+    find_duplicate(repo, collection_id, Some(track_id), entity.body, params)
 }
@@ -21,6 +21,9 @@ use aoide_core::{
 };
 use aoide_repo::track::RecordHeader;
 
+pub mod analyze;
+pub mod artwork;
+pub mod export_metadata;
 pub mod find_duplicate;
 pub mod purge;
 pub mod replace;
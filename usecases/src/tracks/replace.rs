@@ -15,20 +15,26 @@
 
 use super::*;
 
-use crate::media::{
-    import_track_from_local_file_path, ImportMode, ImportTrackFromFileOutcome,
-    SynchronizedImportMode,
+use crate::{
+    media::{
+        import_track_from_local_file_path, ImportMode, ImportTrackFromFileOutcome,
+        SidecarArtworkFileCache, SynchronizedImportMode,
+    },
+    tracks::purge::purge_by_media_source_path_predicates,
 };
 
 use aoide_core::{
     media::{
         resolver::{SourcePathResolver, VirtualFilePathResolver},
-        SourcePath,
+        Source, SourcePath,
     },
     util::clock::DateTime,
 };
 
-use aoide_media::io::import::{ImportTrackConfig, ImportTrackFlags};
+use aoide_media::{
+    cue::{import_virtual_tracks, parse_cue_sheet, CueSheet, File as CueFile},
+    io::import::{ImportTrackConfig, ImportTrackFlags},
+};
 
 use aoide_repo::{
     collection::RecordId as CollectionId,
@@ -37,7 +43,9 @@ use aoide_repo::{
 };
 
 use std::{
-    fs::read_dir,
+    collections::HashMap,
+    fs::{self, read_dir},
+    path::{Path, PathBuf},
     sync::atomic::{AtomicBool, Ordering},
 };
 use url::Url;
@@ -174,30 +182,42 @@ pub fn import_and_replace_by_local_file_path<Repo>(
     replace_mode: ReplaceMode,
     source_path_resolver: &VirtualFilePathResolver,
     source_path: SourcePath,
+    sidecar_artwork_file_cache: &mut SidecarArtworkFileCache,
 ) -> RepoResult<()>
 where
     Repo: EntityRepo,
 {
-    let (media_source_id, last_synchronized_at, collected_track) = repo
+    if SourcePath::is_virtual_track(&source_path) {
+        // Virtual tracks are only imported together with their CUE sheet
+        log::warn!(
+            "Skipping import of virtual track {} without CUE sheet",
+            source_path
+        );
+        summary.not_imported.push(source_path);
+        return Ok(());
+    }
+    let (media_source_id, last_synchronized_at, last_sidecar_artwork_uri, collected_track) = repo
         .load_track_entity_by_media_source_path(collection_id, &source_path)
         .optional()?
         .map(|(media_source_id, _, entity)| {
             (
                 Some(media_source_id),
                 entity.body.media_source.synchronized_at,
+                entity.body.media_source.artwork.uri.clone(),
                 Some(entity.body),
             )
         })
-        .unwrap_or((None, None, None));
+        .unwrap_or((None, None, None, None));
     match import_track_from_local_file_path(
         source_path_resolver,
         source_path.clone(),
-        SynchronizedImportMode::new(import_mode, last_synchronized_at),
+        SynchronizedImportMode::new(import_mode, last_synchronized_at, last_sidecar_artwork_uri),
         import_config,
         import_flags,
         DateTime::now_local(),
+        sidecar_artwork_file_cache,
     ) {
-        Ok(ImportTrackFromFileOutcome::Imported(imported_track)) => {
+        Ok(ImportTrackFromFileOutcome::Imported(imported_track, fingerprint)) => {
             debug_assert_eq!(imported_track.media_source.path, source_path);
             let track = if let Some(mut collected_track) = collected_track {
                 collected_track.merge_newer_from_synchronized_media_source(imported_track);
@@ -213,6 +233,9 @@ where
                 true,
                 track,
             )? {
+                if let Some(fingerprint) = &fingerprint {
+                    repo.update_media_source_fingerprint(media_source_id, Some(fingerprint))?;
+                }
                 media_source_ids.push(media_source_id);
             }
         }
@@ -238,6 +261,239 @@ where
     Ok(())
 }
 
+/// Import a file that is split into virtual tracks by a CUE sheet
+///
+/// The file is only re-imported if either the file or its CUE sheet
+/// have been modified since the virtual tracks have been synchronized.
+/// Virtual tracks that are no longer contained in the CUE sheet and
+/// a track that has previously been imported from the whole file are
+/// purged from the collection.
+// TODO: Reduce number of arguments
+#[allow(clippy::too_many_arguments)]
+pub fn import_and_replace_virtual_tracks_by_local_file_path<Repo>(
+    summary: &mut Summary,
+    media_source_ids: &mut Vec<MediaSourceId>,
+    repo: &Repo,
+    collection_id: CollectionId,
+    import_mode: ImportMode,
+    import_config: &ImportTrackConfig,
+    import_flags: ImportTrackFlags,
+    replace_mode: ReplaceMode,
+    source_path_resolver: &VirtualFilePathResolver,
+    source_path: SourcePath,
+    cue_sheet: &CueSheet,
+    cue_sheet_last_modified_at: DateTime,
+    cue_file: &CueFile,
+    sidecar_artwork_file_cache: &mut SidecarArtworkFileCache,
+) -> RepoResult<()>
+where
+    Repo: EntityRepo,
+{
+    let collected_virtual_tracks =
+        load_collected_virtual_tracks(repo, collection_id, &source_path)?;
+    // All virtual tracks are synchronized together
+    let last_synchronized_at = collected_virtual_tracks
+        .iter()
+        .map(|(_, media_source)| media_source.synchronized_at)
+        .min()
+        .flatten()
+        .filter(|last_synchronized_at| cue_sheet_last_modified_at <= *last_synchronized_at);
+    let last_sidecar_artwork_uri = collected_virtual_tracks
+        .first()
+        .and_then(|(_, media_source)| media_source.artwork.uri.clone());
+    // Neither the results of analyzing the whole file nor its
+    // acoustic fingerprint apply to the virtual tracks
+    let import_flags = import_flags
+        - (ImportTrackFlags::ANALYZE_LOUDNESS
+            | ImportTrackFlags::ANALYZE_TEMPO
+            | ImportTrackFlags::ANALYZE_KEY_SIGNATURE
+            | ImportTrackFlags::ANALYZE_FINGERPRINT
+            | ImportTrackFlags::ANALYZE_WAVEFORM);
+    let mut file_track = match import_track_from_local_file_path(
+        source_path_resolver,
+        source_path.clone(),
+        SynchronizedImportMode::new(import_mode, last_synchronized_at, last_sidecar_artwork_uri),
+        import_config,
+        import_flags,
+        DateTime::now_local(),
+        sidecar_artwork_file_cache,
+    ) {
+        Ok(ImportTrackFromFileOutcome::Imported(file_track, _)) => file_track,
+        Ok(ImportTrackFromFileOutcome::SkippedSynchronized(_)) => {
+            for (media_source_id, media_source) in collected_virtual_tracks {
+                summary.unchanged.push(media_source.path);
+                media_source_ids.push(media_source_id);
+            }
+            return Ok(());
+        }
+        Ok(ImportTrackFromFileOutcome::SkippedDirectory) => {
+            return Ok(());
+        }
+        Err(err) => {
+            log::warn!(
+                "Failed to import track from local file path {}: {}",
+                source_path_resolver.build_file_path(&source_path).display(),
+                err
+            );
+            summary.not_imported.push(source_path);
+            return Ok(());
+        }
+    };
+    // Modifications of the CUE sheet are considered as modifications
+    // of all virtual tracks
+    file_track.media_source.synchronized_at = file_track
+        .media_source
+        .synchronized_at
+        .max(Some(cue_sheet_last_modified_at));
+    let virtual_tracks = import_virtual_tracks(cue_sheet, cue_file, &file_track);
+    let mut replaced_count = 0;
+    let mut purge_path_predicates = Vec::new();
+    for (_, media_source) in collected_virtual_tracks {
+        if virtual_tracks
+            .iter()
+            .all(|virtual_track| virtual_track.media_source.path != media_source.path)
+        {
+            log::info!(
+                "Purging virtual track {} that is no longer contained in the CUE sheet",
+                media_source.path
+            );
+            purge_path_predicates.push(StringPredicate::Equals(media_source.path.into()));
+        }
+    }
+    for virtual_track in virtual_tracks {
+        let track = match repo
+            .load_track_entity_by_media_source_path(collection_id, &virtual_track.media_source.path)
+            .optional()?
+        {
+            Some((_, _, entity)) => {
+                let mut collected_track = entity.body;
+                collected_track.merge_newer_from_synchronized_media_source(virtual_track);
+                collected_track
+            }
+            None => virtual_track,
+        };
+        if let Some(media_source_id) = replace_collected_track_by_media_source_path(
+            summary,
+            repo,
+            collection_id,
+            replace_mode,
+            true,
+            track,
+        )? {
+            media_source_ids.push(media_source_id);
+            replaced_count += 1;
+        }
+    }
+    if replaced_count > 0 {
+        // The virtual tracks replace a track that might have been
+        // imported from the whole file before the CUE sheet existed
+        purge_path_predicates.push(StringPredicate::Equals(source_path.into()));
+    }
+    if !purge_path_predicates.is_empty() {
+        purge_by_media_source_path_predicates(repo, collection_id, purge_path_predicates)?;
+    }
+    Ok(())
+}
+
+/// Load the media sources of all virtual tracks of a file
+fn load_collected_virtual_tracks<Repo>(
+    repo: &Repo,
+    collection_id: CollectionId,
+    source_path: &str,
+) -> RepoResult<Vec<(MediaSourceId, Source)>>
+where
+    Repo: EntityRepo,
+{
+    let path_prefix = format!("{}#", source_path);
+    let media_source_ids = repo.resolve_media_source_ids_by_path_predicate(
+        collection_id,
+        StringPredicateBorrowed::Prefix(&path_prefix),
+    )?;
+    let mut collected_virtual_tracks = Vec::with_capacity(media_source_ids.len());
+    for media_source_id in media_source_ids {
+        let (_, media_source) = repo.load_media_source(media_source_id)?;
+        let (file_path, fragment) = SourcePath::split_virtual_track(&media_source.path);
+        if file_path != source_path || fragment.is_none() {
+            // Another file with a '#' in its name
+            continue;
+        }
+        collected_virtual_tracks.push((media_source_id, media_source));
+    }
+    Ok(collected_virtual_tracks)
+}
+
+const CUE_SHEET_FILE_EXTENSION: &str = "cue";
+
+fn is_cue_sheet(file_path: &Path) -> bool {
+    file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| {
+            extension.eq_ignore_ascii_case(CUE_SHEET_FILE_EXTENSION)
+        })
+}
+
+fn resolve_source_path(
+    source_path_resolver: &VirtualFilePathResolver,
+    file_path: &Path,
+) -> Option<SourcePath> {
+    Url::from_file_path(file_path)
+        .ok()
+        .and_then(|url| source_path_resolver.resolve_path_from_url(&url).ok())
+}
+
+/// Parse all CUE sheets among the files of a directory
+///
+/// Returns the CUE sheets with their last modification time and the
+/// indexes of the sheet and file by the source path of each referenced
+/// file.
+fn load_cue_sheets(
+    source_path_resolver: &VirtualFilePathResolver,
+    dir_path: &Path,
+    file_paths: &[PathBuf],
+) -> (Vec<(CueSheet, DateTime)>, HashMap<String, (usize, usize)>) {
+    let mut cue_sheets = Vec::new();
+    let mut cue_files = HashMap::new();
+    for file_path in file_paths
+        .iter()
+        .filter(|file_path| is_cue_sheet(file_path))
+    {
+        let cue_sheet = match fs::read(file_path)
+            .map_err(Into::into)
+            .and_then(|bytes| parse_cue_sheet(&String::from_utf8_lossy(&bytes)))
+        {
+            Ok(cue_sheet) => cue_sheet,
+            Err(err) => {
+                log::warn!("Failed to load CUE sheet {}: {}", file_path.display(), err);
+                continue;
+            }
+        };
+        let last_modified_at = fs::metadata(file_path)
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::from)
+            .unwrap_or_else(|_| {
+                log::error!("Using current time instead of inaccessible last modification time");
+                DateTime::now_utc()
+            });
+        let sheet_index = cue_sheets.len();
+        for (file_index, cue_file) in cue_sheet.files.iter().enumerate() {
+            // CUE sheets created on Windows use backslashes as path separators
+            let cue_file_path = dir_path.join(cue_file.path.replace('\\', "/"));
+            if let Some(source_path) = resolve_source_path(source_path_resolver, &cue_file_path) {
+                cue_files.insert(source_path.into(), (sheet_index, file_index));
+            } else {
+                log::warn!(
+                    "Skipping file {} of CUE sheet {}",
+                    cue_file_path.display(),
+                    file_path.display()
+                );
+            }
+        }
+        cue_sheets.push((cue_sheet, last_modified_at));
+    }
+    (cue_sheets, cue_files)
+}
+
 pub fn replace_by_media_source_path<Repo>(
     repo: &Repo,
     collection_id: CollectionId,
@@ -283,6 +539,7 @@ where
     let mut summary = Summary::default();
     let mut media_source_ids =
         Vec::with_capacity(expected_source_path_count.unwrap_or(DEFAULT_MEDIA_SOURCE_COUNT));
+    let mut sidecar_artwork_file_cache = SidecarArtworkFileCache::new();
     for source_path in source_path_iter {
         if abort_flag.load(Ordering::Relaxed) {
             log::debug!("Aborting import of {}", source_path);
@@ -303,6 +560,7 @@ where
             replace_mode,
             source_path_resolver,
             source_path,
+            &mut sidecar_artwork_file_cache,
         )?;
     }
     Ok(Outcome {
@@ -332,42 +590,66 @@ where
 {
     let dir_path = source_path_resolver.build_file_path(source_dir_path);
     log::debug!("Importing files from directory: {}", dir_path.display());
-    let dir_entries = read_dir(dir_path)?;
-    let mut summary = Summary::default();
-    let mut media_source_ids = Vec::with_capacity(EXPECTED_NUMBER_OF_DIR_ENTRIES);
+    let dir_entries = read_dir(&dir_path)?;
+    let mut file_paths = Vec::with_capacity(EXPECTED_NUMBER_OF_DIR_ENTRIES);
     for dir_entry in dir_entries {
-        let dir_entry = match dir_entry {
-            Ok(dir_entry) => dir_entry,
+        match dir_entry {
+            Ok(dir_entry) => file_paths.push(dir_entry.path()),
             Err(err) => {
                 log::warn!("Failed to access directory entry: {}", err);
                 // Skip entry and keep going
                 continue;
             }
-        };
+        }
+    }
+    let (cue_sheets, cue_files) = load_cue_sheets(source_path_resolver, &dir_path, &file_paths);
+    let mut summary = Summary::default();
+    let mut media_source_ids = Vec::with_capacity(file_paths.len());
+    let mut sidecar_artwork_file_cache = SidecarArtworkFileCache::new();
+    for file_path in file_paths {
         if abort_flag.load(Ordering::Relaxed) {
-            log::debug!(
-                "Aborting import before visiting {}",
-                dir_entry.path().display()
-            );
+            log::debug!("Aborting import before visiting {}", file_path.display());
             return Ok(Outcome {
                 completion: Completion::Aborted,
                 summary,
                 media_source_ids,
             });
         }
-        let source_path = if let Some(source_path) = Url::from_file_path(dir_entry.path())
-            .ok()
-            .and_then(|url| source_path_resolver.resolve_path_from_url(&url).ok())
-        {
-            source_path.to_owned()
-        } else {
-            log::warn!(
-                "Skipping invalid/unsupported directory entry: {}",
-                dir_entry.path().display()
-            );
-            // Skip entry and keep going
+        if is_cue_sheet(&file_path) {
+            // Already loaded
             continue;
-        };
+        }
+        let source_path =
+            if let Some(source_path) = resolve_source_path(source_path_resolver, &file_path) {
+                source_path
+            } else {
+                log::warn!(
+                    "Skipping invalid/unsupported directory entry: {}",
+                    file_path.display()
+                );
+                // Skip entry and keep going
+                continue;
+            };
+        if let Some((sheet_index, file_index)) = cue_files.get(source_path.as_str()) {
+            let (cue_sheet, cue_sheet_last_modified_at) = &cue_sheets[*sheet_index];
+            import_and_replace_virtual_tracks_by_local_file_path(
+                &mut summary,
+                &mut media_source_ids,
+                repo,
+                collection_id,
+                import_mode,
+                import_config,
+                import_flags,
+                replace_mode,
+                source_path_resolver,
+                source_path,
+                cue_sheet,
+                *cue_sheet_last_modified_at,
+                &cue_sheet.files[*file_index],
+                &mut sidecar_artwork_file_cache,
+            )?;
+            continue;
+        }
         import_and_replace_by_local_file_path(
             &mut summary,
             &mut media_source_ids,
@@ -379,6 +661,7 @@ where
             replace_mode,
             source_path_resolver,
             source_path,
+            &mut sidecar_artwork_file_cache,
         )?;
     }
     Ok(Outcome {